@@ -0,0 +1,170 @@
+//! Pools of helper threads, for blocking calls that have no asynchronous
+//! counterpart.
+
+use futures::channel::oneshot;
+
+use std::collections::VecDeque;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long an idle thread waits for another job before it exits.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A pool that starts threads as jobs come in, up to a limit, and lets them
+/// exit once they have been idle for a while.
+pub(crate) struct Pool {
+    name: &'static str,
+    max_threads: usize,
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    threads: usize,
+    /// Threads waiting for a job that no job has been handed to yet.
+    idle: usize,
+    /// Jobs handed to a waiting thread, which it hasn't woken up for yet.
+    notified: usize,
+}
+
+impl Pool {
+    /// Creates a pool whose threads are called `name`, running at most
+    /// `max_threads` jobs at once; further jobs queue.
+    pub(crate) fn new(name: &'static str, max_threads: usize) -> Pool {
+        Pool {
+            name,
+            max_threads,
+            state: Mutex::new(State {
+                jobs: VecDeque::new(),
+                threads: 0,
+                idle: 0,
+                notified: 0,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Runs `f` on a pool thread, and sends its result to the returned
+    /// receiver.
+    ///
+    /// If `f` panics, the receiver is canceled, and the thread goes on with
+    /// other jobs. Fails only if a thread was needed and couldn't be started.
+    pub(crate) fn run<F, T>(&'static self, f: F) -> io::Result<oneshot::Receiver<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.submit(Box::new(move || drop(tx.send(f()))))?;
+        Ok(rx)
+    }
+
+    fn submit(&'static self, job: Job) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.idle > 0 {
+            // Reserve the thread, so a burst of jobs starts more of them
+            // rather than queueing for this one.
+            state.idle -= 1;
+            state.notified += 1;
+            state.jobs.push_back(job);
+            self.available.notify_one();
+            return Ok(());
+        }
+        if state.threads == self.max_threads {
+            state.jobs.push_back(job);
+            return Ok(());
+        }
+
+        thread::Builder::new()
+            .name(self.name.to_string())
+            .spawn(move || {
+                run_job(job);
+                self.work();
+            })?;
+        state.threads += 1;
+        Ok(())
+    }
+
+    fn work(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match state.jobs.pop_front() {
+                Some(job) => {
+                    drop(state);
+                    run_job(job);
+                    state = self.state.lock().unwrap();
+                }
+                None => {
+                    state.idle += 1;
+                    let (next, timeout) = self.available.wait_timeout(state, IDLE_TIMEOUT).unwrap();
+                    state = next;
+                    // Which thread a notification wakes doesn't matter, as
+                    // long as one stops counting as waiting for each.
+                    if state.notified > 0 {
+                        state.notified -= 1;
+                    } else {
+                        state.idle -= 1;
+                    }
+                    if timeout.timed_out() && state.jobs.is_empty() {
+                        state.threads -= 1;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs `job`, keeping its thread alive if it panics. The panic is still
+/// reported by the panic hook.
+fn run_job(job: Job) {
+    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor;
+    use std::sync::mpsc;
+
+    fn pool(max_threads: usize) -> &'static Pool {
+        Box::leak(Box::new(Pool::new("romio-test", max_threads)))
+    }
+
+    #[test]
+    fn panicking_jobs_leave_their_thread() {
+        let pool = pool(1);
+        for _ in 0..3 {
+            let rx = pool.run(|| panic!("job failed")).unwrap();
+            assert!(executor::block_on(rx).is_err());
+        }
+        let rx = pool.run(|| 42).unwrap();
+        assert_eq!(executor::block_on(rx), Ok(42));
+        assert_eq!(pool.state.lock().unwrap().threads, 1);
+    }
+
+    #[test]
+    fn bursts_start_more_threads() {
+        let pool = pool(2);
+        executor::block_on(pool.run(|| ()).unwrap()).unwrap();
+        // Let the thread start waiting for another job.
+        thread::sleep(Duration::from_millis(50));
+
+        // The first job only finishes once the second one runs.
+        let (tx, rx) = mpsc::channel();
+        let first = pool
+            .run(move || rx.recv_timeout(Duration::from_secs(5)))
+            .unwrap();
+        let second = pool.run(move || tx.send(())).unwrap();
+        assert_eq!(executor::block_on(first), Ok(Ok(())));
+        assert!(executor::block_on(second).unwrap().is_ok());
+        assert_eq!(pool.state.lock().unwrap().threads, 2);
+    }
+}
@@ -0,0 +1,47 @@
+use std::io;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// A codec that passes bytes through unchanged.
+///
+/// Decoding yields whatever has been read so far as one chunk, and encoding
+/// writes each chunk as is. This is useful for putting a raw stream behind
+/// the `Stream` and `Sink` interfaces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BytesCodec {
+    _priv: (),
+}
+
+impl BytesCodec {
+    /// Creates a `BytesCodec`.
+    pub fn new() -> BytesCodec {
+        BytesCodec { _priv: () }
+    }
+}
+
+impl Decoder for BytesCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if src.is_empty() {
+            Ok(None)
+        } else {
+            let len = src.len();
+            Ok(Some(src.split_to(len)))
+        }
+    }
+}
+
+impl Encoder for BytesCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(item.len());
+        dst.put(item);
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::BytesMut;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Sink, Stream};
+
+use super::{Decoder, Encoder};
+
+/// How much to read from the stream at a time.
+const READ_CHUNK: usize = 8 * 1024;
+
+/// How many encoded bytes to buffer before `poll_ready` flushes them.
+const BACKPRESSURE_BOUNDARY: usize = 8 * 1024;
+
+/// The read side of a framed stream.
+struct ReadFrames {
+    buf: BytesMut,
+    /// Where reads land before being appended to `buf`, so that no memory
+    /// has to be zeroed for each read.
+    chunk: Box<[u8]>,
+    /// Whether `buf` may hold a frame that hasn't been decoded yet.
+    is_readable: bool,
+    eof: bool,
+}
+
+impl ReadFrames {
+    fn new() -> ReadFrames {
+        ReadFrames {
+            buf: BytesMut::with_capacity(READ_CHUNK),
+            chunk: vec![0; READ_CHUNK].into_boxed_slice(),
+            is_readable: false,
+            eof: false,
+        }
+    }
+
+    fn poll_next<T, U>(
+        &mut self,
+        cx: &mut Context<'_>,
+        io: &mut T,
+        codec: &mut U,
+    ) -> Poll<Option<Result<U::Item, U::Error>>>
+    where
+        T: AsyncRead + Unpin,
+        U: Decoder,
+    {
+        loop {
+            if self.is_readable {
+                if self.eof {
+                    let frame = codec.decode_eof(&mut self.buf)?;
+                    if frame.is_none() {
+                        self.is_readable = false;
+                    }
+                    return Poll::Ready(frame.map(Ok));
+                }
+
+                if let Some(frame) = codec.decode(&mut self.buf)? {
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                self.is_readable = false;
+            }
+
+            let n = ready!(Pin::new(&mut *io).poll_read(cx, &mut self.chunk))?;
+            self.buf.extend_from_slice(&self.chunk[..n]);
+            self.eof = n == 0;
+            self.is_readable = true;
+        }
+    }
+}
+
+/// The write side of a framed stream.
+struct WriteFrames {
+    buf: BytesMut,
+}
+
+impl WriteFrames {
+    fn new() -> WriteFrames {
+        WriteFrames {
+            buf: BytesMut::new(),
+        }
+    }
+
+    fn poll_ready<T, E>(&mut self, cx: &mut Context<'_>, io: &mut T) -> Poll<Result<(), E>>
+    where
+        T: AsyncWrite + Unpin,
+        E: From<io::Error>,
+    {
+        if self.buf.len() >= BACKPRESSURE_BOUNDARY {
+            ready!(self.poll_flush(cx, io))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_flush<T, E>(&mut self, cx: &mut Context<'_>, io: &mut T) -> Poll<Result<(), E>>
+    where
+        T: AsyncWrite + Unpin,
+        E: From<io::Error>,
+    {
+        while !self.buf.is_empty() {
+            let n = ready!(Pin::new(&mut *io).poll_write(cx, &self.buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write frame to transport",
+                )
+                .into()));
+            }
+            self.buf.advance(n);
+        }
+
+        ready!(Pin::new(&mut *io).poll_flush(cx))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close<T, E>(&mut self, cx: &mut Context<'_>, io: &mut T) -> Poll<Result<(), E>>
+    where
+        T: AsyncWrite + Unpin,
+        E: From<io::Error>,
+    {
+        ready!(self.poll_flush(cx, io))?;
+        ready!(Pin::new(&mut *io).poll_close(cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A `Stream` and `Sink` of frames over an I/O object.
+///
+/// Reading yields the frames decoded from the object. Every frame already
+/// buffered is decoded before the object is read again, so a single read
+/// that delivers many pipelined frames costs a single syscall. Writing
+/// encodes frames into a buffer that is written out when the sink is
+/// flushed, or once it grows past a few kilobytes.
+pub struct Framed<T, U> {
+    io: T,
+    codec: U,
+    read: ReadFrames,
+    write: WriteFrames,
+}
+
+impl<T, U> Framed<T, U> {
+    /// Frames `io` with `codec`.
+    pub fn new(io: T, codec: U) -> Framed<T, U> {
+        Framed {
+            io,
+            codec,
+            read: ReadFrames::new(),
+            write: WriteFrames::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying I/O object.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying I/O object.
+    ///
+    /// Reading from or writing to it directly interferes with the framing.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns a reference to the codec.
+    pub fn codec(&self) -> &U {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut U {
+        &mut self.codec
+    }
+
+    /// Consumes the `Framed`, returning the I/O object.
+    ///
+    /// Bytes that were read but not decoded, or encoded but not written,
+    /// are lost.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, U> Stream for Framed<T, U>
+where
+    T: AsyncRead + Unpin,
+    U: Decoder + Unpin,
+{
+    type Item = Result<U::Item, U::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.read.poll_next(cx, &mut this.io, &mut this.codec)
+    }
+}
+
+impl<T, U> Sink<U::Item> for Framed<T, U>
+where
+    T: AsyncWrite + Unpin,
+    U: Encoder + Unpin,
+{
+    type Error = U::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write.poll_ready(cx, &mut this.io)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: U::Item) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.codec.encode(item, &mut this.write.buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write.poll_flush(cx, &mut this.io)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write.poll_close(cx, &mut this.io)
+    }
+}
+
+impl<T: fmt::Debug, U: fmt::Debug> fmt::Debug for Framed<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Framed")
+            .field("io", &self.io)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+/// A `Stream` of frames decoded from an `AsyncRead`.
+///
+/// This is the read half of [`Framed`](struct.Framed.html), for readers that
+/// can't or shouldn't also be written to, such as the read half of a split
+/// stream.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::codec::{FramedRead, FramedWrite, LinesCodec};
+/// use romio::tcp::TcpStream;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let (reader, writer) = TcpStream::connect(&addr).await?.split();
+/// let mut lines = FramedRead::new(reader, LinesCodec::new());
+/// let mut out = FramedWrite::new(writer, LinesCodec::new());
+///
+/// // echo every line back, upper-cased
+/// while let Some(line) = lines.next().await {
+///     out.send(line?.to_uppercase()).await?;
+/// }
+/// # Ok(())}
+/// ```
+pub struct FramedRead<T, D> {
+    io: T,
+    decoder: D,
+    read: ReadFrames,
+}
+
+impl<T, D> FramedRead<T, D> {
+    /// Decodes the bytes read from `io` with `decoder`.
+    pub fn new(io: T, decoder: D) -> FramedRead<T, D> {
+        FramedRead {
+            io,
+            decoder,
+            read: ReadFrames::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly interferes with the framing.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns a reference to the decoder.
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the decoder.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Consumes the `FramedRead`, returning the reader.
+    ///
+    /// Bytes that were read but not decoded are lost.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, D> Stream for FramedRead<T, D>
+where
+    T: AsyncRead + Unpin,
+    D: Decoder + Unpin,
+{
+    type Item = Result<D::Item, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.read.poll_next(cx, &mut this.io, &mut this.decoder)
+    }
+}
+
+impl<T: fmt::Debug, D: fmt::Debug> fmt::Debug for FramedRead<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedRead")
+            .field("io", &self.io)
+            .field("decoder", &self.decoder)
+            .finish()
+    }
+}
+
+/// A `Sink` of frames encoded into an `AsyncWrite`.
+///
+/// This is the write half of [`Framed`](struct.Framed.html). Frames are
+/// buffered like they are there, so the sink must be flushed or closed for
+/// the last of them to be written.
+pub struct FramedWrite<T, E> {
+    io: T,
+    encoder: E,
+    write: WriteFrames,
+}
+
+impl<T, E> FramedWrite<T, E> {
+    /// Encodes frames with `encoder` and writes them to `io`.
+    pub fn new(io: T, encoder: E) -> FramedWrite<T, E> {
+        FramedWrite {
+            io,
+            encoder,
+            write: WriteFrames::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly interferes with the framing.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns a reference to the encoder.
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Returns a mutable reference to the encoder.
+    pub fn encoder_mut(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+
+    /// Consumes the `FramedWrite`, returning the writer.
+    ///
+    /// Bytes that were encoded but not written are lost.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, E> Sink<E::Item> for FramedWrite<T, E>
+where
+    T: AsyncWrite + Unpin,
+    E: Encoder + Unpin,
+{
+    type Error = E::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write.poll_ready(cx, &mut this.io)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: E::Item) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.encoder.encode(item, &mut this.write.buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write.poll_flush(cx, &mut this.io)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write.poll_close(cx, &mut this.io)
+    }
+}
+
+impl<T: fmt::Debug, E: fmt::Debug> fmt::Debug for FramedWrite<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedWrite")
+            .field("io", &self.io)
+            .field("encoder", &self.encoder)
+            .finish()
+    }
+}
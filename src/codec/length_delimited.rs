@@ -0,0 +1,126 @@
+use std::io;
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// The length of the frame header.
+const HEADER_LEN: usize = 4;
+
+/// The default limit on the length of a frame, 8 MiB.
+const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// A codec for frames prefixed with their length.
+///
+/// Each frame is preceded by its length in bytes, as a 32-bit big-endian
+/// integer that doesn't count itself. Frames longer than the maximum frame
+/// length, 8 MiB by default, are rejected with `InvalidData` when decoding
+/// and `InvalidInput` when encoding, so that a bogus header can't make the
+/// read buffer grow without bound.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use bytes::Bytes;
+/// use futures::prelude::*;
+/// use romio::codec::{Framed, LengthDelimitedCodec};
+/// use romio::tcp::TcpStream;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let stream = TcpStream::connect(&addr).await?;
+/// let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
+///
+/// framed.send(Bytes::from_static(b"ping")).await?;
+/// if let Some(frame) = framed.next().await {
+///     println!("{:?}", frame?);
+/// }
+/// # Ok(())}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LengthDelimitedCodec {
+    max_frame_length: usize,
+    /// The length of the frame being read, once its header has been.
+    frame_len: Option<usize>,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates a `LengthDelimitedCodec` with the default maximum frame
+    /// length.
+    pub fn new() -> LengthDelimitedCodec {
+        LengthDelimitedCodec {
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            frame_len: None,
+        }
+    }
+
+    /// Returns the maximum frame length.
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// Sets the maximum frame length.
+    pub fn set_max_frame_length(&mut self, max_frame_length: usize) {
+        self.max_frame_length = max_frame_length;
+    }
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> LengthDelimitedCodec {
+        LengthDelimitedCodec::new()
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        let len = match self.frame_len {
+            Some(len) => len,
+            None => {
+                if src.len() < HEADER_LEN {
+                    return Ok(None);
+                }
+                let mut header = [0; HEADER_LEN];
+                header.copy_from_slice(&src[..HEADER_LEN]);
+                let len = u32::from_be_bytes(header) as usize;
+                if len > self.max_frame_length {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "frame exceeds the maximum frame length",
+                    ));
+                }
+                src.advance(HEADER_LEN);
+                src.reserve(len);
+                self.frame_len = Some(len);
+                len
+            }
+        };
+
+        if src.len() < len {
+            return Ok(None);
+        }
+        self.frame_len = None;
+        Ok(Some(src.split_to(len)))
+    }
+}
+
+impl Encoder for LengthDelimitedCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        if frame.len() > self.max_frame_length || frame.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame exceeds the maximum frame length",
+            ));
+        }
+        dst.reserve(HEADER_LEN + frame.len());
+        dst.put_u32_be(frame.len() as u32);
+        dst.put(frame);
+        Ok(())
+    }
+}
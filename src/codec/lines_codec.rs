@@ -0,0 +1,112 @@
+use std::cmp;
+use std::io;
+
+use bytes::{BufMut, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// A codec for newline-delimited text.
+///
+/// Each frame is a line of UTF-8 text, without its trailing `\n` or `\r\n`.
+/// Encoding appends a `\n` to every line. At EOF, a last line that isn't
+/// terminated is still returned.
+///
+/// By default lines may be of any length, so a peer that never sends a
+/// newline makes the read buffer grow without bound. Servers facing
+/// untrusted peers should set a limit with
+/// [`with_max_length`](#method.with_max_length).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinesCodec {
+    /// Where to resume looking for a newline, since the bytes before it have
+    /// already been searched.
+    next_index: usize,
+    max_length: usize,
+}
+
+impl LinesCodec {
+    /// Creates a `LinesCodec` for lines of any length.
+    pub fn new() -> LinesCodec {
+        LinesCodec::with_max_length(usize::MAX)
+    }
+
+    /// Creates a `LinesCodec` that fails with `InvalidData` on lines longer
+    /// than `max_length` bytes.
+    pub fn with_max_length(max_length: usize) -> LinesCodec {
+        LinesCodec {
+            next_index: 0,
+            max_length,
+        }
+    }
+
+    /// Returns the longest line this codec decodes.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl Default for LinesCodec {
+    fn default() -> LinesCodec {
+        LinesCodec::new()
+    }
+}
+
+/// Strips a trailing `\r` and checks that `line` is UTF-8.
+fn to_string(line: &[u8]) -> io::Result<String> {
+    let line = match line.last() {
+        Some(b'\r') => &line[..line.len() - 1],
+        _ => line,
+    };
+    String::from_utf8(line.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn too_long() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "line too long")
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        // Only search as far as a line of the maximum length could reach.
+        let end = cmp::min(src.len(), self.max_length.saturating_add(1));
+        match src[self.next_index..end].iter().position(|&b| b == b'\n') {
+            Some(i) => {
+                let i = self.next_index + i;
+                self.next_index = 0;
+                let line = src.split_to(i + 1);
+                to_string(&line[..i]).map(Some)
+            }
+            None if src.len() > self.max_length => Err(too_long()),
+            None => {
+                self.next_index = end;
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> io::Result<Option<String>> {
+        if let Some(line) = self.decode(buf)? {
+            return Ok(Some(line));
+        }
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        self.next_index = 0;
+        let len = buf.len();
+        let line = buf.split_to(len);
+        to_string(&line).map(Some)
+    }
+}
+
+impl Encoder for LinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn encode(&mut self, line: String, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(line.len() + 1);
+        dst.put(line);
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
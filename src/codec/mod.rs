@@ -0,0 +1,151 @@
+//! Framing of byte streams into messages.
+//!
+//! A [`Decoder`] turns bytes read from a stream into frames and an
+//! [`Encoder`] turns frames into bytes to write. [`Framed`] combines a codec
+//! with any `AsyncRead`/`AsyncWrite` type, such as a `TcpStream`, into a
+//! `Stream` of decoded frames and a `Sink` of frames to encode.
+//! [`FramedRead`] and [`FramedWrite`] do one direction each, which suits the
+//! halves of a split stream. [`UdpFramed`] frames datagrams instead: each
+//! datagram received on a `UdpSocket` is decoded into one frame, and each
+//! frame sent is encoded into one datagram.
+//!
+//! Codecs for common framings come with the module: [`LinesCodec`] for
+//! newline-delimited text, [`LengthDelimitedCodec`] for frames prefixed with
+//! their length, and [`BytesCodec`] for raw chunks of bytes.
+//!
+//! The traits have the same shape as those of the `tokio-codec` crate. With
+//! the `tokio-codec` feature enabled, every `tokio_codec::Decoder` and
+//! `tokio_codec::Encoder` is also a romio codec, so existing codecs work with
+//! `Framed` unchanged.
+//!
+//! [`Decoder`]: trait.Decoder.html
+//! [`Encoder`]: trait.Encoder.html
+//! [`Framed`]: struct.Framed.html
+//! [`FramedRead`]: struct.FramedRead.html
+//! [`FramedWrite`]: struct.FramedWrite.html
+//! [`LinesCodec`]: struct.LinesCodec.html
+//! [`LengthDelimitedCodec`]: struct.LengthDelimitedCodec.html
+//! [`BytesCodec`]: struct.BytesCodec.html
+//! [`UdpFramed`]: struct.UdpFramed.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use bytes::BytesMut;
+//! use futures::prelude::*;
+//! use romio::codec::{Decoder, Framed};
+//! use romio::tcp::TcpStream;
+//! use std::io;
+//!
+//! /// Splits a stream into lines.
+//! struct Lines;
+//!
+//! impl Decoder for Lines {
+//!     type Item = BytesMut;
+//!     type Error = io::Error;
+//!
+//!     fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+//!         Ok(src.iter().position(|&b| b == b'\n').map(|i| {
+//!             let mut line = src.split_to(i + 1);
+//!             line.truncate(i);
+//!             line
+//!         }))
+//!     }
+//! }
+//!
+//! # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+//! let addr = "127.0.0.1:8080".parse()?;
+//! let stream = TcpStream::connect(&addr).await?;
+//! let mut lines = Framed::new(stream, Lines);
+//!
+//! while let Some(line) = lines.next().await {
+//!     println!("{:?}", line?);
+//! }
+//! # Ok(())}
+//! ```
+
+use std::io;
+
+use bytes::BytesMut;
+
+mod bytes_codec;
+mod framed;
+mod length_delimited;
+mod lines_codec;
+mod udp;
+
+pub use self::bytes_codec::BytesCodec;
+pub use self::framed::{Framed, FramedRead, FramedWrite};
+pub use self::length_delimited::LengthDelimitedCodec;
+pub use self::lines_codec::LinesCodec;
+pub use self::udp::UdpFramed;
+
+/// Decodes frames from a buffer of bytes.
+pub trait Decoder {
+    /// The type of decoded frames.
+    type Item;
+
+    /// The type of decoding errors.
+    ///
+    /// Errors of the underlying stream are converted into it.
+    type Error: From<io::Error>;
+
+    /// Attempts to decode a frame from the bytes read so far.
+    ///
+    /// If `src` holds a complete frame, this removes its bytes from `src` and
+    /// returns it. Otherwise it returns `Ok(None)`, and is called again once
+    /// more bytes have been read; it may reserve space in `src` if it knows
+    /// how many more are needed.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Decodes a frame when the stream has reached EOF.
+    ///
+    /// By default this calls `decode`, and reports an error if bytes remain
+    /// that don't make up a frame.
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None if buf.is_empty() => Ok(None),
+            None => Err(io::Error::other("bytes remaining on stream").into()),
+        }
+    }
+}
+
+/// Encodes frames into a buffer of bytes.
+pub trait Encoder {
+    /// The type of frames to encode.
+    type Item;
+
+    /// The type of encoding errors.
+    ///
+    /// Errors of the underlying stream are converted into it.
+    type Error: From<io::Error>;
+
+    /// Encodes a frame, appending its bytes to `dst`.
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "tokio-codec")]
+impl<T: tokio_codec::Decoder> Decoder for T {
+    type Item = T::Item;
+    type Error = T::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        tokio_codec::Decoder::decode(self, src)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        tokio_codec::Decoder::decode_eof(self, buf)
+    }
+}
+
+#[cfg(feature = "tokio-codec")]
+impl<T: tokio_codec::Encoder> Encoder for T {
+    type Item = T::Item;
+    type Error = T::Error;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        tokio_codec::Encoder::encode(self, item, dst)
+    }
+}
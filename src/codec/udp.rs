@@ -0,0 +1,203 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_datagram::AsyncDatagram;
+use bytes::BytesMut;
+use futures::{ready, Sink, Stream};
+
+use super::{Decoder, Encoder};
+use crate::udp::UdpSocket;
+
+/// The largest UDP payload, so that no datagram is truncated on receipt.
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+/// A `Stream` and `Sink` of frames over a `UdpSocket`, each paired with the
+/// address it came from or goes to.
+///
+/// Every datagram received is decoded into at most one frame; bytes the
+/// decoder leaves in the buffer are discarded along with the datagram, and
+/// a datagram that doesn't decode to a frame is skipped. Every frame sent is
+/// encoded into its own datagram, which is sent before the next frame is
+/// accepted.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use bytes::{BufMut, BytesMut};
+/// use futures::prelude::*;
+/// use romio::codec::{Decoder, Encoder, UdpFramed};
+/// use romio::udp::UdpSocket;
+/// use std::io;
+///
+/// /// Treats every datagram as one frame of raw bytes.
+/// struct Raw;
+///
+/// impl Decoder for Raw {
+///     type Item = BytesMut;
+///     type Error = io::Error;
+///
+///     fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+///         let len = src.len();
+///         Ok(Some(src.split_to(len)))
+///     }
+/// }
+///
+/// impl Encoder for Raw {
+///     type Item = BytesMut;
+///     type Error = io::Error;
+///
+///     fn encode(&mut self, item: BytesMut, dst: &mut BytesMut) -> io::Result<()> {
+///         dst.reserve(item.len());
+///         dst.put(item);
+///         Ok(())
+///     }
+/// }
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let socket = UdpSocket::bind(&"127.0.0.1:7878".parse()?)?;
+/// let mut framed = UdpFramed::new(socket, Raw);
+///
+/// // echo every datagram back to its sender
+/// while let Some(res) = framed.next().await {
+///     let (frame, addr) = res?;
+///     framed.send((frame, addr)).await?;
+/// }
+/// # Ok(())}
+/// ```
+pub struct UdpFramed<C> {
+    socket: UdpSocket,
+    codec: C,
+    /// Where datagrams land, zeroed once rather than for every receive.
+    recv_buf: Box<[u8]>,
+    read_buf: BytesMut,
+    write_buf: BytesMut,
+    /// Where the encoded frame in `write_buf` goes.
+    out_addr: Option<SocketAddr>,
+}
+
+impl<C> UdpFramed<C> {
+    /// Frames `socket` with `codec`.
+    pub fn new(socket: UdpSocket, codec: C) -> UdpFramed<C> {
+        UdpFramed {
+            socket,
+            codec,
+            recv_buf: vec![0; MAX_DATAGRAM].into_boxed_slice(),
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+            out_addr: None,
+        }
+    }
+
+    /// Returns a reference to the underlying socket.
+    pub fn get_ref(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Returns a mutable reference to the underlying socket.
+    ///
+    /// Datagrams received from it directly aren't decoded.
+    pub fn get_mut(&mut self) -> &mut UdpSocket {
+        &mut self.socket
+    }
+
+    /// Returns a reference to the codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns a mutable reference to the codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Consumes the `UdpFramed`, returning the socket.
+    ///
+    /// A frame that was encoded but not sent yet is lost.
+    pub fn into_inner(self) -> UdpSocket {
+        self.socket
+    }
+}
+
+impl<C: Decoder + Unpin> Stream for UdpFramed<C> {
+    type Item = Result<(C::Item, SocketAddr), C::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let res = Pin::new(&mut this.socket).poll_recv_from(cx, &mut this.recv_buf);
+            let (n, addr) = match res {
+                Poll::Ready(Ok(received)) => received,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Pending => return Poll::Pending,
+            };
+            this.read_buf.extend_from_slice(&this.recv_buf[..n]);
+            let frame = this.codec.decode(&mut this.read_buf);
+            if let Ok(None) = frame {
+                // The datagram is all there is of the frame, so let the codec
+                // drop what it kept of it, as it would at EOF. Whatever that
+                // makes of the rest is skipped along with the datagram.
+                let _ = this.codec.decode_eof(&mut this.read_buf);
+            }
+            this.read_buf.clear();
+            if let Some(frame) = frame? {
+                return Poll::Ready(Some(Ok((frame, addr))));
+            }
+        }
+    }
+}
+
+impl<C: Encoder + Unpin> Sink<(C::Item, SocketAddr)> for UdpFramed<C> {
+    type Error = C::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: (C::Item, SocketAddr)) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let (frame, addr) = item;
+        this.write_buf.clear();
+        this.codec.encode(frame, &mut this.write_buf)?;
+        this.out_addr = Some(addr);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let addr = match this.out_addr {
+            Some(addr) => addr,
+            None => return Poll::Ready(Ok(())),
+        };
+
+        let n = ready!(Pin::new(&mut this.socket).poll_send_to(cx, &this.write_buf, &addr))?;
+        this.out_addr = None;
+        let len = this.write_buf.len();
+        this.write_buf.clear();
+        if n != len {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to send the entire datagram",
+            )
+            .into()));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for UdpFramed<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpFramed")
+            .field("socket", &self.socket)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
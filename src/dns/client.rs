@@ -0,0 +1,387 @@
+//! Lookups sent straight to name servers, over romio's own sockets.
+
+use super::proto::{self, QType, RCODE_NOERROR, RCODE_NXDOMAIN};
+use super::Config;
+
+use crate::reactor::timer::Delay;
+use crate::tcp::{ConnectFuture, TcpStream};
+use crate::udp::UdpSocket;
+
+use async_datagram::AsyncDatagram;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Future, Poll};
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::time::Instant;
+
+/// The largest response accepted over UDP, per RFC 1035.
+const MAX_UDP_RESPONSE: usize = 512;
+
+/// The record types looked up, in the order their addresses are returned.
+const QTYPES: [QType; 2] = [QType::Aaaa, QType::A];
+
+/// Looks up the `AAAA` and `A` records of a name.
+///
+/// Each try sends both queries to one name server over UDP, and repeats
+/// those whose responses were truncated over TCP. A try that fails or times
+/// out moves on to the next name server, asking only for the record types
+/// that are still unanswered.
+pub(super) struct Lookup {
+    config: Arc<Config>,
+    name: String,
+    answers: Answers,
+    tries: usize,
+    state: State,
+    /// Queries of this try whose responses were truncated.
+    truncated: Vec<Query>,
+    last_error: Option<io::Error>,
+}
+
+/// The answers that arrived so far.
+#[derive(Default)]
+struct Answers {
+    /// The addresses for each of `QTYPES`.
+    addrs: [Option<Vec<IpAddr>>; 2],
+    /// Whether a name server said that the name doesn't exist.
+    nxdomain: bool,
+}
+
+struct Query {
+    id: u16,
+    qtype: QType,
+    msg: Vec<u8>,
+}
+
+enum State {
+    /// About to start the next try.
+    Start,
+    Udp {
+        socket: UdpSocket,
+        server: SocketAddr,
+        /// The queries still waiting for a response.
+        queries: Vec<Query>,
+        sent: usize,
+        deadline: Delay,
+    },
+    Tcp {
+        server: SocketAddr,
+        query: Query,
+        step: TcpStep,
+        deadline: Delay,
+    },
+}
+
+enum TcpStep {
+    Connecting(ConnectFuture),
+    /// Writing the length-prefixed query, then reading the length of the
+    /// response, then the response itself, each into or from `buf`.
+    Exchanging {
+        stream: TcpStream,
+        buf: Vec<u8>,
+        pos: usize,
+        phase: Phase,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Write,
+    ReadLen,
+    ReadBody,
+}
+
+/// What a response means for a lookup.
+enum Outcome {
+    Answered,
+    Truncated,
+}
+
+impl Lookup {
+    pub(super) fn new(config: Arc<Config>, name: &str) -> Lookup {
+        Lookup {
+            config,
+            name: name.to_string(),
+            answers: Answers::default(),
+            tries: 0,
+            state: State::Start,
+            truncated: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    /// Starts a try with the next name server.
+    fn start(&mut self) -> io::Result<State> {
+        let servers = &self.config.nameservers;
+        if self.tries == servers.len() * self.config.attempts {
+            return Err(self.last_error.take().unwrap_or_else(timed_out));
+        }
+        let server = servers[self.tries % servers.len()];
+        self.tries += 1;
+        self.truncated.clear();
+
+        let mut queries = Vec::with_capacity(QTYPES.len());
+        for (addrs, &qtype) in self.answers.addrs.iter().zip(QTYPES.iter()) {
+            if addrs.is_none() {
+                let id = random_id();
+                let msg = proto::encode_query(id, &self.name, qtype)?;
+                queries.push(Query { id, qtype, msg });
+            }
+        }
+
+        let local: IpAddr = match server {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        Ok(State::Udp {
+            socket: UdpSocket::bind(&SocketAddr::new(local, 0))?,
+            server,
+            queries,
+            sent: 0,
+            deadline: self.deadline(),
+        })
+    }
+
+    /// Repeats the next truncated query over TCP, if any are left.
+    fn next_tcp(&mut self, server: SocketAddr) {
+        if let Some(query) = self.truncated.pop() {
+            self.state = State::Tcp {
+                server,
+                query,
+                step: TcpStep::Connecting(TcpStream::connect(&server)),
+                deadline: self.deadline(),
+            };
+        }
+    }
+
+    fn deadline(&self) -> Delay {
+        Delay::new(Instant::now() + self.config.timeout)
+    }
+}
+
+impl Future for Lookup {
+    type Output = io::Result<Vec<IpAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            if let Some(res) = this.answers.result() {
+                return Poll::Ready(res);
+            }
+
+            let err = match &mut this.state {
+                State::Start => {
+                    this.state = this.start()?;
+                    continue;
+                }
+                State::Udp {
+                    socket,
+                    server,
+                    queries,
+                    sent,
+                    deadline,
+                } => {
+                    let res = poll_udp(
+                        cx,
+                        socket,
+                        *server,
+                        queries,
+                        sent,
+                        &mut this.answers,
+                        &mut this.truncated,
+                    );
+                    match res {
+                        Poll::Pending => {
+                            ready!(deadline.poll_elapsed(cx))?;
+                            timed_out()
+                        }
+                        Poll::Ready(Ok(())) => {
+                            let server = *server;
+                            this.next_tcp(server);
+                            continue;
+                        }
+                        Poll::Ready(Err(e)) => e,
+                    }
+                }
+                State::Tcp {
+                    server,
+                    query,
+                    step,
+                    deadline,
+                } => match poll_tcp(cx, query, step, &mut this.answers) {
+                    Poll::Pending => {
+                        ready!(deadline.poll_elapsed(cx))?;
+                        timed_out()
+                    }
+                    Poll::Ready(Ok(())) => {
+                        let server = *server;
+                        this.next_tcp(server);
+                        continue;
+                    }
+                    Poll::Ready(Err(e)) => e,
+                },
+            };
+
+            // The try failed; move on to the next name server.
+            this.last_error = Some(err);
+            this.state = State::Start;
+        }
+    }
+}
+
+/// Sends the queries of a try over UDP and waits for their responses.
+fn poll_udp(
+    cx: &mut Context<'_>,
+    socket: &mut UdpSocket,
+    server: SocketAddr,
+    queries: &mut Vec<Query>,
+    sent: &mut usize,
+    answers: &mut Answers,
+    truncated: &mut Vec<Query>,
+) -> Poll<io::Result<()>> {
+    while *sent < queries.len() {
+        ready!(Pin::new(&mut *socket).poll_send_to(cx, &queries[*sent].msg, &server))?;
+        *sent += 1;
+    }
+
+    let mut buf = [0; MAX_UDP_RESPONSE];
+    while !queries.is_empty() {
+        let (n, from) = ready!(Pin::new(&mut *socket).poll_recv_from(cx, &mut buf))?;
+        let msg = &buf[..n];
+        let id = proto::message_id(msg);
+        let index = match queries.iter().position(|q| Some(q.id) == id) {
+            Some(index) if from == server => index,
+            // Stray or forged datagrams are ignored.
+            _ => continue,
+        };
+        match answers.record(&queries[index], msg) {
+            Ok(Outcome::Answered) => drop(queries.remove(index)),
+            Ok(Outcome::Truncated) => truncated.push(queries.remove(index)),
+            // A mangled response; keep waiting for an intact one.
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidData => continue,
+            Err(e) => return Poll::Ready(Err(e)),
+        }
+        *sent -= 1;
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// Sends a query over TCP and reads its response.
+fn poll_tcp(
+    cx: &mut Context<'_>,
+    query: &Query,
+    step: &mut TcpStep,
+    answers: &mut Answers,
+) -> Poll<io::Result<()>> {
+    loop {
+        let (stream, buf, pos, phase) = match step {
+            TcpStep::Connecting(connect) => {
+                let stream = ready!(Pin::new(connect).poll(cx))?;
+                let mut buf = (query.msg.len() as u16).to_be_bytes().to_vec();
+                buf.extend_from_slice(&query.msg);
+                *step = TcpStep::Exchanging {
+                    stream,
+                    buf,
+                    pos: 0,
+                    phase: Phase::Write,
+                };
+                continue;
+            }
+            TcpStep::Exchanging {
+                stream,
+                buf,
+                pos,
+                phase,
+            } => (stream, buf, pos, phase),
+        };
+
+        while *pos < buf.len() {
+            let n = match phase {
+                Phase::Write => ready!(Pin::new(&mut *stream).poll_write(cx, &buf[*pos..]))?,
+                _ => ready!(Pin::new(&mut *stream).poll_read(cx, &mut buf[*pos..]))?,
+            };
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            *pos += n;
+        }
+
+        *pos = 0;
+        match phase {
+            Phase::Write => {
+                *buf = vec![0; 2];
+                *phase = Phase::ReadLen;
+            }
+            Phase::ReadLen => {
+                *buf = vec![0; u16::from_be_bytes([buf[0], buf[1]]) as usize];
+                *phase = Phase::ReadBody;
+            }
+            Phase::ReadBody => {
+                let msg = mem::take(buf);
+                // Over TCP the whole response fits, so it is never truncated.
+                answers.record(query, &msg)?;
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl Answers {
+    /// Records the response `msg` to `query`.
+    fn record(&mut self, query: &Query, msg: &[u8]) -> io::Result<Outcome> {
+        let res = proto::parse_response(msg, &query.msg, query.qtype)?;
+        let index = QTYPES.iter().position(|&t| t == query.qtype).unwrap();
+        match res.rcode {
+            _ if res.truncated => Ok(Outcome::Truncated),
+            RCODE_NOERROR => {
+                self.addrs[index] = Some(res.addrs);
+                Ok(Outcome::Answered)
+            }
+            RCODE_NXDOMAIN => {
+                self.nxdomain = true;
+                self.addrs[index] = Some(Vec::new());
+                Ok(Outcome::Answered)
+            }
+            rcode => Err(io::Error::other(format!(
+                "name server failed with response code {}",
+                rcode
+            ))),
+        }
+    }
+
+    /// Returns the addresses, once all record types have been answered.
+    fn result(&mut self) -> Option<io::Result<Vec<IpAddr>>> {
+        if self.addrs.iter().any(Option::is_none) {
+            return None;
+        }
+        let addrs: Vec<_> = self
+            .addrs
+            .iter_mut()
+            .flat_map(|addrs| addrs.take().unwrap())
+            .collect();
+        if addrs.is_empty() {
+            let msg = if self.nxdomain {
+                "no such host"
+            } else {
+                "no addresses for host"
+            };
+            return Some(Err(io::Error::new(io::ErrorKind::NotFound, msg)));
+        }
+        Some(Ok(addrs))
+    }
+}
+
+/// Returns an unpredictable query ID, which makes forging responses harder.
+fn random_id() -> u16 {
+    RandomState::new().build_hasher().finish() as u16
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "DNS query timed out")
+}
@@ -0,0 +1,359 @@
+//! Asynchronous host name lookups.
+//!
+//! [`lookup_host`] turns a `"host:port"` string into socket addresses without
+//! blocking the task that awaits it, which makes it suitable for resolving
+//! the names a client connects to. Lookups go through a [`Resolver`], which
+//! has one of two backends:
+//!
+//! * The system resolver, used by [`lookup_host`] and [`Resolver::system`].
+//!   It calls `getaddrinfo`, like `std::net::ToSocketAddrs` does, on a small
+//!   pool of helper threads, so it honors `/etc/hosts`, `nsswitch.conf` and
+//!   everything else the platform configures.
+//! * Name servers, set up with a [`ResolverBuilder`]. Queries for `AAAA`
+//!   and `A` records are sent straight to them with romio's own UDP sockets,
+//!   falling back to TCP for responses that don't fit into a datagram. This
+//!   needs no threads, but knows nothing of the hosts file.
+//!
+//! [`lookup_host`]: fn.lookup_host.html
+//! [`Resolver`]: struct.Resolver.html
+//! [`Resolver::system`]: struct.Resolver.html#method.system
+//! [`ResolverBuilder`]: struct.ResolverBuilder.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use romio::dns::{self, Resolver};
+//! use std::time::Duration;
+//!
+//! # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+//! for addr in dns::lookup_host("example.com:80").await? {
+//!     println!("{}", addr);
+//! }
+//!
+//! let resolver = Resolver::builder()
+//!     .nameserver("9.9.9.9:53".parse()?)
+//!     .timeout(Duration::from_secs(2))
+//!     .build()?;
+//! let addrs = resolver.lookup_host("example.com:443").await?;
+//! # drop(addrs);
+//! # Ok(())}
+//! ```
+
+mod client;
+mod proto;
+pub(crate) mod system;
+
+use futures::channel::oneshot;
+use futures::{ready, Future, Poll};
+
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+use std::time::Duration;
+
+/// The port name servers listen on.
+#[cfg(unix)]
+const DNS_PORT: u16 = 53;
+
+/// Looks up the addresses of `host` with the system resolver.
+///
+/// `host` is a host name or an IP address, followed by a port, such as
+/// `"example.com:80"` or `"[::1]:8080"`. This is a shorthand for
+/// `Resolver::system().lookup_host(host)`.
+///
+/// # Errors
+///
+/// Fails if `host` doesn't have a port, or the name can't be resolved.
+pub fn lookup_host(host: &str) -> LookupHost {
+    Resolver::system().lookup_host(host)
+}
+
+/// Resolves host names to addresses, with a configurable backend.
+///
+/// Resolvers are cheap to clone, and clones share their configuration.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    backend: Backend,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    System,
+    NameServers(Arc<Config>),
+}
+
+/// How lookups are sent to name servers.
+#[derive(Debug)]
+struct Config {
+    nameservers: Vec<SocketAddr>,
+    /// How long to wait for each name server.
+    timeout: Duration,
+    /// How many times to go through all of the name servers.
+    attempts: usize,
+}
+
+/// A builder for a [`Resolver`] that queries name servers directly.
+///
+/// [`Resolver`]: struct.Resolver.html
+#[derive(Debug, Clone)]
+pub struct ResolverBuilder {
+    nameservers: Vec<SocketAddr>,
+    timeout: Duration,
+    attempts: usize,
+}
+
+/// The future returned by `lookup_host`, which resolves to the addresses of
+/// a host.
+///
+/// IPv6 addresses come before IPv4 ones when the name-server backend is
+/// used; the system resolver returns them in the order it prefers.
+#[must_use = "futures do nothing unless polled"]
+pub struct LookupHost {
+    inner: Inner,
+}
+
+enum Inner {
+    Done(Option<io::Result<Vec<SocketAddr>>>),
+    System(oneshot::Receiver<io::Result<Vec<SocketAddr>>>),
+    NameServers(Box<client::Lookup>, u16),
+}
+
+// ===== impl Resolver =====
+
+impl Resolver {
+    /// Returns a resolver that uses the system resolver.
+    pub fn system() -> Resolver {
+        Resolver {
+            backend: Backend::System,
+        }
+    }
+
+    /// Returns a builder for a resolver that queries name servers itself,
+    /// with no name servers, a timeout of five seconds and two attempts.
+    pub fn builder() -> ResolverBuilder {
+        ResolverBuilder {
+            nameservers: Vec::new(),
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+
+    /// Looks up the addresses of `host`.
+    ///
+    /// `host` is a host name or an IP address, followed by a port, such as
+    /// `"example.com:80"` or `"[::1]:8080"`. IP addresses resolve right away,
+    /// without involving the backend.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if `host` doesn't have a valid port, and
+    /// otherwise if the name can't be resolved. With the name-server backend
+    /// a name that doesn't exist, or has no addresses, fails with `NotFound`,
+    /// and one whose name servers all failed to respond in time with
+    /// `TimedOut`.
+    pub fn lookup_host(&self, host: &str) -> LookupHost {
+        let done = |res| LookupHost {
+            inner: Inner::Done(Some(res)),
+        };
+
+        if let Ok(addr) = host.parse::<SocketAddr>() {
+            return done(Ok(vec![addr]));
+        }
+        let (name, port) = match split_port(host) {
+            Ok(parts) => parts,
+            Err(e) => return done(Err(e)),
+        };
+
+        let inner = match &self.backend {
+            Backend::System => match system::resolve((name.to_string(), port)) {
+                Ok(rx) => Inner::System(rx),
+                Err(e) => return done(Err(e)),
+            },
+            Backend::NameServers(config) => {
+                Inner::NameServers(Box::new(client::Lookup::new(config.clone(), name)), port)
+            }
+        };
+        LookupHost { inner }
+    }
+}
+
+/// Splits `host` into the name and the port.
+fn split_port(host: &str) -> io::Result<(&str, u16)> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let (name, port) = match host.rfind(':') {
+        Some(i) => (&host[..i], &host[i + 1..]),
+        None => return Err(invalid("missing port")),
+    };
+    let port = port.parse().map_err(|_| invalid("invalid port value"))?;
+    if name.parse::<IpAddr>().is_ok() {
+        // An IPv6 address without brackets, which leaves the port ambiguous.
+        return Err(invalid("invalid socket address"));
+    }
+    Ok((name, port))
+}
+
+// ===== impl ResolverBuilder =====
+
+impl ResolverBuilder {
+    /// Returns a builder with the name servers and options of
+    /// `/etc/resolv.conf`.
+    ///
+    /// The `nameserver` lines are read, along with the `timeout` and
+    /// `attempts` options; everything else is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be read.
+    #[cfg(unix)]
+    pub fn from_resolv_conf() -> io::Result<ResolverBuilder> {
+        let conf = std::fs::read_to_string("/etc/resolv.conf")?;
+        Ok(Resolver::builder().resolv_conf(&conf))
+    }
+
+    #[cfg(unix)]
+    fn resolv_conf(mut self, conf: &str) -> ResolverBuilder {
+        for line in conf.lines() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("nameserver") => {
+                    // Scoped IPv6 addresses don't parse, and are skipped.
+                    if let Some(Ok(ip)) = words.next().map(str::parse::<IpAddr>) {
+                        self.nameservers.push(SocketAddr::new(ip, DNS_PORT));
+                    }
+                }
+                Some("options") => {
+                    for option in words {
+                        let mut parts = option.splitn(2, ':');
+                        let (key, value) = (parts.next(), parts.next().map(str::parse));
+                        match (key, value) {
+                            (Some("timeout"), Some(Ok(n))) if n > 0 => {
+                                self.timeout = Duration::from_secs(n as u64)
+                            }
+                            (Some("attempts"), Some(Ok(n))) if n > 0 => self.attempts = n,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Adds a name server to query, after those that were already added.
+    ///
+    /// Each lookup asks the name servers in turn, moving on to the next one
+    /// when one fails or doesn't respond in time.
+    pub fn nameserver(mut self, addr: SocketAddr) -> ResolverBuilder {
+        self.nameservers.push(addr);
+        self
+    }
+
+    /// Sets how long to wait for a name server to respond before moving on.
+    pub fn timeout(mut self, timeout: Duration) -> ResolverBuilder {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many times a lookup goes through all of the name servers
+    /// before it gives up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn attempts(mut self, n: usize) -> ResolverBuilder {
+        assert!(n > 0, "a resolver needs at least one attempt");
+        self.attempts = n;
+        self
+    }
+
+    /// Creates the resolver.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` if no name servers were added.
+    pub fn build(&self) -> io::Result<Resolver> {
+        if self.nameservers.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a resolver needs at least one name server",
+            ));
+        }
+        Ok(Resolver {
+            backend: Backend::NameServers(Arc::new(Config {
+                nameservers: self.nameservers.clone(),
+                timeout: self.timeout,
+                attempts: self.attempts,
+            })),
+        })
+    }
+}
+
+// ===== impl LookupHost =====
+
+impl Future for LookupHost {
+    type Output = io::Result<Vec<SocketAddr>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.inner {
+            Inner::Done(res) => {
+                Poll::Ready(res.take().expect("polled LookupHost after completion"))
+            }
+            Inner::System(rx) => {
+                Poll::Ready(ready!(Pin::new(rx).poll(cx)).expect("resolver thread panicked"))
+            }
+            Inner::NameServers(lookup, port) => {
+                let ips = ready!(Pin::new(lookup).poll(cx))?;
+                let port = *port;
+                Poll::Ready(Ok(ips
+                    .into_iter()
+                    .map(|ip| SocketAddr::new(ip, port))
+                    .collect()))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for LookupHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LookupHost").finish()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn splits_port() {
+        assert_eq!(split_port("example.com:80").unwrap(), ("example.com", 80));
+        for host in &["example.com", "example.com:", "example.com:http", "::1:80"] {
+            let err = split_port(host).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", host);
+        }
+    }
+
+    #[test]
+    fn reads_resolv_conf() {
+        let conf = "# comment\n\
+                    search example.com\n\
+                    nameserver 10.0.0.1\n\
+                    nameserver fe80::1%eth0\n\
+                    nameserver ::1\n\
+                    options ndots:2 timeout:3 attempts:4\n";
+        let builder = Resolver::builder().resolv_conf(conf);
+        assert_eq!(
+            builder.nameservers,
+            vec![
+                "10.0.0.1:53".parse::<SocketAddr>().unwrap(),
+                "[::1]:53".parse().unwrap(),
+            ]
+        );
+        assert_eq!(builder.timeout, Duration::from_secs(3));
+        assert_eq!(builder.attempts, 4);
+    }
+}
@@ -0,0 +1,298 @@
+//! The parts of the DNS wire format (RFC 1035) needed to look up addresses.
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The length of a message header.
+const HEADER_LEN: usize = 12;
+
+/// The class of Internet records.
+const CLASS_IN: u16 = 1;
+
+/// Set in the flags of a response.
+const FLAG_QR: u16 = 0x8000;
+/// Set in the flags of a response that didn't fit into a UDP datagram.
+const FLAG_TC: u16 = 0x0200;
+/// Asks the server to resolve the name recursively.
+const FLAG_RD: u16 = 0x0100;
+
+/// The response code of a successful query.
+pub(crate) const RCODE_NOERROR: u8 = 0;
+/// The response code for names that don't exist.
+pub(crate) const RCODE_NXDOMAIN: u8 = 3;
+
+/// The types of address records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum QType {
+    A,
+    Aaaa,
+}
+
+impl QType {
+    fn code(self) -> u16 {
+        match self {
+            QType::A => 1,
+            QType::Aaaa => 28,
+        }
+    }
+}
+
+/// The part of a response a lookup needs.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Response {
+    pub(crate) truncated: bool,
+    pub(crate) rcode: u8,
+    /// The addresses of the answer section that match the type queried.
+    pub(crate) addrs: Vec<IpAddr>,
+}
+
+/// Builds a recursive query for records of type `qtype` for `name`.
+///
+/// # Errors
+///
+/// Fails with `InvalidInput` if `name` isn't a valid domain name.
+pub(crate) fn encode_query(id: u16, name: &str, qtype: QType) -> io::Result<Vec<u8>> {
+    let mut msg = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    msg.extend_from_slice(&id.to_be_bytes());
+    msg.extend_from_slice(&FLAG_RD.to_be_bytes());
+    // One question, no answer, authority or additional records.
+    msg.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() > 253 {
+        return Err(invalid_name());
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid_name());
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+
+    msg.extend_from_slice(&qtype.code().to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(msg)
+}
+
+/// Returns the ID of a message, which tells which query a response is for.
+pub(crate) fn message_id(msg: &[u8]) -> Option<u16> {
+    if msg.len() < 2 {
+        return None;
+    }
+    Some(u16::from_be_bytes([msg[0], msg[1]]))
+}
+
+/// Parses a response to `query`, a query for records of type `qtype` built
+/// by `encode_query`.
+///
+/// # Errors
+///
+/// Fails with `InvalidData` if `msg` isn't a well-formed response, or if its
+/// question isn't the one that `query` asked. Names are compared ignoring
+/// ASCII case, since some servers echo them with the case changed.
+pub(crate) fn parse_response(msg: &[u8], query: &[u8], qtype: QType) -> io::Result<Response> {
+    let mut r = Reader { msg, pos: 0 };
+    let _id = r.u16()?;
+    let flags = r.u16()?;
+    if flags & FLAG_QR == 0 {
+        return Err(malformed());
+    }
+    let qdcount = r.u16()?;
+    let ancount = r.u16()?;
+    r.skip(4)?;
+
+    if qdcount != 1 {
+        return Err(mismatched());
+    }
+    let start = r.pos;
+    r.skip_name()?;
+    r.skip(4)?;
+    // The name comes first, so it can't be compressed, and the bytes of the
+    // type, the class and the label lengths are never ASCII letters.
+    if !msg[start..r.pos].eq_ignore_ascii_case(&query[HEADER_LEN..]) {
+        return Err(mismatched());
+    }
+
+    let mut addrs = Vec::new();
+    for _ in 0..ancount {
+        r.skip_name()?;
+        let rtype = r.u16()?;
+        let class = r.u16()?;
+        r.skip(4)?;
+        let rdlength = r.u16()? as usize;
+        let rdata = r.bytes(rdlength)?;
+        if rtype != qtype.code() || class != CLASS_IN {
+            continue;
+        }
+        match (qtype, rdata.len()) {
+            (QType::A, 4) => {
+                let octets = [rdata[0], rdata[1], rdata[2], rdata[3]];
+                addrs.push(IpAddr::V4(Ipv4Addr::from(octets)));
+            }
+            (QType::Aaaa, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(rdata);
+                addrs.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => return Err(malformed()),
+        }
+    }
+
+    Ok(Response {
+        truncated: flags & FLAG_TC != 0,
+        rcode: (flags & 0xf) as u8,
+        addrs,
+    })
+}
+
+struct Reader<'a> {
+    msg: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.msg.len() - self.pos < n {
+            return Err(malformed());
+        }
+        let bytes = &self.msg[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: usize) -> io::Result<()> {
+        self.bytes(n).map(drop)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Skips a possibly compressed name.
+    fn skip_name(&mut self) -> io::Result<()> {
+        loop {
+            let len = self.bytes(1)?[0];
+            match len & 0xc0 {
+                // A name ends with the root label, or with a pointer to the
+                // rest of it elsewhere in the message.
+                0x00 if len == 0 => return Ok(()),
+                0x00 => self.skip(len as usize)?,
+                0xc0 => return self.skip(1),
+                _ => return Err(malformed()),
+            }
+        }
+    }
+}
+
+fn invalid_name() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "invalid domain name")
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed DNS response")
+}
+
+fn mismatched() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "DNS response doesn't match the query",
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// An `A` query for `example.com`.
+    fn query() -> Vec<u8> {
+        encode_query(0x1234, "example.com", QType::A).unwrap()
+    }
+
+    /// A response to `query`, with a `CNAME` and an `A` record whose names
+    /// are compressed.
+    fn response(flags: u16) -> Vec<u8> {
+        let mut msg = query();
+        msg[2..4].copy_from_slice(&(flags | FLAG_QR).to_be_bytes());
+        msg[7] = 2;
+        // www.example.com CNAME example.com
+        msg.extend_from_slice(&[3, b'w', b'w', b'w', 0xc0, 12]);
+        msg.extend_from_slice(&[0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 12]);
+        // example.com A 93.184.216.34
+        msg.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+        msg.extend_from_slice(&[93, 184, 216, 34]);
+        msg
+    }
+
+    #[test]
+    fn encodes_query() {
+        let msg = encode_query(0x1234, "example.com.", QType::Aaaa).unwrap();
+        let mut expected = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        expected.extend_from_slice(b"\x07example\x03com\x00");
+        expected.extend_from_slice(&[0, 28, 0, 1]);
+        assert_eq!(msg, expected);
+        assert_eq!(message_id(&msg), Some(0x1234));
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        for name in &["", ".", "a..b", &"a".repeat(64), &"a.".repeat(128)] {
+            let err = encode_query(1, name, QType::A).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn parses_answers() {
+        let res = parse_response(&response(0), &query(), QType::A).unwrap();
+        assert_eq!(
+            res,
+            Response {
+                truncated: false,
+                rcode: RCODE_NOERROR,
+                addrs: vec!["93.184.216.34".parse().unwrap()],
+            }
+        );
+
+        // The name may be echoed with another case.
+        let mut msg = response(0);
+        msg[13..20].copy_from_slice(b"EXAMPLE");
+        assert_eq!(parse_response(&msg, &query(), QType::A).unwrap(), res);
+    }
+
+    #[test]
+    fn rejects_responses_to_other_questions() {
+        let msg = response(0);
+        let other_name = encode_query(0x1234, "example.org", QType::A).unwrap();
+        let other_type = encode_query(0x1234, "example.com", QType::Aaaa).unwrap();
+        for query in &[other_name, other_type] {
+            let err = parse_response(&msg, query, QType::A).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        let mut msg = msg;
+        msg[5] = 0;
+        assert!(parse_response(&msg, &query(), QType::A).is_err());
+    }
+
+    #[test]
+    fn parses_flags() {
+        let res = parse_response(&response(FLAG_TC | 3), &query(), QType::A).unwrap();
+        assert!(res.truncated);
+        assert_eq!(res.rcode, RCODE_NXDOMAIN);
+    }
+
+    #[test]
+    fn rejects_malformed_responses() {
+        let msg = response(0);
+        for len in 0..msg.len() {
+            let err = parse_response(&msg[..len], &query(), QType::A).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        // A query isn't a response.
+        assert!(parse_response(&query(), &query(), QType::A).is_err());
+    }
+}
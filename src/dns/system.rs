@@ -0,0 +1,24 @@
+//! Lookups through the system resolver, on a pool of helper threads.
+
+use futures::channel::oneshot;
+use lazy_static::lazy_static;
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use crate::blocking::Pool;
+
+/// The most threads blocked in `getaddrinfo` at once; further lookups queue.
+const MAX_THREADS: usize = 4;
+
+lazy_static! {
+    static ref POOL: Pool = Pool::new("romio-resolve", MAX_THREADS);
+}
+
+/// Resolves `host` with `std::net::ToSocketAddrs`, on a pool thread.
+pub(crate) fn resolve<A>(host: A) -> io::Result<oneshot::Receiver<io::Result<Vec<SocketAddr>>>>
+where
+    A: ToSocketAddrs + Send + 'static,
+{
+    POOL.run(move || host.to_socket_addrs().map(Iterator::collect))
+}
@@ -0,0 +1,54 @@
+//! Binding a TCP listener and a UDP socket to the same port.
+
+use std::io;
+use std::net::SocketAddr;
+
+use crate::tcp::TcpListener;
+use crate::udp::UdpSocket;
+
+/// How many ephemeral ports to try before giving up.
+const EPHEMERAL_ATTEMPTS: usize = 16;
+
+/// Binds a TCP listener and a UDP socket to the same address and port.
+///
+/// This is the setup of services that speak both transports on one port,
+/// such as DNS. TCP and UDP ports are separate namespaces, so no sharing
+/// option like `SO_REUSEPORT` is involved. The TCP listener is bound first,
+/// with `SO_REUSEADDR` on Unix, so connections of a previous instance that
+/// linger in `TIME_WAIT` don't make a restart fail with `EADDRINUSE`. The UDP
+/// socket then takes the same port.
+///
+/// With a port of 0, the OS picks a port for the listener. Should that port
+/// already be used by some other UDP socket, a new one is picked, a few times
+/// over. With a fixed port, binding fails if either transport can't have it,
+/// and nothing stays bound.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "0.0.0.0:53".parse()?;
+/// let (listener, socket) = romio::bind_dual_protocol(&addr)?;
+/// assert_eq!(listener.local_addr()?.port(), socket.local_addr()?.port());
+/// # Ok(())}
+/// ```
+pub fn bind_dual_protocol(addr: &SocketAddr) -> io::Result<(TcpListener, UdpSocket)> {
+    let attempts = if addr.port() == 0 {
+        EPHEMERAL_ATTEMPTS
+    } else {
+        1
+    };
+
+    for _ in 1..attempts {
+        let listener = TcpListener::bind(addr)?;
+        match UdpSocket::bind(&listener.local_addr()?) {
+            Ok(socket) => return Ok((listener, socket)),
+            Err(ref e) if e.kind() == io::ErrorKind::AddrInUse => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let listener = TcpListener::bind(addr)?;
+    let socket = UdpSocket::bind(&listener.local_addr()?)?;
+    Ok((listener, socket))
+}
@@ -0,0 +1,346 @@
+use std::cmp;
+use std::fmt;
+use std::fs::{self, DirEntry, Metadata};
+use std::io::{self, Read as _, Seek, SeekFrom, Write as _};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Context;
+
+use futures::channel::oneshot;
+use futures::{ready, Future, Poll};
+use lazy_static::lazy_static;
+
+use crate::blocking::Pool;
+
+/// The most threads blocked on the filesystem at once; further operations
+/// queue.
+const MAX_THREADS: usize = 16;
+
+lazy_static! {
+    static ref POOL: Pool = Pool::new("romio-fs", MAX_THREADS);
+}
+
+/// An open file.
+///
+/// Each operation runs on a pool of helper threads, and its future resolves
+/// once it is done, so the task awaiting it doesn't block on the disk. An
+/// operation starts when its future is first polled, and then runs to
+/// completion even if the future is dropped, e.g. by a timeout. The next
+/// operation on the file waits for it: the bytes a dropped read fetched are
+/// returned by the next read, and the error of a dropped operation by the
+/// next operation.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use romio::fs::File;
+///
+/// # async fn copy() -> std::io::Result<()> {
+/// let mut src = File::open("index.html").await?;
+/// let mut dst = File::create("index.html.bak").await?;
+///
+/// let mut buf = vec![0; 64 * 1024];
+/// loop {
+///     let n = src.read(&mut buf).await?;
+///     if n == 0 {
+///         break;
+///     }
+///     dst.write_all(&buf[..n]).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct File {
+    inner: Arc<fs::File>,
+    /// The read or write running on the pool, if one is.
+    busy: Option<Blocking<Done>>,
+    /// Bytes that were read but not returned yet, which the cursor of
+    /// `inner` is past.
+    unread: Vec<u8>,
+    /// The error of an operation that hasn't been returned yet.
+    err: Option<io::Error>,
+}
+
+/// What a read or write leaves behind.
+#[derive(Debug)]
+enum Done {
+    Read(Vec<u8>),
+    Write,
+}
+
+impl File {
+    /// Opens the file at `path` for reading.
+    pub fn open<P: AsRef<Path>>(path: P) -> Open {
+        let path = path.as_ref().to_path_buf();
+        Open {
+            job: Blocking::new(move || fs::File::open(path).map(File::from_std)),
+        }
+    }
+
+    /// Opens the file at `path` for writing, creating it if it doesn't exist
+    /// and truncating it if it does.
+    pub fn create<P: AsRef<Path>>(path: P) -> Open {
+        let path = path.as_ref().to_path_buf();
+        Open {
+            job: Blocking::new(move || fs::File::create(path).map(File::from_std)),
+        }
+    }
+
+    /// Wraps a file opened with `std::fs`.
+    pub fn from_std(file: fs::File) -> File {
+        File {
+            inner: Arc::new(file),
+            busy: None,
+            unread: Vec::new(),
+            err: None,
+        }
+    }
+
+    /// Reads some bytes from the file into `buf`, resolving to how many
+    /// were read, which is 0 at the end of the file.
+    pub fn read<'a>(&'a mut self, buf: &'a mut [u8]) -> Read<'a> {
+        Read {
+            file: self,
+            buf,
+            started: false,
+        }
+    }
+
+    /// Writes all of `buf` to the file.
+    ///
+    /// `buf` is copied once the future is first polled, so a write that
+    /// runs on after its future is dropped doesn't borrow it.
+    pub fn write_all<'a>(&'a mut self, buf: &'a [u8]) -> WriteAll<'a> {
+        WriteAll {
+            file: self,
+            buf,
+            started: false,
+        }
+    }
+
+    /// Queries metadata about the file.
+    pub fn metadata(&self) -> MetadataFuture {
+        let file = self.inner.clone();
+        MetadataFuture {
+            job: Blocking::new(move || file.metadata()),
+        }
+    }
+
+    /// Waits for the running read or write, if there is one, keeping what
+    /// it leaves behind.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(job) = &mut self.busy {
+            let done = ready!(job.poll(cx));
+            self.busy = None;
+            match done {
+                Ok(Done::Read(data)) => self.unread = data,
+                Ok(Done::Write) => {}
+                Err(e) => self.err = Some(e),
+            }
+        }
+        Poll::Ready(())
+    }
+}
+
+/// Queries metadata about the file or directory at `path`, following
+/// symbolic links.
+pub fn metadata<P: AsRef<Path>>(path: P) -> MetadataFuture {
+    let path = path.as_ref().to_path_buf();
+    MetadataFuture {
+        job: Blocking::new(move || fs::metadata(path)),
+    }
+}
+
+/// Lists the entries of the directory at `path`.
+///
+/// The future resolves to all of them at once, in no particular order,
+/// without `.` and `..`. Querying an entry's metadata or file type may block
+/// again, so use [`metadata`] for those.
+///
+/// [`metadata`]: fn.metadata.html
+pub fn read_dir<P: AsRef<Path>>(path: P) -> ReadDir {
+    let path = path.as_ref().to_path_buf();
+    ReadDir {
+        job: Blocking::new(move || fs::read_dir(path)?.collect()),
+    }
+}
+
+type Job<T> = Box<dyn FnOnce() -> io::Result<T> + Send>;
+
+/// An operation on the pool, which starts once it is first polled; it fails
+/// right away if no thread could be started for it.
+enum Blocking<T> {
+    Idle(Option<Job<T>>),
+    Failed(Option<io::Error>),
+    Running(oneshot::Receiver<io::Result<T>>),
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    fn new<F>(f: F) -> Blocking<T>
+    where
+        F: FnOnce() -> io::Result<T> + Send + 'static,
+    {
+        Blocking::Idle(Some(Box::new(f)))
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<T>> {
+        if let Blocking::Idle(f) = self {
+            let f = f.take().expect("polled a future after completion");
+            *self = match POOL.run(f) {
+                Ok(rx) => Blocking::Running(rx),
+                Err(e) => Blocking::Failed(Some(e)),
+            };
+        }
+        match self {
+            Blocking::Idle(_) => unreachable!(),
+            Blocking::Failed(e) => {
+                Poll::Ready(Err(e.take().expect("polled a future after completion")))
+            }
+            Blocking::Running(rx) => {
+                Poll::Ready(ready!(Pin::new(rx).poll(cx)).expect("filesystem thread panicked"))
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Blocking<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            Blocking::Idle(_) => "idle",
+            Blocking::Failed(_) => "failed",
+            Blocking::Running(_) => "running",
+        };
+        f.debug_tuple("Blocking").field(&state).finish()
+    }
+}
+
+/// The future returned by `File::open` and `File::create`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Open {
+    job: Blocking<File>,
+}
+
+impl Future for Open {
+    type Output = io::Result<File>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.job.poll(cx)
+    }
+}
+
+/// The future returned by `File::read`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Read<'a> {
+    file: &'a mut File,
+    buf: &'a mut [u8],
+    /// Whether this future started a read, whose result it returns.
+    started: bool,
+}
+
+impl Future for Read<'_> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let file = &mut *this.file;
+        loop {
+            ready!(file.poll_idle(cx));
+            if let Some(e) = file.err.take() {
+                return Poll::Ready(Err(e));
+            }
+            if this.started || !file.unread.is_empty() {
+                let n = cmp::min(this.buf.len(), file.unread.len());
+                this.buf[..n].copy_from_slice(&file.unread[..n]);
+                file.unread.drain(..n);
+                return Poll::Ready(Ok(n));
+            }
+
+            let inner = file.inner.clone();
+            let len = this.buf.len();
+            file.busy = Some(Blocking::new(move || {
+                let mut data = vec![0; len];
+                let n = (&*inner).read(&mut data)?;
+                data.truncate(n);
+                Ok(Done::Read(data))
+            }));
+            this.started = true;
+        }
+    }
+}
+
+/// The future returned by `File::write_all`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WriteAll<'a> {
+    file: &'a mut File,
+    buf: &'a [u8],
+    /// Whether this future started a write, whose result it returns.
+    started: bool,
+}
+
+impl Future for WriteAll<'_> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let file = &mut *this.file;
+        loop {
+            ready!(file.poll_idle(cx));
+            if let Some(e) = file.err.take() {
+                return Poll::Ready(Err(e));
+            }
+            if this.started {
+                return Poll::Ready(Ok(()));
+            }
+
+            let inner = file.inner.clone();
+            let data = this.buf.to_vec();
+            // Write where the bytes that weren't returned yet start.
+            let unread = file.unread.len() as i64;
+            file.unread.clear();
+            file.busy = Some(Blocking::new(move || {
+                if unread > 0 {
+                    (&*inner).seek(SeekFrom::Current(-unread))?;
+                }
+                (&*inner).write_all(&data)?;
+                Ok(Done::Write)
+            }));
+            this.started = true;
+        }
+    }
+}
+
+/// The future returned by `File::metadata` and `fs::metadata`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct MetadataFuture {
+    job: Blocking<Metadata>,
+}
+
+impl Future for MetadataFuture {
+    type Output = io::Result<Metadata>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.job.poll(cx)
+    }
+}
+
+/// The future returned by `fs::read_dir`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ReadDir {
+    job: Blocking<Vec<DirEntry>>,
+}
+
+impl Future for ReadDir {
+    type Output = io::Result<Vec<DirEntry>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.job.poll(cx)
+    }
+}
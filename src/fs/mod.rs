@@ -0,0 +1,23 @@
+//! Filesystem operations, and filesystem event notifications.
+//!
+//! Disks can't be polled for readiness the way sockets can, so [`File`]
+//! operations, [`metadata`] and [`read_dir`] run on a pool of helper threads
+//! and resolve once they are done. A server can then read the files it
+//! serves without blocking the threads that run its tasks.
+//!
+//! A [`Watcher`] reports changes to files and directories as a stream of
+//! events, driven by the reactor rather than by a thread blocking on them.
+//! It is built on inotify, and only available on Linux.
+//!
+//! [`File`]: struct.File.html
+//! [`metadata`]: fn.metadata.html
+//! [`read_dir`]: fn.read_dir.html
+//! [`Watcher`]: struct.Watcher.html
+
+mod file;
+#[cfg(target_os = "linux")]
+mod watcher;
+
+pub use self::file::{metadata, read_dir, File, MetadataFuture, Open, Read, ReadDir, WriteAll};
+#[cfg(target_os = "linux")]
+pub use self::watcher::{Event, EventKind, Watcher};
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::Context;
+
+use futures::io::AsyncRead;
+use futures::{ready, Poll, Stream};
+use libc::c_int;
+
+use crate::raw::AsyncFd;
+use crate::sys;
+
+/// The events a watch asks for.
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB
+    | libc::IN_DELETE
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_MOVE_SELF;
+
+/// The size of `struct inotify_event`, without the name that follows it.
+const EVENT_HEADER_LEN: usize = 16;
+
+/// Room for many events, and at least one with the longest name.
+const BUF_SIZE: usize = 4096;
+
+/// Watches files and directories for changes.
+///
+/// Each path given to [`watch`] is watched on its own: a file for changes to
+/// its contents and metadata, and for being removed or moved away, and a
+/// directory for the same, as well as for entries being created, changed,
+/// removed or moved within it. Subdirectories aren't watched unless they are
+/// added too.
+///
+/// The watcher is a `Stream` of the events, which never ends. An error is
+/// reported if reading from inotify fails, and the stream can be polled
+/// again after it.
+///
+/// This is only available on Linux.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::StreamExt;
+/// use romio::fs::{EventKind, Watcher};
+///
+/// # async fn reload() -> std::io::Result<()> {
+/// let mut watcher = Watcher::new()?;
+/// // Editors often replace a file by renaming another over it, so watch the
+/// // directory rather than the file.
+/// watcher.watch("/etc/myserver")?;
+///
+/// while let Some(event) = watcher.next().await {
+///     let event = event?;
+///     if event.kind == EventKind::CloseWrite || event.kind == EventKind::MoveTo {
+///         println!("reloading {}", event.path.display());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`watch`]: #method.watch
+pub struct Watcher {
+    io: AsyncFd<File>,
+    /// The watched paths, by watch descriptor. Paths that lead to the same
+    /// file share its descriptor, and its events are reported under the
+    /// first of them.
+    watches: HashMap<c_int, Vec<PathBuf>>,
+    buf: Box<[u8]>,
+    /// The part of `buf` holding events that weren't returned yet.
+    pos: usize,
+    len: usize,
+}
+
+/// A change to a watched file or directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The path that changed: the watched path itself, or an entry of a
+    /// watched directory. It is empty for an `Overflow`.
+    pub path: PathBuf,
+    /// What happened to it.
+    pub kind: EventKind,
+}
+
+/// What happened to the path of an `Event`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// An entry was created in a watched directory.
+    Create,
+    /// The contents of a file were changed.
+    Modify,
+    /// A file that was opened for writing was closed.
+    CloseWrite,
+    /// The permissions, timestamps, ownership or link count changed.
+    Metadata,
+    /// The path was removed.
+    Remove,
+    /// The path was moved away, or renamed.
+    MoveFrom,
+    /// An entry was moved into a watched directory, or renamed within it.
+    MoveTo,
+    /// Events came in faster than they were read, and some were lost; the
+    /// watched paths should be looked at again.
+    Overflow,
+}
+
+impl Watcher {
+    /// Creates a watcher that doesn't watch anything yet.
+    pub fn new() -> io::Result<Watcher> {
+        Ok(Watcher {
+            io: AsyncFd::new(sys::inotify_init()?)?,
+            watches: HashMap::new(),
+            buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+        })
+    }
+
+    /// Starts watching `path`, a file or a directory.
+    ///
+    /// Events about it are reported with `path` as given, joined with the
+    /// name of the entry for events within a directory. Watching a path
+    /// again does nothing, unless it now leads to another file.
+    ///
+    /// A path that leads to a file that is already watched, such as a
+    /// symbolic link or a hard link to it, shares its watch: events are
+    /// still reported under the path it was first watched as, and it is
+    /// watched until all of its paths are unwatched.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let wd = sys::inotify_add_watch(self.as_raw_fd(), path, WATCH_MASK)?;
+        // The path may have led to another file when it was watched before.
+        if let Some(old) = self.find(path) {
+            if old != wd {
+                self.forget(old, path)?;
+            }
+        }
+        let paths = self.watches.entry(wd).or_default();
+        if !paths.iter().any(|watched| watched == path) {
+            paths.push(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// Stops watching `path`, which must have been given to [`watch`]
+    /// before.
+    ///
+    /// Events about it that were already queued may still be reported.
+    ///
+    /// [`watch`]: #method.watch
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        match self.find(path) {
+            Some(wd) => self.forget(wd, path),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "path is not watched",
+            )),
+        }
+    }
+
+    /// Returns the watch descriptor `path` was watched with.
+    fn find(&self, path: &Path) -> Option<c_int> {
+        self.watches
+            .iter()
+            .find(|(_, paths)| paths.iter().any(|watched| watched == path))
+            .map(|(wd, _)| *wd)
+    }
+
+    /// Removes `path` from the paths of `wd`, and the watch with the last
+    /// of them.
+    fn forget(&mut self, wd: c_int, path: &Path) -> io::Result<()> {
+        let paths = self.watches.get_mut(&wd).expect("no paths for watch");
+        paths.retain(|watched| watched != path);
+        if !paths.is_empty() {
+            return Ok(());
+        }
+        self.watches.remove(&wd);
+        sys::inotify_rm_watch(self.as_raw_fd(), wd)
+    }
+
+    /// Takes the next event out of `buf`, if there is one that is reported.
+    fn next_event(&mut self) -> Option<Event> {
+        while self.len - self.pos >= EVENT_HEADER_LEN {
+            let header = &self.buf[self.pos..self.pos + EVENT_HEADER_LEN];
+            let field = |i: usize| [header[i], header[i + 1], header[i + 2], header[i + 3]];
+            let wd = c_int::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(4));
+            let name_len = u32::from_ne_bytes(field(12)) as usize;
+
+            let start = self.pos + EVENT_HEADER_LEN;
+            self.pos = start + name_len;
+            // The name is padded with NUL bytes.
+            let name = &self.buf[start..self.pos];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                return Some(Event {
+                    path: PathBuf::new(),
+                    kind: EventKind::Overflow,
+                });
+            }
+            if mask & libc::IN_IGNORED != 0 {
+                // The watch is gone, because it was removed, or because its
+                // path was.
+                self.watches.remove(&wd);
+                continue;
+            }
+            let kind = match event_kind(mask) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let path = match self.watches.get(&wd).map(|paths| &paths[0]) {
+                Some(path) if name.is_empty() => path.clone(),
+                Some(path) => path.join(std::ffi::OsStr::from_bytes(name)),
+                // Queued before the path was unwatched.
+                None => continue,
+            };
+            return Some(Event { path, kind });
+        }
+        None
+    }
+}
+
+fn event_kind(mask: u32) -> Option<EventKind> {
+    let kind = if mask & libc::IN_CREATE != 0 {
+        EventKind::Create
+    } else if mask & libc::IN_MODIFY != 0 {
+        EventKind::Modify
+    } else if mask & libc::IN_CLOSE_WRITE != 0 {
+        EventKind::CloseWrite
+    } else if mask & libc::IN_ATTRIB != 0 {
+        EventKind::Metadata
+    } else if mask & (libc::IN_DELETE | libc::IN_DELETE_SELF) != 0 {
+        EventKind::Remove
+    } else if mask & (libc::IN_MOVED_FROM | libc::IN_MOVE_SELF) != 0 {
+        EventKind::MoveFrom
+    } else if mask & libc::IN_MOVED_TO != 0 {
+        EventKind::MoveTo
+    } else {
+        return None;
+    };
+    Some(kind)
+}
+
+impl Stream for Watcher {
+    type Item = io::Result<Event>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(event) = this.next_event() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            // A read returns whole events only.
+            match ready!(Pin::new(&mut this.io).poll_read(cx, &mut this.buf)) {
+                Ok(n) => {
+                    this.pos = 0;
+                    this.len = n;
+                }
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+impl AsRawFd for Watcher {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("fd", &self.as_raw_fd())
+            .field("watches", &self.watches)
+            .finish()
+    }
+}
@@ -0,0 +1,226 @@
+use std::cmp;
+use std::fmt;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncBufRead, AsyncRead, AsyncWrite};
+use futures::ready;
+
+/// The default size of each of the buffers.
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Wraps a stream and buffers its input and its output.
+///
+/// Reads are served from a buffer that is refilled with one large read from
+/// the stream whenever it runs empty, which makes many small reads cheap.
+/// Writes are collected in a second buffer and only written to the stream
+/// when it is full, or when the `BufStream` is flushed or closed.
+///
+/// `BufStream` implements `AsyncBufRead`, following the contract of std's
+/// `BufRead`: at EOF, `poll_fill_buf` returns an empty slice. So if the peer
+/// closes the connection partway through a line, `read_line` and
+/// `read_until` return the partial line first, and the next call returns 0.
+///
+/// Buffered output is lost if the `BufStream` is dropped before it has been
+/// flushed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::io::BufStream;
+/// use romio::tcp::TcpStream;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let mut stream = BufStream::new(TcpStream::connect(&addr).await?);
+///
+/// stream.write_all(b"HELLO\n").await?;
+/// stream.flush().await?;
+///
+/// let mut line = String::new();
+/// while stream.read_line(&mut line).await? != 0 {
+///     print!("{}", line);
+///     line.clear();
+/// }
+/// # Ok(())}
+/// ```
+pub struct BufStream<S> {
+    inner: S,
+    read_buf: Box<[u8]>,
+    /// The unconsumed input is `read_buf[pos..filled]`.
+    pos: usize,
+    filled: usize,
+    write_buf: Vec<u8>,
+}
+
+impl<S> BufStream<S> {
+    /// Wraps `inner` with buffers of the default size, currently 8 KiB each.
+    pub fn new(inner: S) -> BufStream<S> {
+        BufStream::with_capacity(DEFAULT_CAPACITY, DEFAULT_CAPACITY, inner)
+    }
+
+    /// Wraps `inner` with a read buffer of `read_capacity` bytes and a write
+    /// buffer of `write_capacity` bytes.
+    pub fn with_capacity(read_capacity: usize, write_capacity: usize, inner: S) -> BufStream<S> {
+        BufStream {
+            inner,
+            read_buf: vec![0; read_capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            write_buf: Vec::with_capacity(write_capacity),
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from or writing to it directly bypasses the buffers.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Returns the input that has been read from the stream but not consumed
+    /// yet.
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.read_buf[self.pos..self.filled]
+    }
+
+    /// Consumes the `BufStream`, returning the underlying stream.
+    ///
+    /// Buffered input and unflushed output are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncWrite + Unpin> BufStream<S> {
+    /// Writes out the whole write buffer.
+    fn poll_flush_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut written = 0;
+        let mut res = Ok(());
+        while written < self.write_buf.len() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.write_buf[written..]) {
+                Poll::Ready(Ok(0)) => {
+                    res = Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ));
+                    break;
+                }
+                Poll::Ready(Ok(n)) => written += n,
+                Poll::Ready(Err(e)) => {
+                    res = Err(e);
+                    break;
+                }
+                Poll::Pending => {
+                    self.write_buf.drain(..written);
+                    return Poll::Pending;
+                }
+            }
+        }
+        self.write_buf.drain(..written);
+        Poll::Ready(res)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for BufStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        // Large reads go straight to the stream when nothing is buffered.
+        if self.pos == self.filled && buf.len() >= self.read_buf.len() {
+            return Pin::new(&mut self.inner).poll_read(cx, buf);
+        }
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = cmp::min(available.len(), buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncBufRead for BufStream<S> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if this.pos == this.filled {
+            // A read of 0 bytes means EOF, which leaves the buffer empty.
+            let n = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.read_buf))?;
+            this.pos = 0;
+            this.filled = n;
+        }
+        Poll::Ready(Ok(&this.read_buf[this.pos..this.filled]))
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for BufStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let capacity = self.write_buf.capacity();
+        if self.write_buf.len() + buf.len() > capacity {
+            ready!(self.poll_flush_buf(cx))?;
+        }
+        // Writes that don't fit in the buffer go straight to the stream.
+        if buf.len() >= capacity {
+            Pin::new(&mut self.inner).poll_write(cx, buf)
+        } else {
+            self.write_buf.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let capacity = self.write_buf.capacity();
+        let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+        if self.write_buf.len() + len > capacity {
+            ready!(self.poll_flush_buf(cx))?;
+        }
+        if len >= capacity {
+            Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+        } else {
+            for buf in bufs {
+                self.write_buf.extend_from_slice(buf);
+            }
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush_buf(cx))?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_flush_buf(cx))?;
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for BufStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufStream")
+            .field("inner", &self.inner)
+            .field("buffered_input", &(self.filled - self.pos))
+            .field("buffered_output", &self.write_buf.len())
+            .finish()
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt;
+use std::io;
+use std::net::Shutdown;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Future};
+
+#[cfg(target_os = "linux")]
+use crate::raw::{pipe, PipeReader, PipeWriter};
+use crate::tcp::TcpStream;
+#[cfg(unix)]
+use crate::uds::UnixStream;
+
+const BUF_SIZE: usize = 8 * 1024;
+
+/// How much is spliced into a pipe at once: the default capacity of a pipe
+/// on Linux.
+#[cfg(target_os = "linux")]
+const PIPE_SIZE: usize = 64 * 1024;
+
+/// Copies data in both directions between `a` and `b`, until both have
+/// reached the end.
+///
+/// Whatever is read from `a` is written to `b`, and the other way around,
+/// with both directions making progress at the same time. Once one side
+/// reaches the end, the write side of the other is closed, so the end
+/// travels on to it, while the other direction keeps going. The future
+/// resolves to the number of bytes copied from `a` to `b` and from `b` to
+/// `a`, once both directions are done, or fails with the first error
+/// either of them hits.
+///
+/// A write side is closed with [`Duplex::poll_shutdown_write`], which
+/// leaves the read side of the stream open. When both `a` and `b` are a
+/// [`TcpStream`], on Linux, the data is moved with `splice` through a pipe
+/// for each direction, without being copied through userspace. Other
+/// streams go through a buffer.
+///
+/// [`Duplex::poll_shutdown_write`]: trait.Duplex.html#tymethod.poll_shutdown_write
+/// [`TcpStream`]: ../tcp/struct.TcpStream.html
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::io::copy_bidirectional;
+/// use romio::tcp::{TcpListener, TcpStream};
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let mut listener = TcpListener::bind(&"127.0.0.1:8080".parse()?)?;
+/// let upstream = "127.0.0.1:3000".parse()?;
+///
+/// let mut incoming = listener.incoming();
+/// while let Some(mut client) = incoming.next().await {
+///     let mut client = client?;
+///     let mut server = TcpStream::connect(&upstream).await?;
+///     let (sent, received) = copy_bidirectional(&mut client, &mut server).await?;
+///     println!("proxied {} bytes up and {} bytes down", sent, received);
+/// }
+/// # Ok(())}
+/// ```
+pub fn copy_bidirectional<'a, A, B>(a: &'a mut A, b: &'a mut B) -> CopyBidirectional<'a, A, B>
+where
+    A: Duplex + ?Sized,
+    B: Duplex + ?Sized,
+{
+    CopyBidirectional {
+        a,
+        b,
+        halves: Halves::Start,
+    }
+}
+
+/// A stream that [`copy_bidirectional`] can relay, which is both read and
+/// written, and whose write side can be shut down on its own.
+///
+/// It is implemented for [`TcpStream`] and `UnixStream`, and for mutable
+/// references to streams that implement it. Other streams, such as a TLS
+/// session, implement it by shutting down the write side of what they wrap
+/// once they have sent what they need to, or by closing with `poll_close`
+/// if they can't do better.
+///
+/// [`copy_bidirectional`]: fn.copy_bidirectional.html
+/// [`TcpStream`]: ../tcp/struct.TcpStream.html
+pub trait Duplex: AsyncRead + AsyncWrite + Unpin {
+    /// Shuts down the write side of the stream, so the peer reads the end of
+    /// the data, while the read side stays open.
+    fn poll_shutdown_write(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+
+    /// Returns the stream as a `TcpStream`, if it is one, so the data can be
+    /// spliced between two of them.
+    ///
+    /// Not part of the public API: only the implementations in this crate
+    /// override it, and others are expected to keep the provided method,
+    /// which returns `None`.
+    #[doc(hidden)]
+    fn as_tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        None
+    }
+}
+
+impl Duplex for TcpStream {
+    fn poll_shutdown_write(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.shutdown(Shutdown::Write))
+    }
+
+    fn as_tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        Some(self)
+    }
+}
+
+#[cfg(unix)]
+impl Duplex for UnixStream {
+    fn poll_shutdown_write(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.shutdown(Shutdown::Write))
+    }
+}
+
+impl<T: Duplex + ?Sized> Duplex for &mut T {
+    fn poll_shutdown_write(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        (**self).poll_shutdown_write(cx)
+    }
+
+    fn as_tcp_stream(&mut self) -> Option<&mut TcpStream> {
+        (**self).as_tcp_stream()
+    }
+}
+
+/// The future returned by [`copy_bidirectional`].
+///
+/// [`copy_bidirectional`]: fn.copy_bidirectional.html
+#[must_use = "futures do nothing unless polled"]
+pub struct CopyBidirectional<'a, A: ?Sized, B: ?Sized> {
+    a: &'a mut A,
+    b: &'a mut B,
+    halves: Halves,
+}
+
+/// The two directions, from `a` to `b` and from `b` to `a`.
+enum Halves {
+    /// Not polled yet.
+    Start,
+    Copy(CopyHalf, CopyHalf),
+    /// Between two `TcpStream`s, when a pipe could be made for each
+    /// direction.
+    #[cfg(target_os = "linux")]
+    Splice(Box<SpliceHalf>, Box<SpliceHalf>),
+}
+
+impl<A, B> CopyBidirectional<'_, A, B>
+where
+    A: Duplex + ?Sized,
+    B: Duplex + ?Sized,
+{
+    #[cfg(target_os = "linux")]
+    fn start(&mut self) -> Halves {
+        if self.a.as_tcp_stream().is_some() && self.b.as_tcp_stream().is_some() {
+            if let (Ok(a_to_b), Ok(b_to_a)) = (SpliceHalf::new(), SpliceHalf::new()) {
+                return Halves::Splice(Box::new(a_to_b), Box::new(b_to_a));
+            }
+        }
+        Halves::Copy(CopyHalf::new(), CopyHalf::new())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn start(&mut self) -> Halves {
+        Halves::Copy(CopyHalf::new(), CopyHalf::new())
+    }
+}
+
+impl<A, B> Future for CopyBidirectional<'_, A, B>
+where
+    A: Duplex + ?Sized,
+    B: Duplex + ?Sized,
+{
+    type Output = io::Result<(u64, u64)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Halves::Start = this.halves {
+            this.halves = this.start();
+        }
+
+        match &mut this.halves {
+            Halves::Start => unreachable!(),
+            Halves::Copy(a_to_b, b_to_a) => {
+                let a_to_b = a_to_b.poll_copy(cx, &mut *this.a, &mut *this.b)?;
+                let b_to_a = b_to_a.poll_copy(cx, &mut *this.b, &mut *this.a)?;
+                ready!(a_to_b);
+                ready!(b_to_a);
+            }
+            #[cfg(target_os = "linux")]
+            Halves::Splice(a_to_b, b_to_a) => {
+                // What is in the pipes can't be taken back to be copied, so
+                // a stream that stops being a `TcpStream` ends the relay.
+                let (a, b) = match (this.a.as_tcp_stream(), this.b.as_tcp_stream()) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => {
+                        return Poll::Ready(Err(io::Error::other(
+                            "stream stopped being a TcpStream",
+                        )))
+                    }
+                };
+                let a_to_b = a_to_b.poll_splice(cx, a, b)?;
+                let b_to_a = b_to_a.poll_splice(cx, b, a)?;
+                ready!(a_to_b);
+                ready!(b_to_a);
+            }
+        }
+        Poll::Ready(Ok(self.amounts()))
+    }
+}
+
+impl<A: ?Sized, B: ?Sized> CopyBidirectional<'_, A, B> {
+    fn amounts(&self) -> (u64, u64) {
+        match &self.halves {
+            Halves::Start => (0, 0),
+            Halves::Copy(a_to_b, b_to_a) => (a_to_b.amount, b_to_a.amount),
+            #[cfg(target_os = "linux")]
+            Halves::Splice(a_to_b, b_to_a) => (a_to_b.amount, b_to_a.amount),
+        }
+    }
+}
+
+impl<A: ?Sized, B: ?Sized> fmt::Debug for CopyBidirectional<'_, A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let spliced = match &self.halves {
+            #[cfg(target_os = "linux")]
+            Halves::Splice(..) => true,
+            _ => false,
+        };
+        let (a_to_b, b_to_a) = self.amounts();
+        f.debug_struct("CopyBidirectional")
+            .field("spliced", &spliced)
+            .field("a_to_b", &a_to_b)
+            .field("b_to_a", &b_to_a)
+            .finish()
+    }
+}
+
+/// One direction, copied through a buffer.
+struct CopyHalf {
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    amount: u64,
+    /// Whether something was written since the last flush.
+    unflushed: bool,
+    eof: bool,
+    done: bool,
+}
+
+impl CopyHalf {
+    fn new() -> CopyHalf {
+        CopyHalf {
+            buf: vec![0; BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            amount: 0,
+            unflushed: false,
+            eof: false,
+            done: false,
+        }
+    }
+
+    fn poll_copy<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> Poll<io::Result<()>>
+    where
+        R: AsyncRead + Unpin + ?Sized,
+        W: Duplex + ?Sized,
+    {
+        loop {
+            if self.done {
+                return Poll::Ready(Ok(()));
+            }
+
+            if self.pos < self.cap {
+                let n =
+                    ready!(Pin::new(&mut *writer).poll_write(cx, &self.buf[self.pos..self.cap]))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                self.pos += n;
+                self.amount += n as u64;
+                self.unflushed = true;
+                continue;
+            }
+
+            if self.eof {
+                ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+                ready!(writer.poll_shutdown_write(cx))?;
+                self.done = true;
+                continue;
+            }
+
+            match Pin::new(&mut *reader).poll_read(cx, &mut self.buf)? {
+                Poll::Ready(0) => self.eof = true,
+                Poll::Ready(n) => {
+                    self.pos = 0;
+                    self.cap = n;
+                }
+                Poll::Pending => {
+                    // Don't leave what was written sitting in a buffer while
+                    // waiting for more.
+                    if self.unflushed {
+                        ready!(Pin::new(&mut *writer).poll_flush(cx))?;
+                        self.unflushed = false;
+                    }
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// One direction between two `TcpStream`s, spliced through a pipe.
+#[cfg(target_os = "linux")]
+struct SpliceHalf {
+    rx: PipeReader,
+    tx: PipeWriter,
+    /// How much is sitting in the pipe.
+    piped: usize,
+    amount: u64,
+    eof: bool,
+    done: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl SpliceHalf {
+    fn new() -> io::Result<SpliceHalf> {
+        let (rx, tx) = pipe()?;
+        Ok(SpliceHalf {
+            rx,
+            tx,
+            piped: 0,
+            amount: 0,
+            eof: false,
+            done: false,
+        })
+    }
+
+    fn poll_splice(
+        &mut self,
+        cx: &mut Context<'_>,
+        reader: &mut TcpStream,
+        writer: &mut TcpStream,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if self.done {
+                return Poll::Ready(Ok(()));
+            }
+
+            if self.piped > 0 {
+                let n = ready!(writer.poll_splice_from(cx, &mut self.rx, self.piped))?;
+                if n == 0 {
+                    return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+                }
+                self.piped -= n;
+                self.amount += n as u64;
+                continue;
+            }
+
+            if self.eof {
+                writer.shutdown(Shutdown::Write)?;
+                self.done = true;
+                continue;
+            }
+
+            match ready!(reader.poll_splice_to(cx, &mut self.tx, PIPE_SIZE))? {
+                0 => self.eof = true,
+                n => self.piped = n,
+            }
+        }
+    }
+}
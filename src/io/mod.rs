@@ -0,0 +1,40 @@
+//! Helpers for async I/O on top of the sockets in this crate.
+//!
+//! [`BufStream`] adds buffering in both directions to a stream, and
+//! implements `AsyncBufRead` so that line-oriented protocols can use
+//! `read_line` and friends.
+//!
+//! [`write_all_from`] writes a whole buffer like `write_all`, but tracks its
+//! progress in a cursor owned by the caller, so it can be cancelled and
+//! resumed without losing track of what was written.
+//!
+//! [`copy_bidirectional`] relays data both ways between two streams, which
+//! is the heart of a TCP proxy. Between two `TcpStream`s on Linux, it
+//! splices the data instead of copying it.
+//!
+//! [`SharedStream`] is a cloneable handle that reads and writes a single
+//! stream.
+//!
+//! [`stdin`], [`stdout`] and [`stderr`] return asynchronous handles to the
+//! standard streams of the process, so that terminal input can be awaited
+//! alongside sockets.
+//!
+//! [`BufStream`]: struct.BufStream.html
+//! [`copy_bidirectional`]: fn.copy_bidirectional.html
+//! [`SharedStream`]: struct.SharedStream.html
+//! [`write_all_from`]: fn.write_all_from.html
+//! [`stdin`]: fn.stdin.html
+//! [`stdout`]: fn.stdout.html
+//! [`stderr`]: fn.stderr.html
+
+mod buf_stream;
+mod copy_bidirectional;
+mod shared_stream;
+mod stdio;
+mod write_all_from;
+
+pub use self::buf_stream::BufStream;
+pub use self::copy_bidirectional::{copy_bidirectional, CopyBidirectional, Duplex};
+pub use self::shared_stream::SharedStream;
+pub use self::stdio::{stderr, stdin, stdout, Stderr, Stdin, Stdout};
+pub use self::write_all_from::{write_all_from, WriteAllFrom};
@@ -0,0 +1,126 @@
+use std::fmt;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use parking_lot::Mutex;
+
+/// A cloneable handle to a stream, usable for both reading and writing.
+///
+/// Every clone implements `AsyncRead` and `AsyncWrite` over the same stream,
+/// so a stream can be kept in a connection map and still be driven in both
+/// directions by one task, e.g. through a `Framed`, or read by one task
+/// while another writes.
+///
+/// Each poll locks the stream for the duration of that single non-blocking
+/// call, never across a `Poll::Pending`. Reads and writes wait for readiness
+/// separately: a clone blocked on reading doesn't hold up a clone that
+/// writes, and each is woken only for its own direction. As with the stream
+/// itself, when two clones wait in the same direction at once, only the one
+/// that polled last is woken.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::io::SharedStream;
+/// use romio::tcp::TcpStream;
+/// use std::collections::HashMap;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let stream = SharedStream::new(TcpStream::connect(&addr).await?);
+///
+/// let mut connections = HashMap::new();
+/// connections.insert(1, stream.clone());
+///
+/// let mut stream = stream;
+/// stream.write_all(b"ping").await?;
+/// let mut buf = [0; 4];
+/// stream.read_exact(&mut buf).await?;
+/// # Ok(())}
+/// ```
+pub struct SharedStream<S> {
+    inner: Arc<Mutex<S>>,
+}
+
+impl<S> SharedStream<S> {
+    /// Creates a shared handle to `stream`.
+    pub fn new(stream: S) -> SharedStream<S> {
+        SharedStream {
+            inner: Arc::new(Mutex::new(stream)),
+        }
+    }
+
+    /// Returns the stream if this is its last handle, or gives the handle
+    /// back otherwise.
+    pub fn try_unwrap(self) -> Result<S, SharedStream<S>> {
+        Arc::try_unwrap(self.inner)
+            .map(Mutex::into_inner)
+            .map_err(|inner| SharedStream { inner })
+    }
+}
+
+impl<S> Clone for SharedStream<S> {
+    fn clone(&self) -> SharedStream<S> {
+        SharedStream {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for SharedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.inner.lock()).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.inner.lock()).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for SharedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.inner.lock()).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.inner.lock()).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.inner.lock()).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.inner.lock()).poll_close(cx)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for SharedStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.try_lock() {
+            Some(stream) => f.debug_tuple("SharedStream").field(&*stream).finish(),
+            None => f.write_str("SharedStream(<locked>)"),
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::cmp;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::oneshot;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+use lazy_static::lazy_static;
+
+use crate::blocking::Pool;
+
+/// The most a single blocking read or write moves at a time.
+const MAX_BUF: usize = 16 * 1024;
+
+/// The most threads blocked on the standard streams at once. A read of
+/// standard input can wait for good, so this is well above the one thread
+/// per handle that is normally needed.
+const MAX_THREADS: usize = 64;
+
+lazy_static! {
+    static ref POOL: Pool = Pool::new("romio-stdio", MAX_THREADS);
+}
+
+/// Returns a handle to the standard input of the process.
+///
+/// Each read is done on a helper thread, with the descriptor left in
+/// blocking mode, since it is shared with the rest of the process and
+/// possibly with other processes. Input that a read was still waiting for
+/// when the handle is dropped is lost.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::io::{stdin, BufStream};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let mut lines = BufStream::new(stdin());
+/// let mut line = String::new();
+/// while lines.read_line(&mut line).await? != 0 {
+///     print!("you said: {}", line);
+///     line.clear();
+/// }
+/// # Ok(())}
+/// ```
+pub fn stdin() -> Stdin {
+    Stdin {
+        inner: Blocking::new(io::stdin),
+    }
+}
+
+/// Returns a handle to the standard output of the process.
+///
+/// This works like [`stdin`](fn.stdin.html). `poll_write` returns as soon as
+/// the data has been handed over to the helper thread, and errors are
+/// reported by the next write or flush.
+pub fn stdout() -> Stdout {
+    Stdout {
+        inner: Blocking::new(io::stdout),
+    }
+}
+
+/// Returns a handle to the standard error of the process.
+///
+/// This works like [`stdout`](fn.stdout.html).
+pub fn stderr() -> Stderr {
+    Stderr {
+        inner: Blocking::new(io::stderr),
+    }
+}
+
+/// An asynchronous handle to the standard input of the process.
+///
+/// Created by [`stdin`](fn.stdin.html).
+pub struct Stdin {
+    inner: Blocking<io::Stdin>,
+}
+
+/// An asynchronous handle to the standard output of the process.
+///
+/// Created by [`stdout`](fn.stdout.html).
+pub struct Stdout {
+    inner: Blocking<io::Stdout>,
+}
+
+/// An asynchronous handle to the standard error of the process.
+///
+/// Created by [`stderr`](fn.stderr.html).
+pub struct Stderr {
+    inner: Blocking<io::Stderr>,
+}
+
+impl AsyncRead for Stdin {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Stdout {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+}
+
+impl AsyncWrite for Stderr {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+}
+
+impl fmt::Debug for Stdin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("Stdin { .. }")
+    }
+}
+
+impl fmt::Debug for Stdout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("Stdout { .. }")
+    }
+}
+
+impl fmt::Debug for Stderr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("Stderr { .. }")
+    }
+}
+
+/// Runs blocking reads or writes on the helper threads, one at a time.
+struct Blocking<T> {
+    open: fn() -> T,
+    state: State,
+}
+
+enum State {
+    /// Holds the buffer for the next operation. After a read, `buf[pos..]`
+    /// is the input the caller hasn't taken yet.
+    Idle { buf: Vec<u8>, pos: usize },
+    /// An operation is running, and hands the buffer back with its result.
+    Busy(oneshot::Receiver<(Vec<u8>, io::Result<usize>)>),
+}
+
+impl<T> Blocking<T> {
+    fn new(open: fn() -> T) -> Blocking<T> {
+        Blocking {
+            open,
+            state: State::Idle {
+                buf: Vec::new(),
+                pos: 0,
+            },
+        }
+    }
+
+    /// Runs `op` with the buffer on a helper thread.
+    fn spawn<F>(&mut self, mut buf: Vec<u8>, op: F) -> io::Result<()>
+    where
+        F: FnOnce(T, &mut Vec<u8>) -> io::Result<usize> + Send + 'static,
+        T: 'static,
+    {
+        let open = self.open;
+        let rx = POOL.run(move || {
+            let res = op(open(), &mut buf);
+            (buf, res)
+        })?;
+        self.state = State::Busy(rx);
+        Ok(())
+    }
+
+    /// Waits for the running operation, if any, to finish.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let res = match &mut self.state {
+            State::Idle { .. } => return Poll::Ready(Ok(0)),
+            State::Busy(rx) => ready!(Pin::new(rx).poll(cx)),
+        };
+        // The sender is only dropped unsent if the helper thread panicked.
+        let (buf, res) = res.expect("stdio helper thread panicked");
+        self.state = State::Idle { buf, pos: 0 };
+        Poll::Ready(res)
+    }
+}
+
+impl<T: Read + 'static> Blocking<T> {
+    fn poll_read(&mut self, cx: &mut Context<'_>, dst: &mut [u8]) -> Poll<io::Result<usize>> {
+        if dst.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            if let State::Idle { buf, pos } = &mut self.state {
+                if *pos < buf.len() {
+                    let n = cmp::min(buf.len() - *pos, dst.len());
+                    dst[..n].copy_from_slice(&buf[*pos..*pos + n]);
+                    *pos += n;
+                    return Poll::Ready(Ok(n));
+                }
+
+                let mut buf = mem::take(buf);
+                buf.resize(cmp::min(dst.len(), MAX_BUF), 0);
+                self.spawn(buf, |mut input, buf| {
+                    let res = input.read(buf);
+                    buf.truncate(*res.as_ref().unwrap_or(&0));
+                    res
+                })?;
+            }
+
+            // A read of 0 bytes, i.e. EOF, leaves nothing to copy.
+            if ready!(self.poll_idle(cx))? == 0 {
+                return Poll::Ready(Ok(0));
+            }
+        }
+    }
+}
+
+impl<T: Write + 'static> Blocking<T> {
+    fn poll_write(&mut self, cx: &mut Context<'_>, src: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.poll_idle(cx))?;
+
+        let mut buf = match &mut self.state {
+            State::Idle { buf, .. } => mem::take(buf),
+            State::Busy(_) => unreachable!(),
+        };
+        let n = cmp::min(src.len(), MAX_BUF);
+        buf.clear();
+        buf.extend_from_slice(&src[..n]);
+        self.spawn(buf, move |mut output, buf| {
+            output.write_all(buf)?;
+            output.flush()?;
+            Ok(n)
+        })?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_idle(cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use std::thread;
+
+    struct Fake<T>(Blocking<T>);
+
+    impl<T: Read + Unpin + 'static> AsyncRead for Fake<T> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.0.poll_read(cx, buf)
+        }
+    }
+
+    impl<T: Write + Unpin + 'static> AsyncWrite for Fake<T> {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.0.poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.0.poll_flush(cx)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.0.poll_flush(cx)
+        }
+    }
+
+    /// Input that takes a while to arrive.
+    struct Slow(&'static [u8]);
+
+    impl Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(std::time::Duration::from_millis(20));
+            Read::read(&mut self.0, buf)
+        }
+    }
+
+    #[test]
+    fn blocking_reads_keep_what_the_caller_has_no_room_for() {
+        let mut stdin = Fake(Blocking::new(|| Slow(b"hello, world")));
+
+        executor::block_on(async {
+            // Start a large read, then give up on it.
+            let mut buf = [0; 64];
+            let start = futures::future::poll_fn(|cx| {
+                assert!(Pin::new(&mut stdin).poll_read(cx, &mut buf).is_pending());
+                Poll::Ready(())
+            });
+            start.await;
+
+            let mut buf = [0; 5];
+            assert_eq!(stdin.read(&mut buf).await.unwrap(), 5);
+            assert_eq!(&buf, b"hello");
+            let mut rest = [0; 64];
+            assert_eq!(stdin.read(&mut rest).await.unwrap(), 7);
+            assert_eq!(&rest[..7], b", world");
+        });
+    }
+
+    #[test]
+    fn blocking_reads_report_eof() {
+        let mut stdin = Fake(Blocking::new(io::empty));
+        let mut buf = [0; 8];
+        assert_eq!(executor::block_on(stdin.read(&mut buf)).unwrap(), 0);
+    }
+
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn blocking_write_errors_surface_on_flush() {
+        let mut sink = Fake(Blocking::new(io::sink));
+        let mut broken = Fake(Blocking::new(|| Broken));
+
+        executor::block_on(async {
+            sink.write_all(b"discarded").await.unwrap();
+            sink.flush().await.unwrap();
+
+            assert_eq!(broken.write(b"lost").await.unwrap(), 4);
+            let err = broken.flush().await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        });
+    }
+}
@@ -0,0 +1,90 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::AsyncWrite;
+use futures::{ready, Future};
+
+/// Writes all of `buf`, starting at `*cursor`, and advances the cursor as
+/// bytes are accepted.
+///
+/// `write_all` from `futures::io` keeps its progress inside the future, so
+/// when it is dropped before completing, for instance because another branch
+/// of a `select` won, the caller can't tell how much of the buffer already
+/// went out. `write_all_from` keeps the progress in a cursor owned by the
+/// caller instead, which is updated after every successful write. Once the
+/// future is dropped, `buf[..*cursor]` has been written and nothing after it
+/// was, so the write can be resumed by calling `write_all_from` again with the
+/// same cursor.
+///
+/// The future resolves once `*cursor == buf.len()`. A write that returns 0
+/// bytes fails with an error of kind `io::ErrorKind::WriteZero`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::future::{self, Either};
+/// use romio::io::write_all_from;
+/// use romio::tcp::TcpStream;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let mut stream = TcpStream::connect(&addr).await?;
+/// let msg = vec![0; 1 << 20];
+/// let mut cursor = 0;
+///
+/// while cursor < msg.len() {
+///     let write = write_all_from(&mut stream, &msg, &mut cursor);
+///     let other_work = future::ready(());
+///     match future::select(write, other_work).await {
+///         Either::Left((res, _)) => res?,
+///         Either::Right(((), _)) => println!("{} bytes written so far", cursor),
+///     }
+/// }
+/// # Ok(())}
+/// ```
+pub fn write_all_from<'a, W>(
+    writer: &'a mut W,
+    buf: &'a [u8],
+    cursor: &'a mut usize,
+) -> WriteAllFrom<'a, W>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    WriteAllFrom {
+        writer,
+        buf,
+        cursor,
+    }
+}
+
+/// The future returned by [`write_all_from`].
+///
+/// [`write_all_from`]: fn.write_all_from.html
+#[derive(Debug)]
+pub struct WriteAllFrom<'a, W: ?Sized> {
+    writer: &'a mut W,
+    buf: &'a [u8],
+    cursor: &'a mut usize,
+}
+
+impl<W: AsyncWrite + Unpin + ?Sized> Future for WriteAllFrom<'_, W> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let WriteAllFrom {
+            writer,
+            buf,
+            cursor,
+        } = &mut *self;
+        while **cursor < buf.len() {
+            let n = ready!(Pin::new(&mut **writer).poll_write(cx, &buf[**cursor..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            **cursor += n;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
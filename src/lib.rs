@@ -15,6 +15,24 @@
 //! Because futures-preview is currently unstable, this crate requires
 //! nightly Rust.
 //!
+//! # Platforms
+//!
+//! TCP and UDP work on both Unix and Windows. Unix domain sockets, raw IP
+//! sockets, child processes and signals are only available on Unix, and named
+//! pipes, in the `windows` module, only on Windows. Watching the filesystem,
+//! with `fs::Watcher`, is only available on Linux.
+//!
+//! # Running out of file descriptors
+//!
+//! I/O resources are registered with a reactor the first time they are
+//! polled, and the default reactor is created on first use. When either
+//! step fails because the process or the system is out of file descriptors,
+//! the operation fails with the OS error itself, so `raw_os_error()` returns
+//! `EMFILE` or `ENFILE`, and every later poll of that resource reports the
+//! same error. A resource that couldn't be registered still owns its socket,
+//! which is closed when it is dropped. The default reactor is retried on next
+//! use, so things recover once descriptors have been freed.
+//!
 //! # Examples
 //! __TCP Server__
 //! ```rust
@@ -65,11 +83,42 @@ pub mod udp;
 #[cfg(unix)]
 pub mod uds;
 
+#[cfg(unix)]
+pub mod process;
+
+#[cfg(unix)]
+pub mod raw_net;
+
+#[cfg(all(feature = "netlink", target_os = "linux"))]
+pub mod netlink;
+
+#[cfg(windows)]
+pub mod windows;
+
+pub mod codec;
+pub mod dns;
+pub mod fs;
+pub mod io;
+pub mod pool;
+pub mod proxy;
 pub mod raw;
+pub mod signal;
+pub mod timer;
+
+#[cfg(feature = "rustls")]
+pub mod tls;
 
+mod blocking;
+mod dual;
 mod reactor;
+mod socket;
+
+#[cfg(unix)]
+mod sys;
 
 #[doc(inline)]
 pub use crate::tcp::{TcpListener, TcpStream};
 #[doc(inline)]
 pub use crate::udp::UdpSocket;
+
+pub use crate::dual::bind_dual_protocol;
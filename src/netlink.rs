@@ -0,0 +1,266 @@
+//! Netlink sockets, for talking to the Linux kernel.
+//!
+//! Netlink carries requests to kernel subsystems, and their replies, as well
+//! as notifications the kernel multicasts to the sockets that joined a
+//! group. A [`NetlinkSocket`] is driven by the reactor like any other
+//! socket, so a daemon can follow route and link changes along with the rest
+//! of its I/O.
+//!
+//! This module only moves netlink messages: building and parsing them,
+//! headers included, is left to the caller or to a crate dedicated to it.
+//! It is only available on Linux, with the `netlink` feature.
+//!
+//! [`NetlinkSocket`]: struct.NetlinkSocket.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use romio::netlink::NetlinkSocket;
+//!
+//! # async fn monitor() -> std::io::Result<()> {
+//! let mut socket = NetlinkSocket::new(libc::NETLINK_ROUTE)?;
+//! socket.join_group(libc::RTNLGRP_LINK)?;
+//!
+//! let mut buf = vec![0; 8192];
+//! loop {
+//!     let n = socket.recv(&mut buf).await?;
+//!     println!("{} bytes of link changes", n);
+//! }
+//! # }
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::Context;
+
+use futures::{ready, Future, Poll};
+
+use crate::raw::AsyncFd;
+use crate::sys;
+
+/// A netlink socket.
+///
+/// The socket is bound when it is created, to a port the kernel picks, which
+/// [`local_pid`] returns. Messages sent with [`send`] go to the kernel.
+///
+/// A received datagram may hold several netlink messages, and one longer
+/// than the buffer is cut short, so buffers should be at least 8 KiB. When
+/// notifications come in faster than they are received, the socket's receive
+/// buffer overflows and the next receive fails with `ENOBUFS`: some
+/// notifications were lost, and the state they describe has to be fetched
+/// again.
+///
+/// [`local_pid`]: #method.local_pid
+/// [`send`]: #method.send
+pub struct NetlinkSocket {
+    io: AsyncFd<File>,
+    pid: u32,
+}
+
+impl NetlinkSocket {
+    /// Creates a netlink socket for `protocol`, such as
+    /// `libc::NETLINK_ROUTE`, and binds it.
+    pub fn new(protocol: i32) -> io::Result<NetlinkSocket> {
+        let file: File = sys::socket_owned(libc::AF_NETLINK, libc::SOCK_RAW, protocol)?;
+        let pid = sys::bind_netlink(file.as_raw_fd())?;
+        Ok(NetlinkSocket {
+            io: AsyncFd::new(file)?,
+            pid,
+        })
+    }
+
+    /// Returns the port the socket is bound to.
+    ///
+    /// The kernel sets it as the `nlmsg_pid` of its replies to this socket.
+    pub fn local_pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Subscribes to the multicast group `group`, such as
+    /// `libc::RTNLGRP_LINK`, to receive the notifications sent to it.
+    ///
+    /// Some groups can only be joined with `CAP_NET_ADMIN`.
+    pub fn join_group(&self, group: u32) -> io::Result<()> {
+        sys::setsockopt(
+            self.as_raw_fd(),
+            libc::SOL_NETLINK,
+            libc::NETLINK_ADD_MEMBERSHIP,
+            group,
+        )
+    }
+
+    /// Unsubscribes from the multicast group `group`.
+    pub fn leave_group(&self, group: u32) -> io::Result<()> {
+        sys::setsockopt(
+            self.as_raw_fd(),
+            libc::SOL_NETLINK,
+            libc::NETLINK_DROP_MEMBERSHIP,
+            group,
+        )
+    }
+
+    /// Sends `buf`, one or more netlink messages, to the kernel.
+    ///
+    /// The future resolves to the number of bytes sent.
+    pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SendTo<'a, 'b> {
+        self.send_to(buf, 0)
+    }
+
+    /// Sends `buf`, one or more netlink messages, to the port `pid`.
+    pub fn send_to<'a, 'b>(&'a mut self, buf: &'b [u8], pid: u32) -> SendTo<'a, 'b> {
+        SendTo {
+            socket: self,
+            buf,
+            pid,
+        }
+    }
+
+    /// Receives a datagram, resolving to the number of bytes copied into
+    /// `buf`.
+    ///
+    /// Anyone may send to a netlink socket; use [`recv_from`] to check that
+    /// a message came from the kernel.
+    ///
+    /// [`recv_from`]: #method.recv_from
+    pub fn recv<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> Recv<'a, 'b> {
+        Recv { socket: self, buf }
+    }
+
+    /// Receives a datagram, resolving to the number of bytes copied into
+    /// `buf` and the port it came from, which is 0 for the kernel.
+    pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
+        RecvFrom { socket: self, buf }
+    }
+
+    /// Attempts to send `buf` to the port `pid`.
+    ///
+    /// If the socket isn't writable, `Poll::Pending` is returned and the
+    /// current task is woken once it is.
+    pub fn poll_send_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        pid: u32,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match sys::send_to_netlink(self.as_raw_fd(), buf, pid) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Attempts to receive a datagram.
+    ///
+    /// If none is waiting, `Poll::Pending` is returned and the current task
+    /// is woken once one arrives.
+    pub fn poll_recv_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, u32)>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match sys::recv_from_netlink(self.as_raw_fd(), buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Sets the value of the `SO_RCVBUF` option on this socket.
+    ///
+    /// A larger receive buffer makes it less likely that bursts of
+    /// notifications are lost.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        let size = std::cmp::min(size, libc::c_int::MAX as usize) as libc::c_int;
+        sys::setsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF, size)
+    }
+
+    /// Gets the value of the `SO_RCVBUF` option on this socket.
+    ///
+    /// For more information about this option, see [`set_recv_buffer_size`].
+    ///
+    /// [`set_recv_buffer_size`]: #method.set_recv_buffer_size
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        sys::getsockopt::<libc::c_int>(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF)
+            .map(|size| size as usize)
+    }
+}
+
+impl AsRawFd for NetlinkSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for NetlinkSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetlinkSocket")
+            .field("fd", &self.as_raw_fd())
+            .field("pid", &self.pid)
+            .finish()
+    }
+}
+
+/// The future returned by `NetlinkSocket::send` and `NetlinkSocket::send_to`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SendTo<'a, 'b> {
+    socket: &'a mut NetlinkSocket,
+    buf: &'b [u8],
+    pid: u32,
+}
+
+impl Future for SendTo<'_, '_> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendTo { socket, buf, pid } = &mut *self;
+        socket.poll_send_to(cx, buf, *pid)
+    }
+}
+
+/// The future returned by `NetlinkSocket::recv`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Recv<'a, 'b> {
+    socket: &'a mut NetlinkSocket,
+    buf: &'b mut [u8],
+}
+
+impl Future for Recv<'_, '_> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Recv { socket, buf } = &mut *self;
+        let (n, _) = ready!(socket.poll_recv_from(cx, buf))?;
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// The future returned by `NetlinkSocket::recv_from`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct RecvFrom<'a, 'b> {
+    socket: &'a mut NetlinkSocket,
+    buf: &'b mut [u8],
+}
+
+impl Future for RecvFrom<'_, '_> {
+    type Output = io::Result<(usize, u32)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvFrom { socket, buf } = &mut *self;
+        socket.poll_recv_from(cx, buf)
+    }
+}
@@ -0,0 +1,181 @@
+//! Reusable read buffers.
+//!
+//! Servers that read many small messages allocate and free a buffer for each
+//! of them unless buffers are recycled. A [`BufferPool`] hands out fixed-size
+//! [`PooledBuf`]s that go back into the pool when dropped, so steady-state
+//! reads don't touch the allocator.
+//!
+//! [`BufferPool`]: struct.BufferPool.html
+//! [`PooledBuf`]: struct.PooledBuf.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use romio::pool::BufferPool;
+//! use romio::tcp::TcpStream;
+//!
+//! # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+//! let pool = BufferPool::new(4096, 64);
+//! let addr = "127.0.0.1:8080".parse()?;
+//! let mut stream = TcpStream::connect(&addr).await?;
+//!
+//! loop {
+//!     let buf = stream.read_pooled(&pool).await?;
+//!     if buf.is_empty() {
+//!         break; // EOF
+//!     }
+//!     println!("read {} bytes", buf.len());
+//!     // `buf` returns to the pool here
+//! }
+//! # Ok(())}
+//! ```
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+/// A pool of fixed-size byte buffers.
+///
+/// Cloning a pool is cheap and yields a handle to the same set of buffers.
+#[derive(Clone)]
+pub struct BufferPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    buf_size: usize,
+    max_idle: usize,
+    idle: Mutex<Vec<Vec<u8>>>,
+}
+
+/// A buffer borrowed from a `BufferPool`.
+///
+/// It derefs to the filled part of the buffer, `len()` bytes long. The full
+/// capacity, `BufferPool::buf_size()` bytes, can be reached through
+/// [`as_full_mut`] to fill it manually. On drop the buffer returns to its
+/// pool.
+///
+/// [`as_full_mut`]: #method.as_full_mut
+pub struct PooledBuf {
+    /// Always `buf_size` bytes long, so recycling never zeroes it again.
+    buf: Vec<u8>,
+    len: usize,
+    pool: Arc<Inner>,
+}
+
+impl BufferPool {
+    /// Creates a pool of `buf_size`-byte buffers that keeps at most
+    /// `max_idle` of them around for reuse.
+    ///
+    /// Buffers are allocated on demand; when more than `max_idle` are
+    /// returned at once, the surplus is freed.
+    pub fn new(buf_size: usize, max_idle: usize) -> BufferPool {
+        BufferPool {
+            inner: Arc::new(Inner {
+                buf_size,
+                max_idle,
+                idle: Mutex::new(Vec::with_capacity(max_idle)),
+            }),
+        }
+    }
+
+    /// Returns the size of the buffers in this pool.
+    pub fn buf_size(&self) -> usize {
+        self.inner.buf_size
+    }
+
+    /// Takes an empty buffer from the pool, allocating one if none is idle.
+    pub fn get(&self) -> PooledBuf {
+        let buf = self.inner.idle.lock().pop();
+        let buf = buf.unwrap_or_else(|| vec![0; self.inner.buf_size]);
+        PooledBuf {
+            buf,
+            len: 0,
+            pool: self.inner.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("buf_size", &self.inner.buf_size)
+            .field("max_idle", &self.inner.max_idle)
+            .field("idle", &self.inner.idle.lock().len())
+            .finish()
+    }
+}
+
+impl PooledBuf {
+    /// Returns the whole buffer, regardless of how much of it is filled.
+    pub fn as_full_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+
+    /// Sets how many bytes of the buffer are filled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the buffer.
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= self.buf.len(), "length exceeds buffer size");
+        self.len = len;
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[..self.len]
+    }
+}
+
+impl fmt::Debug for PooledBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledBuf")
+            .field("len", &self.len)
+            .field("capacity", &self.buf.len())
+            .finish()
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        let mut idle = self.pool.idle.lock();
+        if idle.len() < self.pool.max_idle {
+            idle.push(std::mem::take(&mut self.buf));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn buffers_are_recycled() {
+        let pool = BufferPool::new(16, 1);
+        let mut a = pool.get();
+        a.set_len(4);
+        let ptr = a.as_ptr();
+        let b = pool.get();
+        drop(a);
+        drop(b);
+
+        // Only one buffer is kept, and it comes back empty.
+        let c = pool.get();
+        assert_eq!(c.as_ptr(), ptr);
+        assert!(c.is_empty());
+        assert_eq!(pool.inner.idle.lock().len(), 0);
+    }
+}
@@ -0,0 +1,342 @@
+//! Child processes with asynchronous I/O.
+//!
+//! [`CommandExt::spawn_async`] spawns a `std::process::Command` like
+//! `spawn` does, but wraps the pipes to the child's standard streams in
+//! [`PipeReader`] and [`PipeWriter`], and returns a [`Child`] whose exit can
+//! be awaited.
+//!
+//! On Linux, the exit of a child is noticed through a pidfd, which the
+//! reactor watches like any other descriptor. Elsewhere, and on kernels
+//! older than 5.3, a helper thread blocks in `waitid` until the child has
+//! exited. Neither installs a `SIGCHLD` handler, so code that reaps children
+//! itself, e.g. with `waitpid(-1, ..)`, keeps working, but can take the exit
+//! status of a `Child` away from it.
+//!
+//! [`CommandExt::spawn_async`]: trait.CommandExt.html#tymethod.spawn_async
+//! [`PipeReader`]: ../raw/struct.PipeReader.html
+//! [`PipeWriter`]: ../raw/struct.PipeWriter.html
+//! [`Child`]: struct.Child.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use futures::prelude::*;
+//! use romio::io::BufStream;
+//! use romio::process::CommandExt;
+//! use std::process::{Command, Stdio};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let mut child = Command::new("ping")
+//!     .args(&["-c", "3", "localhost"])
+//!     .stdout(Stdio::piped())
+//!     .spawn_async()?;
+//!
+//! // print the output as it arrives
+//! let mut lines = BufStream::new(child.stdout.take().unwrap());
+//! let mut line = String::new();
+//! while lines.read_line(&mut line).await? != 0 {
+//!     print!("{}", line);
+//!     line.clear();
+//! }
+//!
+//! let status = child.wait().await?;
+//! println!("ping exited with {}", status);
+//! # Ok(())}
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::process::{self, ExitStatus, Output};
+use std::task::{Context, Poll};
+use std::thread;
+
+use futures::channel::oneshot;
+use futures::io::AsyncRead;
+use futures::{ready, Future};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::fs::File;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::unix::io::FromRawFd;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::raw::AsyncFd;
+use crate::raw::{PipeReader, PipeWriter};
+use crate::sys;
+
+/// Extends `std::process::Command` with spawning of asynchronous children.
+pub trait CommandExt {
+    /// Spawns the command as a child process, returning a handle to it.
+    ///
+    /// The command's settings apply as they do for `Command::spawn`. In
+    /// particular, `stdin`, `stdout` and `stderr` are only piped when set
+    /// to `Stdio::piped()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process can't be spawned, or if its pipes or
+    /// the watch for its exit can't be registered with the reactor. In the
+    /// latter case the child has already been started, and is killed.
+    fn spawn_async(&mut self) -> io::Result<Child>;
+}
+
+impl CommandExt for process::Command {
+    fn spawn_async(&mut self) -> io::Result<Child> {
+        let mut child = self.spawn()?;
+        match Child::parts(&mut child) {
+            Ok((stdin, stdout, stderr, exit)) => Ok(Child {
+                child,
+                exit,
+                status: None,
+                stdin,
+                stdout,
+                stderr,
+            }),
+            Err(e) => {
+                drop(child.kill());
+                drop(child.wait());
+                Err(e)
+            }
+        }
+    }
+}
+
+/// A child process spawned by [`CommandExt::spawn_async`].
+///
+/// Like `std::process::Child`, dropping a `Child` doesn't kill the process,
+/// which keeps running in the background.
+///
+/// [`CommandExt::spawn_async`]: trait.CommandExt.html#tymethod.spawn_async
+pub struct Child {
+    child: process::Child,
+    exit: Exit,
+    status: Option<ExitStatus>,
+
+    /// The pipe to the child's standard input, if it was piped.
+    pub stdin: Option<PipeWriter>,
+    /// The pipe from the child's standard output, if it was piped.
+    pub stdout: Option<PipeReader>,
+    /// The pipe from the child's standard error, if it was piped.
+    pub stderr: Option<PipeReader>,
+}
+
+/// How the exit of a child is noticed.
+enum Exit {
+    /// A pidfd, which becomes readable once the child has exited.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    Pidfd(AsyncFd<File>),
+    /// A helper thread waiting for the exit, once one has been started.
+    Thread(Option<oneshot::Receiver<io::Result<()>>>),
+}
+
+type Parts = (
+    Option<PipeWriter>,
+    Option<PipeReader>,
+    Option<PipeReader>,
+    Exit,
+);
+
+impl Child {
+    /// Wraps the pipes of a newly spawned child, and sets up the watch for
+    /// its exit.
+    fn parts(child: &mut process::Child) -> io::Result<Parts> {
+        let stdin = child.stdin.take().map(PipeWriter::try_from).transpose()?;
+        let stdout = child.stdout.take().map(PipeReader::try_from).transpose()?;
+        let stderr = child.stderr.take().map(PipeReader::try_from).transpose()?;
+        Ok((stdin, stdout, stderr, Exit::new(child.id())?))
+    }
+
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Sends `SIGKILL` to the child, unless it has already exited.
+    ///
+    /// The child still has to be waited for to release its resources.
+    pub fn kill(&mut self) -> io::Result<()> {
+        if self.status.is_some() {
+            return Ok(());
+        }
+        self.child.kill()
+    }
+
+    /// Returns the exit status of the child if it has exited, without
+    /// waiting.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        if self.status.is_none() {
+            self.status = self.child.try_wait()?;
+        }
+        Ok(self.status)
+    }
+
+    /// Waits for the child to exit, returning its exit status.
+    ///
+    /// Unlike `std::process::Child::wait`, this doesn't close the child's
+    /// standard input first. A child that reads its input to the end must
+    /// have `stdin` dropped, or it keeps waiting for more.
+    pub fn wait(&mut self) -> Wait<'_> {
+        Wait { child: self }
+    }
+
+    /// Polls for the exit of the child.
+    ///
+    /// If the child is still running, `Poll::Pending` is returned and the
+    /// current task is woken once it has exited.
+    pub fn poll_wait(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<ExitStatus>> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Poll::Ready(Ok(status));
+            }
+
+            match &mut self.exit {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                Exit::Pidfd(pidfd) => {
+                    ready!(pidfd.poll_read_ready(cx))?;
+                    if let Some(status) = self.child.try_wait()? {
+                        self.status = Some(status);
+                        return Poll::Ready(Ok(status));
+                    }
+                    pidfd.clear_read_ready(cx)?;
+                    return Poll::Pending;
+                }
+                Exit::Thread(waiter) => {
+                    let rx = match waiter {
+                        Some(rx) => rx,
+                        None => waiter.get_or_insert(spawn_waiter(self.child.id())?),
+                    };
+                    let res = ready!(Pin::new(rx).poll(cx));
+                    *waiter = None;
+                    res.expect("waitid helper thread panicked")?;
+                }
+            }
+        }
+    }
+
+    /// Waits for the child to exit, collecting all of its output.
+    ///
+    /// Standard input is closed first, so the child doesn't wait for more
+    /// input. Standard output and standard error are read to the end while
+    /// waiting, and only what was piped is collected.
+    pub fn wait_with_output(mut self) -> WaitWithOutput {
+        drop(self.stdin.take());
+        WaitWithOutput {
+            child: self,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        }
+    }
+}
+
+impl Exit {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn new(pid: u32) -> io::Result<Exit> {
+        match sys::pidfd_open(pid) {
+            Ok(fd) => {
+                let pidfd = AsyncFd::new(unsafe { File::from_raw_fd(fd) })?;
+                Ok(Exit::Pidfd(pidfd))
+            }
+            // Kernels before 5.3 don't have pidfds.
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOSYS) => Ok(Exit::Thread(None)),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn new(_pid: u32) -> io::Result<Exit> {
+        Ok(Exit::Thread(None))
+    }
+}
+
+/// Starts a thread that waits for the child `pid` to exit, without reaping
+/// it.
+fn spawn_waiter(pid: u32) -> io::Result<oneshot::Receiver<io::Result<()>>> {
+    let (tx, rx) = oneshot::channel();
+    thread::Builder::new()
+        .name("romio-waitid".to_string())
+        .spawn(move || drop(tx.send(sys::wait_exited(pid))))?;
+    Ok(rx)
+}
+
+impl fmt::Debug for Child {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Child")
+            .field("id", &self.id())
+            .field("status", &self.status)
+            .field("stdin", &self.stdin)
+            .field("stdout", &self.stdout)
+            .field("stderr", &self.stderr)
+            .finish()
+    }
+}
+
+/// The future returned by [`Child::wait`].
+///
+/// [`Child::wait`]: struct.Child.html#method.wait
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Wait<'a> {
+    child: &'a mut Child,
+}
+
+impl<'a> Future for Wait<'a> {
+    type Output = io::Result<ExitStatus>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.child.poll_wait(cx)
+    }
+}
+
+/// The future returned by [`Child::wait_with_output`].
+///
+/// [`Child::wait_with_output`]: struct.Child.html#method.wait_with_output
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WaitWithOutput {
+    child: Child,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+/// Reads `pipe` to the end into `buf`, dropping it at EOF.
+fn poll_drain(
+    cx: &mut Context<'_>,
+    pipe: &mut Option<PipeReader>,
+    buf: &mut Vec<u8>,
+) -> Poll<io::Result<()>> {
+    let mut chunk = [0; 8 * 1024];
+    while let Some(reader) = pipe {
+        match ready!(Pin::new(reader).poll_read(cx, &mut chunk))? {
+            0 => *pipe = None,
+            n => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    Poll::Ready(Ok(()))
+}
+
+impl Future for WaitWithOutput {
+    type Output = io::Result<Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        // Both pipes are drained at the same time, so that a child blocked
+        // writing to one doesn't keep the other from reaching EOF.
+        let stdout = poll_drain(cx, &mut this.child.stdout, &mut this.stdout)?;
+        let stderr = poll_drain(cx, &mut this.child.stderr, &mut this.stderr)?;
+        if stdout.is_pending() || stderr.is_pending() {
+            return Poll::Pending;
+        }
+
+        let status = ready!(this.child.poll_wait(cx))?;
+        Poll::Ready(Ok(Output {
+            status,
+            stdout: mem::take(&mut this.stdout),
+            stderr: mem::take(&mut this.stderr),
+        }))
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Future};
+
+use super::{invalid_reply, poll_write_buf, TargetAddr};
+use crate::tcp::{ConnectFuture, TcpStream};
+
+/// The longest response header accepted from a proxy.
+const MAX_HEADER_LEN: usize = 8 * 1024;
+
+/// A TCP connection to a target, tunneled through an HTTP proxy with the
+/// `CONNECT` method.
+///
+/// Reads and writes go to the underlying `TcpStream`, which is connected to
+/// the proxy. Bytes the target sent right after the response of the proxy,
+/// in the same packet, are returned by the first reads.
+pub struct HttpTunnelStream {
+    stream: TcpStream,
+    /// What was read past the response header.
+    buffered: Vec<u8>,
+    pos: usize,
+}
+
+/// The future returned by `HttpTunnelStream::connect`, which will resolve to
+/// an `HttpTunnelStream` once the proxy has connected to the target.
+#[must_use = "futures do nothing unless polled"]
+pub struct HttpTunnelConnect {
+    connect: Option<ConnectFuture>,
+    stream: Option<TcpStream>,
+    request: Vec<u8>,
+    written: usize,
+    response: Vec<u8>,
+    /// Why the request can't be made, if it can't.
+    invalid: Option<&'static str>,
+    done: bool,
+}
+
+impl HttpTunnelStream {
+    /// Connects to `target` through the HTTP proxy at `proxy`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the proxy can't be reached, with `PermissionDenied` if it
+    /// requires authentication, and with `io::ErrorKind::Other` if it
+    /// responds with any other status than `2xx`. Responses that aren't HTTP
+    /// fail with `InvalidData`. A target host name that is empty or contains
+    /// whitespace, control characters or `@` fails with `InvalidInput`,
+    /// without connecting to the proxy.
+    pub fn connect<T: Into<TargetAddr>>(proxy: &SocketAddr, target: T) -> HttpTunnelConnect {
+        HttpTunnelConnect::new(proxy, target.into(), None)
+    }
+
+    /// Connects to `target` through the HTTP proxy at `proxy`, logging in
+    /// with `username` and `password` with basic authentication.
+    ///
+    /// The credentials are sent in the clear, unless the connection to the
+    /// proxy is otherwise protected.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`connect`], including with `PermissionDenied` if the
+    /// proxy rejects the credentials. Credentials that contain whitespace,
+    /// control characters or `@`, or a username that contains `:`, fail
+    /// with `InvalidInput`, without connecting to the proxy.
+    ///
+    /// [`connect`]: #method.connect
+    pub fn connect_with_password<T: Into<TargetAddr>>(
+        proxy: &SocketAddr,
+        target: T,
+        username: &str,
+        password: &str,
+    ) -> HttpTunnelConnect {
+        HttpTunnelConnect::new(proxy, target.into(), Some((username, password)))
+    }
+
+    /// Returns a shared reference to the underlying stream, connected to
+    /// the proxy.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the underlying stream, connected to
+    /// the proxy.
+    ///
+    /// Reading from it directly skips bytes that were already buffered.
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.stream
+    }
+
+    /// Consumes the `HttpTunnelStream`, returning the underlying stream and
+    /// the bytes that were buffered but not read yet.
+    pub fn into_inner(mut self) -> (TcpStream, Vec<u8>) {
+        self.buffered.drain(..self.pos);
+        (self.stream, self.buffered)
+    }
+}
+
+impl AsyncRead for HttpTunnelStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if this.pos < this.buffered.len() {
+            let rest = &this.buffered[this.pos..];
+            let n = rest.len().min(buf.len());
+            buf[..n].copy_from_slice(&rest[..n]);
+            this.pos += n;
+            if this.pos == this.buffered.len() {
+                this.buffered = Vec::new();
+                this.pos = 0;
+            }
+            return Poll::Ready(Ok(n));
+        }
+        Pin::new(&mut this.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for HttpTunnelStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
+
+impl fmt::Debug for HttpTunnelStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.stream.fmt(f)
+    }
+}
+
+// ===== impl HttpTunnelConnect =====
+
+impl HttpTunnelConnect {
+    fn new(
+        proxy: &SocketAddr,
+        target: TargetAddr,
+        auth: Option<(&str, &str)>,
+    ) -> HttpTunnelConnect {
+        let mut connect = HttpTunnelConnect {
+            connect: None,
+            stream: None,
+            request: Vec::new(),
+            written: 0,
+            response: Vec::new(),
+            invalid: None,
+            done: false,
+        };
+
+        if let TargetAddr::Domain(host, _) = &target {
+            if host.is_empty() || !is_header_safe(host) {
+                connect.invalid = Some("HTTP proxy target host name empty or invalid");
+                return connect;
+            }
+        }
+        if let Some((username, password)) = auth {
+            if username.contains(':') || !is_header_safe(username) || !is_header_safe(password) {
+                connect.invalid = Some("HTTP proxy username or password invalid");
+                return connect;
+            }
+        }
+
+        let mut request = format!(
+            "CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n",
+            target = target
+        );
+        if let Some((username, password)) = auth {
+            let credentials = format!("{}:{}", username, password);
+            request.push_str("Proxy-Authorization: Basic ");
+            request.push_str(&base64(credentials.as_bytes()));
+            request.push_str("\r\n");
+        }
+        request.push_str("\r\n");
+
+        connect.connect = Some(TcpStream::connect(proxy));
+        connect.request = request.into_bytes();
+        connect
+    }
+
+    /// Reads the response header of the proxy, returning its length.
+    fn poll_read_header(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let stream = self.stream.as_mut().unwrap();
+        let mut buf = [0; 1024];
+        loop {
+            let n = ready!(Pin::new(&mut *stream).poll_read(cx, &mut buf))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "proxy closed the connection",
+                )));
+            }
+            // The end of the header may straddle two reads.
+            let start = self.response.len().saturating_sub(3);
+            self.response.extend_from_slice(&buf[..n]);
+            if let Some(i) = find(&self.response[start..], b"\r\n\r\n") {
+                return Poll::Ready(Ok(start + i + 4));
+            }
+            if self.response.len() > MAX_HEADER_LEN {
+                return Poll::Ready(Err(invalid_reply("response header too long")));
+            }
+        }
+    }
+}
+
+impl Future for HttpTunnelConnect {
+    type Output = io::Result<HttpTunnelStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        assert!(!this.done, "polled HttpTunnelConnect after completion");
+        if let Some(msg) = this.invalid {
+            this.done = true;
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidInput, msg)));
+        }
+        if let Some(connect) = &mut this.connect {
+            this.stream = Some(ready!(Pin::new(connect).poll(cx))?);
+            this.connect = None;
+        }
+
+        let stream = this.stream.as_mut().unwrap();
+        ready!(poll_write_buf(stream, cx, &this.request, &mut this.written))?;
+        let len = ready!(this.poll_read_header(cx))?;
+        this.done = true;
+
+        check_status(&this.response[..len])?;
+        let buffered = this.response.split_off(len);
+        Poll::Ready(Ok(HttpTunnelStream {
+            stream: this.stream.take().unwrap(),
+            buffered,
+            pos: 0,
+        }))
+    }
+}
+
+impl fmt::Debug for HttpTunnelConnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpTunnelConnect")
+            .field("connected", &self.connect.is_none())
+            .finish()
+    }
+}
+
+/// Checks the status line of the response `header`.
+fn check_status(header: &[u8]) -> io::Result<()> {
+    let line = header.split(|&b| b == b'\r').next().unwrap_or_default();
+    let line = String::from_utf8_lossy(line);
+    let mut parts = line.splitn(3, ' ');
+    let code = match (parts.next(), parts.next()) {
+        (Some(version), Some(code)) if version.starts_with("HTTP/1.") => code,
+        _ => return Err(invalid_reply("not HTTP")),
+    };
+    match code.parse::<u16>() {
+        Ok(200..=299) => Ok(()),
+        Ok(407) => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "HTTP proxy requires authentication",
+        )),
+        Ok(_) => Err(io::Error::other(format!(
+            "HTTP proxy refused the tunnel: {}",
+            line
+        ))),
+        Err(_) => Err(invalid_reply("invalid status code")),
+    }
+}
+
+/// Checks that `s` can go in the request header, or in the credentials of
+/// basic authentication, without changing what it means.
+fn is_header_safe(s: &str) -> bool {
+    !s.chars()
+        .any(|c| c.is_control() || c.is_whitespace() || c == '@')
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Encodes `bytes` in standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(
+            base64(b"Aladdin:open sesame"),
+            "QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+
+    #[test]
+    fn checks_status() {
+        assert!(check_status(b"HTTP/1.1 200 Connection established\r\n\r\n").is_ok());
+        assert!(check_status(b"HTTP/1.0 200\r\n\r\n").is_ok());
+
+        let kind = |header: &[u8]| check_status(header).unwrap_err().kind();
+        assert_eq!(
+            kind(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n"),
+            io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            kind(b"HTTP/1.1 502 Bad Gateway\r\n\r\n"),
+            io::ErrorKind::Other
+        );
+        assert_eq!(kind(b"SSH-2.0-OpenSSH\r\n\r\n"), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,132 @@
+//! TCP connections through SOCKS5 and HTTP proxies.
+//!
+//! Both kinds of proxies are reached over TCP, and asked to open a
+//! connection to a target on behalf of the client:
+//!
+//! - [`Socks5Stream::connect`] speaks SOCKS version 5 (RFC 1928), with
+//!   either no authentication or a username and a password (RFC 1929).
+//! - [`HttpTunnelStream::connect`] sends an HTTP `CONNECT` request, with
+//!   optional basic authentication.
+//!
+//! Once the proxy has agreed, the resulting stream relays bytes to and from
+//! the target, and is used like a `TcpStream`. The target is either a
+//! socket address or a host name and a port; a name is sent to the proxy
+//! as is, and resolved there.
+//!
+//! [`Socks5Stream::connect`]: struct.Socks5Stream.html#method.connect
+//! [`HttpTunnelStream::connect`]: struct.HttpTunnelStream.html#method.connect
+//!
+//! # Example
+//!
+//! ```no_run
+//! #![feature(async_await)]
+//! use futures::prelude::*;
+//! use romio::proxy::Socks5Stream;
+//!
+//! # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+//! let proxy = "127.0.0.1:1080".parse()?;
+//! let mut stream = Socks5Stream::connect(&proxy, ("example.com", 80)).await?;
+//! stream.write_all(b"GET / HTTP/1.0\r\nHost: example.com\r\n\r\n").await?;
+//! # Ok(())}
+//! ```
+
+mod http;
+mod socks5;
+
+pub use self::http::{HttpTunnelConnect, HttpTunnelStream};
+pub use self::socks5::{Socks5Connect, Socks5Stream};
+
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+
+use crate::tcp::TcpStream;
+
+/// The target of a connection through a proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetAddr {
+    /// A socket address.
+    Ip(SocketAddr),
+    /// A host name, for the proxy to resolve, and a port.
+    Domain(String, u16),
+}
+
+impl From<SocketAddr> for TargetAddr {
+    fn from(addr: SocketAddr) -> TargetAddr {
+        TargetAddr::Ip(addr)
+    }
+}
+
+impl From<(&str, u16)> for TargetAddr {
+    fn from((host, port): (&str, u16)) -> TargetAddr {
+        TargetAddr::from((host.to_string(), port))
+    }
+}
+
+impl From<(String, u16)> for TargetAddr {
+    /// Takes IP addresses for what they are, and anything else for a name.
+    fn from((host, port): (String, u16)) -> TargetAddr {
+        match host.parse() {
+            Ok(ip) => TargetAddr::Ip(SocketAddr::new(ip, port)),
+            Err(_) => TargetAddr::Domain(host, port),
+        }
+    }
+}
+
+impl fmt::Display for TargetAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetAddr::Ip(addr) => addr.fmt(f),
+            TargetAddr::Domain(host, port) => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+/// Writes `buf[*pos..]` to `stream`, advancing `pos`.
+fn poll_write_buf(
+    stream: &mut TcpStream,
+    cx: &mut Context<'_>,
+    buf: &[u8],
+    pos: &mut usize,
+) -> Poll<io::Result<()>> {
+    while *pos < buf.len() {
+        let n = ready!(Pin::new(&mut *stream).poll_write(cx, &buf[*pos..]))?;
+        if n == 0 {
+            return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+        }
+        *pos += n;
+    }
+    Poll::Ready(Ok(()))
+}
+
+/// Fills `buf[*pos..]` from `stream`, advancing `pos`.
+fn poll_read_buf(
+    stream: &mut TcpStream,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+    pos: &mut usize,
+) -> Poll<io::Result<()>> {
+    while *pos < buf.len() {
+        let n = ready!(Pin::new(&mut *stream).poll_read(cx, &mut buf[*pos..]))?;
+        if n == 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "proxy closed the connection",
+            )));
+        }
+        *pos += n;
+    }
+    Poll::Ready(Ok(()))
+}
+
+fn invalid_reply(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid proxy reply: {}", msg),
+    )
+}
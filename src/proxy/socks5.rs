@@ -0,0 +1,340 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Future};
+
+use super::{invalid_reply, poll_read_buf, poll_write_buf, TargetAddr};
+use crate::tcp::{ConnectFuture, TcpStream};
+
+const VERSION: u8 = 5;
+const METHOD_NONE: u8 = 0;
+const METHOD_PASSWORD: u8 = 2;
+const METHOD_UNACCEPTABLE: u8 = 0xff;
+const CMD_CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+/// A TCP connection to a target, relayed by a SOCKS5 proxy.
+///
+/// Reads and writes go straight to the underlying `TcpStream`, which is
+/// connected to the proxy.
+pub struct Socks5Stream {
+    stream: TcpStream,
+}
+
+/// The future returned by `Socks5Stream::connect`, which will resolve to a
+/// `Socks5Stream` once the proxy has connected to the target.
+#[must_use = "futures do nothing unless polled"]
+pub struct Socks5Connect {
+    connect: Option<ConnectFuture>,
+    stream: Option<TcpStream>,
+    step: Step,
+    /// The subnegotiation for the password method, if one was given.
+    auth: Option<Vec<u8>>,
+    request: Vec<u8>,
+    /// The message being written or read.
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Connecting,
+    WriteGreeting,
+    ReadMethod,
+    WriteAuth,
+    ReadAuth,
+    WriteRequest,
+    /// The reply up to the first byte of the bound address, which tells its
+    /// length.
+    ReadReplyHead,
+    ReadReplyRest,
+    Failed(io::ErrorKind, &'static str),
+    Done,
+}
+
+impl Socks5Stream {
+    /// Connects to `target` through the SOCKS5 proxy at `proxy`, without
+    /// authentication.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the proxy can't be reached, requires authentication, or
+    /// can't connect to the target. Errors the proxy reports map to the
+    /// closest `io::ErrorKind`, such as `ConnectionRefused`, and replies that
+    /// aren't valid SOCKS5 to `InvalidData`.
+    pub fn connect<T: Into<TargetAddr>>(proxy: &SocketAddr, target: T) -> Socks5Connect {
+        Socks5Connect::new(proxy, target.into(), None)
+    }
+
+    /// Connects to `target` through the SOCKS5 proxy at `proxy`, logging in
+    /// with `username` and `password` if the proxy asks for them.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`connect`], and with `PermissionDenied` if the proxy
+    /// rejects the credentials. The future fails with `InvalidInput` if
+    /// either of them is longer than 255 bytes.
+    ///
+    /// [`connect`]: #method.connect
+    pub fn connect_with_password<T: Into<TargetAddr>>(
+        proxy: &SocketAddr,
+        target: T,
+        username: &str,
+        password: &str,
+    ) -> Socks5Connect {
+        Socks5Connect::new(proxy, target.into(), Some((username, password)))
+    }
+
+    /// Returns a shared reference to the underlying stream, connected to
+    /// the proxy.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the underlying stream, connected to
+    /// the proxy.
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.stream
+    }
+
+    /// Consumes the `Socks5Stream`, returning the underlying stream.
+    pub fn into_inner(self) -> TcpStream {
+        self.stream
+    }
+}
+
+impl AsyncRead for Socks5Stream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Socks5Stream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
+
+impl fmt::Debug for Socks5Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.stream.fmt(f)
+    }
+}
+
+// ===== impl Socks5Connect =====
+
+impl Socks5Connect {
+    fn new(proxy: &SocketAddr, target: TargetAddr, auth: Option<(&str, &str)>) -> Socks5Connect {
+        let mut connect = Socks5Connect {
+            connect: None,
+            stream: None,
+            step: Step::Connecting,
+            auth: None,
+            request: Vec::new(),
+            buf: Vec::new(),
+            pos: 0,
+        };
+
+        if let Some((username, password)) = auth {
+            if username.len() > 255 || password.len() > 255 {
+                connect.step = Step::Failed(
+                    io::ErrorKind::InvalidInput,
+                    "SOCKS5 username or password longer than 255 bytes",
+                );
+                return connect;
+            }
+            let mut msg = vec![1, username.len() as u8];
+            msg.extend_from_slice(username.as_bytes());
+            msg.push(password.len() as u8);
+            msg.extend_from_slice(password.as_bytes());
+            connect.auth = Some(msg);
+        }
+
+        let request = &mut connect.request;
+        request.extend_from_slice(&[VERSION, CMD_CONNECT, 0]);
+        let port = match target {
+            TargetAddr::Ip(SocketAddr::V4(addr)) => {
+                request.push(ATYP_IPV4);
+                request.extend_from_slice(&addr.ip().octets());
+                addr.port()
+            }
+            TargetAddr::Ip(SocketAddr::V6(addr)) => {
+                request.push(ATYP_IPV6);
+                request.extend_from_slice(&addr.ip().octets());
+                addr.port()
+            }
+            TargetAddr::Domain(host, port) => {
+                if host.is_empty() || host.len() > 255 {
+                    connect.step = Step::Failed(
+                        io::ErrorKind::InvalidInput,
+                        "SOCKS5 target host name empty or longer than 255 bytes",
+                    );
+                    return connect;
+                }
+                request.push(ATYP_DOMAIN);
+                request.push(host.len() as u8);
+                request.extend_from_slice(host.as_bytes());
+                port
+            }
+        };
+        request.extend_from_slice(&port.to_be_bytes());
+
+        connect.connect = Some(TcpStream::connect(proxy));
+        connect
+    }
+
+    /// Starts writing `msg` in the next step.
+    fn write(&mut self, msg: Vec<u8>, step: Step) {
+        self.buf = msg;
+        self.pos = 0;
+        self.step = step;
+    }
+
+    /// Starts reading `n` bytes in the next step.
+    fn read(&mut self, n: usize, step: Step) {
+        self.buf = vec![0; n];
+        self.pos = 0;
+        self.step = step;
+    }
+}
+
+impl Future for Socks5Connect {
+    type Output = io::Result<Socks5Stream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            if let Step::Connecting = this.step {
+                let stream = ready!(Pin::new(this.connect.as_mut().unwrap()).poll(cx))?;
+                this.connect = None;
+                this.stream = Some(stream);
+                let methods = if this.auth.is_some() {
+                    vec![VERSION, 2, METHOD_NONE, METHOD_PASSWORD]
+                } else {
+                    vec![VERSION, 1, METHOD_NONE]
+                };
+                this.write(methods, Step::WriteGreeting);
+                continue;
+            }
+            if let Step::Failed(kind, msg) = this.step {
+                this.step = Step::Done;
+                return Poll::Ready(Err(io::Error::new(kind, msg)));
+            }
+
+            let stream = this
+                .stream
+                .as_mut()
+                .expect("polled Socks5Connect after completion");
+            match this.step {
+                Step::WriteGreeting | Step::WriteAuth | Step::WriteRequest => {
+                    ready!(poll_write_buf(stream, cx, &this.buf, &mut this.pos))?
+                }
+                _ => ready!(poll_read_buf(stream, cx, &mut this.buf, &mut this.pos))?,
+            }
+
+            match this.step {
+                Step::WriteGreeting => this.read(2, Step::ReadMethod),
+                Step::ReadMethod => {
+                    if this.buf[0] != VERSION {
+                        return Poll::Ready(Err(invalid_reply("not SOCKS5")));
+                    }
+                    match this.buf[1] {
+                        METHOD_NONE => this.write(this.request.clone(), Step::WriteRequest),
+                        METHOD_PASSWORD if this.auth.is_some() => {
+                            let auth = this.auth.take().unwrap();
+                            this.write(auth, Step::WriteAuth);
+                        }
+                        METHOD_UNACCEPTABLE => {
+                            return Poll::Ready(Err(io::Error::new(
+                                io::ErrorKind::PermissionDenied,
+                                "SOCKS5 proxy requires an unsupported authentication method",
+                            )))
+                        }
+                        _ => return Poll::Ready(Err(invalid_reply("unexpected method"))),
+                    }
+                }
+                Step::WriteAuth => this.read(2, Step::ReadAuth),
+                Step::ReadAuth => {
+                    if this.buf[1] != 0 {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::PermissionDenied,
+                            "SOCKS5 proxy rejected the username or password",
+                        )));
+                    }
+                    this.write(this.request.clone(), Step::WriteRequest);
+                }
+                Step::WriteRequest => this.read(5, Step::ReadReplyHead),
+                Step::ReadReplyHead => {
+                    if this.buf[0] != VERSION {
+                        return Poll::Ready(Err(invalid_reply("not SOCKS5")));
+                    }
+                    if this.buf[1] != 0 {
+                        return Poll::Ready(Err(reply_error(this.buf[1])));
+                    }
+                    // The rest of the bound address, and the port.
+                    let rest = match this.buf[3] {
+                        ATYP_IPV4 => 3 + 2,
+                        ATYP_IPV6 => 15 + 2,
+                        ATYP_DOMAIN => this.buf[4] as usize + 2,
+                        _ => return Poll::Ready(Err(invalid_reply("unknown address type"))),
+                    };
+                    this.read(rest, Step::ReadReplyRest);
+                }
+                Step::ReadReplyRest => {
+                    this.step = Step::Done;
+                    let stream = this.stream.take().unwrap();
+                    return Poll::Ready(Ok(Socks5Stream { stream }));
+                }
+                Step::Connecting | Step::Failed(..) | Step::Done => unreachable!(),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Socks5Connect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Socks5Connect")
+            .field("step", &self.step)
+            .finish()
+    }
+}
+
+/// Maps a reply code of RFC 1928 to an error.
+fn reply_error(code: u8) -> io::Error {
+    let (kind, msg) = match code {
+        2 => (
+            io::ErrorKind::PermissionDenied,
+            "connection not allowed by ruleset",
+        ),
+        3 => (io::ErrorKind::NetworkUnreachable, "network unreachable"),
+        4 => (io::ErrorKind::HostUnreachable, "host unreachable"),
+        5 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        6 => (io::ErrorKind::TimedOut, "TTL expired"),
+        7 => (io::ErrorKind::InvalidInput, "command not supported"),
+        8 => (io::ErrorKind::InvalidInput, "address type not supported"),
+        _ => (io::ErrorKind::Other, "general SOCKS server failure"),
+    };
+    io::Error::new(kind, format!("SOCKS5 proxy: {}", msg))
+}
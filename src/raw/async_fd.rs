@@ -0,0 +1,226 @@
+use std::fmt;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use mio::event::Evented;
+use mio::unix::EventedFd;
+use mio::{PollOpt, Ready, Token};
+
+use super::PollEvented;
+use crate::sys;
+
+/// A file descriptor driven by the reactor.
+///
+/// `AsyncFd` takes ownership of any type that has a file descriptor, such as
+/// a `File` opened on a tun device or a serial port, puts the descriptor in
+/// non-blocking mode and registers it with the reactor. Reads and writes go
+/// straight to the descriptor with `read(2)` and `write(2)`, so `AsyncFd`
+/// implements `AsyncRead` and `AsyncWrite` whatever the wrapped type is.
+/// For other operations, such as an `ioctl` that may return `EAGAIN`, the
+/// readiness polling methods work like those of [`PollEvented`].
+///
+/// The descriptor has to be pollable: regular files and directories are
+/// rejected by epoll, so wrapping one fails.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::io::AsyncReadExt;
+/// use romio::raw::AsyncFd;
+/// use std::fs::OpenOptions;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let port = OpenOptions::new().read(true).write(true).open("/dev/ttyUSB0")?;
+/// let mut port = AsyncFd::new(port)?;
+///
+/// let mut buf = [0; 64];
+/// let n = port.read(&mut buf).await?;
+/// println!("{:?}", &buf[..n]);
+/// # Ok(())}
+/// ```
+///
+/// [`PollEvented`]: struct.PollEvented.html
+pub struct AsyncFd<T: AsRawFd> {
+    io: PollEvented<Fd<T>>,
+}
+
+/// Implements `Evented`, `Read` and `Write` on top of the descriptor.
+struct Fd<T>(T);
+
+impl<T: AsRawFd> AsyncFd<T> {
+    /// Wraps `inner`, setting its descriptor to non-blocking mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mode can't be set, or if the reactor can't
+    /// be reached.
+    pub fn new(inner: T) -> io::Result<AsyncFd<T>> {
+        sys::set_nonblocking(inner.as_raw_fd())?;
+        let io = PollEvented::new(Fd(inner));
+        // Registration is otherwise deferred to the first poll; do it now so
+        // descriptors that can't be polled are reported here.
+        io.register()?;
+        Ok(AsyncFd { io })
+    }
+
+    /// Returns a reference to the wrapped value.
+    pub fn get_ref(&self) -> &T {
+        &self.io.get_ref().0
+    }
+
+    /// Returns a mutable reference to the wrapped value.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io.get_mut().0
+    }
+
+    /// Deregisters the descriptor from the reactor and returns the wrapped
+    /// value.
+    ///
+    /// The descriptor stays in non-blocking mode.
+    pub fn into_inner(self) -> io::Result<T> {
+        self.io.into_inner().map(|fd| fd.0)
+    }
+
+    /// Polls the descriptor for read readiness.
+    ///
+    /// Once this returns `Ready`, the descriptor stays read-ready until
+    /// [`clear_read_ready`] is called, which should be done as soon as an
+    /// operation fails with `WouldBlock`.
+    ///
+    /// [`clear_read_ready`]: #method.clear_read_ready
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    /// Polls the descriptor for write readiness.
+    ///
+    /// Once this returns `Ready`, the descriptor stays write-ready until
+    /// [`clear_write_ready`] is called.
+    ///
+    /// [`clear_write_ready`]: #method.clear_write_ready
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+
+    /// Clears the read readiness of the descriptor, and arranges for the
+    /// current task to be woken once it is readable again.
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_read_ready(cx)
+    }
+
+    /// Clears the write readiness of the descriptor, and arranges for the
+    /// current task to be woken once it is writable again.
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_write_ready(cx)
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for AsyncFd<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.get_ref().as_raw_fd()
+    }
+}
+
+impl<T: AsRawFd> AsyncRead for AsyncFd<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl<T: AsRawFd> AsyncWrite for AsyncFd<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsRawFd + fmt::Debug> fmt::Debug for AsyncFd<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AsyncFd").field(self.get_ref()).finish()
+    }
+}
+
+impl<T: AsRawFd> Evented for Fd<T> {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).deregister(poll)
+    }
+}
+
+impl<T: AsRawFd> Read for &Fd<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_vectored(&mut [IoSliceMut::new(buf)])
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        sys::readv(self.0.as_raw_fd(), bufs)
+    }
+}
+
+impl<T: AsRawFd> Write for &Fd<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        sys::writev(self.0.as_raw_fd(), bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
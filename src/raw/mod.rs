@@ -9,3 +9,23 @@ pub use async_ready::*;
 
 mod poll_evented;
 pub use poll_evented::*;
+
+pub use crate::reactor::{
+    Background, Balance, Handle, LocalReactor, LocalSpawner, Reactor, ReactorPool,
+    ReactorPoolBuilder, Shutdown, Turn,
+};
+
+#[cfg(unix)]
+mod async_fd;
+#[cfg(unix)]
+pub use async_fd::AsyncFd;
+
+#[cfg(unix)]
+mod pipe;
+#[cfg(unix)]
+pub use pipe::{pipe, PipeReader, PipeWriter};
+
+#[cfg(unix)]
+mod socket_info;
+#[cfg(unix)]
+pub use socket_info::*;
@@ -0,0 +1,238 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::pin::Pin;
+use std::process::{ChildStderr, ChildStdin, ChildStdout};
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+
+use super::AsyncFd;
+use crate::sys;
+
+/// Creates a pipe, returning its read and write ends.
+///
+/// Both ends are non-blocking, close-on-exec and driven by the reactor.
+///
+/// # Examples
+///
+/// ```rust
+/// #![feature(async_await)]
+/// use futures::executor;
+/// use futures::io::{AsyncReadExt, AsyncWriteExt};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (mut reader, mut writer) = romio::raw::pipe()?;
+///
+/// executor::block_on(async {
+///     writer.write_all(b"hello").await?;
+///     drop(writer);
+///
+///     let mut buf = Vec::new();
+///     reader.read_to_end(&mut buf).await?;
+///     assert_eq!(buf, b"hello");
+///     Ok(())
+/// })
+/// # }
+/// ```
+pub fn pipe() -> io::Result<(PipeReader, PipeWriter)> {
+    let (read, write) = sys::pipe()?;
+    let (read, write) = unsafe { (File::from_raw_fd(read), File::from_raw_fd(write)) };
+    Ok((PipeReader::new(read)?, PipeWriter::new(write)?))
+}
+
+/// The read end of a pipe.
+///
+/// Created by [`pipe`](fn.pipe.html), or adopted from an existing
+/// descriptor, such as the standard output of a child process:
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::io::AsyncReadExt;
+/// use romio::raw::PipeReader;
+/// use std::convert::TryFrom;
+/// use std::process::{Command, Stdio};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let mut child = Command::new("ls").stdout(Stdio::piped()).spawn()?;
+/// let mut stdout = PipeReader::try_from(child.stdout.take().unwrap())?;
+///
+/// let mut listing = String::new();
+/// stdout.read_to_string(&mut listing).await?;
+/// # Ok(())}
+/// ```
+pub struct PipeReader {
+    io: AsyncFd<File>,
+}
+
+/// The write end of a pipe.
+///
+/// Created by [`pipe`](fn.pipe.html), or adopted from an existing
+/// descriptor, such as the standard input of a child process.
+pub struct PipeWriter {
+    io: AsyncFd<File>,
+}
+
+/// Checks that `file` is a pipe before it is wrapped.
+fn adopt(file: File) -> io::Result<AsyncFd<File>> {
+    if !sys::is_fifo(file.as_raw_fd())? {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "file descriptor is not a pipe",
+        ));
+    }
+    AsyncFd::new(file)
+}
+
+impl PipeReader {
+    fn new(file: File) -> io::Result<PipeReader> {
+        adopt(file).map(|io| PipeReader { io })
+    }
+
+    /// Returns the descriptor, for splicing from the pipe.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn as_async_fd(&self) -> &AsyncFd<File> {
+        &self.io
+    }
+}
+
+impl PipeWriter {
+    fn new(file: File) -> io::Result<PipeWriter> {
+        adopt(file).map(|io| PipeWriter { io })
+    }
+
+    /// Returns the descriptor, for splicing into the pipe.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn as_async_fd(&self) -> &AsyncFd<File> {
+        &self.io
+    }
+}
+
+/// Takes ownership of the read end of a pipe or a FIFO opened as a `File`,
+/// and puts it in non-blocking mode.
+///
+/// # Errors
+///
+/// Fails with `InvalidInput` if `file` isn't a pipe or a FIFO. The file is
+/// closed whether this succeeds or not.
+impl TryFrom<File> for PipeReader {
+    type Error = io::Error;
+
+    fn try_from(file: File) -> io::Result<PipeReader> {
+        PipeReader::new(file)
+    }
+}
+
+/// Takes ownership of the write end of a pipe or a FIFO opened as a `File`,
+/// and puts it in non-blocking mode.
+///
+/// # Errors
+///
+/// Fails with `InvalidInput` if `file` isn't a pipe or a FIFO. The file is
+/// closed whether this succeeds or not.
+impl TryFrom<File> for PipeWriter {
+    type Error = io::Error;
+
+    fn try_from(file: File) -> io::Result<PipeWriter> {
+        PipeWriter::new(file)
+    }
+}
+
+impl TryFrom<ChildStdout> for PipeReader {
+    type Error = io::Error;
+
+    fn try_from(stdout: ChildStdout) -> io::Result<PipeReader> {
+        // `into_raw_fd` hands over ownership of the descriptor.
+        PipeReader::new(unsafe { File::from_raw_fd(stdout.into_raw_fd()) })
+    }
+}
+
+impl TryFrom<ChildStderr> for PipeReader {
+    type Error = io::Error;
+
+    fn try_from(stderr: ChildStderr) -> io::Result<PipeReader> {
+        PipeReader::new(unsafe { File::from_raw_fd(stderr.into_raw_fd()) })
+    }
+}
+
+impl TryFrom<ChildStdin> for PipeWriter {
+    type Error = io::Error;
+
+    fn try_from(stdin: ChildStdin) -> io::Result<PipeWriter> {
+        PipeWriter::new(unsafe { File::from_raw_fd(stdin.into_raw_fd()) })
+    }
+}
+
+impl AsRawFd for PipeReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+}
+
+impl AsRawFd for PipeWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+}
+
+impl AsyncRead for PipeReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl AsyncWrite for PipeWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_close(cx)
+    }
+}
+
+impl fmt::Debug for PipeReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipeReader")
+            .field("fd", &self.as_raw_fd())
+            .finish()
+    }
+}
+
+impl fmt::Debug for PipeWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipeWriter")
+            .field("fd", &self.as_raw_fd())
+            .finish()
+    }
+}
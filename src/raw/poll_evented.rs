@@ -2,25 +2,32 @@
 
 use crate::reactor::platform;
 use crate::reactor::registration::Registration;
+use crate::reactor::Handle;
 
 use futures::io::{AsyncRead, AsyncWrite};
 use futures::{ready, Poll};
 use mio;
 use mio::event::Evented;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use mio::unix::UnixReady;
 
 use std::fmt;
 use std::io::{self, Read, Write};
 use std::pin::Pin;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering::Relaxed;
+#[cfg(debug_assertions)]
+use std::sync::Mutex;
 use std::task::Context;
+#[cfg(debug_assertions)]
+use std::task::Waker;
 
 /// Associates an I/O resource that implements the [`std::io::Read`] and/or
 /// [`std::io::Write`] traits with the reactor that drives it.
 ///
-/// `PollEvented` uses [`Registration`] internally to take a type that
-/// implements [`mio::Evented`] as well as [`std::io::Read`] and or
-/// [`std::io::Write`] and associate it with a reactor that will drive it.
+/// `PollEvented` takes a type that implements [`mio::Evented`] as well as
+/// [`std::io::Read`] and or [`std::io::Write`] and associates it with the
+/// reactor that will drive it.
 ///
 /// Once the [`mio::Evented`] type is wrapped by `PollEvented`, it can be
 /// used from within the future's execution model. As such, the `PollEvented`
@@ -34,12 +41,90 @@ use std::task::Context;
 /// view, it will result in unexpected behavior in the form of lost
 /// notifications and tasks hanging.
 ///
+/// Each direction stores a single waker: polling again replaces the waker
+/// from the previous poll, so only the task that polled a direction last is
+/// notified when it becomes ready. This is the usual contract of futures and
+/// keeps the memory used per socket fixed no matter how many tasks poll it.
+/// It also means a second task awaiting the same direction silently takes
+/// over from the first, which then hangs. Both [`poll_read_ready`] and
+/// [`poll_write_ready`] take `&self`, and `&PollEvented` implements
+/// `AsyncRead` as well as `AsyncWrite`, so this can happen in either
+/// direction through a shared reference. Reads, and writes, that may have to
+/// wait should each be made from one task, or serialized by a lock around the
+/// socket. Debug builds panic when a task that was waiting on a direction
+/// polls it again after another task took it over, before the resource
+/// became ready.
+///
+/// ## Wrapping your own resources
+///
+/// `PollEvented` is the way into the reactor for resources this crate has
+/// no wrapper for, such as a `signalfd`, a `timerfd` or a tun device: any
+/// type that implements mio 0.6's [`mio::Evented`] can be wrapped, and is
+/// registered with the reactor the first time it is polled. On Unix,
+/// [`mio::unix::EventedFd`] implements `Evented` for a raw file descriptor,
+/// which the resource must have put in non-blocking mode.
+///
+/// ```rust
+/// #![feature(async_await)]
+/// use futures::executor;
+/// use futures::io::AsyncReadExt;
+/// use mio::unix::EventedFd;
+/// use mio::{Evented, Poll, PollOpt, Ready, Token};
+/// use romio::raw::PollEvented;
+/// use std::fs::File;
+/// use std::io::{self, Read, Write};
+/// use std::os::unix::io::{AsRawFd, FromRawFd};
+///
+/// /// The read end of a pipe.
+/// struct PipeReader(File);
+///
+/// impl Read for PipeReader {
+///     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+///         self.0.read(buf)
+///     }
+/// }
+///
+/// impl Evented for PipeReader {
+///     fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt)
+///         -> io::Result<()>
+///     {
+///         EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+///     }
+///
+///     fn reregister(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt)
+///         -> io::Result<()>
+///     {
+///         EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+///     }
+///
+///     fn deregister(&self, poll: &Poll) -> io::Result<()> {
+///         EventedFd(&self.0.as_raw_fd()).deregister(poll)
+///     }
+/// }
+///
+/// # fn main() -> io::Result<()> {
+/// let mut fds = [0; 2];
+/// unsafe {
+///     assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+///     libc::fcntl(fds[0], libc::F_SETFL, libc::O_NONBLOCK);
+/// }
+/// let mut writer = unsafe { File::from_raw_fd(fds[1]) };
+/// let mut reader = PollEvented::new(PipeReader(unsafe { File::from_raw_fd(fds[0]) }));
+///
+/// writer.write_all(b"ping")?;
+/// let mut buf = [0; 4];
+/// executor::block_on(reader.read_exact(&mut buf))?;
+/// assert_eq!(&buf, b"ping");
+/// # Ok(())
+/// # }
+/// ```
+///
 /// ## Readiness events
 ///
-/// Besides just providing [`AsyncRead`] and [`AsyncWrite`] implementations,
-/// this type also supports access to the underlying readiness event stream.
-/// While similar in function to what [`Registration`] provides, the semantics
-/// are a bit different.
+/// Besides the [`AsyncRead`] and [`AsyncWrite`] implementations, which
+/// need the wrapped type to implement [`std::io::Read`] and
+/// [`std::io::Write`], `PollEvented` gives access to the readiness of the
+/// resource, for operations that those traits don't cover.
 ///
 /// Two functions are provided to access the readiness events:
 /// [`poll_read_ready`] and [`poll_write_ready`]. These functions return the
@@ -53,22 +138,21 @@ use std::task::Context;
 /// event is received.
 ///
 /// This allows the caller to implement additional functions. For example,
-/// [`TcpListener`] implements poll_accept by using [`poll_read_ready`] and
-/// [`clear_read_ready`].
+/// [`TcpListener`] accepts connections by using [`poll_read_ready`] and
+/// [`clear_read_ready`]:
 ///
 /// ```rust,ignore
-/// pub fn poll_accept(&mut self) -> Poll<(net::TcpStream, SocketAddr), io::Error> {
-///     let ready = Ready::readable();
-///
-///     try_ready!(self.poll_evented.poll_read_ready(ready));
+/// pub fn poll_accept_std(&mut self, cx: &mut Context<'_>)
+///     -> Poll<io::Result<(net::TcpStream, SocketAddr)>>
+/// {
+///     ready!(self.io.poll_read_ready(cx)?);
 ///
-///     match self.poll_evented.get_ref().accept_std() {
-///         Ok(pair) => Ok(Async::Ready(pair)),
+///     match self.io.get_ref().accept_std() {
 ///         Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-///             self.poll_evented.clear_read_ready(ready);
-///             Ok(Async::NotReady)
+///             self.io.clear_read_ready(cx)?;
+///             Poll::Pending
 ///         }
-///         Err(e) => Err(e),
+///         res => Poll::Ready(res),
 ///     }
 /// }
 /// ```
@@ -81,11 +165,11 @@ use std::task::Context;
 ///
 /// [`std::io::Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
 /// [`std::io::Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
-/// [`AsyncRead`]: ../io/trait.AsyncRead.html
-/// [`AsyncWrite`]: ../io/trait.AsyncWrite.html
+/// [`AsyncRead`]: https://docs.rs/futures-preview/0.3.0-alpha.19/futures/io/trait.AsyncRead.html
+/// [`AsyncWrite`]: https://docs.rs/futures-preview/0.3.0-alpha.19/futures/io/trait.AsyncWrite.html
 /// [`mio::Evented`]: https://docs.rs/mio/0.6/mio/trait.Evented.html
-/// [`Registration`]: struct.Registration.html
-/// [`TcpListener`]: ../net/struct.TcpListener.html
+/// [`mio::unix::EventedFd`]: https://docs.rs/mio/0.6/mio/unix/struct.EventedFd.html
+/// [`TcpListener`]: ../tcp/struct.TcpListener.html
 /// [`clear_read_ready`]: #method.clear_read_ready
 /// [`clear_write_ready`]: #method.clear_write_ready
 /// [`poll_read_ready`]: #method.poll_read_ready
@@ -105,6 +189,12 @@ struct Inner {
 
     /// Currently visible write readiness
     write_readiness: AtomicUsize,
+
+    #[cfg(debug_assertions)]
+    read_waiter: Waiter,
+
+    #[cfg(debug_assertions)]
+    write_waiter: Waiter,
 }
 
 // ===== impl PollEvented =====
@@ -121,10 +211,30 @@ where
                 registration: Registration::new(),
                 read_readiness: AtomicUsize::new(0),
                 write_readiness: AtomicUsize::new(0),
+                #[cfg(debug_assertions)]
+                read_waiter: Waiter::default(),
+                #[cfg(debug_assertions)]
+                write_waiter: Waiter::default(),
             },
         }
     }
 
+    /// Creates a new `PollEvented` bound to the reactor of `handle`.
+    ///
+    /// Unlike [`new`], this registers `io` right away, so it is driven by
+    /// that reactor no matter where it is first polled. A default handle
+    /// registers with the default reactor.
+    ///
+    /// [`new`]: #method.new
+    pub fn new_with_handle(io: E, handle: &Handle) -> io::Result<PollEvented<E>> {
+        let evented = PollEvented::new(io);
+        evented
+            .inner
+            .registration
+            .register_with(evented.get_ref(), handle)?;
+        Ok(evented)
+    }
+
     /// Returns a shared reference to the underlying I/O object this readiness
     /// stream is wrapping.
     pub fn get_ref(&self) -> &E {
@@ -137,39 +247,54 @@ where
         self.io.as_mut().unwrap()
     }
 
-    // TODO: restore this once we make reactor::poll_evented public
-    // /// Consumes self, returning the inner I/O object
-    // ///
-    // /// This function will deregister the I/O resource from the reactor before
-    // /// returning. If the deregistration operation fails, an error is returned.
-    // ///
-    // /// Note that deregistering does not guarantee that the I/O resource can be
-    // /// registered with a different reactor. Some I/O resource types can only be
-    // /// associated with a single reactor instance for their lifetime.
-    // pub fn into_inner(mut self) -> io::Result<E> {
-    //     let io = self.io.take().unwrap();
-    //     self.inner.registration.deregister(&io)?;
-    //     Ok(io)
-    // }
+    /// Consumes self, returning the inner I/O object.
+    ///
+    /// This function will deregister the I/O resource from the reactor before
+    /// returning. If the deregistration operation fails, an error is returned.
+    ///
+    /// Note that deregistering does not guarantee that the I/O resource can be
+    /// registered with a different reactor. Some I/O resource types can only be
+    /// associated with a single reactor instance for their lifetime.
+    pub fn into_inner(mut self) -> io::Result<E> {
+        let io = self.io.take().unwrap();
+        self.inner.registration.deregister(&io)?;
+        Ok(io)
+    }
+
+    /// Like `into_inner`, but hands the I/O object back even if it can't be
+    /// deregistered, ignoring the error the way dropping does.
+    ///
+    /// This is what the `IntoRawFd` implementations of the socket types use,
+    /// since `into_raw_fd` can't fail. The descriptor is left in non-blocking
+    /// mode, and whatever readiness the reactor had seen for it is forgotten,
+    /// so whoever takes it over should make its calls until they fail with
+    /// `WouldBlock` before waiting for readiness.
+    #[cfg(unix)]
+    pub fn into_inner_lossy(mut self) -> E {
+        let io = self.io.take().unwrap();
+        let _ = self.inner.registration.deregister(&io);
+        io
+    }
 
     /// Check the I/O resource's read readiness state.
     ///
-    /// The mask argument allows specifying what readiness to notify on. This
-    /// can be any value, including platform specific readiness, **except**
-    /// `writable`. HUP is always implicitly included on platforms that support
-    /// it.
+    /// This checks for readable readiness, and also for HUP readiness on
+    /// platforms that support it.
     ///
-    /// If the resource is not ready for a read then `Async::NotReady` is
+    /// If the resource is not ready for a read then `Poll::Pending` is
     /// returned and the current task is notified once a new event is received.
     ///
     /// The I/O resource will remain in a read-ready state until readiness is
     /// cleared by calling [`clear_read_ready`].
     ///
+    /// This is also how code that makes its own syscalls on a resource leaves
+    /// the wakeups to the reactor: poll for readiness, make the syscall, and
+    /// call [`clear_read_ready`] as soon as it fails with `WouldBlock`, so the
+    /// current task is woken once the resource is readable again. The socket
+    /// types expose the same four methods for their descriptors.
+    ///
     /// [`clear_read_ready`]: #method.clear_read_ready
-    pub fn poll_read_ready(
-        self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-    ) -> Poll<io::Result<mio::Ready>> {
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
         self.register()?;
 
         // Load cached & encoded readiness.
@@ -184,7 +309,16 @@ where
             // stream. This happens in a loop to ensure that the stream gets
             // drained.
             loop {
-                let ready = ready!(self.inner.registration.poll_read_ready(cx)?);
+                let ready = match self.inner.registration.poll_read_ready(cx)? {
+                    Poll::Ready(ready) => ready,
+                    Poll::Pending => {
+                        #[cfg(debug_assertions)]
+                        self.inner.read_waiter.wait(cx.waker(), "read");
+                        return Poll::Pending;
+                    }
+                };
+                #[cfg(debug_assertions)]
+                self.inner.read_waiter.woken();
                 cached |= ready.as_usize();
 
                 // Update the cache store
@@ -211,12 +345,9 @@ where
     /// Clears the I/O resource's read readiness state and registers the current
     /// task to be notified once a read readiness event is received.
     ///
-    /// After calling this function, `poll_read_ready` will return `NotReady`
-    /// until a new read readiness event has been received.
-    ///
-    /// The `mask` argument specifies the readiness bits to clear. This may not
-    /// include `writable` or `hup`.
-    pub fn clear_read_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Result<()> {
+    /// After calling this function, `poll_read_ready` will return
+    /// `Poll::Pending` until a new read readiness event has been received.
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
         self.inner
             .read_readiness
             .fetch_and(!mio::Ready::readable().as_usize(), Relaxed);
@@ -234,20 +365,13 @@ where
     /// This always checks for writable readiness and also checks for HUP
     /// readiness on platforms that support it.
     ///
-    /// If the resource is not ready for a write then `Async::NotReady` is
+    /// If the resource is not ready for a write then `Poll::Pending` is
     /// returned and the current task is notified once a new event is received.
     ///
     /// The I/O resource will remain in a write-ready state until readiness is
     /// cleared by calling [`clear_write_ready`].
     ///
     /// [`clear_write_ready`]: #method.clear_write_ready
-    ///
-    /// # Panics
-    ///
-    /// This function panics if:
-    ///
-    /// * `ready` contains bits besides `writable` and `hup`.
-    /// * called from outside of a task context.
     pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<Result<mio::Ready, io::Error>> {
         self.register()?;
 
@@ -263,7 +387,16 @@ where
             // stream. This happens in a loop to ensure that the stream gets
             // drained.
             loop {
-                let ready = ready!(self.inner.registration.poll_write_ready(cx)?);
+                let ready = match self.inner.registration.poll_write_ready(cx)? {
+                    Poll::Ready(ready) => ready,
+                    Poll::Pending => {
+                        #[cfg(debug_assertions)]
+                        self.inner.write_waiter.wait(cx.waker(), "write");
+                        return Poll::Pending;
+                    }
+                };
+                #[cfg(debug_assertions)]
+                self.inner.write_waiter.woken();
                 cached |= ready.as_usize();
 
                 // Update the cache store
@@ -293,13 +426,9 @@ where
     /// This only clears writable readiness. HUP (on platforms that support HUP)
     /// cannot be cleared as it is a final state.
     ///
-    /// After calling this function, `poll_write_ready(Ready::writable())` will
-    /// return `NotReady` until a new write readiness event has been received.
-    ///
-    /// # Panics
-    ///
-    /// This function will panic if called from outside of a task context.
-    pub fn clear_write_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Result<()> {
+    /// After calling this function, `poll_write_ready` will return
+    /// `Poll::Pending` until a new write readiness event has been received.
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
         self.inner
             .write_readiness
             .fetch_and(!mio::Ready::writable().as_usize(), Relaxed);
@@ -312,8 +441,69 @@ where
         Ok(())
     }
 
+    /// Check the I/O resource's priority readiness state.
+    ///
+    /// This checks for `EPOLLPRI`, which is how the kernel signals TCP urgent
+    /// data, among other exceptional conditions. Priority events are part of
+    /// the read readiness event stream, so a task waiting for them shares
+    /// its wakeups with a task waiting to read.
+    ///
+    /// If the resource is not priority-ready then `Poll::Pending` is
+    /// returned and the current task is notified once a new event is received.
+    ///
+    /// The I/O resource will remain in a priority-ready state until readiness
+    /// is cleared by calling [`clear_priority_ready`].
+    ///
+    /// This method is only available on Linux and Android.
+    ///
+    /// [`clear_priority_ready`]: #method.clear_priority_ready
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn poll_priority_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.register()?;
+
+        let mut cached = self.inner.read_readiness.load(Relaxed);
+        let mask = mio::Ready::from(UnixReady::priority());
+
+        if (mio::Ready::from_usize(cached) & mask).is_empty() {
+            loop {
+                let ready = ready!(self.inner.registration.poll_read_ready(cx)?);
+                cached |= ready.as_usize();
+                self.inner.read_readiness.store(cached, Relaxed);
+
+                if !(ready & mask).is_empty() {
+                    return Poll::Ready(Ok(ready));
+                }
+            }
+        } else {
+            if let Some(ready) = self.inner.registration.take_read_ready()? {
+                cached |= ready.as_usize();
+                self.inner.read_readiness.store(cached, Relaxed);
+            }
+
+            Poll::Ready(Ok(mio::Ready::from_usize(cached)))
+        }
+    }
+
+    /// Clears the I/O resource's priority readiness state and registers the
+    /// current task to be notified once a priority event is received.
+    ///
+    /// This method is only available on Linux and Android.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn clear_priority_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.inner
+            .read_readiness
+            .fetch_and(!mio::Ready::from(UnixReady::priority()).as_usize(), Relaxed);
+
+        if self.poll_priority_ready(cx)?.is_ready() {
+            // Notify the current task
+            cx.waker().wake_by_ref();
+        }
+
+        Ok(())
+    }
+
     /// Ensure that the I/O resource is registered with the reactor.
-    fn register(&self) -> io::Result<()> {
+    pub(crate) fn register(&self) -> io::Result<()> {
         self.inner
             .registration
             .register(self.io.as_ref().unwrap())?;
@@ -334,6 +524,10 @@ where
     ) -> Poll<io::Result<usize>> {
         ready!(Pin::new(&mut *self).poll_read_ready(cx)?);
 
+        // Readiness is only a hint to try the read. kqueue reports a stream
+        // at EOF as readable with 0 bytes available, the same way it reports
+        // a pending 0-byte datagram, so only the result of the read itself
+        // tells EOF and data apart.
         let r = PollEvented::get_mut(&mut *self).read(buf);
 
         if is_wouldblock(&r) {
@@ -384,6 +578,100 @@ where
     }
 }
 
+impl<'a, E> AsyncRead for &'a PollEvented<E>
+where
+    E: Evented,
+    &'a E: Read,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_read_ready(cx)?);
+
+        let r = self.get_ref().read(buf);
+
+        if is_wouldblock(&r) {
+            self.clear_read_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+}
+
+impl<'a, E> AsyncWrite for &'a PollEvented<E>
+where
+    E: Evented,
+    &'a E: Write,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_write_ready(cx)?);
+
+        let r = self.get_ref().write(buf);
+
+        if is_wouldblock(&r) {
+            self.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_ready(cx)?);
+
+        let r = self.get_ref().flush();
+
+        if is_wouldblock(&r) {
+            self.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The waker of the last poll of a direction that is still waiting for it,
+/// and the one it took over from, to catch two tasks waiting at once.
+///
+/// A task that was taken over may have dropped its wait, so only its coming
+/// back while the other task still waits shows that both are waiting.
+#[cfg(debug_assertions)]
+#[derive(Default)]
+struct Waiter(Mutex<(Option<Waker>, Option<Waker>)>);
+
+#[cfg(debug_assertions)]
+impl Waiter {
+    fn wait(&self, waker: &Waker, direction: &str) {
+        let mut state = self.0.lock().unwrap();
+        let (waiting, displaced) = &mut *state;
+        let came_back = displaced.as_ref().is_some_and(|w| w.will_wake(waker));
+        if !waiting.as_ref().is_some_and(|w| w.will_wake(waker)) {
+            *displaced = waiting.replace(waker.clone());
+        }
+        drop(state);
+        debug_assert!(
+            !came_back,
+            "PollEvented polled for {} readiness from two tasks at once",
+            direction
+        );
+    }
+
+    fn woken(&self) {
+        *self.0.lock().unwrap() = (None, None);
+    }
+}
+
 fn is_wouldblock<T>(r: &io::Result<T>) -> bool {
     match *r {
         Ok(_) => false,
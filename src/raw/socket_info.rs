@@ -0,0 +1,76 @@
+use std::io;
+use std::os::unix::io::RawFd;
+
+use libc::c_int;
+
+/// The domain, type and protocol of a socket.
+///
+/// Each field holds the raw `libc` constant, e.g. `libc::AF_INET6`,
+/// `libc::SOCK_STREAM` and `libc::IPPROTO_TCP`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketInfo {
+    /// The address family, such as `AF_INET`, `AF_INET6` or `AF_UNIX`.
+    pub domain: c_int,
+    /// The socket type, such as `SOCK_STREAM`, `SOCK_DGRAM` or
+    /// `SOCK_SEQPACKET`.
+    pub sock_type: c_int,
+    /// The protocol, such as `IPPROTO_TCP` or `IPPROTO_UDP`.
+    ///
+    /// This is 0 on platforms that can't report it.
+    pub protocol: c_int,
+}
+
+/// Classifies the socket behind a file descriptor.
+///
+/// This is meant for code that adopts inherited descriptors, e.g. from a
+/// socket activation manager, and needs to pick the matching wrapper: a
+/// `SOCK_STREAM` socket in `AF_INET` or `AF_INET6` is a `TcpListener` or
+/// `TcpStream`, a `SOCK_DGRAM` one a `UdpSocket`, and so on.
+///
+/// On Linux and Android this reads `SO_DOMAIN`, `SO_TYPE` and `SO_PROTOCOL`.
+/// Elsewhere the domain is taken from the socket's local address and the
+/// protocol is reported as 0.
+///
+/// # Errors
+///
+/// Returns an error if `fd` is not a socket.
+///
+/// # Examples
+///
+/// ```rust
+/// use romio::raw::socket_info;
+/// use std::os::unix::io::AsRawFd;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+/// let info = socket_info(socket.as_raw_fd())?;
+/// assert_eq!(info.domain, libc::AF_INET);
+/// assert_eq!(info.sock_type, libc::SOCK_DGRAM);
+/// # Ok(())
+/// # }
+/// ```
+pub fn socket_info(fd: RawFd) -> io::Result<SocketInfo> {
+    let sock_type = crate::sys::getsockopt(fd, libc::SOL_SOCKET, libc::SO_TYPE)?;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let (domain, protocol) = (
+        crate::sys::getsockopt(fd, libc::SOL_SOCKET, libc::SO_DOMAIN)?,
+        crate::sys::getsockopt(fd, libc::SOL_SOCKET, libc::SO_PROTOCOL)?,
+    );
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let (domain, protocol) = {
+        let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of_val(&storage) as libc::socklen_t;
+        crate::sys::cvt(unsafe {
+            libc::getsockname(fd, &mut storage as *mut _ as *mut libc::sockaddr, &mut len)
+        })?;
+        (c_int::from(storage.ss_family), 0)
+    };
+
+    Ok(SocketInfo {
+        domain,
+        sock_type,
+        protocol,
+    })
+}
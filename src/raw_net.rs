@@ -0,0 +1,449 @@
+//! Raw IP sockets.
+//!
+//! A [`RawSocket`] sends and receives the payloads of IP packets of a single
+//! protocol, such as ICMP, without a transport layer in between. This is what
+//! tools like ping and traceroute are built on. [`IcmpSocket`] is a raw
+//! socket for ICMP, or ICMPv6, that also builds echo requests.
+//!
+//! Creating a raw socket requires `CAP_NET_RAW` on Linux, and root elsewhere;
+//! without it, the constructors fail with `PermissionDenied`.
+//!
+//! [`RawSocket`]: struct.RawSocket.html
+//! [`IcmpSocket`]: struct.IcmpSocket.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use romio::raw_net::IcmpSocket;
+//!
+//! # async fn ping() -> Result<(), Box<dyn std::error::Error + 'static>> {
+//! let mut socket = IcmpSocket::new_v4()?;
+//! let target = "127.0.0.1".parse()?;
+//! socket.send_echo(&target, 0x1234, 1, b"hello").await?;
+//!
+//! let mut buf = [0; 1500];
+//! let (n, from) = socket.recv_from(&mut buf).await?;
+//! println!("{} bytes of ICMP type {} from {}", n, buf[0], from);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::task::Context;
+
+use futures::{ready, Future, Poll};
+
+use crate::raw::AsyncFd;
+use crate::sys;
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+
+/// A raw IP socket, for a single protocol.
+///
+/// Datagrams sent with [`send_to`] are the payload of an IP packet, whose
+/// header the kernel fills in. What [`recv_from`] returns depends on the
+/// family: an IPv4 socket receives whole packets, starting with their IP
+/// header, while an IPv6 socket receives only the payload.
+///
+/// [`send_to`]: #method.send_to
+/// [`recv_from`]: #method.recv_from
+pub struct RawSocket {
+    io: AsyncFd<File>,
+    ipv6: bool,
+}
+
+/// A raw socket for ICMP or ICMPv6.
+///
+/// Besides sending echo requests, this receives ICMP messages on their own,
+/// without the IPv4 header a raw IPv4 socket otherwise returns, so both
+/// families can be handled alike.
+#[derive(Debug)]
+pub struct IcmpSocket {
+    socket: RawSocket,
+}
+
+impl RawSocket {
+    /// Creates a raw IPv4 socket for `protocol`, such as
+    /// `libc::IPPROTO_ICMP`.
+    pub fn new_v4(protocol: i32) -> io::Result<RawSocket> {
+        RawSocket::new(libc::AF_INET, protocol)
+    }
+
+    /// Creates a raw IPv6 socket for `protocol`, such as
+    /// `libc::IPPROTO_ICMPV6`.
+    pub fn new_v6(protocol: i32) -> io::Result<RawSocket> {
+        RawSocket::new(libc::AF_INET6, protocol)
+    }
+
+    fn new(family: libc::c_int, protocol: i32) -> io::Result<RawSocket> {
+        let file: File = sys::socket_owned(family, libc::SOCK_RAW, protocol)?;
+        Ok(RawSocket {
+            io: AsyncFd::new(file)?,
+            ipv6: family == libc::AF_INET6,
+        })
+    }
+
+    /// Binds the socket to a local address, so it only receives packets
+    /// sent to that address, and sends from it.
+    pub fn bind(&self, addr: &IpAddr) -> io::Result<()> {
+        sys::bind(self.as_raw_fd(), &SocketAddr::new(*addr, 0))
+    }
+
+    /// Sends `buf` as the payload of an IP packet to `target`.
+    ///
+    /// The future resolves to the number of bytes sent.
+    pub fn send_to<'a, 'b>(&'a mut self, buf: &'b [u8], target: &'b IpAddr) -> SendTo<'a, 'b> {
+        SendTo {
+            socket: self,
+            buf,
+            target,
+        }
+    }
+
+    /// Receives a packet, resolving to the number of bytes copied into `buf`
+    /// and the address it came from.
+    ///
+    /// A packet longer than `buf` is cut short.
+    pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
+        RecvFrom { socket: self, buf }
+    }
+
+    /// Attempts to send `buf` as the payload of an IP packet to `target`.
+    ///
+    /// If the socket isn't writable, `Poll::Pending` is returned and the
+    /// current task is woken once it is.
+    pub fn poll_send_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: &IpAddr,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match sys::send_to(self.as_raw_fd(), buf, &SocketAddr::new(*target, 0)) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Attempts to receive a packet.
+    ///
+    /// If none is waiting, `Poll::Pending` is returned and the current task
+    /// is woken once one arrives.
+    pub fn poll_recv_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, IpAddr)>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match sys::recv_from(self.as_raw_fd(), buf) {
+            Ok((n, addr)) => Poll::Ready(Ok((n, addr.ip()))),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Gets the time-to-live, or hop limit for IPv6, of the packets sent
+    /// from this socket.
+    ///
+    /// For more information about this option, see [`set_ttl`].
+    ///
+    /// [`set_ttl`]: #method.set_ttl
+    pub fn ttl(&self) -> io::Result<u32> {
+        let (level, name) = self.ttl_option();
+        sys::getsockopt::<libc::c_int>(self.as_raw_fd(), level, name).map(|ttl| ttl as u32)
+    }
+
+    /// Sets the time-to-live, or hop limit for IPv6, of the packets sent
+    /// from this socket.
+    ///
+    /// This sets `IP_TTL` on an IPv4 socket, and `IPV6_UNICAST_HOPS` on an
+    /// IPv6 one. A traceroute raises it one hop at a time.
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        let (level, name) = self.ttl_option();
+        sys::setsockopt(self.as_raw_fd(), level, name, ttl as libc::c_int)
+    }
+
+    fn ttl_option(&self) -> (libc::c_int, libc::c_int) {
+        if self.ipv6 {
+            (libc::IPPROTO_IPV6, libc::IPV6_UNICAST_HOPS)
+        } else {
+            (libc::IPPROTO_IP, libc::IP_TTL)
+        }
+    }
+
+    /// Polls the socket for read readiness.
+    ///
+    /// Once this returns `Ready`, the socket stays read-ready until
+    /// [`clear_read_ready`] is called, which should be done as soon as a
+    /// syscall fails with `WouldBlock`.
+    ///
+    /// [`clear_read_ready`]: #method.clear_read_ready
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    /// Polls the socket for write readiness.
+    ///
+    /// This works like [`poll_read_ready`], until [`clear_write_ready`] is
+    /// called.
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    /// [`clear_write_ready`]: #method.clear_write_ready
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+
+    /// Clears the read readiness of the socket, and arranges for the current
+    /// task to be woken once it is readable again.
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_read_ready(cx)
+    }
+
+    /// Clears the write readiness of the socket, and arranges for the
+    /// current task to be woken once it is writable again.
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_write_ready(cx)
+    }
+}
+
+impl AsRawFd for RawSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.as_raw_fd()
+    }
+}
+
+impl fmt::Debug for RawSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawSocket")
+            .field("fd", &self.as_raw_fd())
+            .field("ipv6", &self.ipv6)
+            .finish()
+    }
+}
+
+impl IcmpSocket {
+    /// Creates a raw ICMP socket.
+    pub fn new_v4() -> io::Result<IcmpSocket> {
+        let socket = RawSocket::new_v4(libc::IPPROTO_ICMP)?;
+        Ok(IcmpSocket { socket })
+    }
+
+    /// Creates a raw ICMPv6 socket.
+    pub fn new_v6() -> io::Result<IcmpSocket> {
+        let socket = RawSocket::new_v6(libc::IPPROTO_ICMPV6)?;
+        Ok(IcmpSocket { socket })
+    }
+
+    /// Returns the underlying raw socket, to set options on it.
+    pub fn get_ref(&self) -> &RawSocket {
+        &self.socket
+    }
+
+    /// Sends an echo request to `target`, carrying `payload`.
+    ///
+    /// `ident` and `seq` are echoed back in the reply, which lets the replies
+    /// be matched with the requests. The checksum is filled in: by this, for
+    /// ICMP, and by the kernel, for ICMPv6.
+    pub fn send_echo(
+        &mut self,
+        target: &IpAddr,
+        ident: u16,
+        seq: u16,
+        payload: &[u8],
+    ) -> SendEcho<'_> {
+        let kind = if self.socket.ipv6 {
+            ICMPV6_ECHO_REQUEST
+        } else {
+            ICMP_ECHO_REQUEST
+        };
+        let mut packet = Vec::with_capacity(8 + payload.len());
+        packet.extend_from_slice(&[kind, 0, 0, 0]);
+        packet.extend_from_slice(&ident.to_be_bytes());
+        packet.extend_from_slice(&seq.to_be_bytes());
+        packet.extend_from_slice(payload);
+        if !self.socket.ipv6 {
+            let sum = checksum(&packet);
+            packet[2..4].copy_from_slice(&sum.to_be_bytes());
+        }
+        SendEcho {
+            socket: self,
+            packet,
+            target: *target,
+        }
+    }
+
+    /// Receives an ICMP message, resolving to the number of bytes copied
+    /// into `buf` and the address it came from.
+    ///
+    /// `buf` starts with the ICMP header, whose first byte is the message
+    /// type. Every ICMP message that reaches the host is received, including
+    /// the echo requests of other programs, so replies should be told apart
+    /// by their type, identifier and sequence number.
+    pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> IcmpRecvFrom<'a, 'b> {
+        IcmpRecvFrom { socket: self, buf }
+    }
+
+    /// Attempts to receive an ICMP message.
+    ///
+    /// See [`recv_from`] for details.
+    ///
+    /// [`recv_from`]: #method.recv_from
+    pub fn poll_recv_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, IpAddr)>> {
+        let (n, from) = ready!(self.socket.poll_recv_from(cx, buf))?;
+        if self.socket.ipv6 {
+            return Poll::Ready(Ok((n, from)));
+        }
+
+        // Raw IPv4 sockets receive the IP header too.
+        let header_len = match buf.first() {
+            Some(byte) if n > 0 => usize::from(byte & 0x0f) * 4,
+            _ => 0,
+        };
+        if header_len < 20 || header_len > n {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "truncated IPv4 header",
+            )));
+        }
+        buf.copy_within(header_len..n, 0);
+        Poll::Ready(Ok((n - header_len, from)))
+    }
+}
+
+impl AsRawFd for IcmpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+/// The Internet checksum of RFC 1071.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum = 0u32;
+    for chunk in data.chunks(2) {
+        let word = match chunk {
+            [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+            [hi] => u16::from_be_bytes([*hi, 0]),
+            _ => unreachable!(),
+        };
+        sum += u32::from(word);
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// The future returned by `RawSocket::send_to`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SendTo<'a, 'b> {
+    socket: &'a mut RawSocket,
+    buf: &'b [u8],
+    target: &'b IpAddr,
+}
+
+impl Future for SendTo<'_, '_> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendTo {
+            socket,
+            buf,
+            target,
+        } = &mut *self;
+        socket.poll_send_to(cx, buf, target)
+    }
+}
+
+/// The future returned by `RawSocket::recv_from`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct RecvFrom<'a, 'b> {
+    socket: &'a mut RawSocket,
+    buf: &'b mut [u8],
+}
+
+impl Future for RecvFrom<'_, '_> {
+    type Output = io::Result<(usize, IpAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvFrom { socket, buf } = &mut *self;
+        socket.poll_recv_from(cx, buf)
+    }
+}
+
+/// The future returned by `IcmpSocket::send_echo`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SendEcho<'a> {
+    socket: &'a mut IcmpSocket,
+    packet: Vec<u8>,
+    target: IpAddr,
+}
+
+impl Future for SendEcho<'_> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendEcho {
+            socket,
+            packet,
+            target,
+        } = &mut *self;
+        ready!(socket.socket.poll_send_to(cx, packet, target))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The future returned by `IcmpSocket::recv_from`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct IcmpRecvFrom<'a, 'b> {
+    socket: &'a mut IcmpSocket,
+    buf: &'b mut [u8],
+}
+
+impl Future for IcmpRecvFrom<'_, '_> {
+    type Output = io::Result<(usize, IpAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let IcmpRecvFrom { socket, buf } = &mut *self;
+        socket.poll_recv_from(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::checksum;
+
+    #[test]
+    fn checksum_matches_rfc_1071() {
+        // The example of RFC 1071, section 3.
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(checksum(&data), !0xddf2);
+        // A message with its checksum in place sums to zero.
+        let mut echo = [8, 0, 0, 0, 0x12, 0x34, 0, 1, b'h', b'i', b'!'];
+        let sum = checksum(&echo);
+        echo[2..4].copy_from_slice(&sum.to_be_bytes());
+        assert_eq!(checksum(&echo), 0);
+    }
+}
@@ -24,6 +24,7 @@ pub struct Background {
 }
 
 /// Future that resolves when the reactor thread has shutdown.
+#[must_use = "futures do nothing unless polled"]
 #[derive(Debug)]
 pub struct Shutdown {
     inner: Inner,
@@ -83,10 +84,27 @@ impl Background {
         })
     }
 
+    /// Returns a handle to the reactor running on the background thread.
+    pub fn handle(&self) -> Handle {
+        self.inner.as_ref().unwrap().handle.clone()
+    }
+
     /// Run the reactor on its thread until the process terminates.
     pub fn forget(mut self) {
         drop(self.inner.take());
     }
+
+    /// Stops the reactor thread, returning a future that resolves once it
+    /// has exited.
+    ///
+    /// The reactor is dropped on its thread, so I/O objects bound to it fail
+    /// once the future has resolved. Dropping the `Background` does the
+    /// same, but blocks the current thread until the reactor has stopped.
+    pub fn shutdown_now(mut self) -> Shutdown {
+        let inner = self.inner.take().unwrap();
+        inner.shutdown_now();
+        Shutdown { inner }
+    }
 }
 
 impl Drop for Background {
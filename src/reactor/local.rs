@@ -0,0 +1,281 @@
+use super::{HandlePriv, Reactor};
+
+use futures::future::{FutureObj, LocalFutureObj};
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::task::{waker_ref, ArcWake, LocalSpawn, Spawn, SpawnError};
+use futures::{pin_mut, Future, Poll};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::Arc;
+use std::task::Context;
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+/// A reactor and an executor for tasks that share its thread.
+///
+/// Tasks run by a `LocalReactor` are polled on the same thread that turns
+/// the reactor, in between turns. A socket event thus wakes its task by
+/// putting it back into the queue of the executor, with no other thread
+/// involved: there is no cross-thread wakeup and no context switch on the
+/// way from the event to the task. This suits single-threaded servers and
+/// proxies where that round trip would dominate the latency.
+///
+/// I/O objects and timers that are first polled by one of these tasks bind
+/// to this reactor, the way they do inside [`Reactor::enter`]. Tasks are
+/// spawned through a [`LocalSpawner`], and need not be `Send`; the usual
+/// `futures::executor::LocalPool` API is mirrored by [`run_until`] and
+/// [`run`].
+///
+/// Wakeups from other threads, e.g. through a channel, interrupt a turn of
+/// the reactor that is blocked waiting for events, so they aren't delayed
+/// until the next I/O event.
+///
+/// [`Reactor::enter`]: struct.Reactor.html#method.enter
+/// [`LocalSpawner`]: struct.LocalSpawner.html
+/// [`run_until`]: #method.run_until
+/// [`run`]: #method.run
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use futures::task::LocalSpawnExt;
+/// use romio::raw::LocalReactor;
+/// use romio::tcp::TcpListener;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reactor = LocalReactor::new()?;
+/// let mut spawner = reactor.spawner();
+///
+/// reactor.run_until(async move {
+///     let socket_addr = "127.0.0.1:8080".parse().unwrap();
+///     let mut listener = TcpListener::bind(&socket_addr)?;
+///     let mut incoming = listener.incoming();
+///     while let Some(stream) = incoming.next().await {
+///         let mut stream = stream?;
+///         spawner
+///             .spawn_local(async move {
+///                 let (mut reader, mut writer) = stream.split();
+///                 drop(reader.copy_into(&mut writer).await);
+///             })
+///             .unwrap();
+///     }
+///     Ok(())
+/// })?
+/// # }
+/// ```
+pub struct LocalReactor {
+    reactor: Reactor,
+    pool: FuturesUnordered<LocalFutureObj<'static, ()>>,
+    /// Tasks spawned since the pool was last polled.
+    incoming: Rc<RefCell<Vec<LocalFutureObj<'static, ()>>>>,
+    wakeup: Arc<Wakeup>,
+}
+
+/// A handle for spawning tasks onto a [`LocalReactor`].
+///
+/// Spawning fails once the `LocalReactor` has been dropped.
+///
+/// [`LocalReactor`]: struct.LocalReactor.html
+#[derive(Clone)]
+pub struct LocalSpawner {
+    incoming: Weak<RefCell<Vec<LocalFutureObj<'static, ()>>>>,
+}
+
+/// The waker of the tasks of a `LocalReactor`.
+struct Wakeup {
+    /// Set when a task was woken since the tasks were last polled.
+    woken: AtomicBool,
+    /// The thread running the `LocalReactor`.
+    thread: ThreadId,
+    handle: HandlePriv,
+}
+
+impl ArcWake for Wakeup {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.woken.store(true, SeqCst);
+        // On its own thread the reactor isn't blocked in a turn, and checks
+        // `woken` before it starts the next one.
+        if thread::current().id() != arc_self.thread {
+            arc_self.handle.wakeup();
+        }
+    }
+}
+
+impl LocalReactor {
+    /// Creates a new reactor, with no tasks, for the current thread.
+    pub fn new() -> io::Result<LocalReactor> {
+        let reactor = Reactor::new()?;
+        let handle = reactor.handle().into_priv().unwrap();
+        Ok(LocalReactor {
+            reactor,
+            pool: FuturesUnordered::new(),
+            incoming: Default::default(),
+            wakeup: Arc::new(Wakeup {
+                woken: AtomicBool::new(false),
+                thread: thread::current().id(),
+                handle,
+            }),
+        })
+    }
+
+    /// Returns a handle for spawning tasks onto this reactor.
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner {
+            incoming: Rc::downgrade(&self.incoming),
+        }
+    }
+
+    /// Runs `future` to completion, along with the spawned tasks, and
+    /// returns its output.
+    ///
+    /// Spawned tasks that are still running once `future` has completed stay
+    /// in the pool, and continue with the next call to `run_until` or `run`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if turning the reactor fails, which leaves `future` and the
+    /// tasks unfinished.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called on a different thread than the one that created
+    /// this reactor.
+    pub fn run_until<F: Future>(&mut self, future: F) -> io::Result<F::Output> {
+        pin_mut!(future);
+        self.run_executor(|cx, _| future.as_mut().poll(cx))
+    }
+
+    /// Runs the spawned tasks until all of them have completed.
+    ///
+    /// # Errors
+    ///
+    /// Fails if turning the reactor fails, which leaves the tasks
+    /// unfinished.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called on a different thread than the one that created
+    /// this reactor.
+    pub fn run(&mut self) -> io::Result<()> {
+        self.run_executor(|_, idle| if idle { Poll::Ready(()) } else { Poll::Pending })
+    }
+
+    /// Alternates between polling the tasks and turning the reactor until
+    /// `f`, called after each round of polling with whether the pool is
+    /// empty, is ready.
+    fn run_executor<T, F>(&mut self, mut f: F) -> io::Result<T>
+    where
+        F: FnMut(&mut Context<'_>, bool) -> Poll<T>,
+    {
+        let LocalReactor {
+            reactor,
+            pool,
+            incoming,
+            wakeup,
+        } = self;
+        assert_eq!(
+            thread::current().id(),
+            wakeup.thread,
+            "LocalReactor run on a different thread than it was created on"
+        );
+
+        let waker = waker_ref(wakeup);
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            wakeup.woken.store(false, SeqCst);
+
+            let res = reactor.enter(|| {
+                let idle = poll_pool(&mut cx, pool, incoming);
+                f(&mut cx, idle)
+            });
+            if let Poll::Ready(t) = res {
+                return Ok(t);
+            }
+
+            // Don't block if a task was woken or spawned while polling: pick
+            // up the events that are ready, and poll again.
+            let max_wait = if wakeup.woken.load(SeqCst) || !incoming.borrow().is_empty() {
+                Some(Duration::from_millis(0))
+            } else {
+                None
+            };
+            reactor.turn(max_wait)?;
+        }
+    }
+}
+
+/// Polls the tasks that are ready, returning whether none are left.
+fn poll_pool(
+    cx: &mut Context<'_>,
+    pool: &mut FuturesUnordered<LocalFutureObj<'static, ()>>,
+    incoming: &RefCell<Vec<LocalFutureObj<'static, ()>>>,
+) -> bool {
+    loop {
+        for task in incoming.borrow_mut().drain(..) {
+            pool.push(task);
+        }
+
+        let done = match pool.poll_next_unpin(cx) {
+            Poll::Ready(Some(())) => continue,
+            Poll::Ready(None) => true,
+            Poll::Pending => false,
+        };
+
+        // Tasks spawned by the last task polled are polled before returning.
+        if incoming.borrow().is_empty() {
+            return done;
+        }
+    }
+}
+
+impl fmt::Debug for LocalReactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalReactor")
+            .field("tasks", &(self.pool.len() + self.incoming.borrow().len()))
+            .finish()
+    }
+}
+
+// ===== impl LocalSpawner =====
+
+impl LocalSpawn for LocalSpawner {
+    fn spawn_local_obj(&mut self, future: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
+        match self.incoming.upgrade() {
+            Some(incoming) => {
+                incoming.borrow_mut().push(future);
+                Ok(())
+            }
+            None => Err(SpawnError::shutdown()),
+        }
+    }
+
+    fn status_local(&self) -> Result<(), SpawnError> {
+        match self.incoming.upgrade() {
+            Some(_) => Ok(()),
+            None => Err(SpawnError::shutdown()),
+        }
+    }
+}
+
+impl Spawn for LocalSpawner {
+    fn spawn_obj(&mut self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn_local_obj(future.into())
+    }
+
+    fn status(&self) -> Result<(), SpawnError> {
+        self.status_local()
+    }
+}
+
+impl fmt::Debug for LocalSpawner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalSpawner").finish()
+    }
+}
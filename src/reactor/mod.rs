@@ -1,17 +1,24 @@
 pub(crate) mod background;
+mod local;
+mod pool;
 pub(crate) mod registration;
 mod sharded_rwlock;
+pub(crate) mod timer;
 
 // ===== Public re-exports =====
 
-use self::background::Background;
+pub use self::background::{Background, Shutdown};
+pub use self::local::{LocalReactor, LocalSpawner};
+pub use self::pool::{Balance, ReactorPool, ReactorPoolBuilder};
 pub use crate::raw::PollEvented;
 
 // ===== Private imports =====
 
 use self::sharded_rwlock::RwLock;
+use self::timer::Timers;
 
 use std::cell::RefCell;
+use std::cmp;
 use std::io;
 use std::mem;
 use std::sync::atomic::AtomicUsize;
@@ -23,6 +30,7 @@ use std::{fmt, usize};
 use futures::task::{AtomicWaker, Context};
 use log::{debug, log_enabled, trace, Level};
 use mio::event::Evented;
+use parking_lot::Mutex;
 use slab::Slab;
 
 /// The core reactor, or event loop.
@@ -31,7 +39,37 @@ use slab::Slab;
 /// all other I/O events and notifications happening. Each event loop can have
 /// multiple handles pointing to it, each of which can then be used to create
 /// various I/O objects to interact with the event loop in interesting ways.
-struct Reactor {
+///
+/// I/O objects normally run on a global reactor driven by a background thread.
+/// To drive the event loop from a custom executor instead, create a
+/// `Reactor`, set up and poll I/O objects inside [`enter`] so they bind to it,
+/// and call [`turn`] from the executor's loop.
+///
+/// Once done, [`shutdown`] tears the reactor down; no thread is spawned
+/// for a reactor that is driven this way.
+///
+/// [`enter`]: #method.enter
+/// [`turn`]: #method.turn
+/// [`shutdown`]: #method.shutdown
+///
+/// # Examples
+///
+/// ```rust
+/// use romio::raw::Reactor;
+/// use std::time::Duration;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let mut reactor = Reactor::new()?;
+/// loop {
+///     // wait for I/O, but come back every 10ms to run other work
+///     reactor.turn(Some(Duration::from_millis(10)))?;
+///     # break;
+/// }
+/// reactor.shutdown();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Reactor {
     /// Reuse the `mio::Events` value across calls to poll.
     events: mio::Events,
 
@@ -44,13 +82,23 @@ struct Reactor {
 /// A reference to a reactor.
 ///
 /// A `Handle` is used for associating I/O objects with an event loop
-/// explicitly. Typically though you won't end up using a `Handle` that often
-/// and will instead use the default reactor for the execution context.
+/// explicitly, e.g. through [`TcpStream::connect_on`] or
+/// [`PollEvented::new_with_handle`]. This allows running several reactors
+/// side by side, say one per core, with each socket bound to the one of the
+/// thread that serves it. Typically though you won't end up using a `Handle`
+/// that often and will instead use the default reactor for the execution
+/// context.
+///
+/// A `Handle` doesn't keep its reactor alive. Once the reactor is dropped,
+/// I/O objects bound to it fail with an error instead of waiting forever.
 ///
 /// By default, most components bind lazily to reactors.
 /// To get this behavior when manually passing a `Handle`, use `default()`.
+///
+/// [`TcpStream::connect_on`]: ../tcp/struct.TcpStream.html#method.connect_on
+/// [`PollEvented::new_with_handle`]: struct.PollEvented.html#method.new_with_handle
 #[derive(Clone)]
-struct Handle {
+pub struct Handle {
     inner: Option<HandlePriv>,
 }
 
@@ -65,7 +113,7 @@ struct HandlePriv {
 /// Currently this value doesn't actually provide any functionality, but it may
 /// in the future give insight into what happened during `turn`.
 #[derive(Debug)]
-struct Turn {
+pub struct Turn {
     _priv: (),
 }
 
@@ -85,6 +133,9 @@ struct Inner {
     /// Dispatch slabs for I/O and futures events
     io_dispatch: RwLock<Slab<ScheduledIo>>,
 
+    /// Pending deadlines, fired at the end of each turn
+    timers: Mutex<Timers>,
+
     /// Used to wake up the reactor from a call to `turn`
     wakeup: mio::SetReadiness,
 }
@@ -106,7 +157,14 @@ pub(crate) enum Direction {
 static HANDLE_FALLBACK: AtomicUsize = AtomicUsize::new(0);
 
 // Tracks the reactor for the current execution context.
-thread_local!(static CURRENT_REACTOR: RefCell<Option<HandlePriv>> = RefCell::new(None));
+thread_local!(static CURRENT_REACTOR: RefCell<Option<Current>> = const { RefCell::new(None) });
+
+/// What new I/O objects of the current execution context register with.
+#[derive(Clone)]
+enum Current {
+    Reactor(HandlePriv),
+    Pool(Arc<pool::Shared>),
+}
 
 const TOKEN_SHIFT: usize = 22;
 
@@ -125,7 +183,7 @@ fn _assert_kinds() {
 impl Reactor {
     /// Creates a new event loop, returning any error that happened during the
     /// creation.
-    fn new() -> io::Result<Reactor> {
+    pub fn new() -> io::Result<Reactor> {
         let io = mio::Poll::new()?;
         let wakeup_pair = mio::Registration::new2();
 
@@ -143,6 +201,7 @@ impl Reactor {
                 io: io,
                 next_aba_guard: AtomicUsize::new(0),
                 io_dispatch: RwLock::new(Slab::with_capacity(1)),
+                timers: Mutex::new(Timers::new()),
                 wakeup: wakeup_pair.1,
             }),
         })
@@ -154,7 +213,7 @@ impl Reactor {
     /// Handles are cloneable and clones always refer to the same event loop.
     /// This handle is typically passed into functions that create I/O objects
     /// to bind them to this event loop.
-    fn handle(&self) -> Handle {
+    pub fn handle(&self) -> Handle {
         Handle {
             inner: Some(HandlePriv {
                 inner: Arc::downgrade(&self.inner),
@@ -171,7 +230,10 @@ impl Reactor {
     ///
     /// If a `max_wait` is specified then the method should block no longer than
     /// the duration specified, but this shouldn't be used as a super-precise
-    /// timer but rather a "ballpark approximation"
+    /// timer but rather a "ballpark approximation". With `None` it blocks
+    /// until there is an event. Either way it never blocks past the earliest
+    /// pending timer deadline, and timers that have expired are fired before
+    /// it returns.
     ///
     /// # Return value
     ///
@@ -188,11 +250,42 @@ impl Reactor {
     /// arise and typically mean that things have gone horribly wrong at that
     /// point. Currently this is primarily only known to happen for internal
     /// bugs to `tokio` itself.
-    fn turn(&mut self, max_wait: Option<Duration>) -> io::Result<Turn> {
+    pub fn turn(&mut self, max_wait: Option<Duration>) -> io::Result<Turn> {
         self.poll(max_wait)?;
         Ok(Turn { _priv: () })
     }
 
+    /// Runs `f` with this reactor as the current one.
+    ///
+    /// I/O objects and timers register with the reactor lazily, the first time
+    /// they are polled. Those first polled inside `f` register with this
+    /// reactor rather than the global one, and are driven by calls to
+    /// [`turn`] from then on.
+    ///
+    /// [`turn`]: #method.turn
+    pub fn enter<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let handle = self.handle().into_priv().unwrap();
+        enter(Current::Reactor(handle), f)
+    }
+
+    /// Shuts the reactor down, dropping it.
+    ///
+    /// Every task waiting on an I/O object or timer bound to this reactor is
+    /// woken, and from then on those objects fail with an error rather than
+    /// wait for events that would never come. Nothing is left running in the
+    /// background, so a test or an embedder that drives the reactor with
+    /// [`turn`] can tear it down at a well-defined point.
+    ///
+    /// This does the same as dropping the reactor, but spells out the intent.
+    ///
+    /// [`turn`]: #method.turn
+    pub fn shutdown(self) {
+        drop(self);
+    }
+
     /// Returns true if the reactor is currently idle.
     ///
     /// Idle is defined as all tasks that have been spawned have completed,
@@ -207,11 +300,37 @@ impl Reactor {
     /// reactor to this new thread. It then runs the reactor, driving all
     /// associated I/O resources, until the `Background` handle is dropped or
     /// explicitly shutdown.
-    fn background(self) -> io::Result<Background> {
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::raw::Reactor;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let reactor = Reactor::new()?.background()?;
+    /// let addr = "127.0.0.1:8080".parse().unwrap();
+    /// let stream = TcpStream::connect_on(&reactor.handle(), &addr).await?;
+    ///
+    /// // stops the reactor thread, after which `stream` fails with an error
+    /// reactor.shutdown_now().await.unwrap();
+    /// # Ok(())}
+    /// ```
+    pub fn background(self) -> io::Result<Background> {
         Background::new(self)
     }
 
     fn poll(&mut self, max_wait: Option<Duration>) -> io::Result<()> {
+        // Never sleep past the earliest pending deadline.
+        let max_wait = match self.inner.timers.lock().next_deadline() {
+            Some(deadline) => {
+                let until = deadline.saturating_duration_since(Instant::now());
+                Some(max_wait.map_or(until, |max_wait| cmp::min(max_wait, until)))
+            }
+            None => max_wait,
+        };
+
         // Block waiting for an event to happen, peeling out how many events
         // happened.
         match self.inner.io.poll(&mut self.events, max_wait) {
@@ -242,6 +361,11 @@ impl Reactor {
             }
         }
 
+        let expired = self.inner.timers.lock().fire(Instant::now());
+        for waker in expired {
+            waker.wake();
+        }
+
         if let Some(start) = start {
             let dur = start.elapsed();
             trace!(
@@ -334,6 +458,25 @@ impl fmt::Debug for Handle {
     }
 }
 
+/// Runs `f` with `current` as what I/O objects register with.
+fn enter<F, R>(current: Current, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Reset(Option<Current>);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            let prev = self.0.take();
+            CURRENT_REACTOR.with(|current| *current.borrow_mut() = prev);
+        }
+    }
+
+    let prev = CURRENT_REACTOR.with(|cell| cell.replace(Some(current)));
+    let _reset = Reset(prev);
+    f()
+}
+
 fn set_fallback(handle: HandlePriv) -> Result<(), ()> {
     unsafe {
         let val = handle.into_usize();
@@ -355,7 +498,8 @@ impl HandlePriv {
     /// Returns `Err` if no handle is found.
     pub(crate) fn try_current() -> io::Result<HandlePriv> {
         CURRENT_REACTOR.with(|current| match *current.borrow() {
-            Some(ref handle) => Ok(handle.clone()),
+            Some(Current::Reactor(ref handle)) => Ok(handle.clone()),
+            Some(Current::Pool(ref pool)) => Ok(pool.pick()),
             None => HandlePriv::fallback(),
         })
     }
@@ -370,15 +514,9 @@ impl HandlePriv {
         // handle which will return errors when I/O objects are attempted to be
         // associated.
         if fallback == 0 {
-            let reactor = match Reactor::new() {
-                Ok(reactor) => reactor,
-                Err(_) => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "failed to create reactor",
-                    ))
-                }
-            };
+            // Keep the OS error, e.g. `EMFILE` when the process is out of
+            // file descriptors, so callers can tell what went wrong.
+            let reactor = Reactor::new()?;
 
             // If we successfully set ourselves as the actual fallback then we
             // want to `forget` the helper thread to ensure that it persists
@@ -448,6 +586,12 @@ impl HandlePriv {
     fn inner(&self) -> Option<Arc<Inner>> {
         self.inner.upgrade()
     }
+
+    /// Returns how many I/O resources are registered with the reactor, or
+    /// `None` if it is gone.
+    fn sources(&self) -> Option<usize> {
+        self.inner().map(|inner| inner.io_dispatch.read().len())
+    }
 }
 
 impl fmt::Debug for HandlePriv {
@@ -484,12 +628,17 @@ impl Inner {
             writer: AtomicWaker::new(),
         });
 
-        self.io.register(
+        let res = self.io.register(
             source,
             mio::Token(aba_guard | key),
             mio::Ready::all(),
             mio::PollOpt::edge(),
-        )?;
+        );
+        if let Err(e) = res {
+            // Nothing will ever drop the source for this key.
+            io_dispatch.remove(key);
+            return Err(e);
+        }
 
         Ok(key)
     }
@@ -533,6 +682,10 @@ impl Drop for Inner {
             io.writer.wake();
             io.reader.wake();
         }
+
+        for waker in self.timers.get_mut().drain() {
+            waker.wake();
+        }
     }
 }
 
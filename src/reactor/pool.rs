@@ -0,0 +1,307 @@
+use super::{enter, Background, Current, Handle, HandlePriv, Reactor};
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Relaxed;
+use std::sync::{Arc, Weak};
+
+/// How a [`ReactorPool`] picks the reactor for a new I/O object.
+///
+/// [`ReactorPool`]: struct.ReactorPool.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Balance {
+    /// Hands out the reactors in turn.
+    RoundRobin,
+    /// Picks the reactor with the fewest registered I/O objects.
+    ///
+    /// This evens out pools where connections come and go at different
+    /// rates, at the cost of looking at every reactor for each registration.
+    LeastLoaded,
+    /// Assigns every thread a reactor, round-robin, the first time it
+    /// registers an I/O object, and sticks to it from then on.
+    ///
+    /// With one executor thread per reactor, this keeps the wakeups of each
+    /// executor thread coming from a single reactor thread.
+    PinToThread,
+}
+
+/// A set of reactors, each running on a background thread, that I/O
+/// objects are spread over.
+///
+/// A single reactor thread handles the events of all the I/O objects that
+/// are registered with it, so a busy server can saturate it while other
+/// cores sit idle. A `ReactorPool` runs several reactors, and assigns each
+/// new I/O object to one of them according to a [`Balance`] policy.
+///
+/// I/O objects are assigned either explicitly, by passing the result of
+/// [`handle`] to a constructor such as `TcpStream::connect_on`, or
+/// implicitly for those first polled inside [`enter`].
+///
+/// Dropping the pool shuts all of its reactors down, blocking until their
+/// threads have exited. I/O objects that are still bound to them fail from
+/// then on.
+///
+/// [`Balance`]: enum.Balance.html
+/// [`handle`]: #method.handle
+/// [`enter`]: #method.enter
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::raw::{Balance, ReactorPool};
+/// use romio::tcp::TcpListener;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let reactors = ReactorPool::builder()
+///     .reactors(4)
+///     .balance(Balance::LeastLoaded)
+///     .build()?;
+///
+/// let socket_addr = "127.0.0.1:8080".parse().unwrap();
+/// let mut listener = TcpListener::bind_on(&reactors.handle(), &socket_addr)?;
+/// futures::executor::block_on(async {
+///     let mut incoming = listener.incoming();
+///     while let Some(stream) = incoming.next().await {
+///         // each accepted stream is driven by the least loaded reactor
+///         let stream = stream?;
+///         # drop(stream);
+///     }
+///     Ok(())
+/// })
+/// # }
+/// ```
+pub struct ReactorPool {
+    shared: Arc<Shared>,
+    /// Keeps the reactor threads running; dropped last.
+    _reactors: Vec<Background>,
+}
+
+/// A builder for a [`ReactorPool`].
+///
+/// [`ReactorPool`]: struct.ReactorPool.html
+#[derive(Debug, Clone)]
+pub struct ReactorPoolBuilder {
+    reactors: usize,
+    balance: Balance,
+}
+
+/// The part of a pool that picks reactors.
+pub(super) struct Shared {
+    handles: Vec<HandlePriv>,
+    balance: Balance,
+    next: AtomicUsize,
+}
+
+// The reactor each thread was pinned to, by pool, for `Balance::PinToThread`.
+// Entries of pools that have been dropped are removed when another is added.
+thread_local! {
+    static PINNED: RefCell<Vec<(Weak<Shared>, usize)>> = const { RefCell::new(Vec::new()) };
+}
+
+// ===== impl ReactorPool =====
+
+impl ReactorPool {
+    /// Returns a builder for a pool, defaulting to one reactor per CPU and
+    /// round-robin balancing.
+    pub fn builder() -> ReactorPoolBuilder {
+        ReactorPoolBuilder {
+            reactors: num_cpus::get(),
+            balance: Balance::RoundRobin,
+        }
+    }
+
+    /// Picks a reactor for a new I/O object, returning a handle to it.
+    ///
+    /// Every call makes a new choice according to the balancing policy of
+    /// the pool, so objects that should share a reactor must be created with
+    /// the same handle.
+    pub fn handle(&self) -> Handle {
+        Handle {
+            inner: Some(self.shared.pick()),
+        }
+    }
+
+    /// Returns the number of reactors in the pool.
+    pub fn reactors(&self) -> usize {
+        self.shared.handles.len()
+    }
+
+    /// Runs `f` with this pool as the current reactor.
+    ///
+    /// I/O objects and timers register with the reactor lazily, the first
+    /// time they are polled. Those first polled inside `f` are each assigned
+    /// to one of the reactors of this pool, rather than to the global one.
+    pub fn enter<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        enter(Current::Pool(self.shared.clone()), f)
+    }
+}
+
+impl fmt::Debug for ReactorPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReactorPool")
+            .field("reactors", &self.reactors())
+            .field("balance", &self.shared.balance)
+            .finish()
+    }
+}
+
+// ===== impl ReactorPoolBuilder =====
+
+impl ReactorPoolBuilder {
+    /// Sets the number of reactors, and of threads running them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn reactors(mut self, n: usize) -> ReactorPoolBuilder {
+        assert!(n > 0, "a reactor pool needs at least one reactor");
+        self.reactors = n;
+        self
+    }
+
+    /// Sets how new I/O objects are assigned to reactors.
+    pub fn balance(mut self, balance: Balance) -> ReactorPoolBuilder {
+        self.balance = balance;
+        self
+    }
+
+    /// Creates the reactors and starts their threads.
+    ///
+    /// If any of them can't be created, the ones that were already started
+    /// are shut down again.
+    pub fn build(&self) -> io::Result<ReactorPool> {
+        let mut reactors = Vec::with_capacity(self.reactors);
+        let mut handles = Vec::with_capacity(self.reactors);
+        for _ in 0..self.reactors {
+            let reactor = Reactor::new()?;
+            handles.push(reactor.handle().into_priv().unwrap());
+            reactors.push(reactor.background()?);
+        }
+
+        Ok(ReactorPool {
+            shared: Arc::new(Shared {
+                handles,
+                balance: self.balance,
+                next: AtomicUsize::new(0),
+            }),
+            _reactors: reactors,
+        })
+    }
+}
+
+// ===== impl Shared =====
+
+impl Shared {
+    /// Picks the reactor for a new I/O object.
+    pub(super) fn pick(self: &Arc<Self>) -> HandlePriv {
+        let index = match self.balance {
+            Balance::RoundRobin => self.next_index(),
+            Balance::LeastLoaded => self.least_loaded(),
+            Balance::PinToThread => self.pinned_index(),
+        };
+        self.handles[index].clone()
+    }
+
+    fn pinned_index(self: &Arc<Self>) -> usize {
+        PINNED.with(|pinned| {
+            let mut pinned = pinned.borrow_mut();
+            let ptr = Arc::as_ptr(self);
+            if let Some(&(_, index)) = pinned.iter().find(|(pool, _)| pool.as_ptr() == ptr) {
+                return index;
+            }
+            pinned.retain(|(pool, _)| pool.strong_count() > 0);
+            let index = self.next_index();
+            pinned.push((Arc::downgrade(self), index));
+            index
+        })
+    }
+
+    fn next_index(&self) -> usize {
+        self.next.fetch_add(1, Relaxed) % self.handles.len()
+    }
+
+    fn least_loaded(&self) -> usize {
+        // Start the scan at a rotating index, so ties are spread out too.
+        let start = self.next_index();
+        let n = self.handles.len();
+        (0..n)
+            .map(|i| (start + i) % n)
+            .min_by_key(|&i| self.handles[i].sources().unwrap_or(usize::MAX))
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::raw::PollEvented;
+    use std::sync::Weak;
+    use std::thread;
+
+    fn pool(balance: Balance) -> ReactorPool {
+        ReactorPool::builder()
+            .reactors(2)
+            .balance(balance)
+            .build()
+            .unwrap()
+    }
+
+    /// Returns which reactor of `pool` is picked next.
+    fn pick(pool: &ReactorPool) -> usize {
+        let picked = pool.shared.pick();
+        let handles = &pool.shared.handles;
+        (0..handles.len())
+            .find(|&i| Weak::ptr_eq(&handles[i].inner, &picked.inner))
+            .unwrap()
+    }
+
+    #[test]
+    fn round_robin_takes_turns() {
+        let pool = pool(Balance::RoundRobin);
+        let picks: Vec<_> = (0..4).map(|_| pick(&pool)).collect();
+        assert_eq!(picks, [0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn least_loaded_avoids_busy_reactor() {
+        let pool = pool(Balance::LeastLoaded);
+        let busy = Handle {
+            inner: Some(pool.shared.handles[0].clone()),
+        };
+        let socket = mio::net::UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let _socket = PollEvented::new_with_handle(socket, &busy).unwrap();
+
+        for _ in 0..4 {
+            assert_eq!(pick(&pool), 1);
+        }
+    }
+
+    #[test]
+    fn pin_to_thread_sticks_per_thread() {
+        let pool = Arc::new(pool(Balance::PinToThread));
+        let here = pick(&pool);
+        assert_eq!(pick(&pool), here);
+
+        let other = pool.clone();
+        let there = thread::spawn(move || (pick(&other), pick(&other)))
+            .join()
+            .unwrap();
+        assert_eq!(there, (1 - here, 1 - here));
+        assert_eq!(pick(&pool), here);
+    }
+
+    #[test]
+    fn pin_to_thread_forgets_dropped_pools() {
+        for _ in 0..3 {
+            pick(&pool(Balance::PinToThread));
+        }
+        assert_eq!(PINNED.with(|pinned| pinned.borrow().len()), 1);
+    }
+}
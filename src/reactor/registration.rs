@@ -1,4 +1,4 @@
-use super::{Direction, HandlePriv};
+use super::{Direction, Handle, HandlePriv};
 
 use mio::{self, Evented};
 
@@ -58,6 +58,8 @@ pub(crate) struct Registration {
 struct Inner {
     handle: HandlePriv,
     token: usize,
+    /// The OS error that made registering fail, when `token` is `ERROR`.
+    error: Option<i32>,
 }
 
 /// Waker waiting on readiness notifications.
@@ -115,6 +117,16 @@ impl Registration {
         self.register2(io, || HandlePriv::try_current())
     }
 
+    /// Register the I/O resource with the reactor of `handle`.
+    ///
+    /// A default handle registers with the default reactor, like `register`.
+    pub fn register_with(&self, io: &impl Evented, handle: &Handle) -> io::Result<bool> {
+        match handle.as_priv() {
+            Some(handle) => self.register2(io, || Ok(handle.clone())),
+            None => self.register(io),
+        }
+    }
+
     /// Deregister the I/O resource from the reactor it is associated with.
     ///
     /// This function must be called before the I/O resource associated with the
@@ -393,11 +405,13 @@ unsafe impl Sync for Registration {}
 impl Inner {
     fn new(io: &impl Evented, handle: HandlePriv) -> (Self, io::Result<()>) {
         let mut res = Ok(());
+        let mut error = None;
 
         let token = match handle.inner() {
             Some(inner) => match inner.add_source(io) {
                 Ok(token) => token,
                 Err(e) => {
+                    error = e.raw_os_error();
                     res = Err(e);
                     ERROR
                 }
@@ -408,11 +422,24 @@ impl Inner {
             }
         };
 
-        let inner = Inner { handle, token };
+        let inner = Inner {
+            handle,
+            token,
+            error,
+        };
 
         (inner, res)
     }
 
+    /// Recreates the error that made registering fail, so every later use of
+    /// the resource reports the same cause as the first one.
+    fn registration_error(&self) -> io::Error {
+        match self.error {
+            Some(code) => io::Error::from_raw_os_error(code),
+            None => io::Error::other("failed to associate with reactor"),
+        }
+    }
+
     fn register(&self, cx: &mut Context<'_>, direction: Direction) {
         if self.token == ERROR {
             cx.waker().wake_by_ref();
@@ -432,10 +459,7 @@ impl Inner {
 
     fn deregister<E: Evented>(&self, io: &E) -> io::Result<()> {
         if self.token == ERROR {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "failed to associate with reactor",
-            ));
+            return Err(self.registration_error());
         }
 
         let inner = match self.handle.inner() {
@@ -452,10 +476,7 @@ impl Inner {
         direction: Direction,
     ) -> io::Result<Option<mio::Ready>> {
         if self.token == ERROR {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "failed to associate with reactor",
-            ));
+            return Err(self.registration_error());
         }
 
         let inner = match self.handle.inner() {
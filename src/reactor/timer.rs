@@ -0,0 +1,240 @@
+//! Deadlines driven by the reactor.
+//!
+//! Each reactor keeps an ordered set of pending deadlines next to its I/O
+//! dispatch slab. `Reactor::poll` never blocks past the earliest deadline, and
+//! after every turn it wakes the tasks whose deadlines have passed. A `Delay`
+//! registers itself lazily with the current reactor the first time it is
+//! polled, the same way I/O resources do.
+
+use super::HandlePriv;
+
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+
+use slab::Slab;
+
+/// The set of deadlines registered with a reactor.
+pub(super) struct Timers {
+    entries: Slab<Entry>,
+    queue: BTreeSet<(Instant, usize)>,
+}
+
+struct Entry {
+    deadline: Instant,
+    waker: Option<Waker>,
+    fired: bool,
+}
+
+impl Timers {
+    pub(super) fn new() -> Timers {
+        Timers {
+            entries: Slab::new(),
+            queue: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the earliest pending deadline.
+    pub(super) fn next_deadline(&self) -> Option<Instant> {
+        self.queue.iter().next().map(|&(deadline, _)| deadline)
+    }
+
+    /// Marks every deadline at or before `now` as fired, returning the wakers
+    /// to notify once the lock is released.
+    pub(super) fn fire(&mut self, now: Instant) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        while let Some(&(deadline, key)) = self.queue.iter().next() {
+            if deadline > now {
+                break;
+            }
+            self.queue.remove(&(deadline, key));
+            let entry = &mut self.entries[key];
+            entry.fired = true;
+            wakers.extend(entry.waker.take());
+        }
+        wakers
+    }
+
+    /// Takes every waker, used when the reactor goes away.
+    pub(super) fn drain(&mut self) -> Vec<Waker> {
+        self.queue.clear();
+        self.entries
+            .iter_mut()
+            .filter_map(|(_, entry)| entry.waker.take())
+            .collect()
+    }
+
+    /// Adds a deadline, returning its key and whether it is now the earliest.
+    fn insert(&mut self, deadline: Instant, waker: Waker) -> (usize, bool) {
+        let key = self.entries.insert(Entry {
+            deadline,
+            waker: Some(waker),
+            fired: false,
+        });
+        self.queue.insert((deadline, key));
+        (key, self.next_deadline() == Some(deadline))
+    }
+
+    /// Returns whether the deadline has fired, otherwise storing `waker` to
+    /// be notified when it does.
+    fn poll(&mut self, key: usize, waker: &Waker) -> bool {
+        let entry = &mut self.entries[key];
+        if entry.fired {
+            return true;
+        }
+        match entry.waker {
+            Some(ref w) if w.will_wake(waker) => {}
+            _ => entry.waker = Some(waker.clone()),
+        }
+        false
+    }
+
+    fn remove(&mut self, key: usize) {
+        let entry = self.entries.remove(key);
+        self.queue.remove(&(entry.deadline, key));
+    }
+}
+
+/// A future that completes at a given instant.
+///
+/// The deadline is tracked by the reactor that drives I/O, which the `Delay`
+/// registers with the first time it is polled. No extra thread is involved.
+///
+/// Resolves to an error if the reactor driving it has gone away.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use romio::timer::Delay;
+/// use std::time::{Duration, Instant};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// Delay::new(Instant::now() + Duration::from_millis(100)).await?;
+/// println!("100ms have passed");
+/// # Ok(()) }
+/// ```
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Delay {
+    deadline: Instant,
+    registration: Option<(HandlePriv, usize)>,
+}
+
+impl Delay {
+    /// Creates a delay that completes at `deadline`.
+    pub fn new(deadline: Instant) -> Delay {
+        Delay {
+            deadline,
+            registration: None,
+        }
+    }
+
+    /// Returns the instant the delay completes at.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Moves the deadline to `deadline`, which may be earlier or later than
+    /// the current one.
+    ///
+    /// A delay that has already completed can be reset and awaited again.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.unregister();
+        self.deadline = deadline;
+    }
+
+    /// Polls for the deadline to pass.
+    pub fn poll_elapsed(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(Ok(()));
+        }
+
+        match self.registration {
+            Some((ref handle, key)) => {
+                let inner = match handle.inner() {
+                    Some(inner) => inner,
+                    None => return Poll::Ready(Err(reactor_gone())),
+                };
+                let fired = inner.timers.lock().poll(key, cx.waker());
+                if fired {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+            None => {
+                let handle = HandlePriv::try_current()?;
+                let inner = match handle.inner() {
+                    Some(inner) => inner,
+                    None => return Poll::Ready(Err(reactor_gone())),
+                };
+                let (key, earliest) = inner
+                    .timers
+                    .lock()
+                    .insert(self.deadline, cx.waker().clone());
+                // The reactor may be blocked waiting on a later deadline.
+                if earliest {
+                    handle.wakeup();
+                }
+                self.registration = Some((handle, key));
+                Poll::Pending
+            }
+        }
+    }
+
+    fn unregister(&mut self) {
+        if let Some((handle, key)) = self.registration.take() {
+            if let Some(inner) = handle.inner() {
+                inner.timers.lock().remove(key);
+            }
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_elapsed(cx)
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+fn reactor_gone() -> io::Error {
+    io::Error::other("reactor gone")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::task::noop_waker;
+    use std::time::Duration;
+
+    #[test]
+    fn fires_in_deadline_order() {
+        let waker = noop_waker();
+        let now = Instant::now();
+        let mut timers = Timers::new();
+
+        let (late, _) = timers.insert(now + Duration::from_secs(2), waker.clone());
+        let (early, earliest) = timers.insert(now + Duration::from_secs(1), waker.clone());
+        assert!(earliest);
+        assert_eq!(timers.next_deadline(), Some(now + Duration::from_secs(1)));
+
+        timers.fire(now + Duration::from_secs(1));
+        assert!(timers.poll(early, &waker));
+        assert!(!timers.poll(late, &waker));
+
+        timers.remove(late);
+        assert_eq!(timers.next_deadline(), None);
+    }
+}
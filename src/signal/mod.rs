@@ -0,0 +1,30 @@
+//! Asynchronous signal handling.
+//!
+//! On Unix, [`unix::Signal`] is a `Stream` that yields every time the
+//! process receives a certain signal. Signals are picked up by a handler
+//! that writes to a pipe driven by the same reactor as the sockets of this
+//! crate, so no second event loop is involved.
+//!
+//! [`unix::Signal`]: unix/struct.Signal.html
+//!
+//! # Examples
+//!
+//! Shutting down gracefully on `SIGINT` or `SIGTERM`:
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use futures::prelude::*;
+//! use futures::future;
+//! use romio::signal::unix::Signal;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let sigint = Signal::new(libc::SIGINT)?;
+//! let sigterm = Signal::new(libc::SIGTERM)?;
+//!
+//! future::select(sigint.into_future(), sigterm.into_future()).await;
+//! println!("shutting down");
+//! # Ok(())}
+//! ```
+
+#[cfg(unix)]
+pub mod unix;
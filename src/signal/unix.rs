@@ -0,0 +1,340 @@
+//! Unix signals as streams.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::io::FromRawFd;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use futures::task::{waker, ArcWake};
+use futures::Stream;
+use libc::c_int;
+use parking_lot::Mutex;
+use slab::Slab;
+
+use crate::raw::AsyncFd;
+use crate::sys;
+
+/// One more than the highest signal number on any supported platform.
+const MAX_SIGNAL: usize = 65;
+
+/// Signals that can't be caught, or that a handler returning to the code
+/// that raised them would turn into an endless loop.
+const FORBIDDEN: &[c_int] = &[
+    libc::SIGKILL,
+    libc::SIGSTOP,
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGSEGV,
+    libc::SIGBUS,
+];
+
+/// How many times each signal has been received. This and `PIPE` are all
+/// that the signal handler touches.
+static DELIVERIES: [AtomicUsize; MAX_SIGNAL] = [const { AtomicUsize::new(0) }; MAX_SIGNAL];
+
+/// The write end of the self-pipe, or -1 before it is created.
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+lazy_static::lazy_static! {
+    static ref GLOBALS: Mutex<Option<Arc<Globals>>> = Mutex::new(None);
+}
+
+/// The self-pipe and the tasks waiting for signals.
+struct Globals {
+    /// The read end of the self-pipe.
+    pipe: AsyncFd<File>,
+    /// Which signals the handler has been installed for.
+    installed: [AtomicBool; MAX_SIGNAL],
+    /// Serializes draining the pipe.
+    draining: Mutex<()>,
+    waiters: Arc<Waiters>,
+}
+
+/// The tasks of every `Signal` that is waiting, woken together whenever the
+/// self-pipe becomes readable.
+///
+/// The pipe only stores one waker, and streams come and go, so the pipe is
+/// always polled with the waker of this list rather than that of a task.
+struct Waiters {
+    tasks: Mutex<Slab<Option<Waker>>>,
+}
+
+impl ArcWake for Waiters {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        for (_, task) in arc_self.tasks.lock().iter_mut() {
+            if let Some(task) = task.take() {
+                task.wake();
+            }
+        }
+    }
+}
+
+extern "C" fn handler(signal: c_int) {
+    // The write below may clobber `errno` of the interrupted code.
+    let errno = errno_location().map(|errno| unsafe { *errno });
+
+    DELIVERIES[signal as usize].fetch_add(1, Ordering::SeqCst);
+    let fd = PIPE.load(Ordering::SeqCst);
+    // A full pipe already has a wakeup pending, so errors don't matter.
+    unsafe { libc::write(fd, b"s".as_ptr() as *const libc::c_void, 1) };
+
+    if let (Some(location), Some(errno)) = (errno_location(), errno) {
+        unsafe { *location = errno };
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "emscripten",
+    target_os = "dragonfly",
+    target_os = "hurd",
+    target_os = "redox"
+))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::__errno_location() })
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos",
+    target_os = "freebsd"
+))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::__error() })
+}
+
+#[cfg(any(target_os = "android", target_os = "openbsd", target_os = "netbsd"))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::__errno() })
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::___errno() })
+}
+
+/// Elsewhere there is no known way to reach `errno`, so the handler leaves
+/// it as the write left it.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "emscripten",
+    target_os = "dragonfly",
+    target_os = "hurd",
+    target_os = "redox",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "tvos",
+    target_os = "watchos",
+    target_os = "freebsd",
+    target_os = "android",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "solaris",
+    target_os = "illumos"
+)))]
+fn errno_location() -> Option<*mut c_int> {
+    None
+}
+
+impl Globals {
+    /// Returns the globals, creating the self-pipe on first use.
+    fn get() -> io::Result<Arc<Globals>> {
+        let mut globals = GLOBALS.lock();
+        if let Some(globals) = &*globals {
+            return Ok(globals.clone());
+        }
+
+        let (read, write) = sys::pipe()?;
+        let read = unsafe { File::from_raw_fd(read) };
+        let pipe = match AsyncFd::new(read) {
+            Ok(pipe) => pipe,
+            Err(e) => {
+                unsafe { libc::close(write) };
+                return Err(e);
+            }
+        };
+        // The write end stays open for the life of the process, since a
+        // handler may be running at any time.
+        PIPE.store(write, Ordering::SeqCst);
+
+        let new = Arc::new(Globals {
+            pipe,
+            installed: [const { AtomicBool::new(false) }; MAX_SIGNAL],
+            draining: Mutex::new(()),
+            waiters: Arc::new(Waiters {
+                tasks: Mutex::new(Slab::new()),
+            }),
+        });
+        *globals = Some(new.clone());
+        Ok(new)
+    }
+
+    /// Installs the handler for `signal`, unless that was done before.
+    fn install(&self, signal: c_int) -> io::Result<()> {
+        let _lock = GLOBALS.lock();
+        if self.installed[signal as usize].load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            sys::cvt(libc::sigaction(signal, &action, std::ptr::null_mut()))?;
+        }
+        self.installed[signal as usize].store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Reads the self-pipe empty, so that it wakes the waiters again on the
+    /// next signal.
+    fn drain(&self) -> io::Result<()> {
+        let _lock = self.draining.lock();
+        let waker = waker(self.waiters.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        while let Poll::Ready(res) = self.pipe.poll_read_ready(&mut cx) {
+            res?;
+            let mut buf = [0; 64];
+            loop {
+                match self.pipe.get_ref().read(&mut buf) {
+                    Ok(0) => return Ok(()),
+                    Ok(_) => {}
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            self.pipe.clear_read_ready(&mut cx)?;
+        }
+        Ok(())
+    }
+}
+
+/// A stream of the deliveries of a Unix signal.
+///
+/// Creating a `Signal` installs a handler for the signal, which replaces
+/// the handler that was set before, including the default action. The
+/// handler stays installed for the life of the process, even after every
+/// `Signal` for it has been dropped; signals received while there are none
+/// are then ignored.
+///
+/// Each `Signal` yields once for every time the signal was received since
+/// it was last polled: signals that arrive in quick succession are
+/// coalesced, as the OS does for signals that are pending. Several
+/// `Signal`s for the same signal all see every delivery.
+///
+/// # Examples
+///
+/// Reloading configuration on `SIGHUP`:
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::signal::unix::Signal;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let mut hangups = Signal::new(libc::SIGHUP)?;
+/// while let Some(res) = hangups.next().await {
+///     res?;
+///     println!("reloading configuration");
+/// }
+/// # Ok(())}
+/// ```
+pub struct Signal {
+    signal: c_int,
+    /// The number of deliveries this stream has reported.
+    seen: usize,
+    /// The entry of this stream in `Waiters::tasks`.
+    key: usize,
+    globals: Arc<Globals>,
+}
+
+impl Signal {
+    /// Starts listening for `signal`, e.g. `libc::SIGINT`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `InvalidInput` for signals that can't be caught, i.e.
+    /// `SIGKILL` and `SIGSTOP`, and for `SIGILL`, `SIGFPE`, `SIGSEGV` and
+    /// `SIGBUS`, whose handlers must not return. Also fails if the self-pipe
+    /// can't be created or registered with the reactor.
+    pub fn new(signal: c_int) -> io::Result<Signal> {
+        if signal <= 0 || signal as usize >= MAX_SIGNAL || FORBIDDEN.contains(&signal) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "signal can't be handled",
+            ));
+        }
+
+        let globals = Globals::get()?;
+        globals.install(signal)?;
+        let key = globals.waiters.tasks.lock().insert(None);
+        Ok(Signal {
+            signal,
+            seen: DELIVERIES[signal as usize].load(Ordering::SeqCst),
+            key,
+            globals,
+        })
+    }
+
+    /// Returns the signal number this stream listens for.
+    pub fn signal(&self) -> c_int {
+        self.signal
+    }
+
+    /// Returns `true` and catches up if the signal arrived since the last
+    /// delivery this stream reported.
+    fn take_delivery(&mut self) -> bool {
+        let deliveries = DELIVERIES[self.signal as usize].load(Ordering::SeqCst);
+        if deliveries == self.seen {
+            return false;
+        }
+        self.seen = deliveries;
+        true
+    }
+}
+
+impl Stream for Signal {
+    type Item = io::Result<()>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.take_delivery() {
+            return Poll::Ready(Some(Ok(())));
+        }
+
+        self.globals.waiters.tasks.lock()[self.key] = Some(cx.waker().clone());
+        if let Err(e) = self.globals.drain() {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        // The handler counts a delivery before it writes to the pipe, so
+        // one that raced with registering the waker shows up here.
+        if self.take_delivery() {
+            return Poll::Ready(Some(Ok(())));
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        self.globals.waiters.tasks.lock().remove(self.key);
+    }
+}
+
+impl fmt::Debug for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signal")
+            .field("signal", &self.signal)
+            .finish()
+    }
+}
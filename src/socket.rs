@@ -0,0 +1,77 @@
+//! The raw sockets of the socket types, for `AsRawSocket` on Windows.
+//!
+//! mio doesn't give access to the sockets it wraps on Windows, so the socket
+//! types remember the raw socket when they are created. The helpers here
+//! create sockets the way mio does, returning the raw socket along with the
+//! mio type. Elsewhere, the raw socket is taken from the mio type when it's
+//! needed, and nothing is kept.
+
+#[cfg(windows)]
+use net2::TcpBuilder;
+
+#[cfg(windows)]
+use std::io;
+#[cfg(windows)]
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, RawSocket};
+
+/// The raw socket of a socket type, as far as the platform needs it kept.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SavedSocket(#[cfg(windows)] RawSocket);
+
+impl SavedSocket {
+    /// Saves the raw socket of `socket`.
+    #[cfg(windows)]
+    pub(crate) fn of<S: AsRawSocket>(socket: &S) -> SavedSocket {
+        SavedSocket(socket.as_raw_socket())
+    }
+
+    /// Saves the raw socket of `socket`.
+    #[cfg(not(windows))]
+    pub(crate) fn of<S>(_socket: &S) -> SavedSocket {
+        SavedSocket()
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn get(self) -> RawSocket {
+        self.0
+    }
+}
+
+/// The backlog `TcpListener::bind` listens with, as in mio.
+#[cfg(windows)]
+const DEFAULT_BACKLOG: i32 = 1024;
+
+#[cfg(windows)]
+fn builder(addr: &SocketAddr) -> io::Result<TcpBuilder> {
+    match addr {
+        SocketAddr::V4(_) => TcpBuilder::new_v4(),
+        SocketAddr::V6(_) => TcpBuilder::new_v6(),
+    }
+}
+
+/// Starts connecting a new socket to `addr`.
+#[cfg(windows)]
+pub(crate) fn connect(addr: &SocketAddr) -> io::Result<(mio::net::TcpStream, SavedSocket)> {
+    let builder = builder(addr)?;
+    // The overlapped connect mio issues needs a bound socket.
+    let any = match addr {
+        SocketAddr::V4(_) => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+        SocketAddr::V6(_) => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+    };
+    builder.bind(&any)?;
+    let stream = builder.to_tcp_stream()?;
+    let socket = SavedSocket::of(&stream);
+    Ok((mio::net::TcpStream::connect_stream(stream, addr)?, socket))
+}
+
+/// Creates a listener bound to `addr`.
+#[cfg(windows)]
+pub(crate) fn bind(addr: &SocketAddr) -> io::Result<(mio::net::TcpListener, SavedSocket)> {
+    let builder = builder(addr)?;
+    builder.bind(addr)?;
+    let listener = builder.listen(DEFAULT_BACKLOG)?;
+    let socket = SavedSocket::of(&listener);
+    Ok((mio::net::TcpListener::from_std(listener)?, socket))
+}
@@ -0,0 +1,1718 @@
+//! Socket helpers not covered by mio or std.
+//!
+//! These are thin wrappers around `libc` calls that operate on raw file
+//! descriptors, shared by the socket types in this crate.
+
+use libc::{c_int, c_void, sockaddr_in, sockaddr_in6, sockaddr_storage, socklen_t};
+
+use std::cmp;
+use std::ffi::OsString;
+use std::io::{IoSlice, IoSliceMut};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{io, mem};
+
+/// The maximum number of buffers the kernel accepts in a single vectored
+/// I/O call.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const IOV_MAX: usize = libc::UIO_MAXIOV as usize;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const IOV_MAX: usize = 1024;
+
+/// Converts a `-1` return value into the last OS error.
+pub(crate) fn cvt(ret: c_int) -> io::Result<c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Writes from a list of buffers with a single `writev` call.
+///
+/// At most `IOV_MAX` buffers are passed to the kernel, which would otherwise
+/// reject the call with `EINVAL`. The returned byte count reflects what was
+/// actually written, so callers can advance past it and retry with the rest.
+pub(crate) fn writev(fd: RawFd, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+    let len = cmp::min(bufs.len(), IOV_MAX);
+    // `IoSlice` is guaranteed to be ABI compatible with `iovec` on unix.
+    let ret = unsafe { libc::writev(fd, bufs.as_ptr() as *const libc::iovec, len as c_int) };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// Reads into a list of buffers with a single `readv` call.
+///
+/// Like `writev`, at most `IOV_MAX` buffers are passed to the kernel.
+pub(crate) fn readv(fd: RawFd, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+    let len = cmp::min(bufs.len(), IOV_MAX);
+    // `IoSliceMut` is guaranteed to be ABI compatible with `iovec` on unix.
+    let ret = unsafe { libc::readv(fd, bufs.as_ptr() as *const libc::iovec, len as c_int) };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// Sends a batch of datagrams, each to its own address.
+///
+/// Returns how many datagrams, from the start of `msgs`, the kernel accepted;
+/// this is less than `msgs.len()` when the send buffer fills up partway, or
+/// when a later datagram fails. An error is only returned if the first
+/// datagram couldn't be sent. Linux and Android use a single `sendmmsg`
+/// call for up to `IOV_MAX` datagrams; other platforms call `sendto` for
+/// each.
+pub(crate) fn send_mmsg(fd: RawFd, msgs: &[(&[u8], SocketAddr)]) -> io::Result<usize> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let msgs = &msgs[..cmp::min(msgs.len(), IOV_MAX)];
+        let mut addrs: Vec<_> = msgs
+            .iter()
+            .map(|(_, addr)| addr_to_sockaddr(addr))
+            .collect();
+        let mut iovs: Vec<libc::iovec> = msgs
+            .iter()
+            .map(|(buf, _)| libc::iovec {
+                iov_base: buf.as_ptr() as *mut c_void,
+                iov_len: buf.len(),
+            })
+            .collect();
+        let mut hdrs: Vec<libc::mmsghdr> = addrs
+            .iter_mut()
+            .zip(iovs.iter_mut())
+            .map(|((storage, len), iov)| {
+                let mut hdr: libc::mmsghdr = unsafe { mem::zeroed() };
+                hdr.msg_hdr.msg_name = storage as *mut sockaddr_storage as *mut c_void;
+                hdr.msg_hdr.msg_namelen = *len;
+                hdr.msg_hdr.msg_iov = iov;
+                hdr.msg_hdr.msg_iovlen = 1;
+                hdr
+            })
+            .collect();
+        let n = cvt(unsafe { libc::sendmmsg(fd, hdrs.as_mut_ptr(), hdrs.len() as _, 0) })?;
+        Ok(n as usize)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        for (i, (buf, addr)) in msgs.iter().enumerate() {
+            let (storage, len) = addr_to_sockaddr(addr);
+            let ret = unsafe {
+                libc::sendto(
+                    fd,
+                    buf.as_ptr() as *const c_void,
+                    buf.len(),
+                    0,
+                    &storage as *const sockaddr_storage as *const libc::sockaddr,
+                    len,
+                )
+            };
+            if ret == -1 {
+                let err = io::Error::last_os_error();
+                return if i == 0 { Err(err) } else { Ok(i) };
+            }
+        }
+        Ok(msgs.len())
+    }
+}
+
+/// Receives a batch of datagrams, one into each of `bufs`.
+///
+/// Returns the length and sender of each datagram received, in order, for
+/// as many of `bufs` as there were datagrams waiting; a datagram longer
+/// than its buffer is truncated. An error is only returned if no datagram
+/// could be received, which is `WouldBlock` when none is waiting. Linux and
+/// Android use a single `recvmmsg` call for up to `IOV_MAX` datagrams; other
+/// platforms call `recvfrom` for each.
+pub(crate) fn recv_mmsg<B: AsMut<[u8]>>(
+    fd: RawFd,
+    bufs: &mut [B],
+) -> io::Result<Vec<(usize, SocketAddr)>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let len = cmp::min(bufs.len(), IOV_MAX);
+        let mut addrs: Vec<sockaddr_storage> = vec![unsafe { mem::zeroed() }; len];
+        let mut iovs: Vec<libc::iovec> = bufs[..len]
+            .iter_mut()
+            .map(|buf| {
+                let buf = buf.as_mut();
+                libc::iovec {
+                    iov_base: buf.as_mut_ptr() as *mut c_void,
+                    iov_len: buf.len(),
+                }
+            })
+            .collect();
+        let mut hdrs: Vec<libc::mmsghdr> = addrs
+            .iter_mut()
+            .zip(iovs.iter_mut())
+            .map(|(storage, iov)| {
+                let mut hdr: libc::mmsghdr = unsafe { mem::zeroed() };
+                hdr.msg_hdr.msg_name = storage as *mut sockaddr_storage as *mut c_void;
+                hdr.msg_hdr.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+                hdr.msg_hdr.msg_iov = iov;
+                hdr.msg_hdr.msg_iovlen = 1;
+                hdr
+            })
+            .collect();
+        let n = cvt(unsafe {
+            libc::recvmmsg(
+                fd,
+                hdrs.as_mut_ptr(),
+                hdrs.len() as _,
+                0,
+                std::ptr::null_mut(),
+            )
+        })?;
+        hdrs[..n as usize]
+            .iter()
+            .zip(&addrs)
+            .map(|(hdr, storage)| {
+                let from = sockaddr_to_addr(storage, hdr.msg_hdr.msg_namelen as usize)?;
+                Ok((hdr.msg_len as usize, from))
+            })
+            .collect()
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let mut received = Vec::new();
+        for buf in bufs {
+            let buf = buf.as_mut();
+            let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+            let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+            let ret = unsafe {
+                libc::recvfrom(
+                    fd,
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len(),
+                    0,
+                    &mut storage as *mut _ as *mut libc::sockaddr,
+                    &mut len,
+                )
+            };
+            if ret == -1 {
+                let err = io::Error::last_os_error();
+                return if received.is_empty() {
+                    Err(err)
+                } else {
+                    Ok(received)
+                };
+            }
+            received.push((ret as usize, sockaddr_to_addr(&storage, len as usize)?));
+        }
+        Ok(received)
+    }
+}
+
+/// Creates a non-blocking, close-on-exec socket of a given protocol, or of
+/// the default one for a `protocol` of 0.
+///
+/// The socket is owned by the returned `T`, such as a `File` or a
+/// `std::net` socket, so it is closed if setting it up fails later on.
+pub(crate) fn socket_owned<T: FromRawFd>(
+    family: c_int,
+    ty: c_int,
+    protocol: c_int,
+) -> io::Result<T> {
+    let fd = socket(family, ty, protocol)?;
+    Ok(unsafe { T::from_raw_fd(fd) })
+}
+
+fn socket(family: c_int, ty: c_int, protocol: c_int) -> io::Result<RawFd> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let ty = ty | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+        cvt(unsafe { libc::socket(family, ty, protocol) })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let fd = cvt(unsafe { libc::socket(family, ty, protocol) })?;
+        if let Err(e) = set_cloexec_nonblocking(fd) {
+            unsafe { libc::close(fd) };
+            return Err(e);
+        }
+        Ok(fd)
+    }
+}
+
+/// Creates a non-blocking, close-on-exec Multipath TCP socket, or a plain
+/// TCP one if the kernel doesn't support MPTCP or has it turned off.
+#[cfg(all(feature = "mptcp", target_os = "linux"))]
+pub(crate) fn mptcp_socket<T: FromRawFd>(family: c_int) -> io::Result<T> {
+    match socket_owned(family, libc::SOCK_STREAM, libc::IPPROTO_MPTCP) {
+        // Older kernels don't know the protocol, and `net.mptcp.enabled`
+        // can turn it off.
+        Err(ref e)
+            if e.raw_os_error() == Some(libc::EPROTONOSUPPORT)
+                || e.raw_os_error() == Some(libc::EINVAL)
+                || e.raw_os_error() == Some(libc::ENOPROTOOPT) =>
+        {
+            socket_owned(family, libc::SOCK_STREAM, 0)
+        }
+        r => r,
+    }
+}
+
+/// Whether `fd` is a Multipath TCP socket.
+#[cfg(all(feature = "mptcp", target_os = "linux"))]
+pub(crate) fn is_mptcp(fd: RawFd) -> io::Result<bool> {
+    getsockopt::<c_int>(fd, libc::SOL_SOCKET, libc::SO_PROTOCOL).map(|p| p == libc::IPPROTO_MPTCP)
+}
+
+/// Sends `buf` to the netlink port `pid`, which is 0 for the kernel.
+#[cfg(all(feature = "netlink", target_os = "linux"))]
+pub(crate) fn send_to_netlink(fd: RawFd, buf: &[u8], pid: u32) -> io::Result<usize> {
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_pid = pid;
+    let n = unsafe {
+        libc::sendto(
+            fd,
+            buf.as_ptr() as *const c_void,
+            buf.len(),
+            0,
+            &addr as *const _ as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as socklen_t,
+        )
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
+
+/// Receives a netlink datagram, returning how many bytes were copied into
+/// `buf` and the port of its sender.
+#[cfg(all(feature = "netlink", target_os = "linux"))]
+pub(crate) fn recv_from_netlink(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, u32)> {
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<libc::sockaddr_nl>() as socklen_t;
+    let n = unsafe {
+        libc::recvfrom(
+            fd,
+            buf.as_mut_ptr() as *mut c_void,
+            buf.len(),
+            0,
+            &mut addr as *mut _ as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((n as usize, addr.nl_pid))
+}
+
+/// Binds a netlink socket, letting the kernel pick its port, and returns
+/// that port.
+#[cfg(all(feature = "netlink", target_os = "linux"))]
+pub(crate) fn bind_netlink(fd: RawFd) -> io::Result<u32> {
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    let mut len = mem::size_of::<libc::sockaddr_nl>() as socklen_t;
+    cvt(unsafe { libc::bind(fd, &addr as *const _ as *const libc::sockaddr, len) })?;
+    cvt(unsafe { libc::getsockname(fd, &mut addr as *mut _ as *mut libc::sockaddr, &mut len) })?;
+    Ok(addr.nl_pid)
+}
+
+/// Creates a non-blocking, close-on-exec inotify instance.
+#[cfg(target_os = "linux")]
+pub(crate) fn inotify_init() -> io::Result<std::fs::File> {
+    let fd = cvt(unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) })?;
+    Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+}
+
+/// Watches `path` for the events in `mask`, returning the watch descriptor.
+#[cfg(target_os = "linux")]
+pub(crate) fn inotify_add_watch(fd: RawFd, path: &Path, mask: u32) -> io::Result<c_int> {
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+    cvt(unsafe { libc::inotify_add_watch(fd, path.as_ptr(), mask) })
+}
+
+/// Removes the watch `wd`.
+#[cfg(target_os = "linux")]
+pub(crate) fn inotify_rm_watch(fd: RawFd, wd: c_int) -> io::Result<()> {
+    cvt(unsafe { libc::inotify_rm_watch(fd, wd) })?;
+    Ok(())
+}
+
+/// Creates a connected pair of non-blocking, close-on-exec Unix sockets.
+///
+/// Where the platform allows it, both flags are set by the `socketpair` call
+/// itself, so a concurrent `fork` + `exec` in another thread can never
+/// inherit the descriptors. Unlike mio-uds, this does not fall back to
+/// setting them afterwards if the kernel rejects the flags.
+pub(crate) fn socketpair(ty: c_int) -> io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let ty = ty | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC;
+        cvt(unsafe { libc::socketpair(libc::AF_UNIX, ty, 0, fds.as_mut_ptr()) })?;
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        cvt(unsafe { libc::socketpair(libc::AF_UNIX, ty, 0, fds.as_mut_ptr()) })?;
+        let set = set_cloexec_nonblocking(fds[0]).and_then(|()| set_cloexec_nonblocking(fds[1]));
+        if let Err(e) = set {
+            unsafe {
+                libc::close(fds[0]);
+                libc::close(fds[1]);
+            }
+            return Err(e);
+        }
+    }
+
+    Ok((fds[0], fds[1]))
+}
+
+/// Creates a non-blocking, close-on-exec pipe, returning its read and write
+/// ends.
+pub(crate) fn pipe() -> io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let flags = libc::O_NONBLOCK | libc::O_CLOEXEC;
+        cvt(unsafe { libc::pipe2(fds.as_mut_ptr(), flags) })?;
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        cvt(unsafe { libc::pipe(fds.as_mut_ptr()) })?;
+        let set = set_cloexec_nonblocking(fds[0]).and_then(|()| set_cloexec_nonblocking(fds[1]));
+        if let Err(e) = set {
+            unsafe {
+                libc::close(fds[0]);
+                libc::close(fds[1]);
+            }
+            return Err(e);
+        }
+    }
+
+    Ok((fds[0], fds[1]))
+}
+
+/// Opens a pidfd for the process `pid`, which becomes readable once the
+/// process has exited.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn pidfd_open(pid: u32) -> io::Result<RawFd> {
+    let ret = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as RawFd)
+    }
+}
+
+/// Blocks until the child `pid` has exited, leaving it to be reaped.
+pub(crate) fn wait_exited(pid: u32) -> io::Result<()> {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+        let options = libc::WEXITED | libc::WNOWAIT;
+        match cvt(unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, options) }) {
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            res => return res.map(drop),
+        }
+    }
+}
+
+/// Sends up to `len` bytes of the file `in_fd`, starting at `offset`, to
+/// the socket `out_fd` with `sendfile`.
+#[cfg(target_os = "linux")]
+pub(crate) fn sendfile(out_fd: RawFd, in_fd: RawFd, offset: u64, len: usize) -> io::Result<usize> {
+    if offset > libc::off_t::MAX as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "file offset too large",
+        ));
+    }
+    let mut offset = offset as libc::off_t;
+    let ret = unsafe { libc::sendfile(out_fd, in_fd, &mut offset, len) };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// Moves up to `len` bytes from `fd_in` to `fd_out` with `splice`, without
+/// blocking on either. One of them must be a pipe.
+#[cfg(target_os = "linux")]
+pub(crate) fn splice(fd_in: RawFd, fd_out: RawFd, len: usize) -> io::Result<usize> {
+    let flags = libc::SPLICE_F_NONBLOCK | libc::SPLICE_F_MOVE;
+    let ret = unsafe {
+        libc::splice(
+            fd_in,
+            std::ptr::null_mut(),
+            fd_out,
+            std::ptr::null_mut(),
+            len,
+            flags,
+        )
+    };
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+/// Returns whether `fd` is a pipe or a FIFO.
+pub(crate) fn is_fifo(fd: RawFd) -> io::Result<bool> {
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    cvt(unsafe { libc::fstat(fd, &mut stat) })?;
+    Ok(stat.st_mode & libc::S_IFMT == libc::S_IFIFO)
+}
+
+/// Sets the close-on-exec and non-blocking flags of a new descriptor, for
+/// platforms that can't set them when it is created.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_cloexec_nonblocking(fd: RawFd) -> io::Result<()> {
+    cvt(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) })?;
+    set_nonblocking(fd)
+}
+
+/// Sets the non-blocking flag of a descriptor, keeping its other status
+/// flags.
+pub(crate) fn set_nonblocking(fd: RawFd) -> io::Result<()> {
+    let flags = cvt(unsafe { libc::fcntl(fd, libc::F_GETFL) })?;
+    if flags & libc::O_NONBLOCK == 0 {
+        cvt(unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) })?;
+    }
+    Ok(())
+}
+
+/// The first descriptor passed by socket activation, `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The descriptors passed by socket activation that haven't been taken yet,
+/// or `None` until the environment has been read.
+static LISTEN_FDS: Mutex<Option<Vec<RawFd>>> = Mutex::new(None);
+
+/// Takes the listening stream sockets of the address `families` that were
+/// passed by socket activation, like `sd_listen_fds`.
+///
+/// The first call reads `LISTEN_PID` and `LISTEN_FDS`, ignoring them if
+/// they were meant for another process, removes them and `LISTEN_FDNAMES`
+/// from the environment so children don't inherit them, and marks the
+/// descriptors close-on-exec. Each descriptor is handed out once; those of
+/// other families are left for later calls.
+pub(crate) fn take_listen_fds(families: &[c_int]) -> io::Result<Vec<RawFd>> {
+    let mut remaining = LISTEN_FDS.lock().unwrap_or_else(|e| e.into_inner());
+    if remaining.is_none() {
+        *remaining = Some(read_listen_fds()?);
+    }
+    let remaining = remaining.as_mut().unwrap();
+
+    let mut taken = Vec::new();
+    let mut i = 0;
+    while i < remaining.len() {
+        if is_listening_stream(remaining[i], families)? {
+            taken.push(remaining.remove(i));
+        } else {
+            i += 1;
+        }
+    }
+    Ok(taken)
+}
+
+fn read_listen_fds() -> io::Result<Vec<RawFd>> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let pid = match std::env::var("LISTEN_PID") {
+        Ok(pid) => pid,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let pid: u32 = pid.parse().map_err(|_| invalid("invalid LISTEN_PID"))?;
+    if pid != std::process::id() {
+        return Ok(Vec::new());
+    }
+    let n: RawFd = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .filter(|n| (0..=RawFd::MAX - LISTEN_FDS_START).contains(n))
+        .ok_or_else(|| invalid("invalid LISTEN_FDS"))?;
+
+    for var in &["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(var);
+    }
+    let fds: Vec<_> = (LISTEN_FDS_START..LISTEN_FDS_START + n).collect();
+    for &fd in &fds {
+        cvt(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) })?;
+    }
+    Ok(fds)
+}
+
+/// Whether `fd` is a stream socket of one of `families` that is listening.
+/// Descriptors that aren't sockets, such as FIFOs, are not.
+fn is_listening_stream(fd: RawFd, families: &[c_int]) -> io::Result<bool> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+    let addr = &mut storage as *mut _ as *mut libc::sockaddr;
+    match cvt(unsafe { libc::getsockname(fd, addr, &mut len) }) {
+        Err(ref e) if e.raw_os_error() == Some(libc::ENOTSOCK) => return Ok(false),
+        res => res?,
+    };
+    if !families.contains(&(storage.ss_family as c_int)) {
+        return Ok(false);
+    }
+    let ty: c_int = getsockopt(fd, libc::SOL_SOCKET, libc::SO_TYPE)?;
+    let listening: c_int = getsockopt(fd, libc::SOL_SOCKET, libc::SO_ACCEPTCONN)?;
+    Ok(ty == libc::SOCK_STREAM && listening != 0)
+}
+
+/// Reads a socket option whose value is a plain `T`.
+pub(crate) fn getsockopt<T: Copy>(fd: RawFd, level: c_int, name: c_int) -> io::Result<T> {
+    unsafe {
+        let mut val: T = mem::zeroed();
+        let mut len = mem::size_of::<T>() as socklen_t;
+        cvt(libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut val as *mut T as *mut c_void,
+            &mut len,
+        ))?;
+        assert_eq!(len as usize, mem::size_of::<T>());
+        Ok(val)
+    }
+}
+
+/// Sets a socket option whose value is a plain `T`.
+pub(crate) fn setsockopt<T>(fd: RawFd, level: c_int, name: c_int, val: T) -> io::Result<()> {
+    unsafe {
+        cvt(libc::setsockopt(
+            fd,
+            level,
+            name,
+            &val as *const T as *const c_void,
+            mem::size_of::<T>() as socklen_t,
+        ))?;
+    }
+    Ok(())
+}
+
+/// Binds a socket to the given address.
+pub(crate) fn bind(fd: RawFd, addr: &SocketAddr) -> io::Result<()> {
+    let (storage, len) = addr_to_sockaddr(addr);
+    cvt(unsafe { libc::bind(fd, &storage as *const _ as *const libc::sockaddr, len) })?;
+    Ok(())
+}
+
+/// Binds a Unix socket to `path`.
+pub(crate) fn bind_unix(fd: RawFd, path: &Path) -> io::Result<()> {
+    let (addr, len) = unix_sockaddr(path)?;
+    let addr = &addr as *const _ as *const libc::sockaddr;
+    cvt(unsafe { libc::bind(fd, addr, len) })?;
+    Ok(())
+}
+
+/// Marks a bound socket as accepting connections, queueing up to `backlog`
+/// of them.
+pub(crate) fn listen(fd: RawFd, backlog: u32) -> io::Result<()> {
+    // The kernel caps the backlog itself, e.g. at `somaxconn` on Linux.
+    let backlog = cmp::min(backlog, c_int::MAX as u32) as c_int;
+    cvt(unsafe { libc::listen(fd, backlog) })?;
+    Ok(())
+}
+
+/// Starts connecting a Unix socket to `path`.
+///
+/// Unlike mio-uds, this reports `EAGAIN`, which Linux returns when the
+/// listener's backlog is full, as an error instead of treating it as a
+/// connect in progress. A connect that is actually in progress is `Ok`.
+pub(crate) fn connect_unix(fd: RawFd, path: &Path) -> io::Result<()> {
+    let (addr, len) = unix_sockaddr(path)?;
+    let addr = &addr as *const _ as *const libc::sockaddr;
+    match cvt(unsafe { libc::connect(fd, addr, len) }) {
+        Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => Ok(()),
+        res => res.map(|_| ()),
+    }
+}
+
+/// Encodes `path` as a Unix socket address. A path starting with a NUL byte
+/// names an address in the Linux abstract namespace.
+fn unix_sockaddr(path: &Path) -> io::Result<(libc::sockaddr_un, socklen_t)> {
+    let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+    let bytes = path.as_os_str().as_bytes();
+    // Leave room for the terminating NUL, which abstract names don't need.
+    if bytes.len() >= addr.sun_path.len() || (bytes.first() != Some(&0) && bytes.contains(&0)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "path must be shorter than SUN_LEN and contain no NUL bytes",
+        ));
+    }
+    for (dst, src) in addr.sun_path.iter_mut().zip(bytes) {
+        *dst = *src as libc::c_char;
+    }
+    let base = &addr as *const _ as usize;
+    let offset = &addr.sun_path as *const _ as usize - base;
+    let len = if bytes.first() == Some(&0) {
+        offset + bytes.len()
+    } else {
+        offset + bytes.len() + 1
+    };
+    Ok((addr, len as socklen_t))
+}
+
+/// Turns a Unix socket address back into the path `unix_sockaddr` takes, with
+/// a leading NUL byte for an abstract name.
+pub(crate) fn unix_addr_path(addr: &std::os::unix::net::SocketAddr) -> io::Result<PathBuf> {
+    if let Some(path) = addr.as_pathname() {
+        return Ok(path.to_path_buf());
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        #[cfg(target_os = "android")]
+        use std::os::android::net::SocketAddrExt;
+        #[cfg(target_os = "linux")]
+        use std::os::linux::net::SocketAddrExt;
+
+        if let Some(name) = addr.as_abstract_name() {
+            let mut bytes = vec![0];
+            bytes.extend_from_slice(name);
+            return Ok(PathBuf::from(OsString::from_vec(bytes)));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "unnamed Unix socket address",
+    ))
+}
+
+/// The largest number of descriptors passed with a single message; Linux
+/// rejects more with `EINVAL`.
+const MAX_FDS: usize = 253;
+
+/// Sends `buf` with `fds` attached in an `SCM_RIGHTS` control message.
+///
+/// The descriptors stay owned by the caller; the receiver gets duplicates.
+pub(crate) fn send_with_fds(fd: RawFd, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+    if fds.len() > MAX_FDS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many file descriptors",
+        ));
+    }
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+
+    let data_len = mem::size_of_val(fds);
+    let mut control = cmsg_buffer(data_len);
+    if !fds.is_empty() {
+        msg.msg_control = control.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = unsafe { libc::CMSG_SPACE(data_len as _) } as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(data_len as _) as _;
+            std::ptr::copy_nonoverlapping(
+                fds.as_ptr() as *const u8,
+                libc::CMSG_DATA(cmsg),
+                data_len,
+            );
+        }
+    }
+
+    let n = unsafe { libc::sendmsg(fd, &msg, 0) };
+    if n == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
+
+/// Receives into `buf`, and stores the descriptors of an `SCM_RIGHTS`
+/// control message that came along in `fds`.
+///
+/// Returns the number of bytes and of descriptors received. The received
+/// descriptors are close-on-exec and owned by the caller.
+///
+/// Fails with `InvalidData` if more descriptors were passed than fit in
+/// `fds`: the kernel drops those that don't fit in the control buffer and
+/// flags it with `MSG_CTRUNC`, so all of them are closed rather than handing
+/// over an incomplete set. The bytes are consumed all the same.
+pub(crate) fn recv_with_fds(
+    fd: RawFd,
+    buf: &mut [u8],
+    fds: &mut [RawFd],
+) -> io::Result<(usize, usize)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+
+    let room = cmp::min(fds.len(), MAX_FDS);
+    let mut control = cmsg_buffer(room * mem::size_of::<RawFd>());
+    if room > 0 {
+        msg.msg_control = control.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = (control.len() * mem::size_of::<u64>()) as _;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let flags = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let flags = 0;
+
+    let n = unsafe { libc::recvmsg(fd, &mut msg, flags) };
+    if n == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut received = 0;
+    let mut dropped = msg.msg_flags & libc::MSG_CTRUNC != 0;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let hdr = &*cmsg;
+            if hdr.cmsg_level == libc::SOL_SOCKET && hdr.cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg);
+                let len = hdr.cmsg_len as usize - (data as usize - cmsg as usize);
+                for i in 0..len / mem::size_of::<RawFd>() {
+                    let passed = std::ptr::read_unaligned((data as *const RawFd).add(i));
+                    if received < fds.len() {
+                        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                        {
+                            libc::fcntl(passed, libc::F_SETFD, libc::FD_CLOEXEC);
+                        }
+                        fds[received] = passed;
+                        received += 1;
+                    } else {
+                        libc::close(passed);
+                        dropped = true;
+                    }
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if dropped {
+        for &passed in &fds[..received] {
+            unsafe { libc::close(passed) };
+        }
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "more descriptors were passed than fit in `fds`",
+        ));
+    }
+    Ok((n as usize, received))
+}
+
+/// Allocates a control message buffer with room for `data_len` bytes,
+/// aligned for `cmsghdr`.
+fn cmsg_buffer(data_len: usize) -> Vec<u64> {
+    let space = unsafe { libc::CMSG_SPACE(data_len as _) } as usize;
+    vec![0; space / mem::size_of::<u64>() + 1]
+}
+
+/// Dissolves the association of a connected datagram socket, keeping its
+/// local address.
+pub(crate) fn disconnect(fd: RawFd) -> io::Result<()> {
+    let port = local_addr(fd)?.port();
+
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    storage.ss_family = libc::AF_UNSPEC as libc::sa_family_t;
+    let len = mem::size_of::<sockaddr_storage>() as socklen_t;
+    match cvt(unsafe { libc::connect(fd, &storage as *const _ as *const libc::sockaddr, len) }) {
+        Ok(_) => {}
+        // BSDs report this even though the socket was disconnected.
+        Err(ref e) if e.raw_os_error() == Some(libc::EAFNOSUPPORT) => {}
+        Err(e) => return Err(e),
+    }
+
+    // Linux releases a port picked by the kernel on disconnect, which would
+    // leave the socket unreachable at the address its peers know.
+    let local = local_addr(fd)?;
+    if local.port() == 0 {
+        bind(fd, &SocketAddr::new(local.ip(), port))?;
+    }
+    Ok(())
+}
+
+/// Returns whether a socket is connected to a peer.
+pub(crate) fn is_connected(fd: RawFd) -> io::Result<bool> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+    match cvt(unsafe {
+        libc::getpeername(fd, &mut storage as *mut _ as *mut libc::sockaddr, &mut len)
+    }) {
+        Ok(_) => Ok(true),
+        Err(ref e) if e.raw_os_error() == Some(libc::ENOTCONN) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns the address a socket is bound to.
+pub(crate) fn local_addr(fd: RawFd) -> io::Result<SocketAddr> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+    cvt(unsafe { libc::getsockname(fd, &mut storage as *mut _ as *mut libc::sockaddr, &mut len) })?;
+    sockaddr_to_addr(&storage, len as usize)
+}
+
+/// Encodes a socket address into the form the kernel expects.
+pub(crate) fn addr_to_sockaddr(addr: &SocketAddr) -> (sockaddr_storage, socklen_t) {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let len = match addr {
+        SocketAddr::V4(addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from(*addr.ip()).to_be();
+            mem::size_of::<sockaddr_in>()
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_scope_id = addr.scope_id();
+            mem::size_of::<sockaddr_in6>()
+        }
+    };
+    (storage, len as socklen_t)
+}
+
+/// Sets `SO_BUSY_POLL`, the time in microseconds to busy-poll the device
+/// queue on a blocking receive.
+pub(crate) fn set_busy_poll(fd: RawFd, usecs: u32) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if usecs > c_int::MAX as u32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "busy poll time out of range",
+            ));
+        }
+        setsockopt(fd, libc::SOL_SOCKET, libc::SO_BUSY_POLL, usecs as c_int)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (fd, usecs);
+        Err(unsupported("SO_BUSY_POLL"))
+    }
+}
+
+/// Gets the value of `SO_BUSY_POLL`.
+pub(crate) fn busy_poll(fd: RawFd) -> io::Result<u32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        getsockopt::<c_int>(fd, libc::SOL_SOCKET, libc::SO_BUSY_POLL).map(|v| v as u32)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = fd;
+        Err(unsupported("SO_BUSY_POLL"))
+    }
+}
+
+/// `SO_NO_CHECK`, which libc doesn't export for every Linux target.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SO_NO_CHECK: c_int = 11;
+
+/// Sets `SO_NO_CHECK`, which turns off checksums on outgoing UDP datagrams.
+///
+/// IPv6 makes the UDP checksum mandatory, so IPv6 sockets are rejected.
+pub(crate) fn set_no_check(fd: RawFd, ipv6: bool, on: bool) -> io::Result<()> {
+    if ipv6 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "UDP checksums can't be disabled over IPv6",
+        ));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        setsockopt(fd, libc::SOL_SOCKET, SO_NO_CHECK, on as c_int)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (fd, on);
+        Err(unsupported("SO_NO_CHECK"))
+    }
+}
+
+/// Gets the value of `SO_NO_CHECK`.
+pub(crate) fn no_check(fd: RawFd) -> io::Result<bool> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        getsockopt::<c_int>(fd, libc::SOL_SOCKET, SO_NO_CHECK).map(|v| v != 0)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = fd;
+        Err(unsupported("SO_NO_CHECK"))
+    }
+}
+
+/// The error returned for socket options this platform doesn't have.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn unsupported(option: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} is not supported on this platform", option),
+    )
+}
+
+/// Starts connecting `fd` to `addr` with TCP Fast Open, sending as much of
+/// `buf` along with the SYN as the kernel takes.
+///
+/// Returns how many bytes went out with the SYN. That is 0 when no Fast Open
+/// cookie for `addr` is cached yet, in which case the SYN asks for one and
+/// the data has to be written once connected.
+#[cfg(target_os = "linux")]
+pub(crate) fn connect_fastopen(fd: RawFd, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+    let (storage, len) = addr_to_sockaddr(addr);
+    let n = unsafe {
+        libc::sendto(
+            fd,
+            buf.as_ptr() as *const c_void,
+            buf.len(),
+            libc::MSG_FASTOPEN,
+            &storage as *const _ as *const libc::sockaddr,
+            len,
+        )
+    };
+    if n < 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::EINPROGRESS) {
+            return Ok(0);
+        }
+        return Err(err);
+    }
+    Ok(n as usize)
+}
+
+/// Sends `byte` as TCP urgent data, with `MSG_OOB`.
+pub(crate) fn send_oob(fd: RawFd, byte: u8) -> io::Result<()> {
+    let n = unsafe { libc::send(fd, &byte as *const u8 as *const c_void, 1, libc::MSG_OOB) };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Receives the pending byte of TCP urgent data, with `MSG_OOB`.
+pub(crate) fn recv_oob(fd: RawFd) -> io::Result<u8> {
+    let mut byte = 0u8;
+    let n = unsafe { libc::recv(fd, &mut byte as *mut u8 as *mut c_void, 1, libc::MSG_OOB) };
+    match n {
+        n if n < 0 => Err(io::Error::last_os_error()),
+        0 => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed before urgent data arrived",
+        )),
+        _ => Ok(byte),
+    }
+}
+
+/// Sets `SO_OOBINLINE`, which leaves urgent data in the normal stream.
+pub(crate) fn set_oob_inline(fd: RawFd, on: bool) -> io::Result<()> {
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_OOBINLINE, on as c_int)
+}
+
+/// Gets the value of `SO_OOBINLINE`.
+pub(crate) fn oob_inline(fd: RawFd) -> io::Result<bool> {
+    getsockopt::<c_int>(fd, libc::SOL_SOCKET, libc::SO_OOBINLINE).map(|v| v != 0)
+}
+
+/// Sends `buf` to `addr`, returning how many bytes were sent.
+pub(crate) fn send_to(fd: RawFd, buf: &[u8], addr: &SocketAddr) -> io::Result<usize> {
+    let (storage, len) = addr_to_sockaddr(addr);
+    let n = unsafe {
+        libc::sendto(
+            fd,
+            buf.as_ptr() as *const c_void,
+            buf.len(),
+            0,
+            &storage as *const _ as *const libc::sockaddr,
+            len,
+        )
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(n as usize)
+}
+
+/// Receives a datagram, returning how many bytes were copied into `buf` and
+/// the address of its sender.
+pub(crate) fn recv_from(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    recvfrom(fd, buf, 0)
+}
+
+/// Receives a datagram without removing it from the queue, returning how
+/// many bytes were copied into `buf` and the address of its sender.
+pub(crate) fn peek_from(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+    recvfrom(fd, buf, libc::MSG_PEEK)
+}
+
+fn recvfrom(fd: RawFd, buf: &mut [u8], flags: c_int) -> io::Result<(usize, SocketAddr)> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+    let n = unsafe {
+        libc::recvfrom(
+            fd,
+            buf.as_mut_ptr() as *mut c_void,
+            buf.len(),
+            flags,
+            &mut storage as *mut _ as *mut libc::sockaddr,
+            &mut len,
+        )
+    };
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((n as usize, sockaddr_to_addr(&storage, len as usize)?))
+}
+
+/// Decodes an IPv4 or IPv6 socket address written by the kernel.
+pub(crate) fn sockaddr_to_addr(storage: &sockaddr_storage, len: usize) -> io::Result<SocketAddr> {
+    match storage.ss_family as c_int {
+        libc::AF_INET => {
+            assert!(len >= mem::size_of::<sockaddr_in>());
+            let addr = unsafe { &*(storage as *const _ as *const sockaddr_in) };
+            let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+            let port = u16::from_be(addr.sin_port);
+            Ok(SocketAddr::V4(SocketAddrV4::new(ip, port)))
+        }
+        libc::AF_INET6 => {
+            assert!(len >= mem::size_of::<sockaddr_in6>());
+            let addr = unsafe { &*(storage as *const _ as *const sockaddr_in6) };
+            let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+            let port = u16::from_be(addr.sin6_port);
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                port,
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            )))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid socket address family",
+        )),
+    }
+}
+
+/// Returns the destination a connection had before it was redirected by
+/// Netfilter, using `SO_ORIGINAL_DST`.
+#[cfg(target_os = "linux")]
+pub(crate) fn original_dst(fd: RawFd, ipv6: bool) -> io::Result<SocketAddr> {
+    let (level, name) = if ipv6 {
+        (libc::SOL_IPV6, libc::IP6T_SO_ORIGINAL_DST)
+    } else {
+        (libc::SOL_IP, libc::SO_ORIGINAL_DST)
+    };
+
+    unsafe {
+        let mut storage: sockaddr_storage = mem::zeroed();
+        let mut len = mem::size_of::<sockaddr_storage>() as socklen_t;
+        cvt(libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut storage as *mut _ as *mut c_void,
+            &mut len,
+        ))?;
+        sockaddr_to_addr(&storage, len as usize)
+    }
+}
+
+/// Asks for the original destination of every received datagram, using
+/// `IP_RECVORIGDSTADDR` and, on IPv6 sockets, `IPV6_RECVORIGDSTADDR`.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_recv_orig_dst(fd: RawFd, ipv6: bool, on: bool) -> io::Result<()> {
+    let on = on as c_int;
+    if ipv6 {
+        setsockopt(fd, libc::SOL_IPV6, libc::IPV6_RECVORIGDSTADDR, on)?;
+    }
+    // Also covers the IPv4 datagrams a dual-stack IPv6 socket receives.
+    setsockopt(fd, libc::SOL_IP, libc::IP_RECVORIGDSTADDR, on)
+}
+
+/// Receives a datagram along with its sender and the destination it was
+/// originally addressed to.
+///
+/// Fails with `InvalidData` if the kernel didn't attach the destination,
+/// which happens when `set_recv_orig_dst` hasn't been enabled; the datagram
+/// is consumed all the same.
+#[cfg(target_os = "linux")]
+pub(crate) fn recv_from_with_orig_dst(
+    fd: RawFd,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, SocketAddr)> {
+    recv_msg(fd, buf, |msg| {
+        orig_dst_from_cmsgs(msg)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "datagram carries no original destination",
+            )
+        })
+    })
+}
+
+/// Receives a datagram with `recvmsg`, and extracts a value from the control
+/// messages that came with it.
+///
+/// Fails with `InvalidData` if the control messages didn't fit in the buffer
+/// and were cut short, rather than parsing what is left of them; the
+/// datagram is consumed all the same.
+#[cfg(target_os = "linux")]
+fn recv_msg<T>(
+    fd: RawFd,
+    buf: &mut [u8],
+    parse: impl FnOnce(&libc::msghdr) -> io::Result<T>,
+) -> io::Result<(usize, SocketAddr, T)> {
+    let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+    // Room for the control message asked for plus a few others the socket
+    // may have enabled; `u64` keeps it aligned for `cmsghdr`.
+    let mut control = [0u64; 32];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut storage as *mut sockaddr_storage as *mut c_void;
+    msg.msg_namelen = mem::size_of::<sockaddr_storage>() as socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let n = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+    if n == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "control messages were truncated",
+        ));
+    }
+    let from = sockaddr_to_addr(&storage, msg.msg_namelen as usize)?;
+    Ok((n as usize, from, parse(&msg)?))
+}
+
+/// Finds the `IP_ORIGDSTADDR` or `IPV6_ORIGDSTADDR` control message of a
+/// received message and decodes its address.
+#[cfg(target_os = "linux")]
+fn orig_dst_from_cmsgs(msg: &libc::msghdr) -> io::Result<Option<SocketAddr>> {
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+        while !cmsg.is_null() {
+            let hdr = &*cmsg;
+            let len = match (hdr.cmsg_level, hdr.cmsg_type) {
+                (libc::SOL_IP, libc::IP_ORIGDSTADDR) => mem::size_of::<sockaddr_in>(),
+                (libc::SOL_IPV6, libc::IPV6_ORIGDSTADDR) => mem::size_of::<sockaddr_in6>(),
+                _ => 0,
+            };
+            if len > 0 {
+                if (hdr.cmsg_len as usize) < libc::CMSG_LEN(len as _) as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "truncated original destination",
+                    ));
+                }
+                // The data is only aligned for `cmsghdr`, so copy it out.
+                let mut storage: sockaddr_storage = mem::zeroed();
+                std::ptr::copy_nonoverlapping(
+                    libc::CMSG_DATA(cmsg),
+                    &mut storage as *mut sockaddr_storage as *mut u8,
+                    len,
+                );
+                return sockaddr_to_addr(&storage, len).map(Some);
+            }
+            cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+        }
+    }
+    Ok(None)
+}
+
+/// Asks for the traffic class of every received datagram, using
+/// `IP_RECVTOS` and, on IPv6 sockets, `IPV6_RECVTCLASS`.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_recv_ecn(fd: RawFd, ipv6: bool, on: bool) -> io::Result<()> {
+    let on = on as c_int;
+    if ipv6 {
+        setsockopt(fd, libc::SOL_IPV6, libc::IPV6_RECVTCLASS, on)?;
+    }
+    // Also covers the IPv4 datagrams a dual-stack IPv6 socket receives.
+    setsockopt(fd, libc::SOL_IP, libc::IP_RECVTOS, on)
+}
+
+/// The bits of the TOS byte or traffic class that hold the ECN codepoint.
+#[cfg(target_os = "linux")]
+const ECN_MASK: u8 = 0b11;
+
+/// Sends a datagram whose ECN field is set to `ecn`.
+///
+/// The codepoint travels in an `IP_TOS` or `IPV6_TCLASS` control message,
+/// with the DSCP bits left at zero.
+#[cfg(target_os = "linux")]
+pub(crate) fn send_to_ecn(
+    fd: RawFd,
+    buf: &[u8],
+    target: &SocketAddr,
+    ecn: u8,
+) -> io::Result<usize> {
+    if ecn & !ECN_MASK != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ECN codepoint out of range",
+        ));
+    }
+    let (level, ty) = match target {
+        SocketAddr::V4(_) => (libc::SOL_IP, libc::IP_TOS),
+        SocketAddr::V6(_) => (libc::SOL_IPV6, libc::IPV6_TCLASS),
+    };
+
+    let (mut storage, len) = addr_to_sockaddr(target);
+    let mut control = [0u64; 4];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut storage as *mut sockaddr_storage as *mut c_void;
+    msg.msg_namelen = len;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(mem::size_of::<c_int>() as _) } as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = level;
+        (*cmsg).cmsg_type = ty;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<c_int>() as _) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut c_int, c_int::from(ecn));
+    }
+
+    let n = unsafe { libc::sendmsg(fd, &msg, 0) };
+    if n == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
+
+/// Receives a datagram along with its sender and its ECN codepoint.
+///
+/// Fails with `InvalidData` if the kernel didn't attach the traffic class,
+/// which happens when `set_recv_ecn` hasn't been enabled; the datagram is
+/// consumed all the same.
+#[cfg(target_os = "linux")]
+pub(crate) fn recv_from_with_ecn(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, u8)> {
+    recv_msg(fd, buf, |msg| {
+        ecn_from_cmsgs(msg)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "datagram carries no traffic class",
+            )
+        })
+    })
+}
+
+/// Finds the `IP_TOS` or `IPV6_TCLASS` control message of a received
+/// message and extracts its ECN codepoint.
+#[cfg(target_os = "linux")]
+fn ecn_from_cmsgs(msg: &libc::msghdr) -> io::Result<Option<u8>> {
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+        while !cmsg.is_null() {
+            let hdr = &*cmsg;
+            // IPv4 delivers the TOS as a single byte, IPv6 the traffic class
+            // as an `int`.
+            let len = match (hdr.cmsg_level, hdr.cmsg_type) {
+                (libc::SOL_IP, libc::IP_TOS) => 1,
+                (libc::SOL_IPV6, libc::IPV6_TCLASS) => mem::size_of::<c_int>(),
+                _ => 0,
+            };
+            if len > 0 {
+                if (hdr.cmsg_len as usize) < libc::CMSG_LEN(len as _) as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "truncated traffic class",
+                    ));
+                }
+                let data = libc::CMSG_DATA(cmsg);
+                let tos = if len == 1 {
+                    *data
+                } else {
+                    std::ptr::read_unaligned(data as *const c_int) as u8
+                };
+                return Ok(Some(tos & ECN_MASK));
+            }
+            cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+        }
+    }
+    Ok(None)
+}
+
+/// Receives a datagram along with its sender and, when the kernel
+/// coalesced several datagrams into `buf`, the size of each of them.
+///
+/// Datagrams are only coalesced once `UDP_GRO` is enabled; otherwise, or
+/// when the kernel didn't attach a `UDP_GRO` control message, the segment
+/// size is the length of the single datagram received.
+#[cfg(target_os = "linux")]
+pub(crate) fn recv_from_gro(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, SocketAddr, usize)> {
+    let (n, from, segment_size) = recv_msg(fd, buf, gro_from_cmsgs)?;
+    Ok((n, from, segment_size.unwrap_or(n)))
+}
+
+/// Finds the `UDP_GRO` control message of a received message and extracts
+/// its segment size.
+#[cfg(target_os = "linux")]
+fn gro_from_cmsgs(msg: &libc::msghdr) -> io::Result<Option<usize>> {
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+        while !cmsg.is_null() {
+            let hdr = &*cmsg;
+            if (hdr.cmsg_level, hdr.cmsg_type) == (libc::SOL_UDP, libc::UDP_GRO) {
+                if (hdr.cmsg_len as usize) < libc::CMSG_LEN(mem::size_of::<c_int>() as _) as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "truncated segment size",
+                    ));
+                }
+                let size = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const c_int);
+                return Ok(Some(size as usize));
+            }
+            cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+        }
+    }
+    Ok(None)
+}
+
+/// Restricts a socket to the network interface named `interface`, with
+/// `SO_BINDTODEVICE`.
+#[cfg(target_os = "linux")]
+pub(crate) fn bind_device(fd: RawFd, interface: &str) -> io::Result<()> {
+    if interface.len() >= libc::IFNAMSIZ || interface.contains('\0') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid interface name",
+        ));
+    }
+    cvt(unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            interface.as_ptr() as *const c_void,
+            interface.len() as socklen_t,
+        )
+    })?;
+    Ok(())
+}
+
+/// Asks for the destination address and receiving interface of every
+/// received datagram, using `IP_PKTINFO` and, on IPv6 sockets,
+/// `IPV6_RECVPKTINFO`.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_recv_pktinfo(fd: RawFd, ipv6: bool, on: bool) -> io::Result<()> {
+    let on = on as c_int;
+    if ipv6 {
+        setsockopt(fd, libc::SOL_IPV6, libc::IPV6_RECVPKTINFO, on)?;
+    }
+    // Also covers the IPv4 datagrams a dual-stack IPv6 socket receives.
+    setsockopt(fd, libc::SOL_IP, libc::IP_PKTINFO, on)
+}
+
+/// Asks for the TTL or hop limit of every received datagram, using
+/// `IP_RECVTTL` and, on IPv6 sockets, `IPV6_RECVHOPLIMIT`.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_recv_ttl(fd: RawFd, ipv6: bool, on: bool) -> io::Result<()> {
+    let on = on as c_int;
+    if ipv6 {
+        setsockopt(fd, libc::SOL_IPV6, libc::IPV6_RECVHOPLIMIT, on)?;
+    }
+    setsockopt(fd, libc::SOL_IP, libc::IP_RECVTTL, on)
+}
+
+/// Receives a datagram along with its sender and whatever the control
+/// messages that came with it tell about it.
+#[cfg(target_os = "linux")]
+pub(crate) fn recv_from_with_meta(
+    fd: RawFd,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, crate::udp::RecvMeta)> {
+    recv_msg(fd, buf, |msg| {
+        let mut meta = meta_from_cmsgs(msg)?;
+        meta.ecn = ecn_from_cmsgs(msg)?;
+        Ok(meta)
+    })
+}
+
+/// Decodes the `IP_PKTINFO`, `IPV6_PKTINFO`, `IP_TTL` and `IPV6_HOPLIMIT`
+/// control messages of a received message.
+#[cfg(target_os = "linux")]
+fn meta_from_cmsgs(msg: &libc::msghdr) -> io::Result<crate::udp::RecvMeta> {
+    let mut meta = crate::udp::RecvMeta::default();
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+        while !cmsg.is_null() {
+            let hdr = &*cmsg;
+            let len = match (hdr.cmsg_level, hdr.cmsg_type) {
+                (libc::SOL_IP, libc::IP_PKTINFO) => mem::size_of::<libc::in_pktinfo>(),
+                (libc::SOL_IPV6, libc::IPV6_PKTINFO) => mem::size_of::<libc::in6_pktinfo>(),
+                (libc::SOL_IP, libc::IP_TTL) | (libc::SOL_IPV6, libc::IPV6_HOPLIMIT) => {
+                    mem::size_of::<c_int>()
+                }
+                _ => 0,
+            };
+            if len > 0 {
+                if (hdr.cmsg_len as usize) < libc::CMSG_LEN(len as _) as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "truncated control message",
+                    ));
+                }
+                // The data is only aligned for `cmsghdr`, so read it unaligned.
+                let data = libc::CMSG_DATA(cmsg);
+                match hdr.cmsg_type {
+                    libc::IP_PKTINFO if hdr.cmsg_level == libc::SOL_IP => {
+                        let info = std::ptr::read_unaligned(data as *const libc::in_pktinfo);
+                        let dst = Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr));
+                        meta.dst = Some(dst.into());
+                        meta.interface = Some(info.ipi_ifindex as u32);
+                    }
+                    libc::IPV6_PKTINFO if hdr.cmsg_level == libc::SOL_IPV6 => {
+                        let info = std::ptr::read_unaligned(data as *const libc::in6_pktinfo);
+                        meta.dst = Some(Ipv6Addr::from(info.ipi6_addr.s6_addr).into());
+                        meta.interface = Some(info.ipi6_ifindex);
+                    }
+                    _ => {
+                        let ttl = std::ptr::read_unaligned(data as *const c_int);
+                        meta.ttl = Some(ttl as u8);
+                    }
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+        }
+    }
+    Ok(meta)
+}
+
+/// Sends a datagram from the local address `source`.
+///
+/// The address travels in an `IP_PKTINFO` or `IPV6_PKTINFO` control
+/// message, which overrides the address the socket is bound to, and lets
+/// the routing table pick the interface.
+#[cfg(target_os = "linux")]
+pub(crate) fn send_to_from(
+    fd: RawFd,
+    buf: &[u8],
+    target: &SocketAddr,
+    source: &std::net::IpAddr,
+) -> io::Result<usize> {
+    use std::net::IpAddr;
+
+    let (mut storage, len) = addr_to_sockaddr(target);
+    let mut control = [0u64; 8];
+    let mut iov = libc::iovec {
+        iov_base: buf.as_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = &mut storage as *mut sockaddr_storage as *mut c_void;
+    msg.msg_namelen = len;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut c_void;
+    // Trimmed to the control message once it is written.
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        match (target, source) {
+            (SocketAddr::V4(_), IpAddr::V4(source)) => {
+                let mut info: libc::in_pktinfo = mem::zeroed();
+                info.ipi_spec_dst.s_addr = u32::from(*source).to_be();
+                let size = mem::size_of_val(&info) as _;
+                msg.msg_controllen = libc::CMSG_SPACE(size) as _;
+                (*cmsg).cmsg_level = libc::SOL_IP;
+                (*cmsg).cmsg_type = libc::IP_PKTINFO;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(size) as _;
+                std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::in_pktinfo, info);
+            }
+            (SocketAddr::V6(_), IpAddr::V6(source)) => {
+                let mut info: libc::in6_pktinfo = mem::zeroed();
+                info.ipi6_addr.s6_addr = source.octets();
+                let size = mem::size_of_val(&info) as _;
+                msg.msg_controllen = libc::CMSG_SPACE(size) as _;
+                (*cmsg).cmsg_level = libc::SOL_IPV6;
+                (*cmsg).cmsg_type = libc::IPV6_PKTINFO;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(size) as _;
+                std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::in6_pktinfo, info);
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "source and target address families differ",
+                ))
+            }
+        }
+    }
+
+    let n = unsafe { libc::sendmsg(fd, &msg, 0) };
+    if n == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use libc::sa_family_t;
+
+    #[test]
+    fn sockaddr_decode_v4() {
+        // What the kernel writes back for `SO_ORIGINAL_DST`: a raw
+        // `sockaddr_in` with the port and address in network byte order.
+        let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+        {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut sockaddr_in) };
+            sin.sin_family = libc::AF_INET as sa_family_t;
+            sin.sin_port = 0x1f90u16.to_be();
+            sin.sin_addr.s_addr = u32::from_be_bytes([192, 168, 0, 1]).to_be();
+        }
+
+        let addr = sockaddr_to_addr(&storage, mem::size_of::<sockaddr_in>()).unwrap();
+        assert_eq!(addr, "192.168.0.1:8080".parse::<SocketAddr>().unwrap());
+    }
+
+    #[test]
+    fn sockaddr_roundtrip_v6() {
+        let addr = "[fe80::1%3]:5353".parse::<SocketAddr>().unwrap();
+        let (storage, len) = addr_to_sockaddr(&addr);
+        assert_eq!(sockaddr_to_addr(&storage, len as usize).unwrap(), addr);
+    }
+
+    #[test]
+    fn sockaddr_decode_unknown_family() {
+        let mut storage: sockaddr_storage = unsafe { mem::zeroed() };
+        storage.ss_family = libc::AF_UNIX as sa_family_t;
+        let err = sockaddr_to_addr(&storage, mem::size_of::<sockaddr_storage>()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    /// Lays out a single control message the way the kernel would.
+    #[cfg(target_os = "linux")]
+    fn with_cmsg<T>(level: c_int, ty: c_int, data: &[u8], f: impl FnOnce(&libc::msghdr) -> T) -> T {
+        let mut control = [0u64; 16];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_control = control.as_mut_ptr() as *mut c_void;
+        msg.msg_controllen = unsafe { libc::CMSG_SPACE(data.len() as _) } as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(data.len() as _) as _;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), libc::CMSG_DATA(cmsg), data.len());
+        }
+        f(&msg)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn sockaddr_bytes(addr: &SocketAddr) -> Vec<u8> {
+        let (storage, len) = addr_to_sockaddr(addr);
+        let ptr = &storage as *const sockaddr_storage as *const u8;
+        unsafe { std::slice::from_raw_parts(ptr, len as usize) }.to_vec()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn orig_dst_cmsg_v4() {
+        let addr = "10.0.0.53:53".parse::<SocketAddr>().unwrap();
+        let data = sockaddr_bytes(&addr);
+        let got = with_cmsg(
+            libc::SOL_IP,
+            libc::IP_ORIGDSTADDR,
+            &data,
+            orig_dst_from_cmsgs,
+        );
+        assert_eq!(got.unwrap(), Some(addr));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn orig_dst_cmsg_v6() {
+        let addr = "[2001:db8::1]:443".parse::<SocketAddr>().unwrap();
+        let data = sockaddr_bytes(&addr);
+        let got = with_cmsg(
+            libc::SOL_IPV6,
+            libc::IPV6_ORIGDSTADDR,
+            &data,
+            orig_dst_from_cmsgs,
+        );
+        assert_eq!(got.unwrap(), Some(addr));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn orig_dst_cmsg_other_messages() {
+        let ttl = 64 as c_int;
+        let data = ttl.to_ne_bytes();
+        let got = with_cmsg(libc::SOL_IP, libc::IP_TTL, &data, orig_dst_from_cmsgs);
+        assert_eq!(got.unwrap(), None);
+
+        let got = with_cmsg(
+            libc::SOL_IP,
+            libc::IP_ORIGDSTADDR,
+            &data,
+            orig_dst_from_cmsgs,
+        );
+        assert_eq!(got.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ecn_cmsg_v4_byte() {
+        // Only the low two bits of the TOS byte are the ECN field.
+        let got = with_cmsg(libc::SOL_IP, libc::IP_TOS, &[0xb8 | 0b10], ecn_from_cmsgs);
+        assert_eq!(got.unwrap(), Some(0b10));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn ecn_cmsg_v6_int() {
+        let tclass = 0x03 as c_int;
+        let got = with_cmsg(
+            libc::SOL_IPV6,
+            libc::IPV6_TCLASS,
+            &tclass.to_ne_bytes(),
+            ecn_from_cmsgs,
+        );
+        assert_eq!(got.unwrap(), Some(0b11));
+    }
+}
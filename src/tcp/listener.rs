@@ -1,3 +1,4 @@
+use super::stream::{tcp_from_std, Tcp};
 use super::TcpStream;
 
 use std::convert::TryFrom;
@@ -5,14 +6,25 @@ use std::fmt;
 use std::io;
 use std::net::{self, SocketAddr};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::task::Context;
+use std::time::{Duration, Instant};
 
 use async_ready::AsyncReady;
 use futures::stream::Stream;
-use futures::{ready, Poll};
+use futures::task::AtomicWaker;
+use futures::{ready, Future, Poll};
+use log::debug;
 use mio;
 
-use crate::raw::PollEvented;
+use crate::raw::{Handle, PollEvented};
+use crate::reactor::timer::Delay;
+use crate::socket::SavedSocket;
+
+/// The backlog `TcpListener::bind` listens with.
+#[cfg(unix)]
+const DEFAULT_BACKLOG: u32 = 1024;
 
 /// A TCP socket server, listening for connections.
 ///
@@ -52,8 +64,17 @@ use crate::raw::PollEvented;
 /// ```
 pub struct TcpListener {
     io: PollEvented<mio::net::TcpListener>,
+    /// The reactor accepted streams are bound to, if it was chosen
+    /// explicitly.
+    handle: Option<Handle>,
+    /// Only read on Windows, for `AsRawSocket`.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    socket: SavedSocket,
 }
 
+/// A listening socket as the constructors of `TcpListener` take it.
+type Listener = (mio::net::TcpListener, SavedSocket);
+
 impl TcpListener {
     /// Creates a new `TcpListener` which will be bound to the specified
     /// address.
@@ -78,13 +99,207 @@ impl TcpListener {
     ///
     /// [`local_addr`]: #method.local_addr
     pub fn bind(addr: &SocketAddr) -> io::Result<TcpListener> {
-        let l = mio::net::TcpListener::bind(addr)?;
-        Ok(TcpListener::new(l))
+        Ok(TcpListener::new(bind_listener(addr)?))
     }
 
-    fn new(listener: mio::net::TcpListener) -> TcpListener {
+    /// Creates a new `TcpListener` bound to the reactor of `handle`.
+    ///
+    /// The listener and the streams it accepts are all driven by that
+    /// reactor, rather than by the one that is current when they are first
+    /// polled. See [`bind`] for the rest.
+    ///
+    /// [`bind`]: #method.bind
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::raw::Reactor;
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let reactor = Reactor::new()?;
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let listener = TcpListener::bind_on(&reactor.handle(), &socket_addr)?;
+    /// # Ok(())}
+    /// ```
+    pub fn bind_on(handle: &Handle, addr: &SocketAddr) -> io::Result<TcpListener> {
+        let (l, socket) = bind_listener(addr)?;
+        Ok(TcpListener {
+            io: PollEvented::new_with_handle(l, handle)?,
+            handle: Some(handle.clone()),
+            socket,
+        })
+    }
+
+    /// Takes the TCP listeners passed to this process by socket activation,
+    /// such as by systemd, in the order they were passed.
+    ///
+    /// This follows `sd_listen_fds`: the descriptors, starting at 3, are
+    /// counted by `LISTEN_FDS`, and only taken if `LISTEN_PID` is this
+    /// process. Those variables are removed from the environment on the
+    /// first call, by this or by [`UnixListener::from_listen_fds`], and each
+    /// descriptor is taken at most once; listeners of other kinds are left
+    /// for the other. Without socket activation, the list is empty.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `io::ErrorKind::InvalidData` if the variables are
+    /// malformed, and with the OS error if a passed descriptor isn't open.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let listener = match TcpListener::from_env()?.pop() {
+    ///     Some(listener) => listener,
+    ///     None => TcpListener::bind(&"127.0.0.1:80".parse()?)?,
+    /// };
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`UnixListener::from_listen_fds`]: ../uds/struct.UnixListener.html#method.from_listen_fds
+    #[cfg(unix)]
+    pub fn from_env() -> io::Result<Vec<TcpListener>> {
+        use std::os::unix::io::FromRawFd;
+
+        let fds = crate::sys::take_listen_fds(&[libc::AF_INET, libc::AF_INET6])?;
+        Ok(fds
+            .into_iter()
+            .map(|fd| unsafe { TcpListener::from_raw_fd(fd) })
+            .collect())
+    }
+
+    /// Returns a builder for a listener whose socket options are set before
+    /// it starts listening.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "0.0.0.0:8080".parse()?;
+    /// // Every worker binds its own listener; the kernel spreads the
+    /// // connections over them.
+    /// let listener = TcpListener::builder()
+    ///     .reuse_port(true)
+    ///     .backlog(4096)
+    ///     .bind(&socket_addr)?;
+    /// # Ok(())}
+    /// ```
+    #[cfg(unix)]
+    pub fn builder() -> TcpListenerBuilder {
+        TcpListenerBuilder::new()
+    }
+
+    /// Binds `n` listeners to the same address with `SO_REUSEPORT`, so
+    /// accepting can be sharded across workers.
+    ///
+    /// Each worker thread takes one of the listeners and accepts from it on
+    /// its own. On Linux, the kernel hashes incoming connections across all
+    /// listeners that share the port, so no single accept loop becomes the
+    /// bottleneck. Other platforms may allow the shared bind without
+    /// balancing the load.
+    ///
+    /// With a port of 0, the OS picks a port for the first listener and the
+    /// others share it. If any of the binds fails, no listener is returned.
+    /// Asking for no listeners at all is an `InvalidInput` error.
+    ///
+    /// The listeners use the options of [`TcpListener::builder`] with
+    /// `SO_REUSEPORT` turned on; use the builder directly for other options.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpListener;
+    /// use std::thread;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "0.0.0.0:8080".parse()?;
+    /// let workers: Vec<_> = TcpListener::bind_reuseport(&socket_addr, 4)?
+    ///     .into_iter()
+    ///     .map(|mut listener| {
+    ///         thread::spawn(move || {
+    ///             futures::executor::block_on(async {
+    ///                 while let Ok((_stream, addr)) = listener.accept().await {
+    ///                     println!("new client at {}", addr);
+    ///                 }
+    ///             })
+    ///         })
+    ///     })
+    ///     .collect();
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`TcpListener::builder`]: #method.builder
+    #[cfg(unix)]
+    pub fn bind_reuseport(addr: &SocketAddr, n: usize) -> io::Result<Vec<TcpListener>> {
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "at least one listener is needed",
+            ));
+        }
+
+        let builder = TcpListener::builder().reuse_port(true);
+        let first = builder.bind(addr)?;
+        let addr = first.local_addr()?;
+        let mut listeners = Vec::with_capacity(n);
+        listeners.push(first);
+        for _ in 1..n {
+            listeners.push(builder.bind(&addr)?);
+        }
+        Ok(listeners)
+    }
+
+    /// Converts this listener into a blocking `std::net::TcpListener`.
+    ///
+    /// The socket is deregistered from the reactor and put back into
+    /// blocking mode. Connections that are waiting in the backlog stay there,
+    /// and are accepted by the returned listener.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:0".parse()?;
+    /// let listener = TcpListener::bind(&socket_addr)?;
+    ///
+    /// let listener = listener.into_std()?;
+    /// let (stream, _) = listener.accept()?;
+    /// # Ok(())}
+    /// ```
+    #[cfg(unix)]
+    pub fn into_std(self) -> io::Result<std::net::TcpListener> {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+        let fd = self.io.into_inner()?.into_raw_fd();
+        let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        listener.set_nonblocking(false)?;
+        Ok(listener)
+    }
+
+    fn new((listener, socket): Listener) -> TcpListener {
         let io = PollEvented::new(listener);
-        TcpListener { io }
+        TcpListener {
+            io,
+            handle: None,
+            socket,
+        }
+    }
+
+    /// Wraps a newly accepted stream, binding it to the reactor of this
+    /// listener if that was chosen explicitly.
+    fn stream(&self, io: Tcp) -> io::Result<TcpStream> {
+        match &self.handle {
+            Some(handle) => TcpStream::new_on(io, handle),
+            None => Ok(TcpStream::new(io)),
+        }
     }
 
     /// Returns the local address that this listener is bound to.
@@ -128,7 +343,7 @@ impl TcpListener {
     /// ```rust,no_run
     /// #![feature(async_await)]
     /// use futures::prelude::*;
-    /// use romio::tcp::TcpListener;
+    /// use romio::tcp::{IncomingExt, TcpListener};
     ///
     /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
     /// let socket_addr = "127.0.0.1:80".parse()?;
@@ -150,6 +365,168 @@ impl TcpListener {
         Incoming { inner: self }
     }
 
+    /// Polls to accept a new connection, returning the socket and the address
+    /// of its peer.
+    ///
+    /// If no connection is waiting, `Poll::Pending` is returned and the
+    /// current task is woken once one arrives.
+    pub fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(TcpStream, SocketAddr)>> {
+        Pin::new(self).poll_ready(cx)
+    }
+
+    /// Accepts a new connection, resolving to the socket and the address of
+    /// its peer.
+    ///
+    /// Unlike [`incoming`], this only borrows the listener for as long as the
+    /// future is alive, so a server can accept in a loop and still reach the
+    /// listener between connections, e.g. to drain it on shutdown.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:8080".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    ///
+    /// loop {
+    ///     let (stream, addr) = listener.accept().await?;
+    ///     println!("new client at {}", addr);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`incoming`]: #method.incoming
+    pub fn accept(&mut self) -> Accept<'_> {
+        Accept { listener: self }
+    }
+
+    /// Accepts every connection currently waiting in the backlog, passing
+    /// each one to `handler`, and returns how many there were.
+    ///
+    /// This is meant for graceful shutdown. Connections still queued when a
+    /// listener is closed are reset by the kernel, which clients see as an
+    /// abrupt failure. Draining the backlog right before dropping the listener
+    /// gives the handler a chance to reject each of them cleanly instead, for
+    /// example by writing a "shutting down" response and closing the stream.
+    ///
+    /// This call doesn't wait: it stops as soon as no more connections are
+    /// queued. Connections arriving afterwards are not seen.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::tcp::TcpListener;
+    /// use std::net::Shutdown;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    ///
+    /// // ... serve until shutdown is requested ...
+    ///
+    /// let rejected = listener.drain_backlog(|stream| {
+    ///     drop(stream.shutdown(Shutdown::Both));
+    /// })?;
+    /// println!("rejected {} queued connections", rejected);
+    /// # Ok(())}
+    /// ```
+    pub fn drain_backlog<F>(&mut self, mut handler: F) -> io::Result<usize>
+    where
+        F: FnMut(TcpStream),
+    {
+        let mut drained = 0;
+        loop {
+            match self.io.get_ref().accept_std() {
+                Ok((io, _)) => {
+                    handler(self.stream(tcp_from_std(io)?)?);
+                    drained += 1;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(drained),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Polls to accept a batch of connections, pushing up to `max` of them
+    /// onto `out` and returning how many were accepted.
+    ///
+    /// This accepts as many connections as are queued, up to `max`, for a
+    /// single readiness notification, so a scheduler can hand out a burst of
+    /// connections at once. `Poll::Pending` is only returned if none were
+    /// queued; as soon as at least one was accepted the result is ready, even
+    /// if the backlog turned out to be empty afterwards.
+    ///
+    /// An error is only returned if it occurred before any connection was
+    /// accepted. Otherwise the batch ends there and the accepted connections
+    /// are returned; an error that persists, such as running out of file
+    /// descriptors, is reported by the next call.
+    pub fn poll_accept_multi(
+        &mut self,
+        cx: &mut Context<'_>,
+        out: &mut Vec<(TcpStream, SocketAddr)>,
+        max: usize,
+    ) -> Poll<io::Result<usize>> {
+        let mut accepted = 0;
+        while accepted < max {
+            match Pin::new(&mut *self).poll_ready(cx) {
+                Poll::Ready(Ok(pair)) => {
+                    out.push(pair);
+                    accepted += 1;
+                }
+                Poll::Ready(Err(e)) if accepted == 0 => return Poll::Ready(Err(e)),
+                Poll::Pending if accepted == 0 => return Poll::Pending,
+                Poll::Ready(Err(_)) | Poll::Pending => break,
+            }
+        }
+        Poll::Ready(Ok(accepted))
+    }
+
+    /// Accepts a batch of connections, pushing up to `max` of them onto `out`
+    /// and resolving to how many were accepted.
+    ///
+    /// The future waits for at least one connection, then takes every other
+    /// one that is already queued, up to `max`. See [`poll_accept_multi`] for
+    /// details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:8080".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let mut batch = Vec::new();
+    ///
+    /// loop {
+    ///     listener.accept_many(&mut batch, 64).await?;
+    ///     for (stream, addr) in batch.drain(..) {
+    ///         println!("new client at {}", addr);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`poll_accept_multi`]: #method.poll_accept_multi
+    pub fn accept_many<'a>(
+        &'a mut self,
+        out: &'a mut Vec<(TcpStream, SocketAddr)>,
+        max: usize,
+    ) -> AcceptMany<'a> {
+        AcceptMany {
+            listener: self,
+            out,
+            max,
+        }
+    }
+
     /// Gets the value of the `IP_TTL` option for this socket.
     ///
     /// For more information about this option, see [`set_ttl`].
@@ -161,7 +538,7 @@ impl TcpListener {
     /// ```rust
     /// #![feature(async_await)]
     /// use futures::prelude::*;
-    /// use romio::tcp::TcpListener;
+    /// use romio::tcp::{IncomingExt, TcpListener};
     ///
     /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
     /// let socket_addr = "127.0.0.1:0".parse()?;
@@ -184,7 +561,7 @@ impl TcpListener {
     /// ```rust,no_run
     /// #![feature(async_await)]
     /// use futures::prelude::*;
-    /// use romio::tcp::TcpListener;
+    /// use romio::tcp::{IncomingExt, TcpListener};
     ///
     /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
     /// let socket_addr = "127.0.0.1:0".parse()?;
@@ -196,6 +573,21 @@ impl TcpListener {
         self.io.get_ref().set_ttl(ttl)
     }
 
+    /// Returns whether this listener is a Multipath TCP socket.
+    ///
+    /// This is false for a listener that asked for MPTCP with
+    /// [`TcpListenerBuilder::multipath`] but fell back to plain TCP.
+    ///
+    /// This method is only available on Linux, with the `mptcp` feature.
+    ///
+    /// [`TcpListenerBuilder::multipath`]: struct.TcpListenerBuilder.html#method.multipath
+    #[cfg(all(feature = "mptcp", target_os = "linux"))]
+    pub fn is_multipath(&self) -> io::Result<bool> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::is_mptcp(self.as_raw_fd())
+    }
+
     fn poll_accept_std(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
@@ -218,10 +610,12 @@ impl AsyncReady for TcpListener {
     type Err = std::io::Error;
 
     /// Check if the stream can be read from.
-    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Self::Ok, Self::Err>> {
-        let (io, addr) = ready!(self.poll_accept_std(cx)?);
-        let io = mio::net::TcpStream::from_stream(io)?;
-        let io = TcpStream::new(io);
+    fn poll_ready(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Self::Ok, Self::Err>> {
+        let (io, addr) = ready!(self.as_mut().poll_accept_std(cx)?);
+        let io = self.stream(tcp_from_std(io)?)?;
         Poll::Ready(Ok((io, addr)))
     }
 }
@@ -235,6 +629,9 @@ impl fmt::Debug for TcpListener {
 #[cfg(unix)]
 mod sys {
     use super::TcpListener;
+    use crate::socket::SavedSocket;
+    use std::convert::TryFrom;
+    use std::io;
     use std::os::unix::prelude::*;
 
     impl AsRawFd for TcpListener {
@@ -242,13 +639,298 @@ mod sys {
             self.io.get_ref().as_raw_fd()
         }
     }
+
+    /// Hands the socket over still in non-blocking mode, as
+    /// [`PollEvented::into_inner_lossy`] describes.
+    ///
+    /// [`PollEvented::into_inner_lossy`]: ../raw/struct.PollEvented.html#method.into_inner_lossy
+    impl IntoRawFd for TcpListener {
+        fn into_raw_fd(self) -> RawFd {
+            self.io.into_inner_lossy().into_raw_fd()
+        }
+    }
+
+    /// Takes ownership of a listening socket, and puts it in non-blocking
+    /// mode.
+    impl FromRawFd for TcpListener {
+        unsafe fn from_raw_fd(fd: RawFd) -> TcpListener {
+            // This only fails for descriptors that aren't open, which fail
+            // every later operation as well.
+            let _ = crate::sys::set_nonblocking(fd);
+            let listener = mio::net::TcpListener::from_raw_fd(fd);
+            let socket = SavedSocket::of(&listener);
+            TcpListener::new((listener, socket))
+        }
+    }
+
+    /// Converts the listener with [`TcpListener::into_std`].
+    ///
+    /// [`TcpListener::into_std`]: struct.TcpListener.html#method.into_std
+    impl TryFrom<TcpListener> for std::net::TcpListener {
+        type Error = io::Error;
+
+        fn try_from(listener: TcpListener) -> io::Result<std::net::TcpListener> {
+            listener.into_std()
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::TcpListener;
+    use std::os::windows::prelude::*;
+
+    impl AsRawSocket for TcpListener {
+        fn as_raw_socket(&self) -> RawSocket {
+            self.socket.get()
+        }
+    }
 }
 
 impl TryFrom<std::net::TcpListener> for TcpListener {
     type Error = io::Error;
 
     fn try_from(socket: std::net::TcpListener) -> Result<Self, Self::Error> {
-        mio::net::TcpListener::from_std(socket).map(TcpListener::new)
+        listener_from_std(socket).map(TcpListener::new)
+    }
+}
+
+/// Creates a listening socket bound to `addr`.
+fn bind_listener(addr: &SocketAddr) -> io::Result<Listener> {
+    #[cfg(not(windows))]
+    {
+        let listener = mio::net::TcpListener::bind(addr)?;
+        let socket = SavedSocket::of(&listener);
+        Ok((listener, socket))
+    }
+    #[cfg(windows)]
+    crate::socket::bind(addr)
+}
+
+fn listener_from_std(listener: std::net::TcpListener) -> io::Result<Listener> {
+    let socket = SavedSocket::of(&listener);
+    Ok((mio::net::TcpListener::from_std(listener)?, socket))
+}
+
+/// A builder for a `TcpListener` with socket options that must be set before
+/// the socket is bound or starts listening, returned by
+/// `TcpListener::builder`.
+///
+/// Options that aren't set keep the platform default, except for
+/// `SO_REUSEADDR`, which is on like for [`TcpListener::bind`], and the
+/// backlog, which also matches `bind`.
+///
+/// [`TcpListener::bind`]: struct.TcpListener.html#method.bind
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct TcpListenerBuilder {
+    backlog: u32,
+    reuse_address: bool,
+    reuse_port: bool,
+    only_v6: Option<bool>,
+    ttl: Option<u32>,
+    recv_buffer_size: Option<usize>,
+    #[cfg(target_os = "linux")]
+    fastopen: Option<u32>,
+    #[cfg(all(feature = "mptcp", target_os = "linux"))]
+    multipath: bool,
+}
+
+#[cfg(unix)]
+impl TcpListenerBuilder {
+    fn new() -> TcpListenerBuilder {
+        TcpListenerBuilder {
+            backlog: DEFAULT_BACKLOG,
+            reuse_address: true,
+            reuse_port: false,
+            only_v6: None,
+            ttl: None,
+            recv_buffer_size: None,
+            #[cfg(target_os = "linux")]
+            fastopen: None,
+            #[cfg(all(feature = "mptcp", target_os = "linux"))]
+            multipath: false,
+        }
+    }
+
+    /// Sets how many connections the kernel queues before they're accepted.
+    ///
+    /// The kernel may cap this, e.g. at `net.core.somaxconn` on Linux.
+    pub fn backlog(mut self, backlog: u32) -> TcpListenerBuilder {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Sets `SO_REUSEADDR`, which lets the listener bind while connections
+    /// of a previous one linger in `TIME_WAIT`.
+    pub fn reuse_address(mut self, reuse: bool) -> TcpListenerBuilder {
+        self.reuse_address = reuse;
+        self
+    }
+
+    /// Sets `SO_REUSEPORT`, which lets several listeners bind the same
+    /// address and port.
+    ///
+    /// On Linux, the kernel then load-balances incoming connections across
+    /// all of them, so each worker thread or process can accept from a
+    /// listener of its own.
+    pub fn reuse_port(mut self, reuse: bool) -> TcpListenerBuilder {
+        self.reuse_port = reuse;
+        self
+    }
+
+    /// Sets `IPV6_V6ONLY`, which restricts a listener bound to an IPv6
+    /// address to IPv6 connections only.
+    ///
+    /// Binding an IPv4 address with this option set fails with
+    /// `InvalidInput`.
+    pub fn only_v6(mut self, only_v6: bool) -> TcpListenerBuilder {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Sets `IP_TTL`, as [`TcpListener::set_ttl`] does.
+    ///
+    /// [`TcpListener::set_ttl`]: struct.TcpListener.html#method.set_ttl
+    pub fn ttl(mut self, ttl: u32) -> TcpListenerBuilder {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets `SO_RCVBUF`, the size of the receive buffer.
+    ///
+    /// Accepted connections inherit it. Unlike setting it on each of them,
+    /// setting it before listening also lets the kernel pick a TCP window
+    /// scale that suits it during the handshake.
+    pub fn recv_buffer_size(mut self, size: usize) -> TcpListenerBuilder {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets `TCP_FASTOPEN`, which lets clients send data along with their
+    /// SYN, such as with [`TcpStream::connect_with_data`].
+    ///
+    /// `queue_len` caps how many such connections may be waiting for the
+    /// handshake to complete; past that, clients fall back to a regular
+    /// handshake. The kernel only takes Fast Open connections if the server
+    /// bit of the `net.ipv4.tcp_fastopen` sysctl is set.
+    ///
+    /// This option is only available on Linux.
+    ///
+    /// [`TcpStream::connect_with_data`]: struct.TcpStream.html#method.connect_with_data
+    #[cfg(target_os = "linux")]
+    pub fn fastopen(mut self, queue_len: u32) -> TcpListenerBuilder {
+        self.fastopen = Some(queue_len);
+        self
+    }
+
+    /// Makes the listener a Multipath TCP socket, which accepts connections
+    /// that spread over several network paths, as well as plain TCP ones.
+    ///
+    /// Kernels without MPTCP support, before Linux 5.6 or with the
+    /// `net.mptcp.enabled` sysctl unset, get a plain TCP listener instead.
+    /// [`TcpListener::is_multipath`] tells which one it is.
+    ///
+    /// This option is only available on Linux, with the `mptcp` feature.
+    ///
+    /// [`TcpListener::is_multipath`]: struct.TcpListener.html#method.is_multipath
+    #[cfg(all(feature = "mptcp", target_os = "linux"))]
+    pub fn multipath(mut self, multipath: bool) -> TcpListenerBuilder {
+        self.multipath = multipath;
+        self
+    }
+
+    /// Creates the socket, applies the options, binds it to `addr` and
+    /// starts listening.
+    pub fn bind(&self, addr: &SocketAddr) -> io::Result<TcpListener> {
+        use crate::sys;
+        use std::os::unix::io::AsRawFd;
+
+        let family = match addr {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+        if self.only_v6.is_some() && addr.is_ipv4() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "IPV6_V6ONLY only applies to IPv6 addresses",
+            ));
+        }
+
+        #[cfg(all(feature = "mptcp", target_os = "linux"))]
+        let listener: net::TcpListener = if self.multipath {
+            sys::mptcp_socket(family)?
+        } else {
+            sys::socket_owned(family, libc::SOCK_STREAM, 0)?
+        };
+        #[cfg(not(all(feature = "mptcp", target_os = "linux")))]
+        let listener: net::TcpListener = sys::socket_owned(family, libc::SOCK_STREAM, 0)?;
+        let fd = listener.as_raw_fd();
+
+        let on = |on: bool| on as libc::c_int;
+        sys::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_REUSEADDR,
+            on(self.reuse_address),
+        )?;
+        if self.reuse_port {
+            sys::setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEPORT, on(true))?;
+        }
+        if let Some(only_v6) = self.only_v6 {
+            sys::setsockopt(fd, libc::IPPROTO_IPV6, libc::IPV6_V6ONLY, on(only_v6))?;
+        }
+        if let Some(ttl) = self.ttl {
+            sys::setsockopt(fd, libc::IPPROTO_IP, libc::IP_TTL, ttl as libc::c_int)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            let size = std::cmp::min(size, libc::c_int::MAX as usize) as libc::c_int;
+            sys::setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF, size)?;
+        }
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(queue_len) = self.fastopen {
+                let queue_len = std::cmp::min(queue_len, libc::c_int::MAX as u32) as libc::c_int;
+                sys::setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_FASTOPEN, queue_len)?;
+            }
+        }
+
+        sys::bind(fd, addr)?;
+        sys::listen(fd, self.backlog)?;
+        listener_from_std(listener).map(TcpListener::new)
+    }
+}
+
+/// The future returned by `TcpListener::accept`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Accept<'a> {
+    listener: &'a mut TcpListener,
+}
+
+impl<'a> Future for Accept<'a> {
+    type Output = io::Result<(TcpStream, SocketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.listener.poll_accept(cx)
+    }
+}
+
+/// The future returned by `TcpListener::accept_many`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct AcceptMany<'a> {
+    listener: &'a mut TcpListener,
+    out: &'a mut Vec<(TcpStream, SocketAddr)>,
+    max: usize,
+}
+
+impl<'a> Future for AcceptMany<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let AcceptMany { listener, out, max } = &mut *self;
+        listener.poll_accept_multi(cx, out, *max)
     }
 }
 
@@ -268,3 +950,582 @@ impl<'a> Stream for Incoming<'a> {
         Poll::Ready(Some(Ok(socket)))
     }
 }
+
+/// Adapters for streams of accepted connections, such as [`Incoming`].
+///
+/// Each adapter is itself a stream of connections, except for
+/// [`limit_connections`], so they can be chained, e.g. to limit how many
+/// connections are open while also waiting out running out of file
+/// descriptors:
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::tcp::{IncomingExt, TcpListener};
+///
+/// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let socket_addr = "127.0.0.1:80".parse()?;
+/// let mut listener = TcpListener::bind(&socket_addr)?;
+/// let mut incoming = listener
+///     .incoming()
+///     .retry_transient()
+///     .limit_connections(1000);
+///
+/// while let Some(res) = incoming.next().await {
+///     let (stream, permit) = res?;
+/// }
+/// # Ok(())}
+/// ```
+///
+/// [`Incoming`]: struct.Incoming.html
+/// [`limit_connections`]: #method.limit_connections
+pub trait IncomingExt: Stream<Item = io::Result<TcpStream>> + Sized {
+    /// Limits the rate at which connections are accepted to `rate` per second.
+    ///
+    /// Connections are spaced evenly, at most one every `1 / rate` seconds.
+    /// While the limit is exceeded the stream stops accepting, so pending
+    /// connections wait in the kernel's backlog rather than being accepted
+    /// and queued in user space. Once the backlog is full the kernel refuses
+    /// or drops further connection attempts, which pushes back on a flood
+    /// before it reaches the rest of the application.
+    ///
+    /// This bounds how quickly new connections arrive, not how many are open
+    /// at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::{IncomingExt, TcpListener};
+    ///
+    /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let mut incoming = listener.incoming().throttle(100);
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     println!("new client!");
+    /// }
+    /// # Ok(())}
+    /// ```
+    fn throttle(self, rate: u32) -> Throttle<Self> {
+        assert!(rate > 0, "accept rate must be non-zero");
+        Throttle {
+            incoming: self,
+            period: Duration::from_secs(1) / rate,
+            next: Instant::now(),
+            delay: None,
+        }
+    }
+
+    /// Makes this stream pausable.
+    ///
+    /// While paused, the returned stream stops accepting connections without
+    /// closing the listener: the port stays bound and the listener stays
+    /// registered with the reactor, so new clients complete the handshake and
+    /// wait in the kernel's backlog instead of being refused. Once resumed,
+    /// the queued connections are accepted in order.
+    ///
+    /// Pausing and resuming is done through a [`PauseHandle`], which can be
+    /// moved to whichever task decides when to shed load.
+    ///
+    /// [`PauseHandle`]: struct.PauseHandle.html
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::{IncomingExt, TcpListener};
+    ///
+    /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let mut incoming = listener.incoming().pausable();
+    /// let handle = incoming.handle();
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     // stop accepting until someone calls `handle.resume()`
+    ///     handle.pause();
+    /// }
+    /// # Ok(())}
+    /// ```
+    fn pausable(self) -> Pausable<Self> {
+        Pausable {
+            incoming: self,
+            handle: PauseHandle {
+                inner: Arc::new(PauseState {
+                    paused: AtomicBool::new(false),
+                    waker: AtomicWaker::new(),
+                }),
+            },
+        }
+    }
+
+    /// Stops the stream once `shutdown` completes.
+    ///
+    /// The returned stream ends, yielding `None`, as soon as `shutdown`
+    /// resolves, even while it is waiting for a connection. This lets an
+    /// accept loop be ended from another task, e.g. by sending on a
+    /// `oneshot` channel, after which the loop can return and the listener
+    /// be dropped, closing its socket. Connections that were already
+    /// accepted are unaffected, and can keep being served until they are
+    /// done.
+    ///
+    /// Any value `shutdown` resolves to is ignored, so a `oneshot::Receiver`
+    /// whose sender was dropped shuts the stream down too. Futures that
+    /// aren't `Unpin` can be passed in through `Box::pin`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::channel::oneshot;
+    /// use futures::prelude::*;
+    /// use romio::tcp::{IncomingExt, TcpListener};
+    ///
+    /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let (stop, stopped) = oneshot::channel::<()>();
+    /// // hand `stop` to whichever task decides when to shut down
+    /// # drop(stop);
+    ///
+    /// let mut incoming = listener.incoming().until(stopped);
+    /// while let Some(stream) = incoming.next().await {
+    ///     println!("new client!");
+    /// }
+    ///
+    /// // no longer accepting, so the socket can be closed
+    /// drop(listener);
+    /// # Ok(())}
+    /// ```
+    fn until<F>(self, shutdown: F) -> Until<Self, F>
+    where
+        F: Future + Unpin,
+    {
+        Until {
+            incoming: self,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// Skips accept errors that don't affect the listener, instead of
+    /// yielding them.
+    ///
+    /// Accepting fails when a client gives up while its connection waits in
+    /// the backlog, which is skipped right away, and when the process or
+    /// the system runs out of file descriptors or memory (`EMFILE`,
+    /// `ENFILE`, `ENOBUFS`, `ENOMEM`). The listener stays readable then, so
+    /// retrying at once would spin; instead the stream waits a second, for
+    /// connections to be closed, before accepting again. Other errors are
+    /// still yielded.
+    ///
+    /// Skipped errors are logged at the debug level. For another choice of
+    /// errors or delays, use [`retry_errors`].
+    ///
+    /// [`retry_errors`]: #method.retry_errors
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::{IncomingExt, TcpListener};
+    ///
+    /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let mut incoming = listener.incoming().retry_transient();
+    ///
+    /// // only ends on an error that won't go away
+    /// while let Some(stream) = incoming.next().await {
+    ///     let stream = stream?;
+    /// }
+    /// # Ok(())}
+    /// ```
+    fn retry_transient(self) -> RetryErrors<Self, fn(&io::Error) -> Option<Duration>> {
+        self.retry_errors(transient_delay)
+    }
+
+    /// Skips the accept errors for which `policy` returns a delay, waiting
+    /// that long before accepting again.
+    ///
+    /// Errors for which `policy` returns `None` are yielded. A zero delay
+    /// retries right away, which only suits errors that are consumed by
+    /// failing, such as an aborted connection.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::{IncomingExt, TcpListener};
+    /// use std::time::Duration;
+    ///
+    /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let mut incoming = listener.incoming().retry_errors(|e| {
+    ///     eprintln!("accept failed: {}", e);
+    ///     Some(Duration::from_millis(100))
+    /// });
+    /// # Ok(())}
+    /// ```
+    fn retry_errors<F>(self, policy: F) -> RetryErrors<Self, F>
+    where
+        F: FnMut(&io::Error) -> Option<Duration>,
+    {
+        RetryErrors {
+            incoming: self,
+            policy,
+            delay: None,
+        }
+    }
+
+    /// Stops accepting while `max` accepted connections are open.
+    ///
+    /// Each connection is yielded with a [`ConnectionPermit`], which counts
+    /// it as open until it is dropped; keep it next to the stream, e.g. move
+    /// both into the task serving the connection. While `max` permits are
+    /// held, new clients wait in the kernel's backlog, as with
+    /// [`pausable`], and are accepted as soon as a permit is dropped.
+    ///
+    /// Unlike [`throttle`], this bounds how many connections are open at
+    /// once, not how quickly they arrive.
+    ///
+    /// [`ConnectionPermit`]: struct.ConnectionPermit.html
+    /// [`pausable`]: #method.pausable
+    /// [`throttle`]: #method.throttle
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::{IncomingExt, TcpListener};
+    ///
+    /// # async fn work () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket_addr = "127.0.0.1:80".parse()?;
+    /// let mut listener = TcpListener::bind(&socket_addr)?;
+    /// let mut incoming = listener.incoming().limit_connections(1000);
+    ///
+    /// while let Some(res) = incoming.next().await {
+    ///     let (stream, permit) = res?;
+    ///     // serve `stream` on another task, and drop `permit` with it
+    /// }
+    /// # Ok(())}
+    /// ```
+    fn limit_connections(self, max: usize) -> LimitConnections<Self> {
+        assert!(max > 0, "connection limit must be non-zero");
+        LimitConnections {
+            incoming: self,
+            state: Arc::new(LimitState {
+                open: AtomicUsize::new(0),
+                max,
+                waker: AtomicWaker::new(),
+            }),
+        }
+    }
+}
+
+impl<S> IncomingExt for S where S: Stream<Item = io::Result<TcpStream>> {}
+
+/// How long `IncomingExt::retry_transient` waits after `err` before accepting
+/// again, or `None` if the error is yielded.
+fn transient_delay(err: &io::Error) -> Option<Duration> {
+    match err.kind() {
+        io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::Interrupted => return Some(Duration::from_secs(0)),
+        _ => {}
+    }
+    #[cfg(unix)]
+    let (aborted, exhausted) = (
+        [libc::EPROTO],
+        [libc::EMFILE, libc::ENFILE, libc::ENOBUFS, libc::ENOMEM],
+    );
+    // `WSAEMFILE` and `WSAENOBUFS`.
+    #[cfg(windows)]
+    let (aborted, exhausted) = ([], [10024, 10055]);
+    match err.raw_os_error() {
+        Some(code) if aborted.contains(&code) => Some(Duration::from_secs(0)),
+        Some(code) if exhausted.contains(&code) => Some(Duration::from_secs(1)),
+        _ => None,
+    }
+}
+
+/// Stream returned by `IncomingExt::retry_errors` and
+/// `IncomingExt::retry_transient`, which skips the accept errors its policy
+/// retries.
+#[must_use = "streams do nothing unless polled"]
+pub struct RetryErrors<S, F> {
+    incoming: S,
+    policy: F,
+    /// Set while waiting to accept again after an error.
+    delay: Option<Delay>,
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for RetryErrors<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryErrors")
+            .field("incoming", &self.incoming)
+            .field("waiting", &self.delay.is_some())
+            .finish()
+    }
+}
+
+impl<S, F> Stream for RetryErrors<S, F>
+where
+    S: Stream<Item = io::Result<TcpStream>> + Unpin,
+    F: FnMut(&io::Error) -> Option<Duration> + Unpin,
+{
+    type Item = io::Result<TcpStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(delay) = &mut this.delay {
+                ready!(delay.poll_elapsed(cx)?);
+                this.delay = None;
+            }
+
+            let err = match ready!(Pin::new(&mut this.incoming).poll_next(cx)) {
+                Some(Err(err)) => err,
+                item => return Poll::Ready(item),
+            };
+            match (this.policy)(&err) {
+                Some(delay) => {
+                    debug!("retrying accept in {:?} after error: {}", delay, err);
+                    if delay > Duration::from_secs(0) {
+                        this.delay = Some(Delay::new(Instant::now() + delay));
+                    }
+                }
+                None => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
+
+/// Stream returned by `IncomingExt::limit_connections`, which stops accepting
+/// while too many connections are open.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct LimitConnections<S> {
+    incoming: S,
+    state: Arc<LimitState>,
+}
+
+/// Counts an accepted connection as open until it is dropped, returned with
+/// each connection by `LimitConnections`.
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    state: Arc<LimitState>,
+}
+
+#[derive(Debug)]
+struct LimitState {
+    open: AtomicUsize,
+    max: usize,
+    /// The task waiting for a permit to be dropped.
+    waker: AtomicWaker,
+}
+
+impl<S> LimitConnections<S> {
+    /// Returns how many connections are open, that is how many permits are
+    /// held.
+    pub fn open_connections(&self) -> usize {
+        self.state.open.load(Ordering::SeqCst)
+    }
+}
+
+impl<S> Stream for LimitConnections<S>
+where
+    S: Stream<Item = io::Result<TcpStream>> + Unpin,
+{
+    type Item = io::Result<(TcpStream, ConnectionPermit)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let state = &self.state;
+        if state.open.load(Ordering::SeqCst) >= state.max {
+            state.waker.register(cx.waker());
+            // Check again in case a permit was dropped before the waker was
+            // stored.
+            if state.open.load(Ordering::SeqCst) >= state.max {
+                return Poll::Pending;
+            }
+        }
+
+        let stream = match ready!(Pin::new(&mut self.incoming).poll_next(cx)) {
+            Some(Ok(stream)) => stream,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+        self.state.open.fetch_add(1, Ordering::SeqCst);
+        let permit = ConnectionPermit {
+            state: self.state.clone(),
+        };
+        Poll::Ready(Some(Ok((stream, permit))))
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.state.open.fetch_sub(1, Ordering::SeqCst);
+        self.state.waker.wake();
+    }
+}
+
+/// Stream returned by `IncomingExt::until`, which ends once a shutdown future
+/// completes.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Until<S, F> {
+    incoming: S,
+    /// The shutdown future, or `None` once it has completed.
+    shutdown: Option<F>,
+}
+
+impl<S, F> Until<S, F> {
+    /// Returns whether the shutdown future has completed, ending the stream.
+    pub fn is_shut_down(&self) -> bool {
+        self.shutdown.is_none()
+    }
+}
+
+impl<S, F> Stream for Until<S, F>
+where
+    S: Stream<Item = io::Result<TcpStream>> + Unpin,
+    F: Future + Unpin,
+{
+    type Item = io::Result<TcpStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        let shutdown = match &mut this.shutdown {
+            Some(shutdown) => shutdown,
+            None => return Poll::Ready(None),
+        };
+        if Pin::new(shutdown).poll(cx).is_ready() {
+            this.shutdown = None;
+            return Poll::Ready(None);
+        }
+
+        Pin::new(&mut this.incoming).poll_next(cx)
+    }
+}
+
+/// Stream returned by `IncomingExt::pausable`, which can stop and restart
+/// accepting connections.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Pausable<S> {
+    incoming: S,
+    handle: PauseHandle,
+}
+
+/// A handle to pause and resume a `Pausable` stream from anywhere.
+#[derive(Clone, Debug)]
+pub struct PauseHandle {
+    inner: Arc<PauseState>,
+}
+
+#[derive(Debug)]
+struct PauseState {
+    paused: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl<S> Pausable<S> {
+    /// Returns a handle that pauses and resumes this stream.
+    pub fn handle(&self) -> PauseHandle {
+        self.handle.clone()
+    }
+}
+
+impl PauseHandle {
+    /// Stops accepting connections, leaving new ones in the backlog.
+    ///
+    /// A connection that was already accepted when this is called is still
+    /// yielded.
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resumes accepting connections.
+    pub fn resume(&self) {
+        self.inner.paused.store(false, Ordering::SeqCst);
+        self.inner.waker.wake();
+    }
+
+    /// Returns whether the stream is currently paused.
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
+    }
+}
+
+impl<S> Stream for Pausable<S>
+where
+    S: Stream<Item = io::Result<TcpStream>> + Unpin,
+{
+    type Item = io::Result<TcpStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let state = &self.handle.inner;
+        if state.paused.load(Ordering::SeqCst) {
+            state.waker.register(cx.waker());
+            // Check again in case `resume` ran before the waker was stored.
+            if state.paused.load(Ordering::SeqCst) {
+                return Poll::Pending;
+            }
+        }
+
+        Pin::new(&mut self.incoming).poll_next(cx)
+    }
+}
+
+/// Stream returned by `IncomingExt::throttle`, which accepts connections no
+/// faster than a fixed rate.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Throttle<S> {
+    incoming: S,
+    /// Minimum time between two accepted connections.
+    period: Duration,
+    /// Earliest instant at which the next connection may be accepted.
+    next: Instant,
+    delay: Option<Delay>,
+}
+
+impl<S> Stream for Throttle<S>
+where
+    S: Stream<Item = io::Result<TcpStream>> + Unpin,
+{
+    type Item = io::Result<TcpStream>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if Instant::now() < this.next {
+            let next = this.next;
+            let delay = this.delay.get_or_insert_with(|| Delay::new(next));
+            ready!(delay.poll_elapsed(cx)?);
+        }
+        this.delay = None;
+
+        let item = ready!(Pin::new(&mut this.incoming).poll_next(cx));
+        this.next = Instant::now() + this.period;
+        Poll::Ready(item)
+    }
+}
@@ -43,5 +43,17 @@
 mod listener;
 mod stream;
 
-pub use self::listener::{Incoming, TcpListener};
-pub use self::stream::{ConnectFuture, TcpStream};
+#[cfg(unix)]
+pub use self::listener::TcpListenerBuilder;
+pub use self::listener::{
+    Accept, AcceptMany, ConnectionPermit, Incoming, IncomingExt, LimitConnections, Pausable,
+    PauseHandle, RetryErrors, TcpListener, Throttle, Until,
+};
+#[cfg(unix)]
+pub use self::stream::TcpStreamBuilder;
+pub use self::stream::{
+    ConnectFuture, ConnectHappy, ConnectHost, ConnectTimeout, Peek, ReadHeader, ReadPooled,
+    ReadWithTimeout, Readable, TcpStream, Writable, WriteWithTimeout,
+};
+#[cfg(target_os = "linux")]
+pub use self::stream::{ConnectWithData, RecvOob, SendFile, SendOob, SpliceFrom, SpliceTo};
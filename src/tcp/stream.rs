@@ -1,17 +1,32 @@
 use std::fmt;
 use std::io;
+#[cfg(unix)]
+use std::io::{IoSlice, IoSliceMut};
 use std::mem;
-use std::net::{Shutdown, SocketAddr};
+use std::net::{Shutdown, SocketAddr, ToSocketAddrs};
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::vec;
 
 use async_ready::{AsyncReadReady, AsyncWriteReady};
+use futures::channel::oneshot;
 use futures::io::{AsyncRead, AsyncWrite};
-use futures::Future;
+use futures::{ready, Future};
 use mio;
 
-use crate::raw::PollEvented;
+use crate::dns::system::resolve;
+use crate::pool::{BufferPool, PooledBuf};
+use crate::raw::{Handle, PollEvented};
+use crate::reactor::timer::Delay;
+use crate::socket::SavedSocket;
+
+#[cfg(target_os = "linux")]
+use crate::raw::{AsyncFd, PipeReader, PipeWriter};
+#[cfg(target_os = "linux")]
+use std::fs::File;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::RawFd;
 
 /// A TCP stream between a local and a remote socket.
 ///
@@ -24,11 +39,52 @@ use crate::raw::PollEvented;
 /// portions of the connection can also be shut down individually with the [`shutdown`]
 /// method.
 ///
+/// Every `poll_write` reports exactly how many bytes the kernel accepted, and
+/// a write that returns `Poll::Pending` has written nothing. A `write_all`
+/// dropped halfway, e.g. in a `select`, may thus have sent part of its
+/// buffer; use [`romio::io::write_all_from`] to keep track of how much.
+///
 /// [`connect`]: struct.TcpStream.html#method.connect
+/// [`romio::io::write_all_from`]: ../io/fn.write_all_from.html
 /// [accepting]: struct.TcpListener.html#method.accept
 /// [listener]: struct.TcpListener.html
 pub struct TcpStream {
     io: PollEvented<mio::net::TcpStream>,
+    stall: WriteStall,
+    connect: ConnectState,
+    /// Only read on Windows, for `AsRawSocket`.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    socket: SavedSocket,
+}
+
+/// Where a stream is in establishing its connection.
+#[derive(Debug)]
+enum ConnectState {
+    /// A non-blocking connect was started and hasn't completed yet.
+    Connecting,
+    /// The connection is established.
+    Connected,
+    /// The connection attempt failed.
+    Failed(io::Error),
+}
+
+/// Counts `SO_ERROR` lookups made while completing connects.
+#[cfg(test)]
+static SO_ERROR_CHECKS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Tracks how long the current write has been waiting on write readiness.
+///
+/// The clock is only read when a write transitions between blocked and
+/// unblocked, so the bookkeeping stays off the fast path.
+#[derive(Default)]
+struct WriteStall {
+    since: Option<Instant>,
+    hook: Option<StallHook>,
+}
+
+struct StallHook {
+    threshold: Duration,
+    callback: Box<dyn FnMut(Duration) + Send + Sync>,
 }
 
 /// The future returned by `TcpStream::connect`, which will resolve to a `TcpStream`
@@ -47,6 +103,175 @@ enum ConnectFutureState {
     Empty,
 }
 
+/// A builder for a `TcpStream` whose local end is set up before it
+/// connects.
+///
+/// Returned by [`TcpStream::builder`].
+///
+/// [`TcpStream::builder`]: struct.TcpStream.html#method.builder
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct TcpStreamBuilder {
+    local_addr: Option<SocketAddr>,
+    #[cfg(target_os = "linux")]
+    device: Option<String>,
+    #[cfg(all(feature = "mptcp", target_os = "linux"))]
+    multipath: bool,
+}
+
+/// The future returned by `TcpStream::connect_timeout`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectTimeout {
+    connect: ConnectFuture,
+    deadline: Delay,
+}
+
+/// The future returned by `TcpStream::connect_host`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectHost {
+    state: ConnectHostState,
+    /// The resolved addresses that haven't been tried yet.
+    addrs: vec::IntoIter<SocketAddr>,
+    /// The error of the last address tried.
+    last_error: Option<io::Error>,
+}
+
+#[derive(Debug)]
+enum ConnectHostState {
+    Resolving(oneshot::Receiver<io::Result<Vec<SocketAddr>>>),
+    Connecting(ConnectFuture),
+    /// About to try the next address.
+    Next,
+}
+
+/// The future returned by `TcpStream::connect_happy`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectHappy {
+    resolving: Option<oneshot::Receiver<io::Result<Vec<SocketAddr>>>>,
+    /// The resolved addresses that haven't been tried yet, in the order
+    /// they are tried.
+    addrs: vec::IntoIter<SocketAddr>,
+    attempts: Vec<ConnectFuture>,
+    /// When to start the next attempt, if none of the running ones has
+    /// finished by then.
+    next_attempt: Delay,
+    /// The error of the last attempt that failed.
+    last_error: Option<io::Error>,
+}
+
+/// How long an attempt gets before the next one is started alongside it,
+/// the "Connection Attempt Delay" of RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// The future returned by `TcpStream::peek`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Peek<'a> {
+    stream: &'a mut TcpStream,
+    buf: &'a mut [u8],
+}
+
+/// The future returned by `TcpStream::read_with_timeout`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ReadWithTimeout<'a> {
+    stream: &'a mut TcpStream,
+    buf: &'a mut [u8],
+    delay: Delay,
+}
+
+/// The future returned by `TcpStream::write_with_timeout`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct WriteWithTimeout<'a> {
+    stream: &'a mut TcpStream,
+    buf: &'a [u8],
+    delay: Delay,
+}
+
+/// The future returned by `TcpStream::read_pooled`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ReadPooled<'a> {
+    stream: &'a mut TcpStream,
+    pool: &'a BufferPool,
+    buf: Option<PooledBuf>,
+}
+
+/// The future returned by `TcpStream::read_header_within`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ReadHeader<'a> {
+    stream: &'a mut TcpStream,
+    buf: &'a mut [u8],
+    delimiter: &'a [u8],
+    filled: usize,
+    delay: Delay,
+}
+
+/// The future returned by `TcpStream::connect_with_data`.
+#[cfg(target_os = "linux")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectWithData<'a> {
+    inner: ConnectFutureState,
+    data: &'a [u8],
+    /// How much of `data` was sent so far, some of it with the SYN.
+    sent: usize,
+}
+
+/// The future returned by `TcpStream::send_oob`.
+#[cfg(target_os = "linux")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SendOob<'a> {
+    stream: &'a mut TcpStream,
+    byte: u8,
+}
+
+/// The future returned by `TcpStream::recv_oob`.
+#[cfg(target_os = "linux")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct RecvOob<'a> {
+    stream: &'a mut TcpStream,
+}
+
+/// The future returned by `TcpStream::send_file`.
+#[cfg(target_os = "linux")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SendFile<'a> {
+    stream: &'a mut TcpStream,
+    file: &'a File,
+    offset: u64,
+    len: usize,
+    sent: usize,
+}
+
+/// The future returned by `TcpStream::splice_to`.
+#[cfg(target_os = "linux")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SpliceTo<'a> {
+    stream: &'a mut TcpStream,
+    pipe: &'a mut PipeWriter,
+    len: usize,
+}
+
+/// The future returned by `TcpStream::splice_from`.
+#[cfg(target_os = "linux")]
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SpliceFrom<'a> {
+    stream: &'a mut TcpStream,
+    pipe: &'a mut PipeReader,
+    len: usize,
+}
+
 impl Unpin for TcpStream {}
 
 impl TcpStream {
@@ -72,17 +297,354 @@ impl TcpStream {
     pub fn connect(addr: &SocketAddr) -> ConnectFuture {
         use self::ConnectFutureState::*;
 
-        let inner = match mio::net::TcpStream::connect(addr) {
-            Ok(tcp) => Waiting(TcpStream::new(tcp)),
+        let inner = match TcpStream::connect_nowait(addr) {
+            Ok(stream) => Waiting(stream),
+            Err(e) => Error(e),
+        };
+
+        ConnectFuture { inner }
+    }
+
+    /// Create a new TCP stream bound to the reactor of `handle`, and connect
+    /// it to the specified address.
+    ///
+    /// The stream is driven by that reactor, rather than by the one that is
+    /// current when it is first polled. See [`connect`] for the rest.
+    ///
+    /// [`connect`]: #method.connect
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::io;
+    /// use romio::raw::Reactor;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn connect_localhost(reactor: &Reactor) -> io::Result<TcpStream> {
+    /// let addr = "127.0.0.1:8080".parse().unwrap();
+    /// TcpStream::connect_on(&reactor.handle(), &addr).await
+    /// # }
+    /// ```
+    pub fn connect_on(handle: &Handle, addr: &SocketAddr) -> ConnectFuture {
+        use self::ConnectFutureState::*;
+
+        let stream = TcpStream::start_connect(addr, |tcp| TcpStream::new_on(tcp, handle));
+        let inner = match stream {
+            Ok(stream) => Waiting(stream),
             Err(e) => Error(e),
         };
 
         ConnectFuture { inner }
     }
 
-    pub(crate) fn new(connected: mio::net::TcpStream) -> TcpStream {
-        let io = PollEvented::new(connected);
-        TcpStream { io }
+    /// Returns a builder for a stream whose local address and interface are
+    /// chosen before it connects.
+    ///
+    /// On a host with several addresses or interfaces, this picks which one
+    /// a connection goes out of, instead of leaving it to the routing table.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::io;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn connect_from() -> io::Result<TcpStream> {
+    /// let local = "192.0.2.10:0".parse().unwrap();
+    /// let addr = "198.51.100.1:80".parse().unwrap();
+    /// TcpStream::builder().local_addr(local).connect(&addr).await
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn builder() -> TcpStreamBuilder {
+        TcpStreamBuilder::new()
+    }
+
+    /// Creates a new TCP stream connected to the specified address, failing
+    /// with `io::ErrorKind::TimedOut` if the connection isn't established
+    /// within `timeout`.
+    ///
+    /// Without a timeout, a connect whose SYNs go unanswered waits until the
+    /// kernel gives up, which takes about two minutes with Linux's defaults.
+    /// On timeout, the socket of the pending attempt is closed. Dropping the
+    /// future before it resolves likewise cancels the attempt.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::io;
+    /// use romio::tcp::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn connect_localhost() -> io::Result<TcpStream> {
+    /// let addr = "127.0.0.1:8080".parse().unwrap();
+    /// TcpStream::connect_timeout(&addr, Duration::from_secs(3)).await
+    /// # }
+    /// ```
+    pub fn connect_timeout(addr: &SocketAddr, timeout: Duration) -> ConnectTimeout {
+        ConnectTimeout {
+            connect: TcpStream::connect(addr),
+            deadline: Delay::new(Instant::now() + timeout),
+        }
+    }
+
+    /// Resolves `host` and connects to the first of its addresses that
+    /// accepts the connection.
+    ///
+    /// `host` is anything `std::net::ToSocketAddrs` accepts, such as
+    /// `"example.com:80"` or `("example.com", 80)`. It is resolved by the
+    /// system resolver, like `std::net::TcpStream::connect` does, but on a
+    /// helper thread, so the task isn't blocked while the lookup is in
+    /// flight. The addresses are then tried in the order the resolver
+    /// returned them.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `host` can't be resolved, or with the error of the last
+    /// address tried if none of them could be connected to.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::io;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn connect_localhost() -> io::Result<TcpStream> {
+    /// TcpStream::connect_host("localhost:8080").await
+    /// # }
+    /// ```
+    pub fn connect_host<A>(host: A) -> ConnectHost
+    where
+        A: ToSocketAddrs + Send + 'static,
+    {
+        match resolve(host) {
+            Ok(rx) => ConnectHost {
+                state: ConnectHostState::Resolving(rx),
+                addrs: Vec::new().into_iter(),
+                last_error: None,
+            },
+            Err(e) => ConnectHost {
+                state: ConnectHostState::Next,
+                addrs: Vec::new().into_iter(),
+                last_error: Some(e),
+            },
+        }
+    }
+
+    /// Resolves `host` and connects to `port` on it, racing its IPv6 and
+    /// IPv4 addresses as described by RFC 8305, "Happy Eyeballs".
+    ///
+    /// The addresses are tried alternating between the two families,
+    /// starting with the one the resolver put first. Each attempt gets
+    /// 250 milliseconds before the next one is started alongside it, and a
+    /// failed attempt starts the next one right away. The first connection
+    /// to be established wins, and the other attempts are abandoned. A
+    /// black-holed IPv6 route thus costs a quarter of a second, rather than
+    /// the seconds it takes the first attempt to time out.
+    ///
+    /// `host` is resolved like in [`connect_host`].
+    ///
+    /// [`connect_host`]: #method.connect_host
+    ///
+    /// # Errors
+    ///
+    /// Fails if `host` can't be resolved, or with the error of the last
+    /// attempt to fail if none of them succeeded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::io;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn connect_example() -> io::Result<TcpStream> {
+    /// TcpStream::connect_happy("example.com", 80).await
+    /// # }
+    /// ```
+    pub fn connect_happy(host: &str, port: u16) -> ConnectHappy {
+        let (resolving, last_error) = match resolve((host.to_string(), port)) {
+            Ok(rx) => (Some(rx), None),
+            Err(e) => (None, Some(e)),
+        };
+        ConnectHappy {
+            resolving,
+            addrs: Vec::new().into_iter(),
+            attempts: Vec::new(),
+            next_attempt: Delay::new(Instant::now()),
+            last_error,
+        }
+    }
+
+    /// Starts connecting a new TCP stream to the specified address, returning
+    /// the stream right away instead of once it is connected.
+    ///
+    /// This is for callers that need to own the stream while the connection
+    /// is still being established, for instance to register it in a
+    /// connection table. Use [`poll_connected`] to find out when, and
+    /// whether, the connection succeeded.
+    ///
+    /// Reads and writes issued before then wait for the connection to be
+    /// established, and fail with its error if it couldn't be.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::io;
+    /// use futures::future;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn connect_localhost() -> io::Result<()> {
+    /// let addr = "127.0.0.1:8080".parse().unwrap();
+    /// let mut stream = TcpStream::connect_nowait(&addr)?;
+    /// future::poll_fn(|cx| stream.poll_connected(cx)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`poll_connected`]: #method.poll_connected
+    pub fn connect_nowait(addr: &SocketAddr) -> io::Result<TcpStream> {
+        TcpStream::start_connect(addr, |tcp| Ok(TcpStream::new(tcp)))
+    }
+
+    /// Starts a non-blocking connect to `addr`, wrapping the socket with
+    /// `new`.
+    fn start_connect<F>(addr: &SocketAddr, new: F) -> io::Result<TcpStream>
+    where
+        F: FnOnce(Tcp) -> io::Result<TcpStream>,
+    {
+        #[cfg(not(windows))]
+        let tcp = mio::net::TcpStream::connect(addr).map(|tcp| {
+            let socket = SavedSocket::of(&tcp);
+            (tcp, socket)
+        })?;
+        #[cfg(windows)]
+        let tcp = crate::socket::connect(addr)?;
+
+        let mut stream = new(tcp)?;
+        stream.connect = ConnectState::Connecting;
+        Ok(stream)
+    }
+
+    /// Polls for the connection started by [`connect_nowait`] to be
+    /// established.
+    ///
+    /// Resolves to `Ok(())` once connected, or to the error that made the
+    /// attempt fail. Once resolved, every later call returns the same result
+    /// immediately. Streams from [`connect`] or [`accept`] are always
+    /// connected.
+    ///
+    /// [`connect_nowait`]: #method.connect_nowait
+    /// [`connect`]: #method.connect
+    /// [`accept`]: struct.TcpListener.html#method.accept
+    pub fn poll_connected(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_connect_result(cx)
+    }
+
+    pub(crate) fn new((connected, socket): Tcp) -> TcpStream {
+        TcpStream::from_parts(PollEvented::new(connected), socket)
+    }
+
+    pub(crate) fn new_on((connected, socket): Tcp, handle: &Handle) -> io::Result<TcpStream> {
+        let io = PollEvented::new_with_handle(connected, handle)?;
+        Ok(TcpStream::from_parts(io, socket))
+    }
+
+    fn from_parts(io: PollEvented<mio::net::TcpStream>, socket: SavedSocket) -> TcpStream {
+        TcpStream {
+            io,
+            stall: WriteStall::default(),
+            connect: ConnectState::Connected,
+            socket,
+        }
+    }
+
+    /// Polls for a non-blocking connect to complete.
+    ///
+    /// The first write-readiness edge after `connect(2)` means the attempt has
+    /// finished, one way or the other. `SO_ERROR` is read exactly once at that
+    /// point and the outcome is cached, so polling again never costs another
+    /// syscall.
+    fn poll_connect_result(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.connect {
+            ConnectState::Connected => return Poll::Ready(Ok(())),
+            ConnectState::Failed(ref e) => return Poll::Ready(Err(copy_error(e))),
+            ConnectState::Connecting => {}
+        }
+
+        ready!(self.io.poll_write_ready(cx)?);
+
+        #[cfg(test)]
+        SO_ERROR_CHECKS.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+        let res = match self.io.get_ref().take_error() {
+            Ok(None) => Ok(()),
+            Ok(Some(e)) | Err(e) => Err(e),
+        };
+        self.connect = match res {
+            Ok(()) => ConnectState::Connected,
+            Err(ref e) => ConnectState::Failed(copy_error(e)),
+        };
+        Poll::Ready(res)
+    }
+
+    /// Creates a `TcpStream` from a connected `std::net::TcpStream`.
+    ///
+    /// The socket is switched to non-blocking mode and registered with the
+    /// default reactor. This is the reverse of [`into_std`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// use romio::tcp::TcpStream;
+    ///
+    /// let stream = std::net::TcpStream::connect("127.0.0.1:8080")?;
+    /// let stream = TcpStream::from_std_nonblocking(stream)?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`into_std`]: #method.into_std
+    pub fn from_std_nonblocking(stream: std::net::TcpStream) -> io::Result<TcpStream> {
+        Ok(TcpStream::new(tcp_from_std(stream)?))
+    }
+
+    /// Converts this stream into a blocking `std::net::TcpStream`.
+    ///
+    /// The socket is deregistered from the reactor, which stops tracking it,
+    /// and put back into blocking mode, so that it can be handed to blocking
+    /// code such as a C library. Data already received by the socket stays
+    /// readable from the returned stream.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    /// use std::io::Read;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let mut stream = stream.into_std()?;
+    /// let mut buf = [0; 1024];
+    /// stream.read(&mut buf)?;
+    /// # Ok(())}
+    /// ```
+    #[cfg(unix)]
+    pub fn into_std(self) -> io::Result<std::net::TcpStream> {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+        let fd = self.io.into_inner()?.into_raw_fd();
+        let stream = unsafe { std::net::TcpStream::from_raw_fd(fd) };
+        stream.set_nonblocking(false)?;
+        Ok(stream)
     }
 
     /// Returns the local address that this stream is bound to.
@@ -133,18 +695,30 @@ impl TcpStream {
     /// portions to return immediately with an appropriate value (see the
     /// documentation of `Shutdown`).
     ///
+    /// Shutting down `Shutdown::Write` half-closes the connection: the peer
+    /// reads the end of the stream once it has read everything written so
+    /// far, while this side can keep reading what the peer sends back. This
+    /// never blocks, so it can be called from within a task. Unlike
+    /// `AsyncWrite::poll_close`, which does nothing for a `TcpStream`, it is
+    /// what tells the peer that no more data is coming.
+    ///
     /// # Examples
     ///
     /// ```rust
     /// #![feature(async_await)]
+    /// use futures::prelude::*;
     /// use romio::tcp::TcpStream;
     /// use std::net::Shutdown;
     ///
     /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
     /// let addr = "127.0.0.1:8080".parse()?;
-    /// let stream = TcpStream::connect(&addr).await?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await?;
+    /// stream.shutdown(Shutdown::Write)?;
     ///
-    /// stream.shutdown(Shutdown::Both)?;
+    /// let mut response = Vec::new();
+    /// stream.read_to_end(&mut response).await?;
     /// # Ok(())}
     /// ```
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
@@ -204,7 +778,7 @@ impl TcpStream {
     ///
     /// For more information about this option, see [`set_recv_buffer_size`].
     ///
-    /// [`set_recv_buffer_size`]: #tymethod.set_recv_buffer_size
+    /// [`set_recv_buffer_size`]: #method.set_recv_buffer_size
     ///
     /// # Examples
     ///
@@ -248,9 +822,9 @@ impl TcpStream {
 
     /// Gets the value of the `SO_SNDBUF` option on this socket.
     ///
-    /// For more information about this option, see [`set_send_buffer`].
+    /// For more information about this option, see [`set_send_buffer_size`].
     ///
-    /// [`set_send_buffer`]: #tymethod.set_send_buffer
+    /// [`set_send_buffer_size`]: #method.set_send_buffer_size
     ///
     /// # Examples
     ///
@@ -297,7 +871,7 @@ impl TcpStream {
     ///
     /// For more information about this option, see [`set_keepalive`].
     ///
-    /// [`set_keepalive`]: #tymethod.set_keepalive
+    /// [`set_keepalive`]: #method.set_keepalive
     ///
     /// # Examples
     ///
@@ -353,7 +927,7 @@ impl TcpStream {
     ///
     /// For more information about this option, see [`set_ttl`].
     ///
-    /// [`set_ttl`]: #tymethod.set_ttl
+    /// [`set_ttl`]: #method.set_ttl
     ///
     /// # Examples
     ///
@@ -400,7 +974,7 @@ impl TcpStream {
     ///
     /// For more information about this option, see [`set_linger`].
     ///
-    /// [`set_linger`]: #tymethod.set_linger
+    /// [`set_linger`]: #method.set_linger
     ///
     /// # Examples
     ///
@@ -450,31 +1024,1223 @@ impl TcpStream {
     pub fn set_linger(&self, dur: Option<Duration>) -> io::Result<()> {
         self.io.get_ref().set_linger(dur)
     }
-}
 
-impl AsyncRead for TcpStream {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
-        buf: &mut [u8],
-    ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_read(cx, buf)
+    /// Returns how long the current write has been blocked waiting for the
+    /// socket to become writable.
+    ///
+    /// A write is blocked when the kernel's send buffer is full, which usually
+    /// means the peer is reading slower than we are writing. `None` is
+    /// returned if the last write attempt did not block.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// if let Some(stall) = stream.write_stall() {
+    ///     if stall > Duration::from_secs(10) {
+    ///         println!("peer is not reading");
+    ///     }
+    /// }
+    /// # Ok(())}
+    /// ```
+    pub fn write_stall(&self) -> Option<Duration> {
+        self.stall.since.map(|since| since.elapsed())
     }
-}
 
-impl AsyncWrite for TcpStream {
-    fn poll_write(
-        mut self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+    /// Registers a callback to be invoked when a write was blocked on write
+    /// readiness for at least `threshold`.
+    ///
+    /// The callback receives the total time the write was blocked. It is
+    /// called once per stall, when the blocked write is retried and makes
+    /// progress, so it reports slow consumers after the fact. To detect a
+    /// stall that is still ongoing, use [`write_stall`].
+    ///
+    /// Registering a new callback replaces the previous one.
+    ///
+    /// [`write_stall`]: #method.write_stall
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.on_write_stall(Duration::from_millis(500), |stall| {
+    ///     println!("write blocked for {:?}", stall);
+    /// });
+    /// # Ok(())}
+    /// ```
+    pub fn on_write_stall<F>(&mut self, threshold: Duration, callback: F)
+    where
+        F: FnMut(Duration) + Send + Sync + 'static,
+    {
+        self.stall.hook = Some(StallHook {
+            threshold,
+            callback: Box::new(callback),
+        });
+    }
+
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// When non-zero, a receive that finds no data busy-polls the network
+    /// device queue for up to `usecs` microseconds before going to sleep. This
+    /// shaves off the interrupt and wakeup latency, at the cost of burning a
+    /// CPU core while waiting, so it only pays off for latency-critical
+    /// sockets on otherwise idle cores. Setting a value larger than the
+    /// `net.core.busy_read` sysctl requires `CAP_NET_ADMIN`.
+    ///
+    /// This option is only available on Linux; elsewhere an error of kind
+    /// `io::ErrorKind::Unsupported` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.set_busy_poll(50)?;
+    /// # Ok(())}
+    /// ```
+    #[cfg(unix)]
+    pub fn set_busy_poll(&self, usecs: u32) -> io::Result<()> {
+        crate::sys::set_busy_poll(std::os::unix::io::AsRawFd::as_raw_fd(self), usecs)
+    }
+
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see [`set_busy_poll`].
+    ///
+    /// [`set_busy_poll`]: #method.set_busy_poll
+    #[cfg(unix)]
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        crate::sys::busy_poll(std::os::unix::io::AsRawFd::as_raw_fd(self))
+    }
+
+    /// Applies a bundle of options suited to latency-critical request/response
+    /// traffic.
+    ///
+    /// This turns on `TCP_NODELAY`, so small writes go out without waiting
+    /// for earlier segments to be acknowledged, and shrinks the send buffer to
+    /// 64 KiB so that queued data can't add much delay in front of a new
+    /// message. On Linux it also turns on `TCP_QUICKACK`, so received
+    /// segments are acknowledged right away rather than delayed, and sets
+    /// `SO_BUSY_POLL` to 50 microseconds, see [`set_busy_poll`].
+    ///
+    /// The kernel clears `TCP_QUICKACK` again as it sees fit, so call this
+    /// again after reads if acknowledgements must never be delayed.
+    /// Options the platform doesn't support, or that the process lacks the
+    /// privileges to set, are skipped; other errors are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.tune_low_latency()?;
+    /// assert!(stream.nodelay()?);
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`set_busy_poll`]: #method.set_busy_poll
+    #[cfg(unix)]
+    pub fn tune_low_latency(&self) -> io::Result<()> {
+        self.set_nodelay(true)?;
+        best_effort(self.set_send_buffer_size(64 * 1024))?;
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+            let on: libc::c_int = 1;
+            best_effort(crate::sys::setsockopt(
+                fd,
+                libc::IPPROTO_TCP,
+                libc::TCP_QUICKACK,
+                on,
+            ))?;
+        }
+
+        best_effort(self.set_busy_poll(50))
+    }
+
+    /// Returns the CPU that processed the most recent packets of this
+    /// connection, from the `SO_INCOMING_CPU` option.
+    ///
+    /// On a server that spreads its listeners over several cores with
+    /// `SO_REUSEPORT`, this tells which core the connection's packets land on,
+    /// so its handler can be scheduled on that core or NUMA node and keep the
+    /// processing local.
+    ///
+    /// Returns `None` if the kernel doesn't know yet, and always on platforms
+    /// other than Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "0.0.0.0:8080".parse()?;
+    /// let mut listener = TcpListener::bind(&addr)?;
+    /// let mut incoming = listener.incoming();
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     if let Some(cpu) = stream?.incoming_cpu()? {
+    ///         println!("hand over to the worker on cpu {}", cpu);
+    ///     }
+    /// }
+    /// # Ok(())}
+    /// ```
+    #[cfg(unix)]
+    pub fn incoming_cpu(&self) -> io::Result<Option<u32>> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let cpu: libc::c_int =
+                crate::sys::getsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_INCOMING_CPU)?;
+            Ok(if cpu < 0 { None } else { Some(cpu as u32) })
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            Ok(None)
+        }
+    }
+
+    /// Receives data on the socket without removing it from the queue.
+    ///
+    /// Successive calls return the same data, and a later read returns it
+    /// too. This is meant for protocol sniffing, such as telling a TLS
+    /// ClientHello from a plaintext HTTP request on the same port before
+    /// handing the stream to the right handler. Like a read, this resolves to
+    /// 0 once the peer has closed the connection and all data was read.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8443".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let mut first = [0; 1];
+    /// // 0x16 starts a TLS handshake record.
+    /// if stream.peek(&mut first).await? == 1 && first[0] == 0x16 {
+    ///     println!("TLS client");
+    /// }
+    /// # Ok(())}
+    /// ```
+    pub fn peek<'a>(&'a mut self, buf: &'a mut [u8]) -> Peek<'a> {
+        Peek { stream: self, buf }
+    }
+
+    /// Polls to receive data on the socket without removing it from the
+    /// queue.
+    ///
+    /// See [`peek`] for details. If no data is available, `Poll::Pending` is
+    /// returned and the current task is woken once there is some.
+    ///
+    /// [`peek`]: #method.peek
+    pub fn poll_peek(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        ready!(self.poll_connect_result(cx))?;
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match self.io.get_ref().peek(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Waits for the stream to become readable, without reading from it.
+    ///
+    /// The future resolves to the readiness that was observed once data is
+    /// waiting, or the peer has closed its side, so that the caller can then
+    /// read into buffers of its own choosing only when there is something
+    /// to read. This is the `async` counterpart of [`poll_read_ready`]: the
+    /// stream stays readable, and the future resolves right away, until a
+    /// read fails with `WouldBlock`, after which [`clear_read_ready`] should
+    /// be called if the read didn't go through the stream's own methods.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// use futures::io::AsyncReadExt;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.readable().await?;
+    /// let mut buf = vec![0; 4096];
+    /// let n = stream.read(&mut buf).await?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    /// [`clear_read_ready`]: #method.clear_read_ready
+    pub fn readable(&mut self) -> Readable<'_> {
+        Readable { stream: self }
+    }
+
+    /// Waits for the stream to become writable, without writing to it.
+    ///
+    /// This works like [`readable`], for writes: the stream stays writable
+    /// until a write fails with `WouldBlock`.
+    ///
+    /// [`readable`]: #method.readable
+    pub fn writable(&mut self) -> Writable<'_> {
+        Writable { stream: self }
+    }
+
+    /// Polls the stream for read readiness, for code that makes its own
+    /// syscalls on its descriptor.
+    ///
+    /// This works like [`PollEvented::poll_read_ready`], which describes when
+    /// to clear the readiness again. A stream that is still connecting first
+    /// waits for the outcome, and fails if the connection couldn't be
+    /// established.
+    ///
+    /// [`PollEvented::poll_read_ready`]: ../raw/struct.PollEvented.html#method.poll_read_ready
+    pub fn poll_read_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        ready!(self.poll_connect_result(cx))?;
+        self.io.poll_read_ready(cx)
+    }
+
+    /// Polls the stream for write readiness, for code that makes its own
+    /// syscalls on its descriptor.
+    ///
+    /// This works like [`poll_read_ready`], for writes, including waiting
+    /// for a connect in progress.
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    pub fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        ready!(self.poll_connect_result(cx))?;
+        self.io.poll_write_ready(cx)
+    }
+
+    /// Clears the read readiness of the stream, and arranges for the current
+    /// task to be woken once it is readable again.
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_read_ready(cx)
+    }
+
+    /// Clears the write readiness of the stream, and arranges for the current
+    /// task to be woken once it is writable again.
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_write_ready(cx)
+    }
+
+    /// Reads some bytes from the stream, failing if none arrive within `dur`.
+    ///
+    /// This behaves like `AsyncReadExt::read`, except that the returned future
+    /// resolves to an error of kind `io::ErrorKind::TimedOut` once `dur` has
+    /// elapsed without any data becoming available. A read that times out
+    /// consumes nothing, so the stream can be read again afterwards without
+    /// losing data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let mut buf = [0; 1024];
+    /// let n = stream.read_with_timeout(&mut buf, Duration::from_secs(5)).await?;
+    /// println!("read {} bytes", n);
+    /// # Ok(())}
+    /// ```
+    pub fn read_with_timeout<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        dur: Duration,
+    ) -> ReadWithTimeout<'a> {
+        ReadWithTimeout {
+            stream: self,
+            buf,
+            delay: Delay::new(Instant::now() + dur),
+        }
+    }
+
+    /// Writes some bytes to the stream, failing if none can be written within
+    /// `dur`.
+    ///
+    /// This behaves like `AsyncWriteExt::write`, except that the returned
+    /// future resolves to an error of kind `io::ErrorKind::TimedOut` once
+    /// `dur` has elapsed without the stream becoming writable. A write that
+    /// times out has not written anything.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// stream.write_with_timeout(b"ping", Duration::from_secs(5)).await?;
+    /// # Ok(())}
+    /// ```
+    pub fn write_with_timeout<'a>(
+        &'a mut self,
+        buf: &'a [u8],
+        dur: Duration,
+    ) -> WriteWithTimeout<'a> {
+        WriteWithTimeout {
+            stream: self,
+            buf,
+            delay: Delay::new(Instant::now() + dur),
+        }
+    }
+
+    /// Reads some bytes from the stream into a buffer taken from `pool`.
+    ///
+    /// The returned buffer is filled with the bytes read and goes back to the
+    /// pool when dropped, so a server reading many small messages doesn't
+    /// allocate for each of them. The buffer is only taken from the pool once
+    /// the read is attempted, and is kept across wakeups until it completes.
+    ///
+    /// An empty buffer means the peer closed the connection, just like a read
+    /// of 0 bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::pool::BufferPool;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let pool = BufferPool::new(4096, 64);
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let buf = stream.read_pooled(&pool).await?;
+    /// println!("read {:?}", &buf[..]);
+    /// # Ok(())}
+    /// ```
+    pub fn read_pooled<'a>(&'a mut self, pool: &'a BufferPool) -> ReadPooled<'a> {
+        ReadPooled {
+            stream: self,
+            pool,
+            buf: None,
+        }
+    }
+
+    /// Reads a header terminated by `delimiter`, failing unless the whole
+    /// header arrives before `deadline`.
+    ///
+    /// Bytes are read into `buf` until it contains `delimiter`, for example
+    /// `b"\r\n\r\n"` for an HTTP request head. On success the future
+    /// resolves to `(header_len, filled)`: `buf[..header_len]` is the header
+    /// including the delimiter, and `buf[header_len..filled]` holds any bytes
+    /// that were read past it.
+    ///
+    /// Unlike [`read_with_timeout`], the deadline covers the whole header
+    /// rather than each read, so a client that dribbles a byte at a time
+    /// can't hold the connection open indefinitely. Taking the deadline as
+    /// an `Instant` lets a server start the clock when the connection is
+    /// accepted.
+    ///
+    /// [`read_with_timeout`]: #method.read_with_timeout
+    ///
+    /// # Errors
+    ///
+    /// The future resolves to an error of kind:
+    ///
+    /// - `io::ErrorKind::TimedOut` if `deadline` passes first,
+    /// - `io::ErrorKind::InvalidData` if `buf` fills up without containing
+    ///   `delimiter`,
+    /// - `io::ErrorKind::UnexpectedEof` if the peer closes the connection
+    ///   before sending `delimiter`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `delimiter` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::TcpListener;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut listener = TcpListener::bind(&addr)?;
+    /// let mut incoming = listener.incoming();
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     let mut stream = stream?;
+    ///     let deadline = Instant::now() + Duration::from_secs(10);
+    ///     let mut buf = [0; 8192];
+    ///     match stream.read_header_within(&mut buf, b"\r\n\r\n", deadline).await {
+    ///         Ok((header_len, _)) => println!("header is {} bytes", header_len),
+    ///         Err(_) => continue, // drop slow or misbehaving clients
+    ///     }
+    /// }
+    /// # Ok(())}
+    /// ```
+    pub fn read_header_within<'a>(
+        &'a mut self,
+        buf: &'a mut [u8],
+        delimiter: &'a [u8],
+        deadline: Instant,
+    ) -> ReadHeader<'a> {
+        assert!(!delimiter.is_empty(), "header delimiter must not be empty");
+        ReadHeader {
+            stream: self,
+            buf,
+            delimiter,
+            filled: 0,
+            delay: Delay::new(deadline),
+        }
+    }
+
+    /// Returns the original destination address of a connection that was
+    /// redirected by Netfilter.
+    ///
+    /// Transparent proxies that intercept traffic with an iptables `REDIRECT`
+    /// or `DNAT` rule see the proxy's own address as the local address of the
+    /// accepted stream. This method recovers the address the client actually
+    /// connected to, using the `SO_ORIGINAL_DST` socket option (or
+    /// `IP6T_SO_ORIGINAL_DST` for IPv6).
+    ///
+    /// Connections intercepted with `TPROXY` don't need this: their
+    /// [`local_addr`] already is the original destination.
+    ///
+    /// [`local_addr`]: #method.local_addr
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection was not tracked by Netfilter's
+    /// connection tracking, e.g. because no NAT rule applied to it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::TcpListener;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "0.0.0.0:3128".parse()?;
+    /// let mut listener = TcpListener::bind(&addr)?;
+    /// let mut incoming = listener.incoming();
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     let stream = stream?;
+    ///     println!("client wanted {}", stream.original_dst()?);
+    /// }
+    /// # Ok(())}
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn original_dst(&self) -> io::Result<SocketAddr> {
+        use std::os::unix::io::AsRawFd;
+
+        let ipv6 = self.local_addr()?.is_ipv6();
+        crate::sys::original_dst(self.as_raw_fd(), ipv6)
+    }
+
+    /// Returns whether this stream is a Multipath TCP socket.
+    ///
+    /// This is false for a stream that asked for MPTCP with
+    /// [`TcpStreamBuilder::multipath`] but fell back to plain TCP. It is
+    /// true for streams accepted from a multipath listener once the client
+    /// spoke MPTCP.
+    ///
+    /// This method is only available on Linux, with the `mptcp` feature.
+    ///
+    /// [`TcpStreamBuilder::multipath`]: struct.TcpStreamBuilder.html#method.multipath
+    #[cfg(all(feature = "mptcp", target_os = "linux"))]
+    pub fn is_multipath(&self) -> io::Result<bool> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::is_mptcp(self.as_raw_fd())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl TcpStream {
+    /// Opens a TCP connection to `addr` with TCP Fast Open, sending `data`
+    /// along with the SYN.
+    ///
+    /// Once the client has a Fast Open cookie from an earlier connection to
+    /// the same server, `data` reaches it without waiting for the handshake,
+    /// which saves a round trip on short request/response connections. The
+    /// first connection to a server fetches the cookie, and sends `data` once
+    /// connected, as does any connection to a server that doesn't take Fast
+    /// Open. The same happens if the client bit of the `net.ipv4.tcp_fastopen`
+    /// sysctl is unset. Servers enable it with [`TcpListenerBuilder::fastopen`].
+    ///
+    /// The future resolves to the connected stream once all of `data` was
+    /// sent. Since the data of a SYN may be delivered twice, this is only
+    /// meant for requests that are safe to repeat.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let request = b"GET / HTTP/1.0\r\n\r\n";
+    /// let mut stream = TcpStream::connect_with_data(&addr, request).await?;
+    ///
+    /// let mut response = Vec::new();
+    /// stream.read_to_end(&mut response).await?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`TcpListenerBuilder::fastopen`]: struct.TcpListenerBuilder.html#method.fastopen
+    pub fn connect_with_data<'a>(addr: &SocketAddr, data: &'a [u8]) -> ConnectWithData<'a> {
+        let (inner, sent) = match TcpStream::start_fastopen(addr, data) {
+            Ok((stream, sent)) => (ConnectFutureState::Waiting(stream), sent),
+            Err(e) => (ConnectFutureState::Error(e), 0),
+        };
+        ConnectWithData { inner, data, sent }
+    }
+
+    fn start_fastopen(addr: &SocketAddr, data: &[u8]) -> io::Result<(TcpStream, usize)> {
+        use std::os::unix::io::AsRawFd;
+
+        let family = match addr {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+        let stream: std::net::TcpStream = crate::sys::socket_owned(family, libc::SOCK_STREAM, 0)?;
+        let fd = stream.as_raw_fd();
+
+        let (tcp, sent) = match crate::sys::connect_fastopen(fd, data, addr) {
+            Ok(sent) => (mio::net::TcpStream::from_stream(stream)?, sent),
+            // Fast Open is turned off for clients.
+            Err(ref e) if e.raw_os_error() == Some(libc::EOPNOTSUPP) => {
+                (mio::net::TcpStream::connect_stream(stream, addr)?, 0)
+            }
+            Err(e) => return Err(e),
+        };
+        let socket = SavedSocket::of(&tcp);
+        let mut stream = TcpStream::new((tcp, socket));
+        stream.connect = ConnectState::Connecting;
+        Ok((stream, sent))
+    }
+
+    /// Sends `byte` as TCP urgent data.
+    ///
+    /// Urgent data is sent with `MSG_OOB`, after what was written so far,
+    /// and marks its place in the stream. Unless `SO_OOBINLINE` is set, the
+    /// peer receives the byte separately, with [`recv_oob`], and is told
+    /// about it before it has read up to it. Protocols like telnet and FTP
+    /// use it to interrupt a transfer.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:23".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// // Telnet's Data Mark, sent as urgent data after an interrupt.
+    /// stream.send_oob(242).await?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`recv_oob`]: #method.recv_oob
+    pub fn send_oob(&mut self, byte: u8) -> SendOob<'_> {
+        SendOob { stream: self, byte }
+    }
+
+    /// Attempts to send `byte` as TCP urgent data.
+    ///
+    /// See [`send_oob`] for details. If the socket isn't writable,
+    /// `Poll::Pending` is returned and the current task is woken once it is.
+    ///
+    /// [`send_oob`]: #method.send_oob
+    pub fn poll_send_oob(&mut self, cx: &mut Context<'_>, byte: u8) -> Poll<io::Result<()>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.poll_connect_result(cx))?;
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match crate::sys::send_oob(self.as_raw_fd(), byte) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Receives the byte of TCP urgent data sent by the peer.
+    ///
+    /// The future resolves once urgent data has arrived, whether or not
+    /// everything sent before it has been read yet, so an interrupt can be
+    /// noticed while a large transfer is in progress. Only the latest
+    /// urgent byte is kept: one that wasn't received is replaced by the
+    /// next. This fails with `InvalidInput` if `SO_OOBINLINE` is set, since
+    /// urgent data is then read with the rest of the stream.
+    ///
+    /// Urgent data is signaled through the stream's read readiness, so the
+    /// task doing this should be the one reading from the stream.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:21".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let byte = stream.recv_oob().await?;
+    /// println!("urgent: {}", byte);
+    /// # Ok(())}
+    /// ```
+    pub fn recv_oob(&mut self) -> RecvOob<'_> {
+        RecvOob { stream: self }
+    }
+
+    /// Attempts to receive the byte of TCP urgent data sent by the peer.
+    ///
+    /// See [`recv_oob`] for details. If no urgent data is waiting,
+    /// `Poll::Pending` is returned and the current task is woken once some
+    /// arrives.
+    ///
+    /// [`recv_oob`]: #method.recv_oob
+    pub fn poll_recv_oob(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<u8>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.poll_connect_result(cx))?;
+        ready!(self.io.poll_priority_ready(cx)?);
+
+        match crate::sys::recv_oob(self.as_raw_fd()) {
+            Ok(byte) => {
+                // The byte was taken, so the priority event is spent.
+                self.io.clear_priority_ready(cx)?;
+                Poll::Ready(Ok(byte))
+            }
+            // The urgent mark arrived ahead of the byte itself.
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_priority_ready(cx)?;
+                Poll::Pending
+            }
+            // Either the urgent byte went inline, or it was already taken.
+            Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) && !self.oob_inline()? => {
+                self.io.clear_priority_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Sets the value of the `SO_OOBINLINE` option on this socket.
+    ///
+    /// With this set, urgent data from the peer is left in the stream where
+    /// it was sent, and read like any other byte, instead of being received
+    /// with [`recv_oob`].
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`recv_oob`]: #method.recv_oob
+    pub fn set_oob_inline(&self, on: bool) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::set_oob_inline(self.as_raw_fd(), on)
+    }
+
+    /// Gets the value of the `SO_OOBINLINE` option on this socket.
+    ///
+    /// For more information about this option, see [`set_oob_inline`].
+    ///
+    /// [`set_oob_inline`]: #method.set_oob_inline
+    pub fn oob_inline(&self) -> io::Result<bool> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::oob_inline(self.as_raw_fd())
+    }
+
+    /// Sends `len` bytes of `file`, starting at `offset`, with `sendfile`.
+    ///
+    /// The data goes from the page cache to the socket without being copied
+    /// through userspace, which makes this the cheap way to serve static
+    /// files. The offset of `file` itself isn't changed, so one `File` can
+    /// be sent to many streams at once.
+    ///
+    /// The future resolves to the number of bytes sent, which is less than
+    /// `len` only if the end of the file was reached first.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::tcp::TcpStream;
+    /// use std::fs::File;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = "127.0.0.1:8080".parse()?;
+    /// let mut stream = TcpStream::connect(&addr).await?;
+    ///
+    /// let file = File::open("index.html")?;
+    /// let len = file.metadata()?.len() as usize;
+    /// stream.send_file(&file, 0, len).await?;
+    /// # Ok(())}
+    /// ```
+    pub fn send_file<'a>(&'a mut self, file: &'a File, offset: u64, len: usize) -> SendFile<'a> {
+        SendFile {
+            stream: self,
+            file,
+            offset,
+            len,
+            sent: 0,
+        }
+    }
+
+    /// Attempts to send up to `len` bytes of `file`, starting at `offset`,
+    /// with a single `sendfile` call.
+    ///
+    /// Returns the number of bytes sent, which is zero at the end of the
+    /// file. If the socket isn't writable, `Poll::Pending` is returned and
+    /// the current task is woken once it is.
+    pub fn poll_send_file(
+        &mut self,
+        cx: &mut Context<'_>,
+        file: &File,
+        offset: u64,
+        len: usize,
+    ) -> Poll<io::Result<usize>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.poll_connect_result(cx))?;
+        let res = self.poll_sendfile(cx, file.as_raw_fd(), offset, len);
+        self.stall.update(&res);
+        res
+    }
+
+    fn poll_sendfile(
+        &mut self,
+        cx: &mut Context<'_>,
+        file: RawFd,
+        offset: u64,
+        len: usize,
+    ) -> Poll<io::Result<usize>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match crate::sys::sendfile(self.as_raw_fd(), file, offset, len) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Moves up to `len` bytes received on this stream into `pipe`, with
+    /// `splice`.
+    ///
+    /// This is a read that lands in a pipe instead of a buffer: the data
+    /// stays in the kernel, and can be spliced on to another stream or a
+    /// file with [`splice_from`]. The future resolves to the number of bytes
+    /// moved, which is zero once the peer has shut down its side.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`splice_from`]: #method.splice_from
+    pub fn splice_to<'a>(&'a mut self, pipe: &'a mut PipeWriter, len: usize) -> SpliceTo<'a> {
+        SpliceTo {
+            stream: self,
+            pipe,
+            len,
+        }
+    }
+
+    /// Attempts to move up to `len` bytes received on this stream into
+    /// `pipe`.
+    ///
+    /// If the stream has nothing to read or the pipe is full,
+    /// `Poll::Pending` is returned and the current task is woken once that
+    /// changes.
+    pub fn poll_splice_to(
+        &mut self,
+        cx: &mut Context<'_>,
+        pipe: &mut PipeWriter,
+        len: usize,
+    ) -> Poll<io::Result<usize>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.poll_connect_result(cx))?;
+        let pipe = pipe.as_async_fd();
+        // Both ends are polled, so the task is woken by whichever changes.
+        let stream = self.io.poll_read_ready(cx)?;
+        let piped = pipe.poll_write_ready(cx)?;
+        if stream.is_pending() && piped.is_pending() {
+            return Poll::Pending;
+        }
+
+        match crate::sys::splice(self.as_raw_fd(), pipe.as_raw_fd(), len) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                // This doesn't say which end wasn't ready, so both are
+                // cleared, and the next try waits for news from either: the
+                // end that held this one up reports it once it is ready.
+                self.io.clear_read_ready(cx)?;
+                pipe.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Sends up to `len` bytes from `pipe` on this stream, with `splice`.
+    ///
+    /// This is a write from a pipe instead of a buffer, the counterpart of
+    /// [`splice_to`]. The future resolves to the number of bytes moved,
+    /// which is zero once the write end of the pipe has been closed and the
+    /// pipe is empty.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`splice_to`]: #method.splice_to
+    pub fn splice_from<'a>(&'a mut self, pipe: &'a mut PipeReader, len: usize) -> SpliceFrom<'a> {
+        SpliceFrom {
+            stream: self,
+            pipe,
+            len,
+        }
+    }
+
+    /// Attempts to send up to `len` bytes from `pipe` on this stream.
+    ///
+    /// If the pipe is empty or the stream isn't writable, `Poll::Pending` is
+    /// returned and the current task is woken once that changes.
+    pub fn poll_splice_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        pipe: &mut PipeReader,
+        len: usize,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_connect_result(cx))?;
+        let res = self.poll_splice_out(cx, pipe.as_async_fd(), len);
+        self.stall.update(&res);
+        res
+    }
+
+    fn poll_splice_out(
+        &mut self,
+        cx: &mut Context<'_>,
+        pipe: &AsyncFd<File>,
+        len: usize,
+    ) -> Poll<io::Result<usize>> {
+        use std::os::unix::io::AsRawFd;
+
+        // Polled the same way as in `poll_splice_to`.
+        let piped = pipe.poll_read_ready(cx)?;
+        let stream = self.io.poll_write_ready(cx)?;
+        if piped.is_pending() && stream.is_pending() {
+            return Poll::Pending;
+        }
+
+        match crate::sys::splice(pipe.as_raw_fd(), self.as_raw_fd(), len) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                pipe.clear_read_ready(cx)?;
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl TcpStreamBuilder {
+    fn new() -> TcpStreamBuilder {
+        TcpStreamBuilder {
+            local_addr: None,
+            #[cfg(target_os = "linux")]
+            device: None,
+            #[cfg(all(feature = "mptcp", target_os = "linux"))]
+            multipath: false,
+        }
+    }
+
+    /// Binds the socket to `addr` before connecting.
+    ///
+    /// The connection then comes from that address, which must be one of
+    /// the host's, and from its port, unless the port is 0, in which case
+    /// the kernel picks one. It must be of the same family as the address
+    /// connected to.
+    pub fn local_addr(mut self, addr: SocketAddr) -> TcpStreamBuilder {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// Sets `SO_BINDTODEVICE`, which restricts the socket to the network
+    /// interface named `interface`, such as `"eth1"`.
+    ///
+    /// The connection goes out of that interface whatever the routing table
+    /// says, which is how a multi-homed host picks the egress interface of
+    /// each connection. Before Linux 5.7, this requires `CAP_NET_RAW`.
+    ///
+    /// This option is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn bind_device(mut self, interface: &str) -> TcpStreamBuilder {
+        self.device = Some(interface.to_string());
+        self
+    }
+
+    /// Makes the stream a Multipath TCP socket, whose connection can spread
+    /// over several network paths when the server supports MPTCP too, and
+    /// is plain TCP otherwise.
+    ///
+    /// Kernels without MPTCP support, before Linux 5.6 or with the
+    /// `net.mptcp.enabled` sysctl unset, get a plain TCP socket instead.
+    /// [`TcpStream::is_multipath`] tells which one it is.
+    ///
+    /// This option is only available on Linux, with the `mptcp` feature.
+    ///
+    /// [`TcpStream::is_multipath`]: struct.TcpStream.html#method.is_multipath
+    #[cfg(all(feature = "mptcp", target_os = "linux"))]
+    pub fn multipath(mut self, multipath: bool) -> TcpStreamBuilder {
+        self.multipath = multipath;
+        self
+    }
+
+    /// Creates the socket, applies the options and starts connecting it to
+    /// `addr`.
+    ///
+    /// The future resolves like the one of [`TcpStream::connect`]. Invalid
+    /// options, such as a local address of another family than `addr`, make
+    /// it fail with `InvalidInput`.
+    ///
+    /// [`TcpStream::connect`]: struct.TcpStream.html#method.connect
+    pub fn connect(&self, addr: &SocketAddr) -> ConnectFuture {
+        use self::ConnectFutureState::*;
+
+        let inner = match self.start_connect(addr) {
+            Ok(stream) => Waiting(stream),
+            Err(e) => Error(e),
+        };
+
+        ConnectFuture { inner }
+    }
+
+    fn start_connect(&self, addr: &SocketAddr) -> io::Result<TcpStream> {
+        use crate::sys;
+        use std::os::unix::io::AsRawFd;
+
+        let family = match addr {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => libc::AF_INET6,
+        };
+        if let Some(local) = self.local_addr {
+            if local.is_ipv4() != addr.is_ipv4() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "local and remote address families differ",
+                ));
+            }
+        }
+
+        #[cfg(all(feature = "mptcp", target_os = "linux"))]
+        let stream: std::net::TcpStream = if self.multipath {
+            sys::mptcp_socket(family)?
+        } else {
+            sys::socket_owned(family, libc::SOCK_STREAM, 0)?
+        };
+        #[cfg(not(all(feature = "mptcp", target_os = "linux")))]
+        let stream: std::net::TcpStream = sys::socket_owned(family, libc::SOCK_STREAM, 0)?;
+        let fd = stream.as_raw_fd();
+
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(device) = &self.device {
+                sys::bind_device(fd, device)?;
+            }
+        }
+        if let Some(local) = &self.local_addr {
+            sys::bind(fd, local)?;
+        }
+
+        let tcp = mio::net::TcpStream::connect_stream(stream, addr)?;
+        let socket = SavedSocket::of(&tcp);
+        let mut stream = TcpStream::new((tcp, socket));
+        stream.connect = ConnectState::Connecting;
+        Ok(stream)
+    }
+}
+
+#[cfg(unix)]
+impl TcpStream {
+    fn poll_readv(
+        &mut self,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
+
+        match crate::sys::readv(self.io.get_ref().as_raw_fd(), bufs) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    fn poll_writev(
+        &mut self,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match crate::sys::writev(self.io.get_ref().as_raw_fd(), bufs) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut self.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+impl WriteStall {
+    /// Records the outcome of a write attempt.
+    fn update<T>(&mut self, res: &Poll<T>) {
+        if res.is_pending() {
+            if self.since.is_none() {
+                self.since = Some(Instant::now());
+            }
+        } else if let Some(since) = self.since.take() {
+            if let Some(hook) = &mut self.hook {
+                let stall = since.elapsed();
+                if stall >= hook.threshold {
+                    (hook.callback)(stall);
+                }
+            }
+        }
+    }
+}
+
+impl AsyncRead for TcpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_connect_result(cx))?;
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+
+    #[cfg(unix)]
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.poll_connect_result(cx))?;
+        self.poll_readv(cx, bufs)
+    }
+}
+
+impl AsyncWrite for TcpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_write(cx, buf)
+        // An empty write on a stream is a no-op, so don't wait for readiness
+        // or count it towards a stall.
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        ready!(self.poll_connect_result(cx))?;
+        let res = Pin::new(&mut self.io).poll_write(cx, buf);
+        self.stall.update(&res);
+        res
+    }
+
+    #[cfg(unix)]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        if bufs.iter().all(|buf| buf.is_empty()) {
+            return Poll::Ready(Ok(0));
+        }
+        ready!(self.poll_connect_result(cx))?;
+        let res = self.poll_writev(cx, bufs);
+        self.stall.update(&res);
+        res
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.io).poll_flush(cx)
+        ready!(self.poll_connect_result(cx))?;
+        let res = Pin::new(&mut self.io).poll_flush(cx);
+        self.stall.update(&res);
+        res
     }
 
+    /// Does nothing: the connection is closed when the stream is dropped.
+    /// Use [`shutdown`](#method.shutdown) with `Shutdown::Write` to signal
+    /// the end of the stream to the peer while still reading from it.
     fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         Pin::new(&mut self.io).poll_close(cx)
     }
@@ -536,36 +2302,384 @@ impl Future for ConnectFuture {
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<TcpStream>> {
         match mem::replace(&mut self.inner, ConnectFutureState::Empty) {
-            ConnectFutureState::Waiting(stream) => {
-                // Once we've connected, wait for the stream to be writable as
-                // that's when the actual connection has been initiated. Once we're
-                // writable we check for `take_socket_error` to see if the connect
-                // actually hit an error or not.
-                //
-                // If all that succeeded then we ship everything on up.
-                if let Poll::Pending = stream.io.poll_write_ready(cx)? {
+            ConnectFutureState::Waiting(mut stream) => match stream.poll_connect_result(cx) {
+                Poll::Pending => {
                     self.inner = ConnectFutureState::Waiting(stream);
-                    return Poll::Pending;
+                    Poll::Pending
+                }
+                Poll::Ready(res) => Poll::Ready(res.map(|()| stream)),
+            },
+            ConnectFutureState::Error(e) => Poll::Ready(Err(e)),
+            ConnectFutureState::Empty => panic!("can't poll TCP stream twice"),
+        }
+    }
+}
+
+impl Future for ConnectTimeout {
+    type Output = io::Result<TcpStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<TcpStream>> {
+        if let Poll::Ready(res) = Pin::new(&mut self.connect).poll(cx) {
+            return Poll::Ready(res);
+        }
+        ready!(self.deadline.poll_elapsed(cx))?;
+        // Close the socket of the abandoned attempt right away.
+        self.connect.inner = ConnectFutureState::Empty;
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "connect timed out",
+        )))
+    }
+}
+
+impl Future for ConnectHost {
+    type Output = io::Result<TcpStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<TcpStream>> {
+        let this = &mut *self;
+        loop {
+            match &mut this.state {
+                ConnectHostState::Resolving(rx) => {
+                    let addrs = ready!(Pin::new(rx).poll(cx)).expect("resolver thread panicked")?;
+                    this.addrs = addrs.into_iter();
+                    this.state = ConnectHostState::Next;
                 }
+                ConnectHostState::Connecting(connect) => match ready!(Pin::new(connect).poll(cx)) {
+                    Ok(stream) => return Poll::Ready(Ok(stream)),
+                    Err(e) => {
+                        this.last_error = Some(e);
+                        this.state = ConnectHostState::Next;
+                    }
+                },
+                ConnectHostState::Next => match this.addrs.next() {
+                    Some(addr) => {
+                        this.state = ConnectHostState::Connecting(TcpStream::connect(&addr));
+                    }
+                    None => {
+                        return Poll::Ready(Err(this
+                            .last_error
+                            .take()
+                            .unwrap_or_else(no_addresses)));
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl Future for ConnectHappy {
+    type Output = io::Result<TcpStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<TcpStream>> {
+        let this = &mut *self;
+        if let Some(rx) = &mut this.resolving {
+            let addrs = ready!(Pin::new(rx).poll(cx)).expect("resolver thread panicked");
+            this.resolving = None;
+            this.addrs = interleave_families(addrs?).into_iter();
+        }
 
-                if let Some(e) = stream.io.get_ref().take_error()? {
-                    return Poll::Ready(Err(e));
+        loop {
+            let mut failed = false;
+            let mut i = 0;
+            while i < this.attempts.len() {
+                match Pin::new(&mut this.attempts[i]).poll(cx) {
+                    Poll::Ready(Ok(stream)) => return Poll::Ready(Ok(stream)),
+                    Poll::Ready(Err(e)) => {
+                        this.last_error = Some(e);
+                        drop(this.attempts.swap_remove(i));
+                        failed = true;
+                    }
+                    Poll::Pending => i += 1,
                 }
+            }
 
-                Poll::Ready(Ok(stream))
+            if this.addrs.len() == 0 {
+                if this.attempts.is_empty() {
+                    return Poll::Ready(Err(this.last_error.take().unwrap_or_else(no_addresses)));
+                }
+                return Poll::Pending;
             }
+            if !failed && !this.attempts.is_empty() {
+                ready!(this.next_attempt.poll_elapsed(cx))?;
+            }
+
+            let addr = this.addrs.next().unwrap();
+            this.attempts.push(TcpStream::connect(&addr));
+            this.next_attempt
+                .reset(Instant::now() + CONNECTION_ATTEMPT_DELAY);
+        }
+    }
+}
+
+impl<'a> Future for Peek<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let Peek { stream, buf } = &mut *self;
+        stream.poll_peek(cx, buf)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for ConnectWithData<'a> {
+    type Output = io::Result<TcpStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<TcpStream>> {
+        match mem::replace(&mut self.inner, ConnectFutureState::Empty) {
+            ConnectFutureState::Waiting(mut stream) => match self.poll_send(cx, &mut stream) {
+                Poll::Pending => {
+                    self.inner = ConnectFutureState::Waiting(stream);
+                    Poll::Pending
+                }
+                Poll::Ready(res) => Poll::Ready(res.map(|()| stream)),
+            },
             ConnectFutureState::Error(e) => Poll::Ready(Err(e)),
             ConnectFutureState::Empty => panic!("can't poll TCP stream twice"),
         }
     }
 }
 
+#[cfg(target_os = "linux")]
+impl ConnectWithData<'_> {
+    /// Sends what didn't go out with the SYN, then waits for the connection
+    /// to be established.
+    fn poll_send(&mut self, cx: &mut Context<'_>, stream: &mut TcpStream) -> Poll<io::Result<()>> {
+        // Writes wait for the connection, and fail with its error.
+        while self.sent < self.data.len() {
+            let n = ready!(Pin::new(&mut *stream).poll_write(cx, &self.data[self.sent..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.sent += n;
+        }
+        stream.poll_connect_result(cx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for SendOob<'a> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let SendOob { stream, byte } = &mut *self;
+        stream.poll_send_oob(cx, *byte)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for RecvOob<'a> {
+    type Output = io::Result<u8>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u8>> {
+        self.stream.poll_recv_oob(cx)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for SendFile<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        while this.sent < this.len {
+            let offset = this.offset + this.sent as u64;
+            let len = this.len - this.sent;
+            match ready!(this.stream.poll_send_file(cx, this.file, offset, len))? {
+                0 => break,
+                n => this.sent += n,
+            }
+        }
+        Poll::Ready(Ok(this.sent))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for SpliceTo<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let SpliceTo { stream, pipe, len } = &mut *self;
+        stream.poll_splice_to(cx, pipe, *len)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> Future for SpliceFrom<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let SpliceFrom { stream, pipe, len } = &mut *self;
+        stream.poll_splice_from(cx, pipe, *len)
+    }
+}
+
+/// The future returned by `TcpStream::readable`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Readable<'a> {
+    stream: &'a mut TcpStream,
+}
+
+impl<'a> Future for Readable<'a> {
+    type Output = io::Result<mio::Ready>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_read_ready(cx)
+    }
+}
+
+/// The future returned by `TcpStream::writable`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Writable<'a> {
+    stream: &'a mut TcpStream,
+}
+
+impl<'a> Future for Writable<'a> {
+    type Output = io::Result<mio::Ready>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_write_ready(cx)
+    }
+}
+
+impl<'a> Future for ReadWithTimeout<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if let Poll::Ready(res) = Pin::new(&mut *this.stream).poll_read(cx, this.buf) {
+            return Poll::Ready(res);
+        }
+        ready!(this.delay.poll_elapsed(cx)?);
+        Poll::Ready(Err(timed_out()))
+    }
+}
+
+impl<'a> Future for WriteWithTimeout<'a> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if let Poll::Ready(res) = Pin::new(&mut *this.stream).poll_write(cx, this.buf) {
+            return Poll::Ready(res);
+        }
+        ready!(this.delay.poll_elapsed(cx)?);
+        Poll::Ready(Err(timed_out()))
+    }
+}
+
+impl<'a> Future for ReadPooled<'a> {
+    type Output = io::Result<PooledBuf>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let pool = this.pool;
+        let buf = this.buf.get_or_insert_with(|| pool.get());
+        let n = ready!(Pin::new(&mut *this.stream).poll_read(cx, buf.as_full_mut()))?;
+        let mut buf = this.buf.take().unwrap();
+        buf.set_len(n);
+        Poll::Ready(Ok(buf))
+    }
+}
+
+impl<'a> Future for ReadHeader<'a> {
+    type Output = io::Result<(usize, usize)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        while this.filled < this.buf.len() {
+            let n = match Pin::new(&mut *this.stream).poll_read(cx, &mut this.buf[this.filled..]) {
+                Poll::Ready(res) => res?,
+                Poll::Pending => {
+                    ready!(this.delay.poll_elapsed(cx)?);
+                    return Poll::Ready(Err(timed_out()));
+                }
+            };
+            if n == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before end of header",
+                )));
+            }
+
+            // Only the new bytes, plus enough before them to catch a
+            // delimiter split across reads, need to be searched.
+            let start = this.filled.saturating_sub(this.delimiter.len() - 1);
+            this.filled += n;
+            let found = this.buf[start..this.filled]
+                .windows(this.delimiter.len())
+                .position(|w| w == this.delimiter);
+            if let Some(pos) = found {
+                let header_len = start + pos + this.delimiter.len();
+                return Poll::Ready(Ok((header_len, this.filled)));
+            }
+        }
+
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "header does not fit in buffer",
+        )))
+    }
+}
+
+/// Duplicates an error so it can be both cached and returned.
+fn copy_error(e: &io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(e.kind(), e.to_string()),
+    }
+}
+
+/// Ignores errors from options the platform or the process's privileges
+/// don't allow.
+#[cfg(unix)]
+fn best_effort(res: io::Result<()>) -> io::Result<()> {
+    match res {
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::Unsupported => Ok(()),
+        Err(ref e) if e.raw_os_error() == Some(libc::ENOPROTOOPT) => Ok(()),
+        res => res,
+    }
+}
+
+/// Orders `addrs` for Happy Eyeballs: alternating between IPv6 and IPv4,
+/// starting with the family of the first address, and otherwise keeping
+/// the order of the resolver.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let mut ordered = Vec::with_capacity(addrs.len());
+    let (first, second): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_v6);
+
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => return ordered,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+fn no_addresses() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "could not resolve to any addresses",
+    )
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "operation timed out")
+}
+
 impl std::convert::TryFrom<std::net::TcpStream> for TcpStream {
     type Error = io::Error;
 
     fn try_from(stream: std::net::TcpStream) -> Result<Self, Self::Error> {
-        let tcp = mio::net::TcpStream::from_stream(stream)?;
-        Ok(TcpStream::new(tcp))
+        TcpStream::from_std_nonblocking(stream)
     }
 }
 
@@ -573,14 +2687,29 @@ impl std::convert::TryFrom<&std::net::SocketAddr> for TcpStream {
     type Error = io::Error;
 
     fn try_from(addr: &std::net::SocketAddr) -> Result<Self, Self::Error> {
-        let tcp = mio::net::TcpStream::connect(&addr)?;
-        Ok(TcpStream::new(tcp))
+        let mut stream = TcpStream::connect_nowait(addr)?;
+        // Kept from before `connect_nowait` existed: the stream counts as
+        // connected right away.
+        stream.connect = ConnectState::Connected;
+        Ok(stream)
     }
 }
 
+/// A socket as the constructors of `TcpStream` take it.
+pub(crate) type Tcp = (mio::net::TcpStream, SavedSocket);
+
+/// Prepares a connected `std::net::TcpStream` for `TcpStream::new`.
+pub(crate) fn tcp_from_std(stream: std::net::TcpStream) -> io::Result<Tcp> {
+    let socket = SavedSocket::of(&stream);
+    Ok((mio::net::TcpStream::from_stream(stream)?, socket))
+}
+
 #[cfg(unix)]
 mod sys {
     use super::TcpStream;
+    use crate::socket::SavedSocket;
+    use std::convert::TryFrom;
+    use std::io;
     use std::os::unix::prelude::*;
 
     impl AsRawFd for TcpStream {
@@ -588,4 +2717,118 @@ mod sys {
             self.io.get_ref().as_raw_fd()
         }
     }
+
+    /// Hands the socket over still in non-blocking mode, as
+    /// [`PollEvented::into_inner_lossy`] describes.
+    ///
+    /// [`PollEvented::into_inner_lossy`]: ../raw/struct.PollEvented.html#method.into_inner_lossy
+    impl IntoRawFd for TcpStream {
+        fn into_raw_fd(self) -> RawFd {
+            self.io.into_inner_lossy().into_raw_fd()
+        }
+    }
+
+    /// Takes ownership of a connected socket, and puts it in non-blocking
+    /// mode.
+    impl FromRawFd for TcpStream {
+        unsafe fn from_raw_fd(fd: RawFd) -> TcpStream {
+            // This only fails for descriptors that aren't open, which fail
+            // every later operation as well.
+            let _ = crate::sys::set_nonblocking(fd);
+            let tcp = mio::net::TcpStream::from_raw_fd(fd);
+            let socket = SavedSocket::of(&tcp);
+            TcpStream::new((tcp, socket))
+        }
+    }
+
+    /// Converts the stream with [`TcpStream::into_std`].
+    ///
+    /// [`TcpStream::into_std`]: struct.TcpStream.html#method.into_std
+    impl TryFrom<TcpStream> for std::net::TcpStream {
+        type Error = io::Error;
+
+        fn try_from(stream: TcpStream) -> io::Result<std::net::TcpStream> {
+            stream.into_std()
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::TcpStream;
+    use std::os::windows::prelude::*;
+
+    impl AsRawSocket for TcpStream {
+        fn as_raw_socket(&self) -> RawSocket {
+            self.socket.get()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor;
+    use futures::future::join_all;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn interleave_families_alternates() {
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+        let addrs = vec![
+            addr("[::1]:1"),
+            addr("[::2]:1"),
+            addr("[::3]:1"),
+            addr("10.0.0.1:1"),
+        ];
+        assert_eq!(
+            interleave_families(addrs),
+            [
+                addr("[::1]:1"),
+                addr("10.0.0.1:1"),
+                addr("[::2]:1"),
+                addr("[::3]:1"),
+            ]
+        );
+
+        let addrs = vec![addr("10.0.0.1:1"), addr("10.0.0.2:1"), addr("[::1]:1")];
+        assert_eq!(
+            interleave_families(addrs),
+            [addr("10.0.0.1:1"), addr("[::1]:1"), addr("10.0.0.2:1")]
+        );
+        assert_eq!(interleave_families(vec![]), []);
+    }
+
+    #[test]
+    fn connect_checks_so_error_once() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Nothing listens on a freshly closed port, so these are refused.
+        let refused = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let before = SO_ERROR_CHECKS.load(SeqCst);
+        let connects =
+            (0..32).map(|i| TcpStream::connect(if i % 2 == 0 { &addr } else { &refused }));
+        let results = executor::block_on(join_all(connects));
+        assert_eq!(SO_ERROR_CHECKS.load(SeqCst) - before, 32);
+
+        let mut ok = 0;
+        for res in results {
+            match res {
+                Ok(mut stream) => {
+                    // Asking again is answered from the cache.
+                    let waker = futures::task::noop_waker();
+                    let mut cx = Context::from_waker(&waker);
+                    assert!(stream.poll_connect_result(&mut cx).is_ready());
+                    ok += 1;
+                }
+                Err(e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused),
+            }
+        }
+        assert_eq!(ok, 16);
+        assert_eq!(SO_ERROR_CHECKS.load(SeqCst) - before, 32);
+    }
 }
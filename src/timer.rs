@@ -0,0 +1,424 @@
+//! Timers driven by the reactor.
+//!
+//! Deadlines are kept by the same reactor that dispatches I/O readiness: it
+//! never blocks past the earliest pending deadline, and wakes the tasks whose
+//! deadlines have passed after every turn. So timers need no thread of their
+//! own, and firing one doesn't involve a cross-thread wakeup beyond the one
+//! I/O events already use.
+//!
+//! * [`Delay`] is a future that completes at a given instant.
+//! * [`Interval`] is a stream that yields at a fixed period.
+//! * [`Timeout`] bounds how long an I/O future may take.
+//! * [`TimeoutIo`] bounds how long each read or write on a stream or socket
+//!   may wait.
+//!
+//! [`Delay`]: struct.Delay.html
+//! [`Interval`]: struct.Interval.html
+//! [`Timeout`]: struct.Timeout.html
+//! [`TimeoutIo`]: struct.TimeoutIo.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use futures::prelude::*;
+//! use romio::tcp::TcpStream;
+//! use romio::timer::{Interval, Timeout};
+//! use std::time::Duration;
+//!
+//! # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+//! let addr = "127.0.0.1:8080".parse()?;
+//! let mut stream = Timeout::new(TcpStream::connect(&addr), Duration::from_secs(3)).await?;
+//!
+//! let mut ticks = Interval::new_interval(Duration::from_secs(1));
+//! while let Some(tick) = ticks.next().await {
+//!     tick?;
+//!     stream.write_all(b"ping\n").await?;
+//! }
+//! # Ok(())}
+//! ```
+
+use std::future::Future;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use async_datagram::AsyncDatagram;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Stream};
+
+pub use crate::reactor::timer::Delay;
+
+/// A stream that yields the instant of each tick, one every `period`.
+///
+/// If the stream isn't polled for longer than a period, for example because
+/// the task is busy, the missed ticks are skipped rather than delivered in a
+/// burst: the next tick comes one period after the late one.
+///
+/// Yields an error if the reactor driving it has gone away.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Interval {
+    delay: Delay,
+    period: Duration,
+}
+
+impl Interval {
+    /// Creates an interval whose first tick is at `start` and that ticks
+    /// every `period` afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn new(start: Instant, period: Duration) -> Interval {
+        assert!(
+            period > Duration::from_secs(0),
+            "interval period must be non-zero"
+        );
+        Interval {
+            delay: Delay::new(start),
+            period,
+        }
+    }
+
+    /// Creates an interval whose first tick is one `period` from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn new_interval(period: Duration) -> Interval {
+        Interval::new(Instant::now() + period, period)
+    }
+}
+
+impl Stream for Interval {
+    type Item = io::Result<Instant>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        ready!(self.delay.poll_elapsed(cx))?;
+        let tick = self.delay.deadline();
+        let now = Instant::now();
+        let mut next = tick + self.period;
+        if next <= now {
+            next = now + self.period;
+        }
+        self.delay.reset(next);
+        Poll::Ready(Some(Ok(tick)))
+    }
+}
+
+/// Fails an I/O future with `io::ErrorKind::TimedOut` if it doesn't complete
+/// before a deadline.
+///
+/// This wraps any future that resolves to an `io::Result`, such as a connect
+/// or a read. The wrapped future is polled first, so if it is ready at the
+/// same time as the deadline passes, its result wins. When the deadline
+/// passes first, the wrapped future is left unfinished; dropping the
+/// `Timeout` drops it, which cancels the operation.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Timeout<F> {
+    future: F,
+    delay: Delay,
+}
+
+impl<F> Timeout<F> {
+    /// Wraps `future`, failing it if it takes longer than `timeout`.
+    pub fn new(future: F, timeout: Duration) -> Timeout<F> {
+        Timeout::new_at(future, Instant::now() + timeout)
+    }
+
+    /// Wraps `future`, failing it if it hasn't completed at `deadline`.
+    pub fn new_at(future: F, deadline: Instant) -> Timeout<F> {
+        Timeout {
+            future,
+            delay: Delay::new(deadline),
+        }
+    }
+
+    /// Returns a reference to the wrapped future.
+    pub fn get_ref(&self) -> &F {
+        &self.future
+    }
+
+    /// Returns a mutable reference to the wrapped future.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.future
+    }
+
+    /// Consumes the `Timeout`, returning the wrapped future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F, T> Future for Timeout<F>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    type Output = io::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // The future is structurally pinned and never moved out; the delay
+        // is `Unpin`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if let Poll::Ready(res) = future.poll(cx) {
+            return Poll::Ready(res);
+        }
+        ready!(this.delay.poll_elapsed(cx))?;
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "operation timed out",
+        )))
+    }
+}
+
+/// Wraps an I/O object, failing reads and writes that make no progress
+/// within a timeout with `io::ErrorKind::TimedOut`.
+///
+/// This works with anything that implements `AsyncRead`, `AsyncWrite` or
+/// `AsyncDatagram`, such as `TcpStream`, `UnixStream` and `UdpSocket`. The
+/// timeouts apply per operation: the clock for a read starts when it first
+/// has to wait, and stops once it completes, so a connection that is merely
+/// slow never times out as long as each read makes progress. The same goes
+/// for writes, flushes and closes with the write timeout.
+///
+/// A read that is dropped before completing, for instance because it lost a
+/// `select!`, can't be told apart from the same read being polled again, so
+/// the next read from the same task keeps its clock running. Call
+/// [`reset_read_timeout`] before that read to give it a timeout of its own;
+/// a read from another task gets one anyway. Likewise for writes, with
+/// [`reset_write_timeout`].
+///
+/// A timed out operation can be retried; whether that makes sense depends
+/// on the operation. A read that timed out consumed nothing, for instance,
+/// while a timed out `write_all` may have written part of its buffer.
+///
+/// Both timeouts are off initially.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use futures::prelude::*;
+/// use romio::tcp::TcpStream;
+/// use romio::timer::TimeoutIo;
+/// use std::time::Duration;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let addr = "127.0.0.1:8080".parse()?;
+/// let mut stream = TimeoutIo::new(TcpStream::connect(&addr).await?);
+/// stream.set_read_timeout(Some(Duration::from_secs(30)));
+///
+/// let mut request = [0; 512];
+/// // Fails with `TimedOut` if the peer stalls for 30 seconds.
+/// stream.read_exact(&mut request).await?;
+/// # Ok(())}
+/// ```
+///
+/// [`reset_read_timeout`]: #method.reset_read_timeout
+/// [`reset_write_timeout`]: #method.reset_write_timeout
+#[derive(Debug)]
+pub struct TimeoutIo<S> {
+    inner: S,
+    read: Deadline,
+    write: Deadline,
+}
+
+/// The timeout of one direction of a `TimeoutIo`.
+#[derive(Debug, Default)]
+struct Deadline {
+    timeout: Option<Duration>,
+    /// Armed while an operation is waiting, along with the waker of the task
+    /// that armed it.
+    wait: Option<(Delay, Waker)>,
+}
+
+impl Deadline {
+    fn set(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+        self.wait = None;
+    }
+
+    /// Passes on the result of polling an operation, turning `Pending` into
+    /// a timeout error once the operation has waited too long.
+    fn poll<T>(&mut self, cx: &mut Context<'_>, res: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        if res.is_ready() {
+            self.wait = None;
+            return res;
+        }
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return Poll::Pending,
+        };
+        // Only one task waits on each direction, so a wait armed by another
+        // task belongs to an operation that was given up.
+        let same_task = match &self.wait {
+            Some((_, waker)) => waker.will_wake(cx.waker()),
+            None => false,
+        };
+        if !same_task {
+            let delay = Delay::new(Instant::now() + timeout);
+            self.wait = Some((delay, cx.waker().clone()));
+        }
+        let (delay, _) = self.wait.as_mut().unwrap();
+        ready!(delay.poll_elapsed(cx))?;
+        self.wait = None;
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "operation timed out",
+        )))
+    }
+}
+
+impl<S> TimeoutIo<S> {
+    /// Wraps `inner`, with no timeouts set.
+    pub fn new(inner: S) -> TimeoutIo<S> {
+        TimeoutIo {
+            inner,
+            read: Deadline::default(),
+            write: Deadline::default(),
+        }
+    }
+
+    /// Returns the read timeout.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read.timeout
+    }
+
+    /// Sets the read timeout, or turns it off with `None`.
+    ///
+    /// A read that is already waiting starts over with the new timeout.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read.set(timeout);
+    }
+
+    /// Starts the read timeout over, for a read that replaces one that was
+    /// dropped while waiting.
+    pub fn reset_read_timeout(&mut self) {
+        self.read.wait = None;
+    }
+
+    /// Returns the write timeout.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write.timeout
+    }
+
+    /// Sets the write timeout, or turns it off with `None`.
+    ///
+    /// A write that is already waiting starts over with the new timeout.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write.set(timeout);
+    }
+
+    /// Starts the write timeout over, for a write that replaces one that was
+    /// dropped while waiting.
+    pub fn reset_write_timeout(&mut self) {
+        self.write.wait = None;
+    }
+
+    /// Returns a reference to the wrapped I/O object.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped I/O object.
+    ///
+    /// Operations on it directly aren't subject to the timeouts.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consumes the `TimeoutIo`, returning the wrapped I/O object.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TimeoutIo<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_read(cx, buf);
+        this.read.poll(cx, res)
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_read_vectored(cx, bufs);
+        this.read.poll(cx, res)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for TimeoutIo<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_write(cx, buf);
+        this.write.poll(cx, res)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_write_vectored(cx, bufs);
+        this.write.poll(cx, res)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_flush(cx);
+        this.write.poll(cx, res)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_close(cx);
+        this.write.poll(cx, res)
+    }
+}
+
+impl<S> AsyncDatagram for TimeoutIo<S>
+where
+    S: AsyncDatagram<Err = io::Error> + Unpin,
+{
+    type Sender = S::Sender;
+    type Receiver = S::Receiver;
+    type Err = io::Error;
+
+    fn poll_send_to(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        receiver: &Self::Receiver,
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_send_to(cx, buf, receiver);
+        this.write.poll(cx, res)
+    }
+
+    fn poll_recv_from(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, Self::Sender)>> {
+        let this = &mut *self;
+        let res = Pin::new(&mut this.inner).poll_recv_from(cx, buf);
+        this.read.poll(cx, res)
+    }
+}
@@ -0,0 +1,435 @@
+//! TLS over romio streams, with rustls.
+//!
+//! This module is only available with the `rustls` feature enabled.
+//!
+//! A [`TlsConnector`] starts the client side of a session over any stream
+//! that implements `AsyncRead` and `AsyncWrite`, and a [`TlsAcceptor`] the
+//! server side. Both return a future that drives the handshake, reading and
+//! writing as the underlying stream becomes ready, and resolves to a
+//! [`TlsStream`] that encrypts what is written to it and decrypts what is
+//! read from it.
+//!
+//! The connector and the acceptor wrap a `rustls::ClientConfig` or a
+//! `rustls::ServerConfig`, which hold the certificates, the keys and every
+//! other setting of the sessions; see the rustls documentation for how to
+//! build them.
+//!
+//! [`TlsConnector`]: struct.TlsConnector.html
+//! [`TlsAcceptor`]: struct.TlsAcceptor.html
+//! [`TlsStream`]: struct.TlsStream.html
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! #![feature(async_await)]
+//! use futures::prelude::*;
+//! use romio::tcp::TcpStream;
+//! use romio::tls::TlsConnector;
+//! use std::sync::Arc;
+//!
+//! # async fn run(config: rustls::ClientConfig) -> std::io::Result<()> {
+//! let connector = TlsConnector::from(Arc::new(config));
+//!
+//! let stream = TcpStream::connect_host("example.com:443").await?;
+//! let mut stream = connector.connect("example.com", stream).await?;
+//! stream.write_all(b"GET / HTTP/1.0\r\nHost: example.com\r\n\r\n").await?;
+//!
+//! let mut response = Vec::new();
+//! stream.read_to_end(&mut response).await?;
+//! # Ok(())}
+//! ```
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Future};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, Connection, ServerConfig, ServerConnection};
+
+/// Starts the client side of TLS sessions.
+///
+/// Connectors are cheap to clone, and clones share their configuration.
+#[derive(Clone)]
+pub struct TlsConnector {
+    config: Arc<ClientConfig>,
+}
+
+/// Starts the server side of TLS sessions.
+///
+/// Acceptors are cheap to clone, and clones share their configuration.
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    config: Arc<ServerConfig>,
+}
+
+/// A TLS session over a stream.
+///
+/// Writes are encrypted into TLS records that are sent on the underlying
+/// stream as it becomes writable; `flush` returns once all of them have been
+/// sent. Closing the stream sends a `close_notify` alert before closing the
+/// underlying stream, and reads return `Ok(0)` once the peer has sent one. A
+/// peer that closes the connection without it makes reads fail with
+/// `io::ErrorKind::UnexpectedEof`, since the data may have been truncated.
+pub struct TlsStream<S> {
+    io: S,
+    conn: Connection,
+    /// Set once `close_notify` has been queued.
+    closing: bool,
+}
+
+/// The future returned by `TlsConnector::connect`, which will resolve to a
+/// `TlsStream` once the handshake has completed.
+#[must_use = "futures do nothing unless polled"]
+pub struct Connect<S>(Handshake<S>);
+
+/// The future returned by `TlsAcceptor::accept`, which will resolve to a
+/// `TlsStream` once the handshake has completed.
+#[must_use = "futures do nothing unless polled"]
+pub struct Accept<S>(Handshake<S>);
+
+enum Handshake<S> {
+    Running(Box<TlsStream<S>>),
+    Failed(io::Error),
+    Done,
+}
+
+// ===== impl TlsConnector =====
+
+impl TlsConnector {
+    /// Starts a session with the server at the other end of `stream`.
+    ///
+    /// `domain` is the name the certificate of the server is checked
+    /// against, and is also sent to the server with the SNI extension, if
+    /// that is enabled in the configuration. It can also be an IP address.
+    ///
+    /// # Errors
+    ///
+    /// The future fails with `io::ErrorKind::InvalidInput` if `domain` is
+    /// neither a valid DNS name nor an IP address, with
+    /// `io::ErrorKind::InvalidData` if the handshake fails, e.g. because the
+    /// certificate of the server isn't trusted, and otherwise with the error
+    /// of the underlying stream.
+    pub fn connect<S>(&self, domain: &str, stream: S) -> Connect<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let conn = ServerName::try_from(domain.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+            .and_then(|name| {
+                ClientConnection::new(self.config.clone(), name).map_err(invalid_data)
+            });
+        match conn {
+            Ok(conn) => Connect(Handshake::Running(Box::new(TlsStream::new(
+                stream,
+                conn.into(),
+            )))),
+            Err(e) => Connect(Handshake::Failed(e)),
+        }
+    }
+}
+
+impl From<Arc<ClientConfig>> for TlsConnector {
+    fn from(config: Arc<ClientConfig>) -> TlsConnector {
+        TlsConnector { config }
+    }
+}
+
+impl fmt::Debug for TlsConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector").finish()
+    }
+}
+
+// ===== impl TlsAcceptor =====
+
+impl TlsAcceptor {
+    /// Starts a session with the client at the other end of `stream`.
+    ///
+    /// # Errors
+    ///
+    /// The future fails with `io::ErrorKind::InvalidData` if the handshake
+    /// fails, e.g. because the client doesn't support any of the protocol
+    /// versions or cipher suites of the configuration, and otherwise with
+    /// the error of the underlying stream.
+    pub fn accept<S>(&self, stream: S) -> Accept<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        match ServerConnection::new(self.config.clone()) {
+            Ok(conn) => Accept(Handshake::Running(Box::new(TlsStream::new(
+                stream,
+                conn.into(),
+            )))),
+            Err(e) => Accept(Handshake::Failed(invalid_data(e))),
+        }
+    }
+}
+
+impl From<Arc<ServerConfig>> for TlsAcceptor {
+    fn from(config: Arc<ServerConfig>) -> TlsAcceptor {
+        TlsAcceptor { config }
+    }
+}
+
+impl fmt::Debug for TlsAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsAcceptor").finish()
+    }
+}
+
+// ===== impl Connect, Accept =====
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Future for Connect<S> {
+    type Output = io::Result<TlsStream<S>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll(cx)
+    }
+}
+
+impl<S> fmt::Debug for Connect<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connect").finish()
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Future for Accept<S> {
+    type Output = io::Result<TlsStream<S>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll(cx)
+    }
+}
+
+impl<S> fmt::Debug for Accept<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Accept").finish()
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Handshake<S> {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<TlsStream<S>>> {
+        match self {
+            Handshake::Running(stream) => ready!(stream.poll_handshake(cx))?,
+            Handshake::Failed(_) => {}
+            Handshake::Done => panic!("polled TLS handshake after completion"),
+        }
+        match std::mem::replace(self, Handshake::Done) {
+            Handshake::Running(stream) => Poll::Ready(Ok(*stream)),
+            Handshake::Failed(e) => Poll::Ready(Err(e)),
+            Handshake::Done => unreachable!(),
+        }
+    }
+}
+
+// ===== impl TlsStream =====
+
+impl<S> TlsStream<S> {
+    fn new(io: S, conn: Connection) -> TlsStream<S> {
+        TlsStream {
+            io,
+            conn,
+            closing: false,
+        }
+    }
+
+    /// Returns a shared reference to the underlying stream and the rustls
+    /// session, e.g. to look up the negotiated ALPN protocol.
+    pub fn get_ref(&self) -> (&S, &Connection) {
+        (&self.io, &self.conn)
+    }
+
+    /// Returns a mutable reference to the underlying stream and the rustls
+    /// session.
+    ///
+    /// Reading from or writing to the underlying stream directly corrupts
+    /// the session.
+    pub fn get_mut(&mut self) -> (&mut S, &mut Connection) {
+        (&mut self.io, &mut self.conn)
+    }
+
+    /// Consumes the `TlsStream`, returning the underlying stream and the
+    /// rustls session.
+    ///
+    /// Records that were still buffered in the session are not sent.
+    pub fn into_inner(self) -> (S, Connection) {
+        (self.io, self.conn)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TlsStream<S> {
+    /// Reads TLS records from the underlying stream into the session,
+    /// returning how many bytes were read; zero at the end of the stream.
+    fn read_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let n = match self.conn.read_tls(&mut SyncIo {
+            io: &mut self.io,
+            cx,
+        }) {
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        if let Err(e) = self.conn.process_new_packets() {
+            // Try to tell the peer why the session ends, if there is room.
+            drop(self.write_tls(cx));
+            return Poll::Ready(Err(invalid_data(e)));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    /// Writes TLS records from the session to the underlying stream.
+    fn write_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.conn.write_tls(&mut SyncIo {
+            io: &mut self.io,
+            cx,
+        }) {
+            Ok(0) => Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+            Ok(_) => Poll::Ready(Ok(())),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Writes all TLS records that are buffered in the session.
+    fn poll_write_tls(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.conn.wants_write() {
+            ready!(self.write_tls(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Writes as many buffered TLS records as the underlying stream takes
+    /// without blocking.
+    fn try_write_tls(&mut self, cx: &mut Context<'_>) -> io::Result<()> {
+        match self.poll_write_tls(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Ok(()),
+        }
+    }
+
+    fn poll_handshake(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.poll_write_tls(cx))?;
+            if !self.conn.is_handshaking() {
+                return Pin::new(&mut self.io).poll_flush(cx);
+            }
+            if ready!(self.read_tls(cx))? == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed during TLS handshake",
+                )));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for TlsStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        loop {
+            match this.conn.reader().read(buf) {
+                Ok(n) => return Poll::Ready(Ok(n)),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+            // Once the end of the underlying stream has been read the session
+            // reports it, with either `Ok(0)` or `UnexpectedEof`.
+            ready!(this.read_tls(cx))?;
+            // Reading may have queued records, e.g. key updates.
+            this.try_write_tls(cx)?;
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for TlsStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        loop {
+            let n = this.conn.writer().write(buf)?;
+            if n > 0 || buf.is_empty() {
+                this.try_write_tls(cx)?;
+                return Poll::Ready(Ok(n));
+            }
+            // The session buffers as much as it allows; make room for more.
+            ready!(this.write_tls(cx))?;
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        this.conn.writer().flush()?;
+        ready!(this.poll_write_tls(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if !this.closing {
+            this.conn.send_close_notify();
+            this.closing = true;
+        }
+        ready!(this.poll_write_tls(cx))?;
+        Pin::new(&mut this.io).poll_close(cx)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TlsStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsStream")
+            .field("io", &self.io)
+            .field("handshaking", &self.conn.is_handshaking())
+            .finish()
+    }
+}
+
+/// Adapts a nonblocking stream to `std::io::{Read, Write}` for rustls,
+/// turning `Poll::Pending` into `io::ErrorKind::WouldBlock`.
+struct SyncIo<'a, 'b, S> {
+    io: &'a mut S,
+    cx: &'a mut Context<'b>,
+}
+
+impl<S: AsyncRead + Unpin> Read for SyncIo<'_, '_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.io).poll_read(self.cx, buf) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Write for SyncIo<'_, '_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match Pin::new(&mut *self.io).poll_write(self.cx, buf) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match Pin::new(&mut *self.io).poll_flush(self.cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+fn invalid_data(e: rustls::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
@@ -14,8 +14,11 @@
 use std::convert::TryFrom;
 use std::fmt;
 use std::io;
+#[cfg(unix)]
+use std::net::{IpAddr, SocketAddrV4, SocketAddrV6};
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::Context;
 
 use async_datagram::AsyncDatagram;
@@ -24,13 +27,41 @@ use futures::Future;
 use futures::{ready, Poll};
 use mio;
 
-use crate::raw::PollEvented;
+use crate::raw::{Handle, PollEvented};
+use crate::socket::SavedSocket;
 
 /// A UDP socket.
 pub struct UdpSocket {
     io: PollEvented<mio::net::UdpSocket>,
+    /// Default destination set by `connect_roaming`.
+    roaming_peer: Option<SocketAddr>,
+    /// Only read on Windows, for `AsRawSocket`.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    socket: SavedSocket,
 }
 
+/// What the control messages of a datagram received with
+/// `UdpSocket::recv_from_with_meta` tell about it.
+///
+/// Each field is only filled in when the option that asks for it is enabled
+/// on the socket.
+#[cfg(target_os = "linux")]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct RecvMeta {
+    /// The address the datagram was sent to, with `set_recv_pktinfo`.
+    pub dst: Option<IpAddr>,
+    /// The index of the interface the datagram arrived on, with
+    /// `set_recv_pktinfo`.
+    pub interface: Option<u32>,
+    /// The TTL or hop limit of the datagram, with `set_recv_ttl`.
+    pub ttl: Option<u8>,
+    /// The ECN codepoint of the datagram, with `set_recv_ecn`.
+    pub ecn: Option<u8>,
+}
+
+/// A socket as the constructors of `UdpSocket` take it.
+type Udp = (mio::net::UdpSocket, SavedSocket);
+
 impl UdpSocket {
     /// Creates a UDP socket from the given address.
     ///
@@ -52,12 +83,138 @@ impl UdpSocket {
     /// # }
     /// ```
     pub fn bind(addr: &SocketAddr) -> io::Result<UdpSocket> {
-        mio::net::UdpSocket::bind(addr).map(UdpSocket::new)
+        bind_socket(addr).map(UdpSocket::new)
+    }
+
+    /// Creates a UDP socket bound to the reactor of `handle`, and binds it
+    /// to the given address.
+    ///
+    /// The socket is driven by that reactor, rather than by the one that is
+    /// current when it is first polled. See [`bind`] for the rest.
+    ///
+    /// [`bind`]: #method.bind
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::raw::Reactor;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let reactor = Reactor::new()?;
+    /// let socket_addr = "127.0.0.1:0".parse()?;
+    /// let socket = UdpSocket::bind_on(&reactor.handle(), &socket_addr)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_on(handle: &Handle, addr: &SocketAddr) -> io::Result<UdpSocket> {
+        let (io, socket) = bind_socket(addr)?;
+        Ok(UdpSocket {
+            io: PollEvented::new_with_handle(io, handle)?,
+            roaming_peer: None,
+            socket,
+        })
+    }
+
+    /// Creates a UDP socket that receives datagrams sent to an IPv4 multicast
+    /// group.
+    ///
+    /// The socket has `SO_REUSEADDR` set so several receivers can share the
+    /// group and port, is bound the way the platform expects, and joins
+    /// `group` on `interface`. Linux and Android deliver group traffic to
+    /// sockets bound to `INADDR_ANY`, so the socket is bound to the
+    /// unspecified address there. Other platforms bind to the group address
+    /// itself, which also keeps unrelated unicast traffic on the same port
+    /// away from the socket.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::udp::UdpSocket;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let group = "239.255.0.1:5000".parse()?;
+    /// let socket = UdpSocket::bind_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn bind_multicast_v4(group: &SocketAddrV4, interface: &Ipv4Addr) -> io::Result<UdpSocket> {
+        let bind_ip = if BIND_UNSPECIFIED_FOR_MULTICAST {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            *group.ip()
+        };
+        let bind_addr = SocketAddr::V4(SocketAddrV4::new(bind_ip, group.port()));
+        let socket = multicast_socket(libc::AF_INET, &bind_addr)?;
+        socket.join_multicast_v4(group.ip(), interface)?;
+        UdpSocket::try_from(socket)
+    }
+
+    /// Creates a UDP socket that receives datagrams sent to an IPv6 multicast
+    /// group.
+    ///
+    /// This is the IPv6 counterpart of [`bind_multicast_v4`]; `interface` is
+    /// the index of the interface to join on, or 0 to let the system choose.
+    ///
+    /// [`bind_multicast_v4`]: #method.bind_multicast_v4
+    #[cfg(unix)]
+    pub fn bind_multicast_v6(group: &SocketAddrV6, interface: u32) -> io::Result<UdpSocket> {
+        let bind_addr = if BIND_UNSPECIFIED_FOR_MULTICAST {
+            SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, group.port(), 0, 0)
+        } else {
+            // Group addresses with link or interface scope need the interface
+            // to be named.
+            SocketAddrV6::new(*group.ip(), group.port(), 0, interface)
+        };
+        let bind_addr = SocketAddr::V6(bind_addr);
+        let socket = multicast_socket(libc::AF_INET6, &bind_addr)?;
+        socket.join_multicast_v6(group.ip(), interface)?;
+        UdpSocket::try_from(socket)
+    }
+
+    /// Converts this socket into a blocking `std::net::UdpSocket`.
+    ///
+    /// The socket is deregistered from the reactor and put back into
+    /// blocking mode. Datagrams it has already received stay queued, and are
+    /// received by the returned socket. A default destination set with
+    /// [`connect_roaming`] is not carried over.
+    ///
+    /// [`connect_roaming`]: #method.connect_roaming
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let socket_addr = "127.0.0.1:0".parse()?;
+    /// let socket = UdpSocket::bind(&socket_addr)?;
+    ///
+    /// let socket = socket.into_std()?;
+    /// let mut buf = [0; 1024];
+    /// let (n, peer) = socket.recv_from(&mut buf)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn into_std(self) -> io::Result<std::net::UdpSocket> {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+        let fd = self.io.into_inner()?.into_raw_fd();
+        let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+        socket.set_nonblocking(false)?;
+        Ok(socket)
     }
 
-    fn new(socket: mio::net::UdpSocket) -> UdpSocket {
-        let io = PollEvented::new(socket);
-        UdpSocket { io: io }
+    fn new((io, socket): Udp) -> UdpSocket {
+        let io = PollEvented::new(io);
+        UdpSocket {
+            io,
+            roaming_peer: None,
+            socket,
+        }
     }
 
     /// Returns the local address that this listener is bound to.
@@ -107,6 +264,21 @@ impl UdpSocket {
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// Unlike writing an empty buffer to a stream, sending an empty `buf`
+    /// is not a no-op: it sends a zero-length datagram, which the receiver
+    /// sees as a 0-byte `recv_from`.
+    ///
+    /// # Errors
+    ///
+    /// A datagram that is too large to be sent in one piece, because it
+    /// exceeds the send buffer or the path MTU while fragmentation is
+    /// disabled, fails with `EMSGSIZE`. That error is reported with kind
+    /// [`io::ErrorKind::InvalidInput`], wrapping the original OS error, so it
+    /// can be told apart from other failures and retried with a smaller
+    /// payload. Nothing is sent in that case.
+    ///
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
     pub fn send_to<'a, 'b>(&'a mut self, buf: &'b [u8], target: &'b SocketAddr) -> SendTo<'a, 'b> {
         SendTo {
             buf,
@@ -138,6 +310,443 @@ impl UdpSocket {
         RecvFrom { buf, socket: self }
     }
 
+    /// Receives a datagram without removing it from the queue. On success,
+    /// returns the number of bytes read and the address it came from.
+    ///
+    /// The next receive returns the same datagram. This lets a demultiplexer
+    /// look at the sender, and at a header at the start of the payload, to
+    /// find the task that owns the datagram, which then receives it. Like
+    /// `recv_from`, this copies at most `buf.len()` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn route() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"0.0.0.0:4433".parse()?)?;
+    /// let mut header = [0; 1];
+    ///
+    /// let (_, peer) = socket.peek_from(&mut header).await?;
+    /// println!("next datagram is from {}", peer);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn peek_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> PeekFrom<'a, 'b> {
+        PeekFrom { buf, socket: self }
+    }
+
+    #[cfg(unix)]
+    fn poll_peek_from(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        use std::os::unix::io::AsRawFd;
+
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match crate::sys::peek_from(self.as_raw_fd(), buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Connects the socket to a remote address.
+    ///
+    /// Afterwards [`send`] sends to `addr` and [`recv`] receives from it.
+    /// The kernel discards datagrams from any other source, which also means
+    /// a connected socket never notices that its peer moved to a new address,
+    /// e.g. after a NAT rebinding. To follow a peer that may migrate, use
+    /// [`connect_roaming`] instead.
+    ///
+    /// [`send`]: #method.send
+    /// [`recv`]: #method.recv
+    /// [`connect_roaming`]: #method.connect_roaming
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn ping() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse()?)?;
+    /// socket.connect(&"127.0.0.1:7878".parse()?)?;
+    ///
+    /// socket.send(b"ping").await?;
+    /// let mut buf = vec![0; 1024];
+    /// let n = socket.recv(&mut buf).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn connect(&mut self, addr: &SocketAddr) -> io::Result<()> {
+        self.io.get_ref().connect(*addr)?;
+        self.roaming_peer = None;
+        Ok(())
+    }
+
+    /// Sets the default destination of the socket without filtering what it
+    /// receives.
+    ///
+    /// After this [`send`] sends to `addr`, but unlike with [`connect`] the
+    /// socket keeps receiving from every source and [`recv_from`] reports the
+    /// actual sender of each datagram. When a datagram from the peer arrives
+    /// from a new address, for example because a NAT rebound its mapping or a
+    /// mobile client changed networks, the application can validate it and
+    /// call `connect_roaming` again to follow the peer. This is the shape of
+    /// connection migration in protocols like QUIC.
+    ///
+    /// If the socket was connected with [`connect`], it is disconnected first.
+    ///
+    /// [`send`]: #method.send
+    /// [`connect`]: #method.connect
+    /// [`recv_from`]: #method.recv_from
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn follow() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut peer = "127.0.0.1:7878".parse()?;
+    /// let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse()?)?;
+    /// socket.connect_roaming(&peer)?;
+    ///
+    /// let mut buf = vec![0; 1024];
+    /// loop {
+    ///     let (n, from) = socket.recv_from(&mut buf).await?;
+    ///     if from != peer {
+    ///         // authenticate the datagram before trusting the new path
+    ///         peer = from;
+    ///         socket.connect_roaming(&peer)?;
+    ///     }
+    ///     socket.send(&buf[..n]).await?;
+    /// }
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn connect_roaming(&mut self, addr: &SocketAddr) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let fd = self.as_raw_fd();
+        if crate::sys::is_connected(fd)? {
+            crate::sys::disconnect(fd)?;
+        }
+        self.roaming_peer = Some(*addr);
+        Ok(())
+    }
+
+    /// Sends data to the address set by [`connect`] or [`connect_roaming`].
+    /// On success, returns the number of bytes written.
+    ///
+    /// Errors are reported as for [`send_to`].
+    ///
+    /// [`connect`]: #method.connect
+    /// [`connect_roaming`]: #method.connect_roaming
+    /// [`send_to`]: #method.send_to
+    pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SendFuture<'a, 'b> {
+        SendFuture { buf, socket: self }
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes
+    /// read.
+    ///
+    /// On a socket connected with [`connect`] only datagrams from the peer
+    /// are received. After [`connect_roaming`] datagrams from any source are,
+    /// so use [`recv_from`] there to learn who sent them.
+    ///
+    /// [`connect`]: #method.connect
+    /// [`connect_roaming`]: #method.connect_roaming
+    /// [`recv_from`]: #method.recv_from
+    pub fn recv<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFuture<'a, 'b> {
+        RecvFuture { buf, socket: self }
+    }
+
+    /// Splits the socket into a receiving half and a sending half, which can
+    /// be used from different tasks.
+    ///
+    /// Both halves refer to the same socket, and no lock is taken to use
+    /// either: sending and receiving on a UDP socket are independent, so a
+    /// receive loop and a send loop can each own one half and run in
+    /// parallel. The socket is closed once both halves are dropped, or can
+    /// be put back together with [`RecvHalf::reunite`].
+    ///
+    /// The halves keep the socket's default destination, so [`SendHalf::send`]
+    /// works as [`send`] does.
+    ///
+    /// [`RecvHalf::reunite`]: struct.RecvHalf.html#method.reunite
+    /// [`SendHalf::send`]: struct.SendHalf.html#method.send
+    /// [`send`]: #method.send
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    /// use std::thread;
+    ///
+    /// # fn main() -> Result<(), Box<dyn Error + 'static>> {
+    /// let socket = UdpSocket::bind(&"0.0.0.0:7777".parse()?)?;
+    /// let (mut recv, mut send) = socket.split();
+    ///
+    /// thread::spawn(move || {
+    ///     futures::executor::block_on(async {
+    ///         let mut buf = vec![0; 1500];
+    ///         while let Ok((n, from)) = recv.recv_from(&mut buf).await {
+    ///             println!("{} bytes from {}", n, from);
+    ///         }
+    ///     })
+    /// });
+    ///
+    /// futures::executor::block_on(async {
+    ///     let peer = "10.0.0.2:7777".parse().unwrap();
+    ///     send.send_to(b"state update", &peer).await
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split(self) -> (RecvHalf, SendHalf) {
+        let inner = Arc::new(self);
+        let recv = RecvHalf {
+            inner: inner.clone(),
+        };
+        (recv, SendHalf { inner })
+    }
+
+    /// Polls the socket for read readiness, for code that makes its own
+    /// syscalls on its descriptor.
+    ///
+    /// This works like [`PollEvented::poll_read_ready`], which describes when
+    /// to clear the readiness again.
+    ///
+    /// # Examples
+    ///
+    /// Receiving with `recv(2)` directly, to pass flags `recv_from` doesn't:
+    ///
+    /// ```rust,no_run
+    /// use futures::ready;
+    /// use romio::udp::UdpSocket;
+    /// use std::io;
+    /// use std::os::unix::io::AsRawFd;
+    /// use std::task::{Context, Poll};
+    ///
+    /// fn poll_recv_trunc(
+    ///     socket: &UdpSocket,
+    ///     cx: &mut Context<'_>,
+    ///     buf: &mut [u8],
+    /// ) -> Poll<io::Result<usize>> {
+    ///     ready!(socket.poll_read_ready(cx))?;
+    ///     let fd = socket.as_raw_fd();
+    ///     let ptr = buf.as_mut_ptr() as *mut libc::c_void;
+    ///     let n = unsafe { libc::recv(fd, ptr, buf.len(), libc::MSG_TRUNC) };
+    ///     if n >= 0 {
+    ///         return Poll::Ready(Ok(n as usize));
+    ///     }
+    ///     let err = io::Error::last_os_error();
+    ///     if err.kind() == io::ErrorKind::WouldBlock {
+    ///         socket.clear_read_ready(cx)?;
+    ///         return Poll::Pending;
+    ///     }
+    ///     Poll::Ready(Err(err))
+    /// }
+    /// ```
+    ///
+    /// [`PollEvented::poll_read_ready`]: ../raw/struct.PollEvented.html#method.poll_read_ready
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    /// Polls the socket for write readiness, for code that makes its own
+    /// syscalls on its descriptor.
+    ///
+    /// This works like [`poll_read_ready`], for writes.
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+
+    /// Clears the read readiness of the socket, and arranges for the current
+    /// task to be woken once it is readable again.
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_read_ready(cx)
+    }
+
+    /// Clears the write readiness of the socket, and arranges for the current
+    /// task to be woken once it is writable again.
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_write_ready(cx)
+    }
+
+    /// Sends a batch of datagrams, each to its own address. On success,
+    /// returns the number of datagrams sent, which is always `msgs.len()`.
+    ///
+    /// On Linux the datagrams are handed to the kernel with as few
+    /// `sendmmsg` calls as possible. When the send buffer fills up partway
+    /// through a batch, the returned future waits for the socket to become
+    /// writable again and resumes with the first datagram that wasn't
+    /// accepted, so every datagram is sent exactly once and in order.
+    ///
+    /// # Errors
+    ///
+    /// Errors are reported as for [`send_to`]. The datagrams before the one
+    /// that failed have been sent; [`SendToMany::sent`] tells how many, so
+    /// the rest of the batch can be retried. The future is `Unpin`, so it can
+    /// be awaited through a mutable reference to still be there to ask.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn send_data() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse()?)?;
+    /// let a = "127.0.0.1:7878".parse()?;
+    /// let b = "127.0.0.1:7879".parse()?;
+    ///
+    /// let msgs = [(&b"to a"[..], a), (&b"to b"[..], b)];
+    /// let mut send = socket.send_to_many(&msgs);
+    /// if let Err(e) = (&mut send).await {
+    ///     println!("sent {} of {}: {}", send.sent(), msgs.len(), e);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`send_to`]: #method.send_to
+    /// [`SendToMany::sent`]: struct.SendToMany.html#method.sent
+    #[cfg(unix)]
+    pub fn send_to_many<'a, 'b>(
+        &'a mut self,
+        msgs: &'b [(&'b [u8], SocketAddr)],
+    ) -> SendToMany<'a, 'b> {
+        SendToMany {
+            socket: self,
+            msgs,
+            sent: 0,
+        }
+    }
+
+    /// Receives a batch of datagrams, one into each of `bufs`.
+    ///
+    /// The future waits for at least one datagram, then receives as many of
+    /// those waiting as there are buffers, and resolves to the length and
+    /// sender of each, in order: entry `i` describes the datagram copied
+    /// into `bufs[i]`. As with [`recv_from`], a datagram longer than its
+    /// buffer is truncated. An empty `bufs` resolves to an empty list right
+    /// away.
+    ///
+    /// On Linux the datagrams are received with a single `recvmmsg` call,
+    /// which saves a system call per datagram compared to calling
+    /// [`recv_from`] in a loop. It is the receiving counterpart of
+    /// [`send_to_many`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn recv_data() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"127.0.0.1:4433".parse()?)?;
+    /// let mut bufs = vec![[0; 1500]; 32];
+    ///
+    /// for (i, (n, from)) in socket.recv_from_many(&mut bufs).await?.into_iter().enumerate() {
+    ///     println!("{} bytes from {}: {:?}", n, from, &bufs[i][..n]);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`recv_from`]: #method.recv_from
+    /// [`send_to_many`]: #method.send_to_many
+    #[cfg(unix)]
+    pub fn recv_from_many<'a, 'b, B: AsMut<[u8]>>(
+        &'a mut self,
+        bufs: &'b mut [B],
+    ) -> RecvFromMany<'a, 'b, B> {
+        RecvFromMany { socket: self, bufs }
+    }
+
+    fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        if let Some(peer) = self.roaming_peer {
+            return self.poll_send_to_ref(cx, buf, &peer);
+        }
+
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send(buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(map_message_size(e))),
+        }
+    }
+
+    fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv(buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    fn poll_send_to_ref(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: &SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().send_to(buf, target) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(map_message_size(e))),
+        }
+    }
+
+    fn poll_recv_from_ref(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<(usize, SocketAddr)>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match self.io.get_ref().recv_from(buf) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
     /// Gets the value of the `SO_BROADCAST` option for this socket.
     ///
     /// For more information about this option, see [`set_broadcast`].
@@ -234,11 +843,516 @@ impl UdpSocket {
         self.io.get_ref().set_ttl(ttl)
     }
 
-    /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
+    /// Gets the value of the `SO_RCVBUF` option on this socket.
     ///
-    /// This function specifies a new multicast group for this socket to join.
-    /// The address must be a valid multicast address, and `interface` is the
-    /// address of the local interface with which the system should join the
+    /// For more information about this option, see [`set_recv_buffer_size`].
+    ///
+    /// [`set_recv_buffer_size`]: #method.set_recv_buffer_size
+    #[cfg(unix)]
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::getsockopt::<libc::c_int>(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF)
+            .map(|size| size as usize)
+    }
+
+    /// Sets the value of the `SO_RCVBUF` option on this socket.
+    ///
+    /// Changes the size of the operating system's receive buffer associated
+    /// with the socket. Datagrams that arrive while it is full are dropped,
+    /// so sockets that receive bursts need a larger one. The kernel may
+    /// adjust the value: Linux doubles it to account for its bookkeeping,
+    /// and caps it at the `net.core.rmem_max` sysctl.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let socket = UdpSocket::bind(&"127.0.0.1:0".parse()?)?;
+    /// socket.set_recv_buffer_size(64 * 1024)?;
+    /// assert!(socket.recv_buffer_size()? >= 64 * 1024);
+    /// # Ok(()) }
+    /// ```
+    #[cfg(unix)]
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let size = std::cmp::min(size, libc::c_int::MAX as usize) as libc::c_int;
+        crate::sys::setsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVBUF, size)
+    }
+
+    /// Gets the value of the `SO_SNDBUF` option on this socket.
+    ///
+    /// For more information about this option, see [`set_send_buffer_size`].
+    ///
+    /// [`set_send_buffer_size`]: #method.set_send_buffer_size
+    #[cfg(unix)]
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::getsockopt::<libc::c_int>(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_SNDBUF)
+            .map(|size| size as usize)
+    }
+
+    /// Sets the value of the `SO_SNDBUF` option on this socket.
+    ///
+    /// Changes the size of the operating system's send buffer associated with
+    /// the socket. As with [`set_recv_buffer_size`], the kernel may adjust
+    /// the value; on Linux the cap is the `net.core.wmem_max` sysctl.
+    ///
+    /// [`set_recv_buffer_size`]: #method.set_recv_buffer_size
+    #[cfg(unix)]
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let size = std::cmp::min(size, libc::c_int::MAX as usize) as libc::c_int;
+        crate::sys::setsockopt(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_SNDBUF, size)
+    }
+
+    /// Sets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// This works as it does for [`TcpStream::set_busy_poll`], and likewise
+    /// fails with `io::ErrorKind::Unsupported` off Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let socket = UdpSocket::bind(&"0.0.0.0:9000".parse()?)?;
+    /// socket.set_busy_poll(50)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TcpStream::set_busy_poll`]: ../tcp/struct.TcpStream.html#method.set_busy_poll
+    #[cfg(unix)]
+    pub fn set_busy_poll(&self, usecs: u32) -> io::Result<()> {
+        crate::sys::set_busy_poll(std::os::unix::io::AsRawFd::as_raw_fd(self), usecs)
+    }
+
+    /// Gets the value of the `SO_BUSY_POLL` option on this socket.
+    ///
+    /// For more information about this option, see [`set_busy_poll`].
+    ///
+    /// [`set_busy_poll`]: #method.set_busy_poll
+    #[cfg(unix)]
+    pub fn busy_poll(&self) -> io::Result<u32> {
+        crate::sys::busy_poll(std::os::unix::io::AsRawFd::as_raw_fd(self))
+    }
+
+    /// Sets the value of the `SO_NO_CHECK` option on this socket.
+    ///
+    /// When enabled, datagrams are sent without a UDP checksum, which saves
+    /// computing one per packet. Receivers still verify checksums that are
+    /// present.
+    ///
+    /// **Only use this on links where a lower layer already guarantees
+    /// integrity.** Without a checksum, a datagram corrupted in transit is
+    /// delivered as if it were intact; never disable it for traffic that
+    /// crosses the open internet.
+    ///
+    /// This option is only available for IPv4 sockets on Linux, since IPv6
+    /// requires the checksum; elsewhere an error of kind
+    /// `io::ErrorKind::Unsupported` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let socket = UdpSocket::bind(&"10.0.0.1:9000".parse()?)?;
+    /// socket.set_no_check(true)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn set_no_check(&self, on: bool) -> io::Result<()> {
+        let ipv6 = self.local_addr()?.is_ipv6();
+        crate::sys::set_no_check(std::os::unix::io::AsRawFd::as_raw_fd(self), ipv6, on)
+    }
+
+    /// Gets the value of the `SO_NO_CHECK` option on this socket.
+    ///
+    /// For more information about this option, see [`set_no_check`].
+    ///
+    /// [`set_no_check`]: #method.set_no_check
+    #[cfg(unix)]
+    pub fn no_check(&self) -> io::Result<bool> {
+        crate::sys::no_check(std::os::unix::io::AsRawFd::as_raw_fd(self))
+    }
+
+    /// Sets whether received datagrams report their original destination.
+    ///
+    /// This sets `IP_RECVORIGDSTADDR`, and also `IPV6_RECVORIGDSTADDR` for
+    /// IPv6 sockets. A transparent proxy, whose socket is bound with
+    /// `IP_TRANSPARENT` and receives traffic redirected by a Netfilter
+    /// `TPROXY` rule, uses it to learn where each intercepted datagram was
+    /// headed; it is the UDP counterpart of [`TcpStream::original_dst`]. The
+    /// destinations are read with [`recv_from_with_orig_dst`].
+    ///
+    /// This option is only available on Linux.
+    ///
+    /// [`TcpStream::original_dst`]: ../tcp/struct.TcpStream.html#method.original_dst
+    /// [`recv_from_with_orig_dst`]: #method.recv_from_with_orig_dst
+    #[cfg(target_os = "linux")]
+    pub fn set_recv_orig_dst(&self, on: bool) -> io::Result<()> {
+        let ipv6 = self.local_addr()?.is_ipv6();
+        crate::sys::set_recv_orig_dst(std::os::unix::io::AsRawFd::as_raw_fd(self), ipv6, on)
+    }
+
+    /// Receives a datagram, along with its sender and the destination it was
+    /// originally sent to.
+    ///
+    /// On success, the future resolves to the number of bytes read, the
+    /// sender's address and the original destination address. That is the
+    /// local address the datagram arrived on, unless it was redirected to
+    /// this socket, in which case it is the address the sender used.
+    ///
+    /// [`set_recv_orig_dst`] must have been enabled, otherwise each datagram
+    /// is received and then rejected with an error of kind
+    /// `io::ErrorKind::InvalidData`. So is a datagram whose control messages
+    /// were cut short by the kernel, which reports it with `MSG_CTRUNC`.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`set_recv_orig_dst`]: #method.set_recv_orig_dst
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn proxy() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"0.0.0.0:5353".parse()?)?;
+    /// socket.set_recv_orig_dst(true)?;
+    ///
+    /// let mut buf = vec![0; 1500];
+    /// let (n, from, orig_dst) = socket.recv_from_with_orig_dst(&mut buf).await?;
+    /// println!("{} bytes from {} for {}", n, from, orig_dst);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn recv_from_with_orig_dst<'a, 'b>(
+        &'a mut self,
+        buf: &'b mut [u8],
+    ) -> RecvFromWithOrigDst<'a, 'b> {
+        RecvFromWithOrigDst { buf, socket: self }
+    }
+
+    /// Sends a datagram to `target` with the given ECN codepoint.
+    ///
+    /// `ecn` goes into the two ECN bits of the IP header: 0 for Not-ECT, 1
+    /// for ECT(1), 2 for ECT(0) and 3 for CE. The DSCP bits are sent as zero.
+    /// Larger values fail with an error of kind `io::ErrorKind::InvalidInput`.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn send() -> Result<(), Box<dyn Error + 'static>> {
+    /// const ECT_0: u8 = 0b10;
+    ///
+    /// let mut socket = UdpSocket::bind(&"0.0.0.0:0".parse()?)?;
+    /// let target = "127.0.0.1:4433".parse()?;
+    /// socket.send_to_ecn(b"hello", &target, ECT_0).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn send_to_ecn<'a, 'b>(
+        &'a mut self,
+        buf: &'b [u8],
+        target: &'b SocketAddr,
+        ecn: u8,
+    ) -> SendToEcn<'a, 'b> {
+        SendToEcn {
+            socket: self,
+            buf,
+            target,
+            ecn,
+        }
+    }
+
+    /// Sets whether received datagrams report their ECN codepoint.
+    ///
+    /// This sets `IP_RECVTOS`, and also `IPV6_RECVTCLASS` for IPv6 sockets.
+    /// The codepoints are read with [`recv_from_with_ecn`].
+    ///
+    /// This option is only available on Linux.
+    ///
+    /// [`recv_from_with_ecn`]: #method.recv_from_with_ecn
+    #[cfg(target_os = "linux")]
+    pub fn set_recv_ecn(&self, on: bool) -> io::Result<()> {
+        let ipv6 = self.local_addr()?.is_ipv6();
+        crate::sys::set_recv_ecn(std::os::unix::io::AsRawFd::as_raw_fd(self), ipv6, on)
+    }
+
+    /// Receives a datagram, along with its sender and its ECN codepoint.
+    ///
+    /// On success, the future resolves to the number of bytes read, the
+    /// sender's address and the two ECN bits of the datagram's IP header, as
+    /// described for [`send_to_ecn`].
+    ///
+    /// [`set_recv_ecn`] must have been enabled, otherwise each datagram is
+    /// received and then rejected with an error of kind
+    /// `io::ErrorKind::InvalidData`.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`send_to_ecn`]: #method.send_to_ecn
+    /// [`set_recv_ecn`]: #method.set_recv_ecn
+    #[cfg(target_os = "linux")]
+    pub fn recv_from_with_ecn<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFromWithEcn<'a, 'b> {
+        RecvFromWithEcn { buf, socket: self }
+    }
+
+    /// Sets whether received datagrams report the address they were sent to
+    /// and the interface they arrived on.
+    ///
+    /// This sets `IP_PKTINFO`, and also `IPV6_RECVPKTINFO` for IPv6 sockets.
+    /// A socket bound to the unspecified address receives datagrams sent to
+    /// any of the host's addresses; a server that replies from the address
+    /// each request arrived on, as DNS and QUIC clients expect, learns it
+    /// from [`recv_from_with_meta`] and replies with [`send_to_from`].
+    ///
+    /// This option is only available on Linux.
+    ///
+    /// [`recv_from_with_meta`]: #method.recv_from_with_meta
+    /// [`send_to_from`]: #method.send_to_from
+    #[cfg(target_os = "linux")]
+    pub fn set_recv_pktinfo(&self, on: bool) -> io::Result<()> {
+        let ipv6 = self.local_addr()?.is_ipv6();
+        crate::sys::set_recv_pktinfo(std::os::unix::io::AsRawFd::as_raw_fd(self), ipv6, on)
+    }
+
+    /// Sets whether received datagrams report their TTL, or hop limit for
+    /// IPv6.
+    ///
+    /// This sets `IP_RECVTTL`, and also `IPV6_RECVHOPLIMIT` for IPv6
+    /// sockets. The values are read with [`recv_from_with_meta`].
+    ///
+    /// This option is only available on Linux.
+    ///
+    /// [`recv_from_with_meta`]: #method.recv_from_with_meta
+    #[cfg(target_os = "linux")]
+    pub fn set_recv_ttl(&self, on: bool) -> io::Result<()> {
+        let ipv6 = self.local_addr()?.is_ipv6();
+        crate::sys::set_recv_ttl(std::os::unix::io::AsRawFd::as_raw_fd(self), ipv6, on)
+    }
+
+    /// Receives a datagram, along with its sender and what its control
+    /// messages tell about it.
+    ///
+    /// On success, the future resolves to the number of bytes read, the
+    /// sender's address and a [`RecvMeta`] holding the destination address
+    /// and interface, the TTL and the ECN codepoint of the datagram, as
+    /// enabled with [`set_recv_pktinfo`], [`set_recv_ttl`] and
+    /// [`set_recv_ecn`]. What wasn't enabled is left as `None`.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`RecvMeta`]: struct.RecvMeta.html
+    /// [`set_recv_pktinfo`]: #method.set_recv_pktinfo
+    /// [`set_recv_ttl`]: #method.set_recv_ttl
+    /// [`set_recv_ecn`]: #method.set_recv_ecn
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn serve() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"0.0.0.0:53".parse()?)?;
+    /// socket.set_recv_pktinfo(true)?;
+    ///
+    /// let mut buf = vec![0; 1500];
+    /// loop {
+    ///     let (n, from, meta) = socket.recv_from_with_meta(&mut buf).await?;
+    ///     // Answer from the address the query was sent to.
+    ///     let source = meta.dst.expect("IP_PKTINFO is enabled");
+    ///     socket.send_to_from(&buf[..n], &from, &source).await?;
+    /// }
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn recv_from_with_meta<'a, 'b>(
+        &'a mut self,
+        buf: &'b mut [u8],
+    ) -> RecvFromWithMeta<'a, 'b> {
+        RecvFromWithMeta { buf, socket: self }
+    }
+
+    /// Sends a datagram to `target` from the local address `source`.
+    ///
+    /// `source` has to be one of the host's addresses, of the same family as
+    /// `target`, otherwise the send fails; it overrides the address the
+    /// socket is bound to. This is how a socket bound to the unspecified
+    /// address replies from the address a request arrived on, as reported
+    /// by [`recv_from_with_meta`].
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`recv_from_with_meta`]: #method.recv_from_with_meta
+    #[cfg(target_os = "linux")]
+    pub fn send_to_from<'a, 'b>(
+        &'a mut self,
+        buf: &'b [u8],
+        target: &'b SocketAddr,
+        source: &'b IpAddr,
+    ) -> SendToFrom<'a, 'b> {
+        SendToFrom {
+            socket: self,
+            buf,
+            target,
+            source,
+        }
+    }
+
+    /// Sets the value of the `UDP_SEGMENT` option on this socket.
+    ///
+    /// When non-zero, this enables generic segmentation offload (GSO): every
+    /// buffer sent is split into datagrams of `size` bytes, and a shorter
+    /// last one, by the kernel or the network card instead of the caller. A
+    /// single `send_to` then puts up to 64 datagrams on the wire, which
+    /// saves most of the per-datagram cost of the send path. The buffer may
+    /// not hold more than 64 segments, and `size` has to fit in the path
+    /// MTU, otherwise sends fail. Setting it to 0 turns segmentation off.
+    ///
+    /// This option is only available on Linux 4.18 and later.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn send() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"0.0.0.0:0".parse()?)?;
+    /// socket.set_gso_segment_size(1200)?;
+    ///
+    /// // Goes out as ten datagrams of 1200 bytes.
+    /// let target = "127.0.0.1:4433".parse()?;
+    /// socket.send_to(&[0; 12_000], &target).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn set_gso_segment_size(&self, size: u16) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let size = libc::c_int::from(size);
+        crate::sys::setsockopt(self.as_raw_fd(), libc::SOL_UDP, libc::UDP_SEGMENT, size)
+    }
+
+    /// Gets the value of the `UDP_SEGMENT` option on this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_gso_segment_size`].
+    ///
+    /// [`set_gso_segment_size`]: #method.set_gso_segment_size
+    #[cfg(target_os = "linux")]
+    pub fn gso_segment_size(&self) -> io::Result<u16> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::getsockopt::<libc::c_int>(self.as_raw_fd(), libc::SOL_UDP, libc::UDP_SEGMENT)
+            .map(|size| size as u16)
+    }
+
+    /// Sets the value of the `UDP_GRO` option on this socket.
+    ///
+    /// This enables generic receive offload (GRO): consecutive datagrams of
+    /// the same size from the same sender may be handed over coalesced in a
+    /// single buffer, so that many of them are received at the cost of one.
+    /// Since a plain `recv_from` can't tell where the datagrams of such a
+    /// buffer start, they have to be received with [`recv_from_gro`].
+    ///
+    /// This option is only available on Linux 5.0 and later.
+    ///
+    /// [`recv_from_gro`]: #method.recv_from_gro
+    #[cfg(target_os = "linux")]
+    pub fn set_gro(&self, on: bool) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let on = on as libc::c_int;
+        crate::sys::setsockopt(self.as_raw_fd(), libc::SOL_UDP, libc::UDP_GRO, on)
+    }
+
+    /// Gets the value of the `UDP_GRO` option on this socket.
+    ///
+    /// For more information about this option, see [`set_gro`].
+    ///
+    /// [`set_gro`]: #method.set_gro
+    #[cfg(target_os = "linux")]
+    pub fn gro(&self) -> io::Result<bool> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::getsockopt::<libc::c_int>(self.as_raw_fd(), libc::SOL_UDP, libc::UDP_GRO)
+            .map(|on| on != 0)
+    }
+
+    /// Receives one or more datagrams coalesced by [`set_gro`], along with
+    /// their sender and their size.
+    ///
+    /// On success, the future resolves to the number of bytes read, the
+    /// sender's address and the segment size: `buf[..n]` holds consecutive
+    /// datagrams of that many bytes each, except for the last one, which may
+    /// be shorter. A datagram that wasn't coalesced is its own segment, so
+    /// the segment size is 0 for an empty datagram, which can't be passed to
+    /// `chunks`. The buffer should be large enough for a full batch, up to
+    /// 64 KiB, or the datagrams past its end are lost.
+    ///
+    /// This method is only available on Linux.
+    ///
+    /// [`set_gro`]: #method.set_gro
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::udp::UdpSocket;
+    ///
+    /// # async fn recv() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UdpSocket::bind(&"0.0.0.0:4433".parse()?)?;
+    /// socket.set_gro(true)?;
+    ///
+    /// let mut buf = vec![0; 64 * 1024];
+    /// let (n, from, segment_size) = socket.recv_from_gro(&mut buf).await?;
+    /// if n == 0 {
+    ///     println!("0 bytes from {}", from);
+    /// }
+    /// for datagram in buf[..n].chunks(segment_size.max(1)) {
+    ///     println!("{} bytes from {}", datagram.len(), from);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn recv_from_gro<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFromGro<'a, 'b> {
+        RecvFromGro { buf, socket: self }
+    }
+
+    /// Executes an operation of the `IP_ADD_MEMBERSHIP` type.
+    ///
+    /// This function specifies a new multicast group for this socket to join.
+    /// The address must be a valid multicast address, and `interface` is the
+    /// address of the local interface with which the system should join the
     /// multicast group. If it's equal to `INADDR_ANY` then an appropriate
     /// interface is chosen by the system.
     ///
@@ -301,6 +1415,93 @@ impl UdpSocket {
     pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> io::Result<()> {
         self.io.get_ref().leave_multicast_v6(multiaddr, interface)
     }
+
+    /// Sets the value of the `IP_MULTICAST_IF` option for this socket.
+    ///
+    /// Selects the local interface, by its address, that outgoing IPv4
+    /// multicast datagrams are sent from. With `INADDR_ANY`, the default, the
+    /// system chooses one from the routing table. On hosts with several
+    /// interfaces, such as an mDNS responder on a machine with both wired
+    /// and wireless links, this must match the interface the group was
+    /// joined on for replies to reach the same network.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::udp::UdpSocket;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let interface = Ipv4Addr::new(192, 168, 1, 10);
+    /// let mdns_group = "224.0.0.251:5353".parse()?;
+    ///
+    /// let socket = UdpSocket::bind_multicast_v4(&mdns_group, &interface)?;
+    /// socket.set_multicast_if_v4(&interface)?;
+    /// # Ok(()) }
+    /// ```
+    #[cfg(unix)]
+    pub fn set_multicast_if_v4(&self, interface: &Ipv4Addr) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        let addr = libc::in_addr {
+            s_addr: u32::from(*interface).to_be(),
+        };
+        crate::sys::setsockopt(
+            self.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_MULTICAST_IF,
+            addr,
+        )
+    }
+
+    /// Gets the value of the `IP_MULTICAST_IF` option for this socket.
+    ///
+    /// For more information about this option, see [`set_multicast_if_v4`].
+    ///
+    /// [`set_multicast_if_v4`]: #method.set_multicast_if_v4
+    #[cfg(unix)]
+    pub fn multicast_if_v4(&self) -> io::Result<Ipv4Addr> {
+        use std::os::unix::io::AsRawFd;
+
+        let addr: libc::in_addr =
+            crate::sys::getsockopt(self.as_raw_fd(), libc::IPPROTO_IP, libc::IP_MULTICAST_IF)?;
+        Ok(Ipv4Addr::from(u32::from_be(addr.s_addr)))
+    }
+
+    /// Sets the value of the `IPV6_MULTICAST_IF` option for this socket.
+    ///
+    /// Selects the local interface, by its index, that outgoing IPv6
+    /// multicast datagrams are sent from. With 0, the default, the system
+    /// chooses one. Link-local groups such as mDNS's `ff02::fb` exist on
+    /// every link, so hosts with several interfaces need to pick one.
+    #[cfg(unix)]
+    pub fn set_multicast_if_v6(&self, interface: u32) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::setsockopt(
+            self.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MULTICAST_IF,
+            interface as libc::c_int,
+        )
+    }
+
+    /// Gets the value of the `IPV6_MULTICAST_IF` option for this socket.
+    ///
+    /// For more information about this option, see [`set_multicast_if_v6`].
+    ///
+    /// [`set_multicast_if_v6`]: #method.set_multicast_if_v6
+    #[cfg(unix)]
+    pub fn multicast_if_v6(&self) -> io::Result<u32> {
+        use std::os::unix::io::AsRawFd;
+
+        crate::sys::getsockopt::<libc::c_int>(
+            self.as_raw_fd(),
+            libc::IPPROTO_IPV6,
+            libc::IPV6_MULTICAST_IF,
+        )
+        .map(|index| index as u32)
+    }
 }
 
 impl AsyncDatagram for UdpSocket {
@@ -309,38 +1510,20 @@ impl AsyncDatagram for UdpSocket {
     type Err = io::Error;
 
     fn poll_send_to(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
         receiver: &Self::Receiver,
     ) -> Poll<io::Result<usize>> {
-        ready!(self.io.poll_write_ready(cx)?);
-
-        match self.io.get_ref().send_to(buf, receiver) {
-            Ok(n) => Poll::Ready(Ok(n)),
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                Pin::new(&mut self.io).clear_write_ready(cx)?;
-                Poll::Pending
-            }
-            Err(e) => Poll::Ready(Err(e)),
-        }
+        self.poll_send_to_ref(cx, buf, receiver)
     }
 
     fn poll_recv_from(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<(usize, Self::Sender)>> {
-        ready!(Pin::new(&mut self.io).poll_read_ready(cx)?);
-
-        match self.io.get_ref().recv_from(buf) {
-            Ok(n) => Poll::Ready(Ok(n)),
-            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                Pin::new(&mut self.io).clear_read_ready(cx)?;
-                Poll::Pending
-            }
-            Err(e) => Poll::Ready(Err(e)),
-        }
+        self.poll_recv_from_ref(cx, buf)
     }
 }
 
@@ -394,6 +1577,9 @@ impl fmt::Debug for UdpSocket {
 #[cfg(all(unix))]
 mod sys {
     use super::UdpSocket;
+    use crate::socket::SavedSocket;
+    use std::convert::TryFrom;
+    use std::io;
     use std::os::unix::prelude::*;
 
     impl AsRawFd for UdpSocket {
@@ -401,20 +1587,206 @@ mod sys {
             self.io.get_ref().as_raw_fd()
         }
     }
+
+    /// Hands the socket over still in non-blocking mode, as
+    /// [`PollEvented::into_inner_lossy`] describes.
+    ///
+    /// [`PollEvented::into_inner_lossy`]: ../raw/struct.PollEvented.html#method.into_inner_lossy
+    impl IntoRawFd for UdpSocket {
+        fn into_raw_fd(self) -> RawFd {
+            self.io.into_inner_lossy().into_raw_fd()
+        }
+    }
+
+    /// Takes ownership of a UDP socket, and puts it in non-blocking mode.
+    impl FromRawFd for UdpSocket {
+        unsafe fn from_raw_fd(fd: RawFd) -> UdpSocket {
+            // This only fails for descriptors that aren't open, which fail
+            // every later operation as well.
+            let _ = crate::sys::set_nonblocking(fd);
+            let io = mio::net::UdpSocket::from_raw_fd(fd);
+            let socket = SavedSocket::of(&io);
+            UdpSocket::new((io, socket))
+        }
+    }
+
+    /// Converts the socket with [`UdpSocket::into_std`].
+    ///
+    /// [`UdpSocket::into_std`]: struct.UdpSocket.html#method.into_std
+    impl TryFrom<UdpSocket> for std::net::UdpSocket {
+        type Error = io::Error;
+
+        fn try_from(socket: UdpSocket) -> io::Result<std::net::UdpSocket> {
+            socket.into_std()
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::UdpSocket;
+    use std::os::windows::prelude::*;
+
+    impl AsRawSocket for UdpSocket {
+        fn as_raw_socket(&self) -> RawSocket {
+            self.socket.get()
+        }
+    }
 }
 
 impl TryFrom<std::net::UdpSocket> for UdpSocket {
     type Error = io::Error;
 
     fn try_from(socket: std::net::UdpSocket) -> Result<Self, Self::Error> {
-        mio::net::UdpSocket::from_socket(socket).map(UdpSocket::new)
+        udp_from_std(socket).map(UdpSocket::new)
+    }
+}
+
+/// Creates a socket bound to `addr`, the way `mio::net::UdpSocket::bind`
+/// does.
+fn bind_socket(addr: &SocketAddr) -> io::Result<Udp> {
+    udp_from_std(std::net::UdpSocket::bind(addr)?)
+}
+
+fn udp_from_std(socket: std::net::UdpSocket) -> io::Result<Udp> {
+    let saved = SavedSocket::of(&socket);
+    Ok((mio::net::UdpSocket::from_socket(socket)?, saved))
+}
+
+/// Reports `EMSGSIZE` as `InvalidInput`, which std leaves uncategorized.
+fn map_message_size(err: io::Error) -> io::Error {
+    #[cfg(unix)]
+    {
+        if err.raw_os_error() == Some(libc::EMSGSIZE) {
+            return io::Error::new(io::ErrorKind::InvalidInput, err);
+        }
+    }
+    err
+}
+
+/// Whether multicast receivers bind to the unspecified address rather than to
+/// the group address.
+#[cfg(unix)]
+const BIND_UNSPECIFIED_FOR_MULTICAST: bool = cfg!(any(target_os = "linux", target_os = "android"));
+
+/// Creates a `SO_REUSEADDR` datagram socket bound to `addr`.
+#[cfg(unix)]
+fn multicast_socket(family: libc::c_int, addr: &SocketAddr) -> io::Result<std::net::UdpSocket> {
+    use std::os::unix::io::AsRawFd;
+
+    let socket: std::net::UdpSocket = crate::sys::socket_owned(family, libc::SOCK_DGRAM, 0)?;
+    let fd = socket.as_raw_fd();
+    crate::sys::setsockopt(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR, 1 as libc::c_int)?;
+    crate::sys::bind(fd, addr)?;
+    Ok(socket)
+}
+
+/// The receiving half of a `UdpSocket`, created by [`UdpSocket::split`].
+///
+/// [`UdpSocket::split`]: struct.UdpSocket.html#method.split
+#[derive(Debug)]
+pub struct RecvHalf {
+    inner: Arc<UdpSocket>,
+}
+
+/// The sending half of a `UdpSocket`, created by [`UdpSocket::split`].
+///
+/// [`UdpSocket::split`]: struct.UdpSocket.html#method.split
+#[derive(Debug)]
+pub struct SendHalf {
+    inner: Arc<UdpSocket>,
+}
+
+/// The error returned by [`RecvHalf::reunite`] when the halves belong to
+/// different sockets.
+///
+/// It gives the halves back.
+///
+/// [`RecvHalf::reunite`]: struct.RecvHalf.html#method.reunite
+#[derive(Debug)]
+pub struct ReuniteError(pub RecvHalf, pub SendHalf);
+
+impl fmt::Display for ReuniteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves that are not from the same socket")
+    }
+}
+
+impl std::error::Error for ReuniteError {}
+
+impl RecvHalf {
+    /// Receives data from the socket, as [`UdpSocket::recv_from`] does.
+    ///
+    /// [`UdpSocket::recv_from`]: struct.UdpSocket.html#method.recv_from
+    pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
+        RecvFrom {
+            buf,
+            socket: &self.inner,
+        }
+    }
+
+    /// Receives data from the connected peer, as [`UdpSocket::recv`] does.
+    ///
+    /// [`UdpSocket::recv`]: struct.UdpSocket.html#method.recv
+    pub fn recv<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFuture<'a, 'b> {
+        RecvFuture {
+            buf,
+            socket: &self.inner,
+        }
+    }
+
+    /// Puts the two halves of a socket back together.
+    ///
+    /// Fails if `send` was split off a different socket than this half.
+    pub fn reunite(self, send: SendHalf) -> Result<UdpSocket, ReuniteError> {
+        if !Arc::ptr_eq(&self.inner, &send.inner) {
+            return Err(ReuniteError(self, send));
+        }
+        drop(send);
+        Ok(Arc::try_unwrap(self.inner).expect("UdpSocket: the halves were the only owners"))
+    }
+}
+
+impl SendHalf {
+    /// Sends data to the given address, as [`UdpSocket::send_to`] does.
+    ///
+    /// [`UdpSocket::send_to`]: struct.UdpSocket.html#method.send_to
+    pub fn send_to<'a, 'b>(&'a mut self, buf: &'b [u8], target: &'b SocketAddr) -> SendTo<'a, 'b> {
+        SendTo {
+            buf,
+            target,
+            socket: &self.inner,
+        }
+    }
+
+    /// Sends data to the socket's default destination, as
+    /// [`UdpSocket::send`] does.
+    ///
+    /// [`UdpSocket::send`]: struct.UdpSocket.html#method.send
+    pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SendFuture<'a, 'b> {
+        SendFuture {
+            buf,
+            socket: &self.inner,
+        }
+    }
+}
+
+impl AsRef<UdpSocket> for RecvHalf {
+    fn as_ref(&self) -> &UdpSocket {
+        &self.inner
+    }
+}
+
+impl AsRef<UdpSocket> for SendHalf {
+    fn as_ref(&self) -> &UdpSocket {
+        &self.inner
     }
 }
 
 /// The future returned by `UdpSocket::send_to`
 #[derive(Debug)]
 pub struct SendTo<'a, 'b> {
-    socket: &'a mut UdpSocket,
+    socket: &'a UdpSocket,
     buf: &'b [u8],
     target: &'b SocketAddr,
 }
@@ -428,14 +1800,88 @@ impl<'a, 'b> Future for SendTo<'a, 'b> {
             buf,
             target,
         } = &mut *self;
-        Pin::new(&mut **socket).poll_send_to(cx, buf, target)
+        socket.poll_send_to_ref(cx, buf, target)
+    }
+}
+
+/// The future returned by `UdpSocket::send_to_many`
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct SendToMany<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    msgs: &'b [(&'b [u8], SocketAddr)],
+    /// How many datagrams from the start of `msgs` have been sent.
+    sent: usize,
+}
+
+#[cfg(unix)]
+impl<'a, 'b> SendToMany<'a, 'b> {
+    /// Returns how many datagrams from the start of the batch have been sent
+    /// so far.
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+}
+
+#[cfg(unix)]
+impl<'a, 'b> Future for SendToMany<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let SendToMany { socket, msgs, sent } = &mut *self;
+        while *sent < msgs.len() {
+            ready!(socket.io.poll_write_ready(cx)?);
+
+            match crate::sys::send_mmsg(socket.as_raw_fd(), &msgs[*sent..]) {
+                Ok(n) => *sent += n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    Pin::new(&mut socket.io).clear_write_ready(cx)?;
+                    return Poll::Pending;
+                }
+                Err(e) => return Poll::Ready(Err(map_message_size(e))),
+            }
+        }
+        Poll::Ready(Ok(*sent))
+    }
+}
+
+/// The future returned by `UdpSocket::recv_from_many`
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct RecvFromMany<'a, 'b, B> {
+    socket: &'a mut UdpSocket,
+    bufs: &'b mut [B],
+}
+
+#[cfg(unix)]
+impl<'a, 'b, B: AsMut<[u8]>> Future for RecvFromMany<'a, 'b, B> {
+    type Output = io::Result<Vec<(usize, SocketAddr)>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let RecvFromMany { socket, bufs } = &mut *self;
+        if bufs.is_empty() {
+            return Poll::Ready(Ok(Vec::new()));
+        }
+        ready!(Pin::new(&mut socket.io).poll_read_ready(cx)?);
+
+        match crate::sys::recv_mmsg(socket.as_raw_fd(), bufs) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut socket.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
     }
 }
 
 /// The future returned by `UdpSocket::recv_from`
 #[derive(Debug)]
 pub struct RecvFrom<'a, 'b> {
-    socket: &'a mut UdpSocket,
+    socket: &'a UdpSocket,
     buf: &'b mut [u8],
 }
 
@@ -444,6 +1890,240 @@ impl<'a, 'b> Future for RecvFrom<'a, 'b> {
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let RecvFrom { socket, buf } = &mut *self;
-        Pin::new(&mut **socket).poll_recv_from(cx, buf)
+        socket.poll_recv_from_ref(cx, buf)
+    }
+}
+
+/// The future returned by `UdpSocket::peek_from`
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct PeekFrom<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b mut [u8],
+}
+
+#[cfg(unix)]
+impl<'a, 'b> Future for PeekFrom<'a, 'b> {
+    type Output = io::Result<(usize, SocketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let PeekFrom { socket, buf } = &mut *self;
+        socket.poll_peek_from(cx, buf)
+    }
+}
+
+/// The future returned by `UdpSocket::recv_from_with_orig_dst`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct RecvFromWithOrigDst<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b mut [u8],
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for RecvFromWithOrigDst<'a, 'b> {
+    type Output = io::Result<(usize, SocketAddr, SocketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let RecvFromWithOrigDst { socket, buf } = &mut *self;
+        ready!(Pin::new(&mut socket.io).poll_read_ready(cx)?);
+
+        match crate::sys::recv_from_with_orig_dst(socket.as_raw_fd(), buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut socket.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+/// The future returned by `UdpSocket::send_to_ecn`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct SendToEcn<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b [u8],
+    target: &'b SocketAddr,
+    ecn: u8,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for SendToEcn<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let SendToEcn {
+            socket,
+            buf,
+            target,
+            ecn,
+        } = &mut *self;
+        ready!(socket.io.poll_write_ready(cx)?);
+
+        match crate::sys::send_to_ecn(socket.as_raw_fd(), buf, target, *ecn) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                socket.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(map_message_size(e))),
+        }
+    }
+}
+
+/// The future returned by `UdpSocket::recv_from_with_ecn`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct RecvFromWithEcn<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b mut [u8],
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for RecvFromWithEcn<'a, 'b> {
+    type Output = io::Result<(usize, SocketAddr, u8)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let RecvFromWithEcn { socket, buf } = &mut *self;
+        ready!(socket.io.poll_read_ready(cx)?);
+
+        match crate::sys::recv_from_with_ecn(socket.as_raw_fd(), buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                socket.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+/// The future returned by `UdpSocket::recv_from_with_meta`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct RecvFromWithMeta<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b mut [u8],
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for RecvFromWithMeta<'a, 'b> {
+    type Output = io::Result<(usize, SocketAddr, RecvMeta)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let RecvFromWithMeta { socket, buf } = &mut *self;
+        ready!(Pin::new(&mut socket.io).poll_read_ready(cx)?);
+
+        match crate::sys::recv_from_with_meta(socket.as_raw_fd(), buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut socket.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+/// The future returned by `UdpSocket::send_to_from`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct SendToFrom<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b [u8],
+    target: &'b SocketAddr,
+    source: &'b IpAddr,
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for SendToFrom<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let SendToFrom {
+            socket,
+            buf,
+            target,
+            source,
+        } = &mut *self;
+        ready!(socket.io.poll_write_ready(cx)?);
+
+        match crate::sys::send_to_from(socket.as_raw_fd(), buf, target, source) {
+            Ok(n) => Poll::Ready(Ok(n)),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut socket.io).clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(map_message_size(e))),
+        }
+    }
+}
+
+/// The future returned by `UdpSocket::recv_from_gro`
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct RecvFromGro<'a, 'b> {
+    socket: &'a mut UdpSocket,
+    buf: &'b mut [u8],
+}
+
+#[cfg(target_os = "linux")]
+impl<'a, 'b> Future for RecvFromGro<'a, 'b> {
+    type Output = io::Result<(usize, SocketAddr, usize)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::os::unix::io::AsRawFd;
+
+        let RecvFromGro { socket, buf } = &mut *self;
+        ready!(Pin::new(&mut socket.io).poll_read_ready(cx)?);
+
+        match crate::sys::recv_from_gro(socket.as_raw_fd(), buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Pin::new(&mut socket.io).clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+/// The future returned by `UdpSocket::send`
+#[derive(Debug)]
+pub struct SendFuture<'a, 'b> {
+    socket: &'a UdpSocket,
+    buf: &'b [u8],
+}
+
+impl<'a, 'b> Future for SendFuture<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendFuture { socket, buf } = &mut *self;
+        socket.poll_send(cx, buf)
+    }
+}
+
+/// The future returned by `UdpSocket::recv`
+#[derive(Debug)]
+pub struct RecvFuture<'a, 'b> {
+    socket: &'a UdpSocket,
+    buf: &'b mut [u8],
+}
+
+impl<'a, 'b> Future for RecvFuture<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvFuture { socket, buf } = &mut *self;
+        socket.poll_recv(cx, buf)
     }
 }
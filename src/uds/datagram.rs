@@ -1,19 +1,21 @@
 use crate::raw::PollEvented;
+use crate::sys;
 
 use async_datagram::AsyncDatagram;
 use async_ready::{AsyncReadReady, AsyncWriteReady, TakeError};
 use futures::{ready, Poll};
 use mio_uds;
 
+use std::convert::TryFrom;
 use std::fmt;
+use std::future::Future;
 use std::io;
 use std::net::Shutdown;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::os::unix::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::{self, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::Context;
-use std::future::Future;
 
 /// An I/O object representing a Unix datagram socket.
 pub struct UnixDatagram {
@@ -57,6 +59,90 @@ impl<'a, 'b> Future for RecvFrom<'a, 'b> {
     }
 }
 
+/// The future returned by `UnixDatagram::send`.
+#[derive(Debug)]
+pub struct SendFuture<'a, 'b> {
+    socket: &'a mut UnixDatagram,
+    buf: &'b [u8],
+}
+
+impl<'a, 'b> Future for SendFuture<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendFuture { socket, buf } = &mut *self;
+        socket.poll_send(cx, buf)
+    }
+}
+
+/// The future returned by `UnixDatagram::recv`.
+#[derive(Debug)]
+pub struct RecvFuture<'a, 'b> {
+    socket: &'a mut UnixDatagram,
+    buf: &'b mut [u8],
+}
+
+impl<'a, 'b> Future for RecvFuture<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvFuture { socket, buf } = &mut *self;
+        socket.poll_recv(cx, buf)
+    }
+}
+
+/// The future returned by `UnixDatagram::send_with_fds`.
+#[derive(Debug)]
+pub struct SendWithFds<'a, 'b> {
+    socket: &'a mut UnixDatagram,
+    buf: &'b [u8],
+    fds: &'b [RawFd],
+}
+
+impl<'a, 'b> Future for SendWithFds<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendWithFds { socket, buf, fds } = &mut *self;
+        ready!(socket.io.poll_write_ready(cx)?);
+
+        let r = sys::send_with_fds(socket.as_raw_fd(), buf, fds);
+
+        if is_wouldblock(&r) {
+            socket.io.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+}
+
+/// The future returned by `UnixDatagram::recv_with_fds`.
+#[derive(Debug)]
+pub struct RecvWithFds<'a, 'b> {
+    socket: &'a mut UnixDatagram,
+    buf: &'b mut [u8],
+    fds: &'b mut [RawFd],
+}
+
+impl<'a, 'b> Future for RecvWithFds<'a, 'b> {
+    type Output = io::Result<(usize, usize)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvWithFds { socket, buf, fds } = &mut *self;
+        ready!(socket.io.poll_read_ready(cx)?);
+
+        let r = sys::recv_with_fds(socket.as_raw_fd(), buf, fds);
+
+        if is_wouldblock(&r) {
+            socket.io.clear_read_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+}
+
 impl UnixDatagram {
     /// Creates a new `UnixDatagram` bound to the specified path.
     ///
@@ -90,13 +176,25 @@ impl UnixDatagram {
     /// # Ok(()) }
     /// ```
     pub fn pair() -> io::Result<(UnixDatagram, UnixDatagram)> {
-        let (a, b) = mio_uds::UnixDatagram::pair()?;
-        let a = UnixDatagram::new(a);
-        let b = UnixDatagram::new(b);
+        let (a, b) = sys::socketpair(libc::SOCK_DGRAM)?;
+        let a = UnixDatagram::new(unsafe { mio_uds::UnixDatagram::from_raw_fd(a) });
+        let b = UnixDatagram::new(unsafe { mio_uds::UnixDatagram::from_raw_fd(b) });
 
         Ok((a, b))
     }
 
+    /// Converts this socket into a blocking `std::os::unix::net::UnixDatagram`.
+    ///
+    /// The socket is deregistered from the reactor and put back into
+    /// blocking mode. Datagrams it has already received
+    /// stay queued, and are received by the returned socket.
+    pub fn into_std(self) -> io::Result<net::UnixDatagram> {
+        let fd = self.io.into_inner()?.into_raw_fd();
+        let socket = unsafe { net::UnixDatagram::from_raw_fd(fd) };
+        socket.set_nonblocking(false)?;
+        Ok(socket)
+    }
+
     fn new(socket: mio_uds::UnixDatagram) -> UnixDatagram {
         let io = PollEvented::new(socket);
         UnixDatagram { io }
@@ -180,7 +278,8 @@ impl UnixDatagram {
     /// ```no_run
     /// #![feature(async_await)]
     /// # use std::error::Error;
-    /// use romio::udp::UdpSocket;
+    /// use romio::uds::UnixDatagram;
+    /// use std::path::PathBuf;
     ///
     /// const THE_MERCHANT_OF_VENICE: &[u8] = b"
     ///     If you prick us, do we not bleed?
@@ -190,9 +289,8 @@ impl UnixDatagram {
     /// ";
     ///
     /// # async fn send_data() -> Result<(), Box<dyn Error + 'static>> {
-    /// let addr = "/tmp/out.socket".parse()?;
-    /// let target = "/tmp/in.socket".parse()?;
-    /// let mut socket = UdpSocket::bind(&addr)?;
+    /// let target = PathBuf::from("/tmp/in.socket");
+    /// let mut socket = UnixDatagram::bind("/tmp/out.socket")?;
     ///
     /// socket.send_to(THE_MERCHANT_OF_VENICE, &target).await?;
     /// # Ok(())
@@ -209,16 +307,15 @@ impl UnixDatagram {
     /// Receives data from the socket. On success, returns the number of bytes
     /// read and the address from whence the data came.
     ///
-    /// # Exampes
+    /// # Examples
     ///
     /// ```no_run
     /// #![feature(async_await)]
     /// # use std::error::Error;
-    /// use romio::udp::UdpSocket;
+    /// use romio::uds::UnixDatagram;
     ///
     /// # async fn recv_data() -> Result<Vec<u8>, Box<dyn Error + 'static>> {
-    /// let addr = "/tmp/in.socket".parse()?;
-    /// let mut socket = UdpSocket::bind(&addr)?;
+    /// let mut socket = UnixDatagram::bind("/tmp/in.socket")?;
     /// let mut buf = vec![0; 1024];
     ///
     /// socket.recv_from(&mut buf).await?;
@@ -228,6 +325,118 @@ impl UnixDatagram {
     pub fn recv_from<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFrom<'a, 'b> {
         RecvFrom { buf, socket: self }
     }
+
+    /// Connects the socket to the socket bound at `path`.
+    ///
+    /// Afterwards [`send`] sends to that socket and [`recv`] only receives
+    /// from it.
+    ///
+    /// [`send`]: #method.send
+    /// [`recv`]: #method.recv
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// # use std::error::Error;
+    /// use romio::uds::UnixDatagram;
+    ///
+    /// # async fn ping() -> Result<(), Box<dyn Error + 'static>> {
+    /// let mut socket = UnixDatagram::unbound()?;
+    /// socket.connect("/tmp/in.socket")?;
+    ///
+    /// socket.send(b"ping").await?;
+    /// let mut buf = vec![0; 1024];
+    /// let n = socket.recv(&mut buf).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn connect(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.io.get_ref().connect(path)
+    }
+
+    /// Sends data to the socket this one is connected to. On success,
+    /// returns the number of bytes written.
+    ///
+    /// The socket must have been connected with [`connect`], or created with
+    /// [`pair`].
+    ///
+    /// [`connect`]: #method.connect
+    /// [`pair`]: #method.pair
+    pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SendFuture<'a, 'b> {
+        SendFuture { buf, socket: self }
+    }
+
+    /// Receives data from the socket this one is connected to. On success,
+    /// returns the number of bytes read.
+    pub fn recv<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> RecvFuture<'a, 'b> {
+        RecvFuture { buf, socket: self }
+    }
+
+    /// Sends a datagram to the connected peer along with file descriptors.
+    /// On success, returns the number of bytes written.
+    ///
+    /// This works like [`UnixStream::send_with_fds`]; the socket must be
+    /// connected.
+    ///
+    /// [`UnixStream::send_with_fds`]: struct.UnixStream.html#method.send_with_fds
+    pub fn send_with_fds<'a, 'b>(
+        &'a mut self,
+        buf: &'b [u8],
+        fds: &'b [RawFd],
+    ) -> SendWithFds<'a, 'b> {
+        SendWithFds {
+            socket: self,
+            buf,
+            fds,
+        }
+    }
+
+    /// Receives a datagram along with the file descriptors that were sent
+    /// with it. On success, returns the number of bytes read and the number
+    /// of descriptors stored at the start of `fds`.
+    ///
+    /// This works like [`UnixStream::recv_with_fds`]; in particular the
+    /// caller owns the received descriptors.
+    ///
+    /// [`UnixStream::recv_with_fds`]: struct.UnixStream.html#method.recv_with_fds
+    pub fn recv_with_fds<'a, 'b>(
+        &'a mut self,
+        buf: &'b mut [u8],
+        fds: &'b mut [RawFd],
+    ) -> RecvWithFds<'a, 'b> {
+        RecvWithFds {
+            socket: self,
+            buf,
+            fds,
+        }
+    }
+
+    fn poll_send(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        let r = self.io.get_ref().send(buf);
+
+        if is_wouldblock(&r) {
+            self.io.clear_write_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        let r = self.io.get_ref().recv(buf);
+
+        if is_wouldblock(&r) {
+            self.io.clear_read_ready(cx)?;
+            Poll::Pending
+        } else {
+            Poll::Ready(r)
+        }
+    }
 }
 
 impl AsyncDatagram for UnixDatagram {
@@ -333,6 +542,45 @@ impl AsRawFd for UnixDatagram {
     }
 }
 
+/// Hands the socket over still in non-blocking mode, as
+/// [`PollEvented::into_inner_lossy`] describes.
+///
+/// [`PollEvented::into_inner_lossy`]: ../raw/struct.PollEvented.html#method.into_inner_lossy
+impl IntoRawFd for UnixDatagram {
+    fn into_raw_fd(self) -> RawFd {
+        self.io.into_inner_lossy().into_raw_fd()
+    }
+}
+
+/// Takes ownership of a Unix datagram socket, and puts it in non-blocking mode.
+impl FromRawFd for UnixDatagram {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixDatagram {
+        // This only fails for descriptors that aren't open, which fail every
+        // later operation as well.
+        let _ = sys::set_nonblocking(fd);
+        UnixDatagram::new(mio_uds::UnixDatagram::from_raw_fd(fd))
+    }
+}
+
+impl TryFrom<net::UnixDatagram> for UnixDatagram {
+    type Error = io::Error;
+
+    fn try_from(socket: net::UnixDatagram) -> Result<Self, Self::Error> {
+        mio_uds::UnixDatagram::from_datagram(socket).map(UnixDatagram::new)
+    }
+}
+
+/// Converts the socket with [`UnixDatagram::into_std`].
+///
+/// [`UnixDatagram::into_std`]: struct.UnixDatagram.html#method.into_std
+impl TryFrom<UnixDatagram> for net::UnixDatagram {
+    type Error = io::Error;
+
+    fn try_from(socket: UnixDatagram) -> io::Result<net::UnixDatagram> {
+        socket.into_std()
+    }
+}
+
 fn is_wouldblock<T>(r: &io::Result<T>) -> bool {
     match *r {
         Ok(_) => false,
@@ -1,20 +1,29 @@
-use super::UnixStream;
+use super::{UCred, UnixStream};
 
 use crate::raw::PollEvented;
+use crate::sys;
 
 use async_ready::{AsyncReady, TakeError};
-use futures::{ready, Poll, Stream};
+use futures::{ready, Future, Poll, Stream};
+use libc::{gid_t, uid_t};
 use mio_uds;
 
+use std::cmp;
 use std::convert::TryFrom;
 use std::fmt;
+use std::fs;
 use std::io;
-use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::os::unix::net::{self, SocketAddr};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::Context;
 
+/// The backlog `UnixListener::bind` listens with, which is std's.
+pub(super) const DEFAULT_BACKLOG: u32 = 128;
+
 /// A Unix socket which can accept connections from other Unix sockets.
 ///
 /// # Examples
@@ -29,7 +38,7 @@ use std::task::Context;
 /// }
 ///
 /// async fn listen() -> Result<(), Box<dyn std::error::Error + 'static>> {
-///     let listener = UnixListener::bind("/tmp/sock")?;
+///     let mut listener = UnixListener::bind("/tmp/sock")?;
 ///     let mut incoming = listener.incoming();
 ///
 ///     // accept connections and process them serially
@@ -41,6 +50,18 @@ use std::task::Context;
 /// ```
 pub struct UnixListener {
     io: PollEvented<mio_uds::UnixListener>,
+    /// The socket file to remove on drop, for listeners bound with cleanup.
+    file: Option<SocketFile>,
+}
+
+/// A socket file that is removed when dropped, unless another one has
+/// replaced it in the meantime.
+#[derive(Debug)]
+struct SocketFile {
+    /// Empty once the file has been handed over with the socket.
+    path: PathBuf,
+    dev: u64,
+    ino: u64,
 }
 
 impl UnixListener {
@@ -57,15 +78,142 @@ impl UnixListener {
     /// # Ok(())}
     /// ```
     ///
+    /// On Linux, a path starting with a NUL byte binds to the abstract
+    /// namespace instead of the filesystem: the rest of it is the name, and
+    /// no file is created.
+    ///
+    /// ```rust,no_run
+    /// use romio::uds::UnixListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket = UnixListener::bind("\0romio")?;
+    /// # Ok(())}
+    /// ```
     pub fn bind(path: impl AsRef<Path>) -> io::Result<UnixListener> {
         let listener = mio_uds::UnixListener::bind(path)?;
         let io = PollEvented::new(listener);
-        Ok(UnixListener { io })
+        Ok(UnixListener { io, file: None })
+    }
+
+    /// Creates a new `UnixListener` bound to `path`, which removes the
+    /// socket file when it is dropped.
+    ///
+    /// Otherwise the file stays behind once the listener is closed, and
+    /// binding to the same path again, e.g. on the next start, fails with
+    /// `AddrInUse`. The file is only removed if it is still the one this
+    /// listener created, and is left alone if the listener is turned into a
+    /// std listener or a raw descriptor. Listeners that are never dropped,
+    /// such as when the process is killed, leave it behind, which
+    /// [`UnixListenerBuilder::unlink_existing`] takes care of.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::uds::UnixListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let socket = UnixListener::bind_with_cleanup("/tmp/sock")?;
+    /// drop(socket);
+    /// assert!(!std::path::Path::new("/tmp/sock").exists());
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`UnixListenerBuilder::unlink_existing`]: struct.UnixListenerBuilder.html#method.unlink_existing
+    pub fn bind_with_cleanup(path: impl AsRef<Path>) -> io::Result<UnixListener> {
+        UnixListener::builder().remove_on_drop(true).bind(path)
+    }
+
+    /// Creates a new `UnixListener` bound to `addr`.
+    ///
+    /// The address is either a path, or on Linux an abstract name, such as
+    /// one built with `SocketAddrExt::from_abstract_name`. Unnamed addresses
+    /// fail with `io::ErrorKind::InvalidInput`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::uds::UnixListener;
+    /// use std::os::linux::net::SocketAddrExt;
+    /// use std::os::unix::net::SocketAddr;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let addr = SocketAddr::from_abstract_name(b"romio")?;
+    /// let socket = UnixListener::bind_addr(&addr)?;
+    /// # Ok(())}
+    /// ```
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<UnixListener> {
+        UnixListener::bind(sys::unix_addr_path(addr)?)
+    }
+
+    /// Takes the Unix listeners passed to this process by socket activation,
+    /// such as by systemd, in the order they were passed.
+    ///
+    /// This works like [`TcpListener::from_env`], which takes the TCP
+    /// listeners out of the same descriptors.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::uds::UnixListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// for listener in UnixListener::from_listen_fds()? {
+    ///     println!("listening on {:?}", listener.local_addr()?);
+    /// }
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`TcpListener::from_env`]: ../tcp/struct.TcpListener.html#method.from_env
+    pub fn from_listen_fds() -> io::Result<Vec<UnixListener>> {
+        let fds = sys::take_listen_fds(&[libc::AF_UNIX])?;
+        Ok(fds
+            .into_iter()
+            .map(|fd| unsafe { UnixListener::from_raw_fd(fd) })
+            .collect())
+    }
+
+    /// Returns a builder for a listener whose socket options are set before
+    /// it starts listening.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::uds::UnixListener;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let listener = UnixListener::builder().backlog(4096).bind("/tmp/sock")?;
+    /// # Ok(())}
+    /// ```
+    pub fn builder() -> UnixListenerBuilder {
+        UnixListenerBuilder {
+            backlog: DEFAULT_BACKLOG,
+            recv_buffer_size: None,
+            mode: None,
+            owner: None,
+            unlink_existing: false,
+            remove_on_drop: false,
+        }
+    }
+
+    /// Converts this socket into a blocking `std::os::unix::net::UnixListener`.
+    ///
+    /// The socket is deregistered from the reactor and put back into
+    /// blocking mode. Connections that are waiting in
+    /// the backlog stay there, and are accepted by the returned listener.
+    pub fn into_std(self) -> io::Result<net::UnixListener> {
+        let UnixListener { io, file } = self;
+        if let Some(file) = file {
+            file.keep();
+        }
+        let fd = io.into_inner()?.into_raw_fd();
+        let listener = unsafe { net::UnixListener::from_raw_fd(fd) };
+        listener.set_nonblocking(false)?;
+        Ok(listener)
     }
 
     fn new(listener: mio_uds::UnixListener) -> Self {
         let io = PollEvented::new(listener);
-        Self { io }
+        Self { io, file: None }
     }
 
     /// Returns the local socket address of this listener.
@@ -84,8 +232,10 @@ impl UnixListener {
         self.io.get_ref().local_addr()
     }
 
-    /// Consumes this listener, returning a stream of the sockets this listener
-    /// accepts.
+    /// Returns a stream of the sockets this listener accepts.
+    ///
+    /// The stream borrows the listener, which stays usable once the stream
+    /// is dropped, e.g. to read its address or to close it.
     ///
     /// This method returns an implementation of the `Stream` trait which
     /// resolves to the sockets the are accepted on this listener.
@@ -99,7 +249,7 @@ impl UnixListener {
     /// use futures::prelude::*;
     ///
     /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
-    /// let listener = UnixListener::bind("/tmp/sock")?;
+    /// let mut listener = UnixListener::bind("/tmp/sock")?;
     /// let mut incoming = listener.incoming();
     ///
     /// // accept connections and process them serially
@@ -113,8 +263,156 @@ impl UnixListener {
     /// }
     /// # Ok(())}
     /// ```
-    pub fn incoming(self) -> Incoming {
-        Incoming::new(self)
+    pub fn incoming(&mut self) -> Incoming<'_> {
+        Incoming { inner: self }
+    }
+
+    /// Returns a stream of the sockets this listener accepts together with
+    /// their peer addresses.
+    ///
+    /// On Linux, a client that autobinds to the abstract namespace is
+    /// reported with the unique abstract name the kernel assigned to it, which
+    /// a server can use to reply to it later, e.g. from a `UnixDatagram`.
+    /// Clients that didn't bind at all have an unnamed address.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixListener;
+    /// use futures::prelude::*;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut listener = UnixListener::bind("/tmp/sock")?;
+    /// let mut incoming = listener.incoming_with_addr();
+    ///
+    /// while let Some(res) = incoming.next().await {
+    ///     let (stream, addr) = res?;
+    ///     println!("new client at {:?}", addr);
+    /// }
+    /// # Ok(())}
+    /// ```
+    pub fn incoming_with_addr(&mut self) -> IncomingWithAddr<'_> {
+        IncomingWithAddr { inner: self }
+    }
+
+    /// Polls to accept a new connection, returning the socket and the address
+    /// of its peer.
+    ///
+    /// If no connection is waiting, `Poll::Pending` is returned and the
+    /// current task is woken once one arrives. The peer address is reported
+    /// as described for [`incoming_with_addr`].
+    ///
+    /// [`incoming_with_addr`]: #method.incoming_with_addr
+    pub fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(UnixStream, SocketAddr)>> {
+        Pin::new(self).poll_ready(cx)
+    }
+
+    /// Accepts a new connection, resolving to the socket and the address of
+    /// its peer.
+    ///
+    /// Unlike [`incoming`], this doesn't consume the listener, so a server
+    /// can accept in a loop and still reach the listener between
+    /// connections, e.g. to drain it on shutdown.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixListener;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut listener = UnixListener::bind("/tmp/sock")?;
+    ///
+    /// loop {
+    ///     let (stream, addr) = listener.accept().await?;
+    ///     println!("new client at {:?}", addr);
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`incoming`]: #method.incoming
+    pub fn accept(&mut self) -> Accept<'_> {
+        Accept { listener: self }
+    }
+
+    /// Accepts a new connection, resolving to the socket, the address of its
+    /// peer and the credentials of the peer process.
+    ///
+    /// This is [`accept`] followed by [`UnixStream::peer_cred`], for servers
+    /// that authorize each client by its user or group. The credentials are
+    /// those the peer had when it connected. If they can't be read, the
+    /// connection is closed and the error returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixListener;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut listener = UnixListener::bind("/tmp/sock")?;
+    ///
+    /// loop {
+    ///     let (stream, _, cred) = listener.accept_with_cred().await?;
+    ///     if cred.uid != 0 {
+    ///         drop(stream);
+    ///         continue;
+    ///     }
+    ///     // ... serve the client ...
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [`accept`]: #method.accept
+    /// [`UnixStream::peer_cred`]: struct.UnixStream.html#method.peer_cred
+    pub fn accept_with_cred(&mut self) -> AcceptWithCred<'_> {
+        AcceptWithCred { listener: self }
+    }
+
+    /// Accepts every connection currently waiting in the backlog, passing
+    /// each one to `handler`, and returns how many there were.
+    ///
+    /// This is meant for graceful shutdown: connections still queued when the
+    /// listener is closed would otherwise fail abruptly, while the handler can
+    /// reject them cleanly. This call doesn't wait for new connections.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::uds::UnixListener;
+    /// use std::net::Shutdown;
+    ///
+    /// # fn main () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut listener = UnixListener::bind("/tmp/sock")?;
+    ///
+    /// // ... serve until shutdown is requested ...
+    ///
+    /// listener.drain_backlog(|stream| {
+    ///     drop(stream.shutdown(Shutdown::Both));
+    /// })?;
+    /// # Ok(())}
+    /// ```
+    pub fn drain_backlog<F>(&mut self, mut handler: F) -> io::Result<usize>
+    where
+        F: FnMut(UnixStream),
+    {
+        let mut drained = 0;
+        loop {
+            match self.io.get_ref().accept_std() {
+                Ok(Some((io, _))) => {
+                    let io = mio_uds::UnixStream::from_stream(io)?;
+                    handler(UnixStream::new(io));
+                    drained += 1;
+                }
+                Ok(None) => return Ok(drained),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(drained),
+                Err(e) => return Err(e),
+            }
+        }
     }
 
     fn poll_accept_std(
@@ -186,6 +484,30 @@ impl AsRawFd for UnixListener {
     }
 }
 
+/// Hands the socket over still in non-blocking mode, as
+/// [`PollEvented::into_inner_lossy`] describes.
+///
+/// [`PollEvented::into_inner_lossy`]: ../raw/struct.PollEvented.html#method.into_inner_lossy
+impl IntoRawFd for UnixListener {
+    fn into_raw_fd(self) -> RawFd {
+        let UnixListener { io, file } = self;
+        if let Some(file) = file {
+            file.keep();
+        }
+        io.into_inner_lossy().into_raw_fd()
+    }
+}
+
+/// Takes ownership of a listening socket, and puts it in non-blocking mode.
+impl FromRawFd for UnixListener {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixListener {
+        // This only fails for descriptors that aren't open, which fail every
+        // later operation as well.
+        let _ = sys::set_nonblocking(fd);
+        UnixListener::new(mio_uds::UnixListener::from_raw_fd(fd))
+    }
+}
+
 impl TryFrom<net::UnixListener> for UnixListener {
     type Error = io::Error;
 
@@ -194,23 +516,250 @@ impl TryFrom<net::UnixListener> for UnixListener {
     }
 }
 
-/// Stream of listeners
+/// Converts the socket with [`UnixListener::into_std`].
+///
+/// [`UnixListener::into_std`]: struct.UnixListener.html#method.into_std
+impl TryFrom<UnixListener> for net::UnixListener {
+    type Error = io::Error;
+
+    fn try_from(listener: UnixListener) -> io::Result<net::UnixListener> {
+        listener.into_std()
+    }
+}
+
+impl SocketFile {
+    fn new(path: &Path) -> io::Result<SocketFile> {
+        let meta = fs::symlink_metadata(path)?;
+        Ok(SocketFile {
+            path: path.to_path_buf(),
+            dev: meta.dev(),
+            ino: meta.ino(),
+        })
+    }
+
+    /// Leaves the file in place.
+    fn keep(mut self) {
+        self.path = PathBuf::new();
+    }
+}
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if self.path.as_os_str().is_empty() {
+            return;
+        }
+        match fs::symlink_metadata(&self.path) {
+            Ok(ref meta) if meta.dev() == self.dev && meta.ino() == self.ino => {
+                let _ = fs::remove_file(&self.path);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A builder for a `UnixListener` with socket options that are set before it
+/// starts listening, returned by `UnixListener::builder`.
+///
+/// Options that aren't set keep the platform default; the backlog matches
+/// [`UnixListener::bind`].
+///
+/// [`UnixListener::bind`]: struct.UnixListener.html#method.bind
+#[derive(Debug, Clone)]
+pub struct UnixListenerBuilder {
+    backlog: u32,
+    recv_buffer_size: Option<usize>,
+    mode: Option<u32>,
+    owner: Option<(uid_t, gid_t)>,
+    unlink_existing: bool,
+    remove_on_drop: bool,
+}
+
+impl UnixListenerBuilder {
+    /// Sets how many connections the kernel queues before they're accepted.
+    ///
+    /// The kernel may cap this, e.g. at `net.core.somaxconn` on Linux.
+    pub fn backlog(mut self, backlog: u32) -> UnixListenerBuilder {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Sets `SO_RCVBUF`, the size of the receive buffer, which accepted
+    /// connections inherit.
+    pub fn recv_buffer_size(mut self, size: usize) -> UnixListenerBuilder {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the permissions of the socket file, such as `0o660`, after
+    /// which only the users they allow can connect.
+    ///
+    /// Unlike a `chmod` after binding, this leaves no window in which
+    /// others can connect: on Linux, the file is created with no more than
+    /// the owner's permissions, and only gets the rest once its mode and
+    /// owner are set. The `umask` doesn't apply. Abstract names have no file,
+    /// and ignore this.
+    pub fn mode(mut self, mode: u32) -> UnixListenerBuilder {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Sets the owner and group of the socket file, which requires the
+    /// privileges of `chown`.
+    ///
+    /// Combined with [`mode`], this lets a service running as root hand
+    /// its socket to the group of its clients. Abstract names ignore this.
+    ///
+    /// [`mode`]: #method.mode
+    pub fn chown(mut self, uid: uid_t, gid: gid_t) -> UnixListenerBuilder {
+        self.owner = Some((uid, gid));
+        self
+    }
+
+    /// Sets whether a socket file already at the path, typically left over
+    /// by a previous run, is removed before binding. Defaults to `false`,
+    /// in which case binding fails with `AddrInUse`.
+    ///
+    /// Only sockets are removed; binding over any other kind of file still
+    /// fails.
+    pub fn unlink_existing(mut self, unlink: bool) -> UnixListenerBuilder {
+        self.unlink_existing = unlink;
+        self
+    }
+
+    /// Sets whether the listener removes the socket file when it is dropped,
+    /// like one bound with [`UnixListener::bind_with_cleanup`]. Defaults to
+    /// `false`.
+    ///
+    /// [`UnixListener::bind_with_cleanup`]: struct.UnixListener.html#method.bind_with_cleanup
+    pub fn remove_on_drop(mut self, remove: bool) -> UnixListenerBuilder {
+        self.remove_on_drop = remove;
+        self
+    }
+
+    /// Creates the socket, applies the options, binds it to `path` and
+    /// starts listening.
+    ///
+    /// If the mode or the owner of the file can't be set, the file is
+    /// removed again and the error returned.
+    pub fn bind(&self, path: impl AsRef<Path>) -> io::Result<UnixListener> {
+        let path = path.as_ref();
+        let listener: net::UnixListener = sys::socket_owned(libc::AF_UNIX, libc::SOCK_STREAM, 0)?;
+        let fd = listener.as_raw_fd();
+
+        if let Some(size) = self.recv_buffer_size {
+            let size = cmp::min(size, libc::c_int::MAX as usize) as libc::c_int;
+            sys::setsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVBUF, size)?;
+        }
+
+        let has_file = path.as_os_str().as_bytes().first() != Some(&0);
+        if self.unlink_existing && has_file {
+            match fs::symlink_metadata(path) {
+                Ok(ref meta) if meta.file_type().is_socket() => fs::remove_file(path)?,
+                _ => {}
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            // Linux creates the file with the mode of the unbound socket,
+            // less the umask. Without a mode, the file keeps that default,
+            // and the owner set below can connect through it.
+            if let Some(mode) = self.mode.filter(|_| has_file) {
+                sys::cvt(unsafe { libc::fchmod(fd, mode & 0o700) })?;
+            }
+        }
+
+        sys::bind_unix(fd, path)?;
+        if has_file && (self.mode.is_some() || self.owner.is_some()) {
+            if let Err(e) = self.set_file_permissions(path) {
+                let _ = fs::remove_file(path);
+                return Err(e);
+            }
+        }
+        let file = if self.remove_on_drop && has_file {
+            Some(SocketFile::new(path)?)
+        } else {
+            None
+        };
+        sys::listen(fd, self.backlog)?;
+        let mut listener = mio_uds::UnixListener::from_listener(listener).map(UnixListener::new)?;
+        listener.file = file;
+        Ok(listener)
+    }
+
+    fn set_file_permissions(&self, path: &Path) -> io::Result<()> {
+        if let Some((uid, gid)) = self.owner {
+            std::os::unix::fs::chown(path, Some(uid), Some(gid))?;
+        }
+        if let Some(mode) = self.mode {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    }
+}
+
+/// The future returned by `UnixListener::accept`.
+#[must_use = "futures do nothing unless polled"]
 #[derive(Debug)]
-pub struct Incoming {
-    inner: UnixListener,
+pub struct Accept<'a> {
+    listener: &'a mut UnixListener,
 }
 
-impl Incoming {
-    pub(crate) fn new(listener: UnixListener) -> Incoming {
-        Incoming { inner: listener }
+impl<'a> Future for Accept<'a> {
+    type Output = io::Result<(UnixStream, SocketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.listener.poll_accept(cx)
     }
 }
 
-impl Stream for Incoming {
+/// The future returned by `UnixListener::accept_with_cred`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct AcceptWithCred<'a> {
+    listener: &'a mut UnixListener,
+}
+
+impl<'a> Future for AcceptWithCred<'a> {
+    type Output = io::Result<(UnixStream, SocketAddr, UCred)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (stream, addr) = ready!(self.listener.poll_accept(cx))?;
+        let cred = stream.peer_cred()?;
+        Poll::Ready(Ok((stream, addr, cred)))
+    }
+}
+
+/// Stream returned by `UnixListener::incoming`, of the sockets accepted by
+/// the listener.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct Incoming<'a> {
+    inner: &'a mut UnixListener,
+}
+
+impl<'a> Stream for Incoming<'a> {
     type Item = io::Result<UnixStream>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let (socket, _) = ready!(Pin::new(&mut self.inner).poll_ready(cx)?);
+        let (socket, _) = ready!(Pin::new(&mut *self.inner).poll_ready(cx)?);
         Poll::Ready(Some(Ok(socket)))
     }
 }
+
+/// Stream of accepted sockets and their peer addresses, returned by
+/// `UnixListener::incoming_with_addr`.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct IncomingWithAddr<'a> {
+    inner: &'a mut UnixListener,
+}
+
+impl<'a> Stream for IncomingWithAddr<'a> {
+    type Item = io::Result<(UnixStream, SocketAddr)>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let accepted = ready!(self.inner.poll_accept(cx)?);
+        Poll::Ready(Some(Ok(accepted)))
+    }
+}
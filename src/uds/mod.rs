@@ -12,7 +12,7 @@
 //! }
 //!
 //! async fn listen() -> Result<(), Box<dyn std::error::Error + 'static>> {
-//!     let listener = UnixListener::bind("/tmp/sock")?;
+//!     let mut listener = UnixListener::bind("/tmp/sock")?;
 //!     let mut incoming = listener.incoming();
 //!
 //!     // accept connections and process them serially
@@ -25,10 +25,21 @@
 
 mod datagram;
 mod listener;
+mod seqpacket;
+mod split;
 mod stream;
 mod ucred;
 
 pub use self::datagram::UnixDatagram;
-pub use self::listener::{Incoming, UnixListener};
-pub use self::stream::{ConnectFuture, UnixStream};
+pub use self::listener::{
+    Accept, AcceptWithCred, Incoming, IncomingWithAddr, UnixListener, UnixListenerBuilder,
+};
+pub use self::seqpacket::{
+    SeqPacketAccept, SeqPacketConnect, SeqPacketListener, SeqPacketRecv, SeqPacketSend,
+    SeqPacketStream,
+};
+pub use self::split::{OwnedReadHalf, OwnedWriteHalf, ReuniteError};
+pub use self::stream::{
+    ConnectFuture, ConnectTimeout, Readable, RecvWithFds, SendWithFds, UnixStream, Writable,
+};
 pub use self::ucred::UCred;
@@ -0,0 +1,362 @@
+use super::listener::DEFAULT_BACKLOG;
+use super::ucred::{self, UCred};
+
+use crate::raw::PollEvented;
+use crate::sys;
+
+use futures::{ready, Future, Poll};
+use mio_uds;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::{self, SocketAddr};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::Context;
+
+/// A Unix socket listening for `SOCK_SEQPACKET` connections.
+///
+/// Like a [`UnixListener`], but the connections it accepts are
+/// [`SeqPacketStream`]s, which keep the boundaries between the messages
+/// sent over them.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// #![feature(async_await)]
+/// use romio::uds::SeqPacketListener;
+///
+/// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+/// let mut listener = SeqPacketListener::bind("/tmp/sock")?;
+///
+/// loop {
+///     let (mut stream, _) = listener.accept().await?;
+///     let mut buf = [0; 1024];
+///     let n = stream.recv(&mut buf).await?;
+///     stream.send(&buf[..n]).await?;
+/// }
+/// # }
+/// ```
+///
+/// [`UnixListener`]: struct.UnixListener.html
+/// [`SeqPacketStream`]: struct.SeqPacketStream.html
+pub struct SeqPacketListener {
+    io: PollEvented<mio_uds::UnixListener>,
+}
+
+/// A connected `SOCK_SEQPACKET` Unix socket.
+///
+/// Each [`send`] arrives as a single message, received whole by one
+/// [`recv`], in order and reliably; receiving an empty message means the
+/// peer closed the connection, unless it sent one on purpose. Connect one
+/// with [`SeqPacketStream::connect`], accept one from a
+/// [`SeqPacketListener`], or create a pair with [`SeqPacketStream::pair`].
+///
+/// [`send`]: #method.send
+/// [`recv`]: #method.recv
+/// [`SeqPacketStream::connect`]: #method.connect
+/// [`SeqPacketStream::pair`]: #method.pair
+/// [`SeqPacketListener`]: struct.SeqPacketListener.html
+pub struct SeqPacketStream {
+    io: PollEvented<mio_uds::UnixStream>,
+}
+
+impl SeqPacketListener {
+    /// Creates a new `SeqPacketListener` bound to the specified path.
+    ///
+    /// As with [`UnixListener::bind`], a path starting with a NUL byte names
+    /// an address in the Linux abstract namespace.
+    ///
+    /// [`UnixListener::bind`]: struct.UnixListener.html#method.bind
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<SeqPacketListener> {
+        let listener: net::UnixListener =
+            sys::socket_owned(libc::AF_UNIX, libc::SOCK_SEQPACKET, 0)?;
+        sys::bind_unix(listener.as_raw_fd(), path.as_ref())?;
+        sys::listen(listener.as_raw_fd(), DEFAULT_BACKLOG)?;
+        let io = PollEvented::new(mio_uds::UnixListener::from_listener(listener)?);
+        Ok(SeqPacketListener { io })
+    }
+
+    /// Creates a new `SeqPacketListener` bound to `addr`, which is either a
+    /// path or an abstract name.
+    pub fn bind_addr(addr: &SocketAddr) -> io::Result<SeqPacketListener> {
+        SeqPacketListener::bind(sys::unix_addr_path(addr)?)
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    /// Attempts to accept a connection, returning the socket and the address
+    /// of its peer.
+    pub fn poll_accept(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<(SeqPacketStream, SocketAddr)>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match self.io.get_ref().accept_std() {
+            Ok(Some((sock, addr))) => {
+                let io = mio_uds::UnixStream::from_stream(sock)?;
+                Poll::Ready(Ok((SeqPacketStream::new(io), addr)))
+            }
+            Ok(None) => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+
+    /// Accepts a new connection, resolving to the socket and the address of
+    /// its peer.
+    pub fn accept(&mut self) -> SeqPacketAccept<'_> {
+        SeqPacketAccept { listener: self }
+    }
+}
+
+impl fmt::Debug for SeqPacketListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for SeqPacketListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl SeqPacketStream {
+    /// Connects to the `SOCK_SEQPACKET` socket named by `path`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::SeqPacketStream;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let mut stream = SeqPacketStream::connect("/tmp/sock").await?;
+    /// stream.send(b"hello").await?;
+    /// # Ok(()) }
+    /// ```
+    pub fn connect(path: impl AsRef<Path>) -> SeqPacketConnect {
+        let state = match SeqPacketStream::start_connect(path.as_ref()) {
+            Ok(stream) => State::Waiting(stream),
+            Err(e) => State::Error(e),
+        };
+        SeqPacketConnect { state }
+    }
+
+    /// Connects to the socket at `addr`, which is either a path or an
+    /// abstract name.
+    pub fn connect_addr(addr: &SocketAddr) -> SeqPacketConnect {
+        match sys::unix_addr_path(addr) {
+            Ok(path) => SeqPacketStream::connect(path),
+            Err(e) => SeqPacketConnect {
+                state: State::Error(e),
+            },
+        }
+    }
+
+    fn start_connect(path: &Path) -> io::Result<SeqPacketStream> {
+        let stream: mio_uds::UnixStream =
+            sys::socket_owned(libc::AF_UNIX, libc::SOCK_SEQPACKET, 0)?;
+        sys::connect_unix(stream.as_raw_fd(), path)?;
+        Ok(SeqPacketStream::new(stream))
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    pub fn pair() -> io::Result<(SeqPacketStream, SeqPacketStream)> {
+        let (a, b) = sys::socketpair(libc::SOCK_SEQPACKET)?;
+        let a = SeqPacketStream::new(unsafe { mio_uds::UnixStream::from_raw_fd(a) });
+        let b = SeqPacketStream::new(unsafe { mio_uds::UnixStream::from_raw_fd(b) });
+
+        Ok((a, b))
+    }
+
+    fn new(stream: mio_uds::UnixStream) -> SeqPacketStream {
+        let io = PollEvented::new(stream);
+        SeqPacketStream { io }
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().local_addr()
+    }
+
+    /// Returns the socket address of the remote half of this connection.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.io.get_ref().peer_addr()
+    }
+
+    /// Returns effective credentials of the process which called `connect`
+    /// or `pair`.
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        ucred::get_peer_cred(self)
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    ///
+    /// Shutting down the write half makes the peer receive an empty message
+    /// once it has received everything sent before.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.io.get_ref().shutdown(how)
+    }
+
+    /// Sends `buf` as a single message, resolving to its length.
+    ///
+    /// A message larger than the send buffer of the socket fails, rather
+    /// than being split.
+    pub fn send<'a, 'b>(&'a mut self, buf: &'b [u8]) -> SeqPacketSend<'a, 'b> {
+        SeqPacketSend { stream: self, buf }
+    }
+
+    /// Receives the next message into `buf`, resolving to the number of
+    /// bytes received.
+    ///
+    /// A message longer than `buf` is truncated, and the rest of it is
+    /// discarded. Zero means the peer closed the connection, or sent an
+    /// empty message.
+    pub fn recv<'a, 'b>(&'a mut self, buf: &'b mut [u8]) -> SeqPacketRecv<'a, 'b> {
+        SeqPacketRecv { stream: self, buf }
+    }
+
+    /// Attempts to send `buf` as a single message.
+    pub fn poll_send(&mut self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match self.io.get_ref().write(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    /// Attempts to receive the next message into `buf`.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match self.io.get_ref().read(buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+impl fmt::Debug for SeqPacketStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl AsRawFd for SeqPacketStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.io.get_ref().as_raw_fd()
+    }
+}
+
+impl IntoRawFd for SeqPacketStream {
+    fn into_raw_fd(self) -> RawFd {
+        self.io.into_inner_lossy().into_raw_fd()
+    }
+}
+
+/// The future returned by `SeqPacketListener::accept`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SeqPacketAccept<'a> {
+    listener: &'a mut SeqPacketListener,
+}
+
+impl<'a> Future for SeqPacketAccept<'a> {
+    type Output = io::Result<(SeqPacketStream, SocketAddr)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.listener.poll_accept(cx)
+    }
+}
+
+/// The future returned by `SeqPacketStream::connect`, which will resolve to
+/// a `SeqPacketStream` when the stream is connected.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SeqPacketConnect {
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    Waiting(SeqPacketStream),
+    Error(io::Error),
+    Empty,
+}
+
+impl Future for SeqPacketConnect {
+    type Output = io::Result<SeqPacketStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        use std::mem;
+
+        if let State::Waiting(ref mut stream) = self.state {
+            ready!(stream.io.poll_write_ready(cx)?);
+
+            if let Some(e) = stream.io.get_ref().take_error()? {
+                return Poll::Ready(Err(e));
+            }
+        }
+
+        match mem::replace(&mut self.state, State::Empty) {
+            State::Waiting(stream) => Poll::Ready(Ok(stream)),
+            State::Error(e) => Poll::Ready(Err(e)),
+            State::Empty => panic!("can't poll stream twice"),
+        }
+    }
+}
+
+/// The future returned by `SeqPacketStream::send`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SeqPacketSend<'a, 'b> {
+    stream: &'a mut SeqPacketStream,
+    buf: &'b [u8],
+}
+
+impl<'a, 'b> Future for SeqPacketSend<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SeqPacketSend { stream, buf } = &mut *self;
+        stream.poll_send(cx, buf)
+    }
+}
+
+/// The future returned by `SeqPacketStream::recv`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct SeqPacketRecv<'a, 'b> {
+    stream: &'a mut SeqPacketStream,
+    buf: &'b mut [u8],
+}
+
+impl<'a, 'b> Future for SeqPacketRecv<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SeqPacketRecv { stream, buf } = &mut *self;
+        stream.poll_recv(cx, buf)
+    }
+}
@@ -1,19 +1,24 @@
+use super::split::{self, OwnedReadHalf, OwnedWriteHalf};
 use super::ucred::{self, UCred};
 
 use crate::raw::PollEvented;
+use crate::reactor::timer::Delay;
+use crate::sys;
 
 use async_ready::{AsyncReadReady, AsyncWriteReady, TakeError};
 use futures::io::{AsyncRead, AsyncWrite};
 use futures::{ready, Future, Poll};
 
+use std::convert::TryFrom;
 use std::fmt;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
 use std::net::Shutdown;
-use std::os::unix::io::{AsRawFd, RawFd};
-use std::os::unix::net::SocketAddr;
-use std::path::Path;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::{self, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::task::Context;
+use std::time::{Duration, Instant};
 
 /// A structure representing a connected Unix socket.
 ///
@@ -38,6 +43,20 @@ enum State {
     Empty,
 }
 
+/// Future returned by `UnixStream::connect_timeout`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectTimeout {
+    path: PathBuf,
+    connect: Option<ConnectFuture>,
+    /// Set while waiting to retry a connect refused by a full backlog.
+    retry: Option<Delay>,
+    deadline: Delay,
+}
+
+/// How long to wait before retrying a connect refused by a full backlog.
+const CONNECT_RETRY: Duration = Duration::from_millis(10);
+
 impl Unpin for UnixStream {}
 
 impl UnixStream {
@@ -57,6 +76,9 @@ impl UnixStream {
     /// let stream = UnixStream::connect("/tmp/sock").await;
     /// # Ok(()) }
     /// ```
+    ///
+    /// On Linux, a path starting with a NUL byte names a socket in the
+    /// abstract namespace, as in `UnixStream::connect("\0romio")`.
     pub fn connect(path: impl AsRef<Path>) -> ConnectFuture {
         let res = mio_uds::UnixStream::connect(path).map(UnixStream::new);
 
@@ -68,6 +90,66 @@ impl UnixStream {
         ConnectFuture { inner }
     }
 
+    /// Connects to the socket at `addr`.
+    ///
+    /// The address is either a path, or on Linux an abstract name, such as
+    /// one built with `SocketAddrExt::from_abstract_name` or returned by
+    /// `UnixListener::local_addr`. Unnamed addresses fail with
+    /// `io::ErrorKind::InvalidInput`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixStream;
+    /// use std::os::linux::net::SocketAddrExt;
+    /// use std::os::unix::net::SocketAddr;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let addr = SocketAddr::from_abstract_name(b"romio")?;
+    /// let stream = UnixStream::connect_addr(&addr).await?;
+    /// # Ok(()) }
+    /// ```
+    pub fn connect_addr(addr: &SocketAddr) -> ConnectFuture {
+        match sys::unix_addr_path(addr) {
+            Ok(path) => UnixStream::connect(path),
+            Err(e) => ConnectFuture {
+                inner: State::Error(e),
+            },
+        }
+    }
+
+    /// Connects to the socket named by `path`, failing with
+    /// `io::ErrorKind::TimedOut` if the connection isn't established within
+    /// `timeout`.
+    ///
+    /// Connecting to a local socket is usually instant, but not when the
+    /// listener's backlog is full because its server stopped accepting. On
+    /// Linux the kernel then refuses the connect instead of queueing it, so
+    /// this keeps retrying until the server makes room or the timeout
+    /// expires. On timeout, the socket of the pending attempt is closed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixStream;
+    /// use std::time::Duration;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let stream = UnixStream::connect_timeout("/tmp/sock", Duration::from_secs(1)).await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    pub fn connect_timeout(path: impl AsRef<Path>, timeout: Duration) -> ConnectTimeout {
+        ConnectTimeout {
+            path: path.as_ref().to_path_buf(),
+            connect: None,
+            retry: None,
+            deadline: Delay::new(Instant::now() + timeout),
+        }
+    }
+
     /// Creates an unnamed pair of connected sockets.
     ///
     /// This function will create a pair of interconnected Unix sockets for
@@ -85,13 +167,25 @@ impl UnixStream {
     /// # Ok(()) }
     /// ```
     pub fn pair() -> io::Result<(UnixStream, UnixStream)> {
-        let (a, b) = mio_uds::UnixStream::pair()?;
-        let a = UnixStream::new(a);
-        let b = UnixStream::new(b);
+        let (a, b) = sys::socketpair(libc::SOCK_STREAM)?;
+        let a = UnixStream::new(unsafe { mio_uds::UnixStream::from_raw_fd(a) });
+        let b = UnixStream::new(unsafe { mio_uds::UnixStream::from_raw_fd(b) });
 
         Ok((a, b))
     }
 
+    /// Converts this socket into a blocking `std::os::unix::net::UnixStream`.
+    ///
+    /// The socket is deregistered from the reactor and put back into
+    /// blocking mode. Data already received by the socket
+    /// stays readable from the returned stream.
+    pub fn into_std(self) -> io::Result<net::UnixStream> {
+        let fd = self.io.into_inner()?.into_raw_fd();
+        let stream = unsafe { net::UnixStream::from_raw_fd(fd) };
+        stream.set_nonblocking(false)?;
+        Ok(stream)
+    }
+
     pub(crate) fn new(stream: mio_uds::UnixStream) -> UnixStream {
         let io = PollEvented::new(stream);
         UnixStream { io }
@@ -154,46 +248,340 @@ impl UnixStream {
     /// specified portions to immediately return with an appropriate value
     /// (see the documentation of `Shutdown`).
     ///
+    /// Shutting down `Shutdown::Write` half-closes the connection: the peer
+    /// reads the end of the stream after what was written so far, and this
+    /// side can keep reading. Unlike `AsyncWrite::poll_close`, which does
+    /// nothing for a `UnixStream`, it is what tells the peer that no more
+    /// data is coming. It never blocks.
+    ///
     /// ```rust
     /// #![feature(async_await)]
+    /// use futures::prelude::*;
     /// use romio::uds::UnixStream;
     /// use std::net::Shutdown;
     ///
     /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
-    /// let stream = UnixStream::connect("/tmp/sock").await?;
-    /// stream.shutdown(Shutdown::Both)?;
+    /// let mut stream = UnixStream::connect("/tmp/sock").await?;
+    /// stream.write_all(b"request").await?;
+    /// stream.shutdown(Shutdown::Write)?;
+    ///
+    /// let mut reply = Vec::new();
+    /// stream.read_to_end(&mut reply).await?;
     /// # Ok(())}
     /// ```
     pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
         self.io.get_ref().shutdown(how)
     }
+
+    /// Splits the stream into a reading half and a writing half, which can
+    /// be moved to separate tasks.
+    ///
+    /// Each half only waits for readiness in its own direction, so neither
+    /// holds up the other, and no lock is involved. The halves can be put
+    /// back together with [`OwnedReadHalf::reunite`].
+    ///
+    /// [`OwnedReadHalf::reunite`]: struct.OwnedReadHalf.html#method.reunite
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use futures::prelude::*;
+    /// use romio::uds::UnixStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let stream = UnixStream::connect("/tmp/sock").await?;
+    /// let (mut reader, mut writer) = stream.split();
+    ///
+    /// let mut buf = [0; 1024];
+    /// let (n, ()) = future::try_join(
+    ///     reader.read(&mut buf),
+    ///     writer.write_all(b"request"),
+    /// )
+    /// .await?;
+    ///
+    /// let stream = reader.reunite(writer)?;
+    /// # Ok(())}
+    /// ```
+    pub fn split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        split::split(self)
+    }
+
+    /// Sends data on the socket along with file descriptors. On success,
+    /// returns the number of bytes written.
+    ///
+    /// The descriptors travel in an `SCM_RIGHTS` control message and arrive
+    /// attached to the first byte written, then [`recv_with_fds`] on the other
+    /// end receives duplicates of them. They stay open here, and remain owned
+    /// by the caller. Up to 253 descriptors can be sent at once.
+    ///
+    /// If only part of `buf` is written, the descriptors have still been
+    /// sent, so the rest of the data should be sent without them.
+    ///
+    /// [`recv_with_fds`]: #method.recv_with_fds
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixStream;
+    /// use std::fs::File;
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut stream = UnixStream::connect("/tmp/sock").await?;
+    /// let file = File::open("/etc/hosts")?;
+    /// stream.send_with_fds(b"hosts", &[file.as_raw_fd()]).await?;
+    /// # Ok(())}
+    /// ```
+    pub fn send_with_fds<'a, 'b>(
+        &'a mut self,
+        buf: &'b [u8],
+        fds: &'b [RawFd],
+    ) -> SendWithFds<'a, 'b> {
+        SendWithFds {
+            socket: self,
+            buf,
+            fds,
+        }
+    }
+
+    /// Receives data from the socket along with the file descriptors that
+    /// were sent with it. On success, returns the number of bytes read and
+    /// the number of descriptors stored at the start of `fds`.
+    ///
+    /// The received descriptors are close-on-exec, and owned by the caller,
+    /// who must close them, e.g. by turning them into a `File` with
+    /// `FromRawFd`. If more descriptors were sent than fit in `fds`, all of
+    /// them are closed and the call fails with
+    /// `io::ErrorKind::InvalidData`; the bytes that came with them are lost.
+    ///
+    /// Descriptors are sent with [`send_with_fds`].
+    ///
+    /// [`send_with_fds`]: #method.send_with_fds
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::uds::UnixStream;
+    /// use std::fs::File;
+    /// use std::os::unix::io::FromRawFd;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut stream = UnixStream::connect("/tmp/sock").await?;
+    /// let mut buf = [0; 1024];
+    /// let mut fds = [0; 4];
+    /// let (n, nfds) = stream.recv_with_fds(&mut buf, &mut fds).await?;
+    /// let files: Vec<File> = fds[..nfds]
+    ///     .iter()
+    ///     .map(|&fd| unsafe { File::from_raw_fd(fd) })
+    ///     .collect();
+    /// # Ok(())}
+    /// ```
+    pub fn recv_with_fds<'a, 'b>(
+        &'a mut self,
+        buf: &'b mut [u8],
+        fds: &'b mut [RawFd],
+    ) -> RecvWithFds<'a, 'b> {
+        RecvWithFds {
+            socket: self,
+            buf,
+            fds,
+        }
+    }
+
+    /// Waits for the stream to become readable, without reading from it.
+    ///
+    /// The future resolves to the readiness that was observed once data is
+    /// waiting, or the peer has closed its side, so that the caller can then
+    /// read into buffers of its own choosing only when there is something
+    /// to read. This is the `async` counterpart of [`poll_read_ready`]: the
+    /// stream stays readable, and the future resolves right away, until a
+    /// read fails with `WouldBlock`, after which [`clear_read_ready`] should
+    /// be called if the read didn't go through the stream's own methods.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// #![feature(async_await)]
+    /// use futures::io::AsyncReadExt;
+    /// use romio::uds::UnixStream;
+    ///
+    /// # async fn run () -> Result<(), Box<dyn std::error::Error + 'static>> {
+    /// let mut stream = UnixStream::connect("/tmp/sock").await?;
+    ///
+    /// stream.readable().await?;
+    /// let mut buf = vec![0; 4096];
+    /// let n = stream.read(&mut buf).await?;
+    /// # Ok(())}
+    /// ```
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    /// [`clear_read_ready`]: #method.clear_read_ready
+    pub fn readable(&self) -> Readable<'_> {
+        Readable { stream: self }
+    }
+
+    /// Waits for the stream to become writable, without writing to it.
+    ///
+    /// This works like [`readable`], for writes: the stream stays writable
+    /// until a write fails with `WouldBlock`.
+    ///
+    /// [`readable`]: #method.readable
+    pub fn writable(&self) -> Writable<'_> {
+        Writable { stream: self }
+    }
+
+    /// Polls the stream for read readiness, for code that makes its own
+    /// syscalls on its descriptor.
+    ///
+    /// This works like [`PollEvented::poll_read_ready`], which describes when
+    /// to clear the readiness again.
+    ///
+    /// [`PollEvented::poll_read_ready`]: ../raw/struct.PollEvented.html#method.poll_read_ready
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    /// Polls the stream for write readiness, for code that makes its own
+    /// syscalls on its descriptor.
+    ///
+    /// This works like [`poll_read_ready`], for writes.
+    ///
+    /// [`poll_read_ready`]: #method.poll_read_ready
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<mio::Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+
+    /// Clears the read readiness of the stream, and arranges for the current
+    /// task to be woken once it is readable again.
+    pub fn clear_read_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_read_ready(cx)
+    }
+
+    /// Clears the write readiness of the stream, and arranges for the current
+    /// task to be woken once it is writable again.
+    pub fn clear_write_ready(&self, cx: &mut Context<'_>) -> io::Result<()> {
+        self.io.clear_write_ready(cx)
+    }
 }
 
 impl AsyncRead for UnixStream {
     fn poll_read(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_read(cx, buf)
+        Pin::new(&mut &*self).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self).poll_read_vectored(cx, bufs)
     }
 }
 
 impl AsyncWrite for UnixStream {
     fn poll_write(
-        mut self: Pin<&mut Self>,
+        self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.io).poll_write(cx, buf)
+        Pin::new(&mut &*self).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &*self).poll_write_vectored(cx, bufs)
     }
 
-    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.io).poll_flush(cx)
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self).poll_flush(cx)
     }
 
-    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.io).poll_close(cx)
+    /// Does nothing: the connection is closed when the stream is dropped.
+    /// Use [`shutdown`](#method.shutdown) with `Shutdown::Write` to signal
+    /// the end of the stream to the peer while still reading from it.
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &*self).poll_close(cx)
+    }
+}
+
+/// A shared reference reads and writes the stream just like an owned one,
+/// which lets the two directions be driven through separate references.
+/// Each direction should still be driven by one task at a time: a second
+/// task reading through another reference takes the wakeups of the first.
+impl AsyncRead for &UnixStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.io.poll_read_ready(cx)?);
+
+        match sys::readv(self.io.get_ref().as_raw_fd(), bufs) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+impl AsyncWrite for &UnixStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        // An empty write on a stream is a no-op, so don't wait for readiness.
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        Pin::new(&mut &self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        if bufs.iter().all(|buf| buf.is_empty()) {
+            return Poll::Ready(Ok(0));
+        }
+        ready!(self.io.poll_write_ready(cx)?);
+
+        match sys::writev(self.io.get_ref().as_raw_fd(), bufs) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                self.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.io).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.io).poll_close(cx)
     }
 }
 
@@ -260,6 +648,125 @@ impl AsRawFd for UnixStream {
     }
 }
 
+/// Hands the socket over still in non-blocking mode, as
+/// [`PollEvented::into_inner_lossy`] describes.
+///
+/// [`PollEvented::into_inner_lossy`]: ../raw/struct.PollEvented.html#method.into_inner_lossy
+impl IntoRawFd for UnixStream {
+    fn into_raw_fd(self) -> RawFd {
+        self.io.into_inner_lossy().into_raw_fd()
+    }
+}
+
+/// Takes ownership of a connected socket, and puts it in non-blocking mode.
+impl FromRawFd for UnixStream {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixStream {
+        // This only fails for descriptors that aren't open, which fail every
+        // later operation as well.
+        let _ = sys::set_nonblocking(fd);
+        UnixStream::new(mio_uds::UnixStream::from_raw_fd(fd))
+    }
+}
+
+impl TryFrom<net::UnixStream> for UnixStream {
+    type Error = io::Error;
+
+    fn try_from(stream: net::UnixStream) -> Result<Self, Self::Error> {
+        mio_uds::UnixStream::from_stream(stream).map(UnixStream::new)
+    }
+}
+
+/// Converts the socket with [`UnixStream::into_std`].
+///
+/// [`UnixStream::into_std`]: struct.UnixStream.html#method.into_std
+impl TryFrom<UnixStream> for net::UnixStream {
+    type Error = io::Error;
+
+    fn try_from(stream: UnixStream) -> io::Result<net::UnixStream> {
+        stream.into_std()
+    }
+}
+
+/// The future returned by `UnixStream::readable`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Readable<'a> {
+    stream: &'a UnixStream,
+}
+
+impl<'a> Future for Readable<'a> {
+    type Output = io::Result<mio::Ready>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_read_ready(cx)
+    }
+}
+
+/// The future returned by `UnixStream::writable`.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Writable<'a> {
+    stream: &'a UnixStream,
+}
+
+impl<'a> Future for Writable<'a> {
+    type Output = io::Result<mio::Ready>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.stream.poll_write_ready(cx)
+    }
+}
+
+/// The future returned by `UnixStream::send_with_fds`.
+#[derive(Debug)]
+pub struct SendWithFds<'a, 'b> {
+    socket: &'a mut UnixStream,
+    buf: &'b [u8],
+    fds: &'b [RawFd],
+}
+
+impl<'a, 'b> Future for SendWithFds<'a, 'b> {
+    type Output = io::Result<usize>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let SendWithFds { socket, buf, fds } = &mut *self;
+        ready!(socket.io.poll_write_ready(cx)?);
+
+        match sys::send_with_fds(socket.as_raw_fd(), buf, fds) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                socket.io.clear_write_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
+/// The future returned by `UnixStream::recv_with_fds`.
+#[derive(Debug)]
+pub struct RecvWithFds<'a, 'b> {
+    socket: &'a mut UnixStream,
+    buf: &'b mut [u8],
+    fds: &'b mut [RawFd],
+}
+
+impl<'a, 'b> Future for RecvWithFds<'a, 'b> {
+    type Output = io::Result<(usize, usize)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let RecvWithFds { socket, buf, fds } = &mut *self;
+        ready!(socket.io.poll_read_ready(cx)?);
+
+        match sys::recv_with_fds(socket.as_raw_fd(), buf, fds) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                socket.io.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            r => Poll::Ready(r),
+        }
+    }
+}
+
 impl Future for ConnectFuture {
     type Output = io::Result<UnixStream>;
 
@@ -291,3 +798,50 @@ impl Future for ConnectFuture {
         }
     }
 }
+
+impl Future for ConnectTimeout {
+    type Output = io::Result<UnixStream>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<UnixStream>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(ref mut retry) = this.retry {
+                if retry.poll_elapsed(cx)?.is_pending() {
+                    break;
+                }
+                this.retry = None;
+            }
+
+            match this.connect {
+                Some(ref mut connect) => match Pin::new(connect).poll(cx) {
+                    Poll::Ready(res) => return Poll::Ready(res),
+                    Poll::Pending => break,
+                },
+                None => {
+                    let stream: mio_uds::UnixStream =
+                        sys::socket_owned(libc::AF_UNIX, libc::SOCK_STREAM, 0)?;
+                    match sys::connect_unix(stream.as_raw_fd(), &this.path) {
+                        Ok(()) => {
+                            let inner = State::Waiting(UnixStream::new(stream));
+                            this.connect = Some(ConnectFuture { inner });
+                        }
+                        // The backlog is full; the kernel doesn't queue the
+                        // attempt, so try again shortly.
+                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            this.retry = Some(Delay::new(Instant::now() + CONNECT_RETRY));
+                        }
+                        Err(e) => return Poll::Ready(Err(e)),
+                    }
+                }
+            }
+        }
+
+        ready!(this.deadline.poll_elapsed(cx))?;
+        this.connect = None;
+        Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "connect timed out",
+        )))
+    }
+}
@@ -24,14 +24,13 @@ pub(crate) use self::impl_macos::get_peer_cred;
 
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub(crate) mod impl_linux {
-    use crate::uds::UnixStream;
     use libc::{c_void, getsockopt, socklen_t, SOL_SOCKET, SO_PEERCRED};
     use std::os::unix::io::AsRawFd;
     use std::{io, mem};
 
     use libc::ucred;
 
-    pub(crate) fn get_peer_cred(sock: &UnixStream) -> io::Result<super::UCred> {
+    pub(crate) fn get_peer_cred(sock: &impl AsRawFd) -> io::Result<super::UCred> {
         unsafe {
             let raw_fd = sock.as_raw_fd();
 
@@ -77,12 +76,11 @@ pub(crate) mod impl_linux {
     target_os = "openbsd"
 ))]
 pub(crate) mod impl_macos {
-    use crate::uds::UnixStream;
     use libc::getpeereid;
     use std::os::unix::io::AsRawFd;
     use std::{io, mem};
 
-    pub(crate) fn get_peer_cred(sock: &UnixStream) -> io::Result<super::UCred> {
+    pub(crate) fn get_peer_cred(sock: &impl AsRawFd) -> io::Result<super::UCred> {
         unsafe {
             let raw_fd = sock.as_raw_fd();
 
@@ -0,0 +1,39 @@
+//! Windows-specific I/O types.
+//!
+//! This module holds the async counterparts of Windows IPC primitives that
+//! have no portable equivalent, the way [`uds`] does for Unix.
+//!
+//! [`uds`]: ../uds/index.html
+//!
+//! # Example
+//!
+//! ```no_run
+//! #![feature(async_await)]
+//! use futures::prelude::*;
+//! use romio::windows::{NamedPipeClient, NamedPipeServer};
+//!
+//! const PIPE_NAME: &str = r"\\.\pipe\romio-example";
+//!
+//! async fn serve() -> std::io::Result<()> {
+//!     let mut server = NamedPipeServer::create(PIPE_NAME)?;
+//!     loop {
+//!         server.accept().await?;
+//!         // create the next instance before handling this client, so that
+//!         // clients connecting in the meantime find one
+//!         let next = server.next_instance()?;
+//!         let mut client = std::mem::replace(&mut server, next);
+//!         client.write_all(b"Shall I hear more, or shall I speak at this?!").await?;
+//!     }
+//! }
+//!
+//! async fn connect() -> std::io::Result<()> {
+//!     let mut client = NamedPipeClient::connect(PIPE_NAME).await?;
+//!     let mut line = vec![];
+//!     client.read_to_end(&mut line).await?;
+//!     Ok(())
+//! }
+//! ```
+
+mod named_pipe;
+
+pub use self::named_pipe::{Accept, ConnectFuture, NamedPipeClient, NamedPipeServer};
@@ -0,0 +1,320 @@
+use crate::raw::PollEvented;
+use crate::reactor::timer::Delay;
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Future, Poll};
+use miow::pipe::NamedPipeBuilder;
+use winapi::shared::winerror::ERROR_PIPE_BUSY;
+use winapi::um::winbase::FILE_FLAG_OVERLAPPED;
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io;
+use std::os::windows::fs::OpenOptionsExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, IntoRawHandle, RawHandle};
+use std::pin::Pin;
+use std::task::Context;
+use std::time::{Duration, Instant};
+
+/// How long to wait before retrying a connect to a pipe whose instances are
+/// all busy.
+const CONNECT_RETRY: Duration = Duration::from_millis(10);
+
+/// The server end of a named pipe.
+///
+/// Each `NamedPipeServer` is one instance of the pipe, which serves a single
+/// client at a time. An instance is created with [`create`], or with
+/// [`next_instance`] for further instances of a pipe that already exists, and
+/// waits for a client with [`accept`]. Once a client has connected, the
+/// instance reads and writes like a stream.
+///
+/// To serve several clients, create the next instance right after a client
+/// was accepted: clients that connect while no instance is waiting fail
+/// with `ERROR_PIPE_BUSY`, which [`NamedPipeClient::connect`] retries.
+///
+/// [`create`]: #method.create
+/// [`next_instance`]: #method.next_instance
+/// [`accept`]: #method.accept
+/// [`NamedPipeClient::connect`]: struct.NamedPipeClient.html#method.connect
+pub struct NamedPipeServer {
+    io: PollEvented<mio_named_pipes::NamedPipe>,
+    name: OsString,
+}
+
+/// The client end of a named pipe, connected with
+/// [`NamedPipeClient::connect`].
+///
+/// [`NamedPipeClient::connect`]: #method.connect
+pub struct NamedPipeClient {
+    io: PollEvented<mio_named_pipes::NamedPipe>,
+}
+
+/// The future returned by [`NamedPipeServer::accept`].
+///
+/// [`NamedPipeServer::accept`]: struct.NamedPipeServer.html#method.accept
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Accept<'a> {
+    server: &'a NamedPipeServer,
+}
+
+/// The future returned by [`NamedPipeClient::connect`].
+///
+/// [`NamedPipeClient::connect`]: struct.NamedPipeClient.html#method.connect
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct ConnectFuture {
+    name: OsString,
+    /// Set while waiting to retry a connect to a busy pipe.
+    retry: Option<Delay>,
+}
+
+// ===== impl NamedPipeServer =====
+
+impl NamedPipeServer {
+    /// Creates the first instance of the pipe named `name`.
+    ///
+    /// Pipe names have the form `\\.\pipe\<name>`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a pipe of that name already exists, so that a server can't
+    /// unknowingly take over the clients of another one. Further instances of
+    /// an existing pipe are created with [`next_instance`].
+    ///
+    /// [`next_instance`]: #method.next_instance
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use romio::windows::NamedPipeServer;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let server = NamedPipeServer::create(r"\\.\pipe\romio-example")?;
+    /// # Ok(()) }
+    /// ```
+    pub fn create(name: impl AsRef<OsStr>) -> io::Result<NamedPipeServer> {
+        NamedPipeServer::new(name.as_ref(), true)
+    }
+
+    /// Creates another instance of this pipe, for the next client.
+    pub fn next_instance(&self) -> io::Result<NamedPipeServer> {
+        NamedPipeServer::new(&self.name, false)
+    }
+
+    fn new(name: &OsStr, first: bool) -> io::Result<NamedPipeServer> {
+        let pipe = NamedPipeBuilder::new(name).first(first).create()?;
+        let pipe = unsafe { mio_named_pipes::NamedPipe::from_raw_handle(pipe.into_raw_handle()) };
+        Ok(NamedPipeServer {
+            io: PollEvented::new(pipe),
+            name: name.to_owned(),
+        })
+    }
+
+    /// Waits for a client to connect to this instance.
+    ///
+    /// A client that connected before `accept` was called is accepted right
+    /// away.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::windows::NamedPipeServer;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let server = NamedPipeServer::create(r"\\.\pipe\romio-example")?;
+    /// server.accept().await?;
+    /// # Ok(()) }
+    /// ```
+    pub fn accept(&self) -> Accept<'_> {
+        Accept { server: self }
+    }
+
+    /// Polls for a client to connect to this instance.
+    ///
+    /// If no client has connected yet, `Poll::Pending` is returned and the
+    /// current task is woken once one has.
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            // Registers the pipe on the first call, which `connect` needs.
+            ready!(self.io.poll_write_ready(cx))?;
+            if let Some(e) = self.io.get_ref().take_error()? {
+                return Poll::Ready(Err(e));
+            }
+            match self.io.get_ref().connect() {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    self.io.clear_write_ready(cx)?;
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+
+    /// Disconnects the client of this instance, if any.
+    ///
+    /// Data the client hasn't read yet is discarded. The instance can then
+    /// [`accept`] the next client.
+    ///
+    /// [`accept`]: #method.accept
+    pub fn disconnect(&self) -> io::Result<()> {
+        self.io.get_ref().disconnect()
+    }
+}
+
+impl AsyncRead for NamedPipeServer {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NamedPipeServer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.io).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.io).poll_close(cx)
+    }
+}
+
+impl AsRawHandle for NamedPipeServer {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.io.get_ref().as_raw_handle()
+    }
+}
+
+impl fmt::Debug for NamedPipeServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NamedPipeServer")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<'a> Future for Accept<'a> {
+    type Output = io::Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.server.poll_accept(cx)
+    }
+}
+
+// ===== impl NamedPipeClient =====
+
+impl NamedPipeClient {
+    /// Connects to the pipe named `name`.
+    ///
+    /// While all instances of the pipe are serving other clients, this keeps
+    /// retrying until the server creates another one.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `io::ErrorKind::NotFound` if there is no pipe of that name.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// #![feature(async_await)]
+    /// use romio::windows::NamedPipeClient;
+    ///
+    /// # async fn run() -> std::io::Result<()> {
+    /// let client = NamedPipeClient::connect(r"\\.\pipe\romio-example").await?;
+    /// # Ok(()) }
+    /// ```
+    pub fn connect(name: impl AsRef<OsStr>) -> ConnectFuture {
+        ConnectFuture {
+            name: name.as_ref().to_owned(),
+            retry: None,
+        }
+    }
+}
+
+impl AsyncRead for NamedPipeClient {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NamedPipeClient {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.io).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.io).poll_close(cx)
+    }
+}
+
+impl AsRawHandle for NamedPipeClient {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.io.get_ref().as_raw_handle()
+    }
+}
+
+impl fmt::Debug for NamedPipeClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.io.get_ref().fmt(f)
+    }
+}
+
+impl Future for ConnectFuture {
+    type Output = io::Result<NamedPipeClient>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        loop {
+            if let Some(ref mut retry) = this.retry {
+                ready!(retry.poll_elapsed(cx))?;
+                this.retry = None;
+            }
+
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(FILE_FLAG_OVERLAPPED)
+                .open(&this.name);
+            match file {
+                Ok(file) => {
+                    let pipe = unsafe {
+                        mio_named_pipes::NamedPipe::from_raw_handle(file.into_raw_handle())
+                    };
+                    let io = PollEvented::new(pipe);
+                    return Poll::Ready(Ok(NamedPipeClient { io }));
+                }
+                // Every instance is serving a client; the server may create
+                // another one shortly.
+                Err(ref e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) => {
+                    this.retry = Some(Delay::new(Instant::now() + CONNECT_RETRY));
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::executor;
+use futures::io::AsyncRead;
+use futures::{SinkExt, StreamExt};
+
+use romio::codec::{
+    BytesCodec, Decoder, Encoder, Framed, FramedRead, FramedWrite, LengthDelimitedCodec,
+    LinesCodec, UdpFramed,
+};
+use romio::udp::UdpSocket;
+use romio::uds::UnixStream;
+
+/// A newline-delimited codec shaped like `tokio_codec::LinesCodec`.
+struct Lines;
+
+impl Decoder for Lines {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        let i = match src.iter().position(|&b| b == b'\n') {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+        let line = src.split_to(i + 1);
+        String::from_utf8(line[..i].to_vec())
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Encoder for Lines {
+    type Item = String;
+    type Error = io::Error;
+
+    fn encode(&mut self, line: String, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(line.len() + 1);
+        dst.put(line);
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+#[test]
+fn framed_round_trips_lines() -> io::Result<()> {
+    let (a, b) = UnixStream::pair()?;
+    let mut tx = Framed::new(a, Lines);
+    let mut rx = Framed::new(b, Lines);
+
+    executor::block_on(async {
+        tx.send("hello".to_string()).await?;
+        tx.send("world".to_string()).await?;
+        tx.close().await?;
+        drop(tx);
+
+        assert_eq!(rx.next().await.unwrap()?, "hello");
+        assert_eq!(rx.next().await.unwrap()?, "world");
+        assert!(rx.next().await.is_none());
+        Ok(())
+    })
+}
+
+#[test]
+fn framed_reports_trailing_bytes_at_eof() -> io::Result<()> {
+    let (mut a, b) = UnixStream::pair()?;
+    let mut rx = Framed::new(b, Lines);
+
+    executor::block_on(async {
+        use futures::io::AsyncWriteExt;
+        a.write_all(b"complete\npartial").await?;
+        drop(a);
+
+        assert_eq!(rx.next().await.unwrap()?, "complete");
+        assert!(rx.next().await.unwrap().is_err());
+        Ok(())
+    })
+}
+
+#[cfg(feature = "tokio-codec")]
+#[test]
+fn framed_accepts_tokio_codecs() -> io::Result<()> {
+    use tokio_codec::LinesCodec;
+
+    let (a, b) = UnixStream::pair()?;
+    let mut tx = Framed::new(a, LinesCodec::new());
+    let mut rx = Framed::new(b, LinesCodec::new());
+
+    executor::block_on(async {
+        tx.send("hello".to_string()).await?;
+        tx.close().await?;
+        drop(tx);
+
+        assert_eq!(rx.next().await.unwrap()?, "hello");
+        assert!(rx.next().await.is_none());
+        Ok(())
+    })
+}
+
+/// Counts the reads issued against the wrapped reader.
+struct CountReads<R> {
+    inner: R,
+    reads: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CountReads<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.reads += 1;
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+#[test]
+fn framed_decodes_buffered_frames_before_reading() -> io::Result<()> {
+    let io = CountReads {
+        inner: &b"one\ntwo\nthree\n"[..],
+        reads: 0,
+    };
+    let mut rx = Framed::new(io, Lines);
+
+    executor::block_on(async {
+        assert_eq!(rx.next().await.unwrap()?, "one");
+        assert_eq!(rx.next().await.unwrap()?, "two");
+        assert_eq!(rx.next().await.unwrap()?, "three");
+        assert_eq!(rx.get_ref().reads, 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn udp_framed_decodes_one_frame_per_datagram() -> io::Result<()> {
+    let any = "127.0.0.1:0".parse().unwrap();
+    let a = UdpSocket::bind(&any)?;
+    let b = UdpSocket::bind(&any)?;
+    let a_addr = a.local_addr()?;
+    let b_addr = b.local_addr()?;
+    let mut tx = UdpFramed::new(a, Lines);
+    let mut rx = UdpFramed::new(b, Lines);
+
+    executor::block_on(async {
+        tx.send(("hello".to_string(), b_addr)).await?;
+        // not a complete line, so this datagram yields no frame
+        tx.get_mut().send_to(b"partial", &b_addr).await?;
+        tx.send(("world".to_string(), b_addr)).await?;
+
+        assert_eq!(rx.next().await.unwrap()?, ("hello".to_string(), a_addr));
+        assert_eq!(rx.next().await.unwrap()?, ("world".to_string(), a_addr));
+        Ok(())
+    })
+}
+
+#[test]
+fn udp_framed_lines_skip_unterminated_datagrams() -> io::Result<()> {
+    let any = "127.0.0.1:0".parse().unwrap();
+    let a = UdpSocket::bind(&any)?;
+    let b = UdpSocket::bind(&any)?;
+    let a_addr = a.local_addr()?;
+    let b_addr = b.local_addr()?;
+    let mut tx = UdpFramed::new(a, LinesCodec::new());
+    let mut rx = UdpFramed::new(b, LinesCodec::new());
+
+    executor::block_on(async {
+        tx.get_mut().send_to(b"no newline", &b_addr).await?;
+        tx.send(("short".to_string(), b_addr)).await?;
+
+        assert_eq!(rx.next().await.unwrap()?, ("short".to_string(), a_addr));
+        Ok(())
+    })
+}
+
+#[test]
+fn udp_framed_lines_find_early_newlines_after_unterminated_datagrams() -> io::Result<()> {
+    let any = "127.0.0.1:0".parse().unwrap();
+    let a = UdpSocket::bind(&any)?;
+    let b = UdpSocket::bind(&any)?;
+    let a_addr = a.local_addr()?;
+    let b_addr = b.local_addr()?;
+    let mut tx = UdpFramed::new(a, LinesCodec::new());
+    let mut rx = UdpFramed::new(b, LinesCodec::new());
+
+    executor::block_on(async {
+        tx.get_mut().send_to(b"no newline", &b_addr).await?;
+        tx.get_mut().send_to(b"hi\nxxxxxxxxxxxx", &b_addr).await?;
+
+        assert_eq!(rx.next().await.unwrap()?, ("hi".to_string(), a_addr));
+        Ok(())
+    })
+}
+
+#[test]
+fn framed_read_and_write_carry_lines() -> io::Result<()> {
+    let (a, b) = UnixStream::pair()?;
+    let mut tx = FramedWrite::new(a, LinesCodec::new());
+    let mut rx = FramedRead::new(b, LinesCodec::new());
+
+    executor::block_on(async {
+        use futures::io::AsyncWriteExt;
+        tx.send("hello".to_string()).await?;
+        tx.get_mut().write_all(b"crlf\r\nunterminated").await?;
+        tx.close().await?;
+        drop(tx);
+
+        assert_eq!(rx.next().await.unwrap()?, "hello");
+        assert_eq!(rx.next().await.unwrap()?, "crlf");
+        assert_eq!(rx.next().await.unwrap()?, "unterminated");
+        assert!(rx.next().await.is_none());
+        Ok(())
+    })
+}
+
+#[test]
+fn lines_codec_rejects_long_lines() {
+    let mut codec = LinesCodec::with_max_length(4);
+    let mut buf = BytesMut::from(&b"abcd\nabcdefgh"[..]);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "abcd");
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn length_delimited_codec_reassembles_split_frames() -> io::Result<()> {
+    let mut codec = LengthDelimitedCodec::new();
+    let mut encoded = BytesMut::new();
+    codec.encode(Bytes::from_static(b"hello"), &mut encoded)?;
+    codec.encode(Bytes::from_static(b""), &mut encoded)?;
+    assert_eq!(&encoded[..], &b"\0\0\0\x05hello\0\0\0\0"[..]);
+
+    // feed the bytes in one at a time
+    let mut buf = BytesMut::new();
+    let mut frames = Vec::new();
+    for &byte in encoded.iter() {
+        buf.extend_from_slice(&[byte]);
+        while let Some(frame) = codec.decode(&mut buf)? {
+            frames.push(frame);
+        }
+    }
+    assert_eq!(frames, vec![&b"hello"[..], &b""[..]]);
+    Ok(())
+}
+
+#[test]
+fn length_delimited_codec_enforces_the_maximum_frame_length() {
+    let mut codec = LengthDelimitedCodec::new();
+    codec.set_max_frame_length(4);
+
+    let mut buf = BytesMut::new();
+    let err = codec
+        .encode(Bytes::from_static(b"hello"), &mut buf)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let mut buf = BytesMut::from(&b"\0\0\0\x05hello"[..]);
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn framed_passes_bytes_through() -> io::Result<()> {
+    let (a, b) = UnixStream::pair()?;
+    let mut tx = Framed::new(a, BytesCodec::new());
+    let mut rx = Framed::new(b, BytesCodec::new());
+
+    executor::block_on(async {
+        tx.send(Bytes::from_static(b"raw bytes")).await?;
+        tx.close().await?;
+        drop(tx);
+
+        let mut received = Vec::new();
+        while let Some(chunk) = rx.next().await {
+            received.extend_from_slice(&chunk?);
+        }
+        assert_eq!(received, b"raw bytes");
+        Ok(())
+    })
+}
@@ -0,0 +1,178 @@
+#![feature(async_await)]
+use std::io::{self, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+use futures::executor;
+
+use romio::dns::{self, Resolver};
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const FLAG_TC: u16 = 0x0200;
+const RCODE_NXDOMAIN: u16 = 3;
+
+/// Answers `query` with `ips` of the type asked for, and `flags` set.
+fn respond(query: &[u8], flags: u16, ips: &[IpAddr]) -> Vec<u8> {
+    let name_end = 12 + query[12..].iter().position(|&b| b == 0).unwrap() + 1;
+    let qtype = u16::from_be_bytes([query[name_end], query[name_end + 1]]);
+    let mut msg = query[..name_end + 4].to_vec();
+    msg[2..4].copy_from_slice(&(0x8180 | flags).to_be_bytes());
+
+    let mut ancount = 0u16;
+    for ip in ips {
+        let (rtype, rdata) = match ip {
+            IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+        };
+        if rtype != qtype {
+            continue;
+        }
+        ancount += 1;
+        msg.extend_from_slice(&[0xc0, 12]);
+        msg.extend_from_slice(&rtype.to_be_bytes());
+        msg.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+        msg.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        msg.extend_from_slice(&rdata);
+    }
+    msg[6..8].copy_from_slice(&ancount.to_be_bytes());
+    msg
+}
+
+/// Runs a name server on a thread, returning its address.
+fn name_server<F>(f: F) -> SocketAddr
+where
+    F: Fn(&[u8]) -> Vec<u8> + Send + 'static,
+{
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf).unwrap();
+            socket.send_to(&f(&buf[..n]), &from).unwrap();
+        }
+    });
+    addr
+}
+
+fn resolver(nameservers: &[SocketAddr]) -> Resolver {
+    let mut builder = Resolver::builder()
+        .timeout(Duration::from_millis(200))
+        .attempts(1);
+    for &addr in nameservers {
+        builder = builder.nameserver(addr);
+    }
+    builder.build().unwrap()
+}
+
+fn ips() -> Vec<IpAddr> {
+    vec!["10.1.2.3".parse().unwrap(), "fd00::1".parse().unwrap()]
+}
+
+#[test]
+fn system_lookup_resolves_localhost() {
+    drop(env_logger::try_init());
+    let addrs = executor::block_on(dns::lookup_host("localhost:80")).unwrap();
+    assert!(!addrs.is_empty());
+    assert!(addrs.iter().all(|addr| addr.ip().is_loopback()));
+    assert!(addrs.iter().all(|addr| addr.port() == 80));
+}
+
+#[test]
+fn ip_addresses_resolve_to_themselves() {
+    drop(env_logger::try_init());
+    // No name server is ever asked.
+    let resolver = resolver(&["127.0.0.1:9".parse().unwrap()]);
+    for host in &["127.0.0.1:80", "[::1]:443"] {
+        let addrs = executor::block_on(resolver.lookup_host(host)).unwrap();
+        assert_eq!(addrs, vec![host.parse::<SocketAddr>().unwrap()]);
+    }
+}
+
+#[test]
+fn lookup_requires_port() {
+    drop(env_logger::try_init());
+    let err = executor::block_on(dns::lookup_host("localhost")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn resolver_requires_name_server() {
+    let err = Resolver::builder().build().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn name_server_lookup_returns_both_families() {
+    drop(env_logger::try_init());
+    let server = name_server(|query| respond(query, 0, &ips()));
+
+    let addrs = executor::block_on(resolver(&[server]).lookup_host("example.com:80")).unwrap();
+    let expected: Vec<SocketAddr> = vec![
+        "[fd00::1]:80".parse().unwrap(),
+        "10.1.2.3:80".parse().unwrap(),
+    ];
+    assert_eq!(addrs, expected);
+}
+
+#[test]
+fn name_server_lookup_reports_nxdomain() {
+    drop(env_logger::try_init());
+    let server = name_server(|query| respond(query, RCODE_NXDOMAIN, &[]));
+
+    let err =
+        executor::block_on(resolver(&[server]).lookup_host("nowhere.example:80")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn name_server_lookup_moves_on_after_timeout() {
+    drop(env_logger::try_init());
+    // Never responds.
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server = name_server(|query| respond(query, 0, &ips()));
+
+    let resolver = resolver(&[silent.local_addr().unwrap(), server]);
+    let addrs = executor::block_on(resolver.lookup_host("example.com:80")).unwrap();
+    assert_eq!(addrs.len(), 2);
+
+    let resolver = self::resolver(&[silent.local_addr().unwrap()]);
+    let err = executor::block_on(resolver.lookup_host("example.com:80")).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn truncated_responses_are_repeated_over_tcp() {
+    drop(env_logger::try_init());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let socket = UdpSocket::bind(addr).unwrap();
+
+    thread::spawn(move || {
+        let mut buf = [0; 512];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf).unwrap();
+            socket
+                .send_to(&respond(&buf[..n], FLAG_TC, &[]), &from)
+                .unwrap();
+        }
+    });
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut query = vec![0; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut query).unwrap();
+
+            let msg = respond(&query, 0, &ips());
+            stream.write_all(&(msg.len() as u16).to_be_bytes()).unwrap();
+            stream.write_all(&msg).unwrap();
+        }
+    });
+
+    let addrs = executor::block_on(resolver(&[addr]).lookup_host("example.com:80")).unwrap();
+    assert_eq!(addrs.len(), 2);
+}
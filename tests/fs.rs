@@ -0,0 +1,201 @@
+#![feature(async_await)]
+use std::io;
+
+use futures::executor;
+use tempdir::TempDir;
+
+use romio::fs::{self, File};
+
+#[test]
+fn files_are_written_and_read_back() -> io::Result<()> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("files_are_written_and_read_back")?;
+    let path = tmp_dir.path().join("file");
+
+    executor::block_on(async {
+        let mut file = File::create(&path).await?;
+        file.write_all(b"hello ").await?;
+        file.write_all(b"world").await?;
+        assert_eq!(file.metadata().await?.len(), 11);
+        drop(file);
+
+        let mut file = File::open(&path).await?;
+        let mut buf = [0; 8];
+        assert_eq!(file.read(&mut buf).await?, 8);
+        assert_eq!(&buf, b"hello wo");
+        assert_eq!(file.read(&mut buf).await?, 3);
+        assert_eq!(&buf[..3], b"rld");
+        assert_eq!(file.read(&mut buf).await?, 0);
+
+        assert!(fs::metadata(&path).await?.is_file());
+        Ok(())
+    })
+}
+
+#[test]
+fn dropped_reads_keep_their_bytes() -> io::Result<()> {
+    use futures::task::noop_waker;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("dropped_reads_keep_their_bytes")?;
+    let path = tmp_dir.path().join("file");
+    std::fs::write(&path, b"hello world")?;
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)?;
+    let mut file = File::from_std(file);
+
+    // Start a read, and drop it before it is done.
+    let mut buf = [0; 16];
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    if let Poll::Ready(res) = Pin::new(&mut file.read(&mut buf)).poll(&mut cx) {
+        // It was done already, which leaves nothing to check.
+        res?;
+        return Ok(());
+    }
+
+    executor::block_on(async {
+        // The next read returns what the dropped one fetched, and a write
+        // starts right after that.
+        let mut head = [0; 5];
+        assert_eq!(file.read(&mut head).await?, 5);
+        assert_eq!(&head, b"hello");
+        file.write_all(b"!").await
+    })?;
+    assert_eq!(std::fs::read(&path)?, b"hello!world");
+    Ok(())
+}
+
+#[test]
+fn read_dir_lists_entries() -> io::Result<()> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("read_dir_lists_entries")?;
+    std::fs::write(tmp_dir.path().join("a"), b"")?;
+    std::fs::create_dir(tmp_dir.path().join("b"))?;
+
+    let entries = executor::block_on(fs::read_dir(tmp_dir.path()))?;
+    let mut names: Vec<_> = entries.iter().map(|entry| entry.file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["a", "b"]);
+    Ok(())
+}
+
+#[test]
+fn opening_a_missing_file_fails() -> io::Result<()> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("opening_a_missing_file_fails")?;
+
+    let err = executor::block_on(File::open(tmp_dir.path().join("missing"))).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod watcher {
+    use std::fs;
+    use std::io;
+    use std::path::Path;
+
+    use futures::executor;
+    use futures::StreamExt;
+    use tempdir::TempDir;
+
+    use romio::fs::{Event, EventKind, Watcher};
+
+    fn event(path: &Path, kind: EventKind) -> Event {
+        Event {
+            path: path.to_path_buf(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn watcher_reports_directory_changes() -> io::Result<()> {
+        drop(env_logger::try_init());
+        let tmp_dir = TempDir::new("watcher_reports_directory_changes")?;
+        let file = tmp_dir.path().join("file");
+        let renamed = tmp_dir.path().join("renamed");
+
+        let mut watcher = Watcher::new()?;
+        watcher.watch(tmp_dir.path())?;
+
+        fs::write(&file, b"hello")?;
+        fs::rename(&file, &renamed)?;
+        fs::remove_file(&renamed)?;
+
+        let expected = vec![
+            event(&file, EventKind::Create),
+            event(&file, EventKind::Modify),
+            event(&file, EventKind::CloseWrite),
+            event(&file, EventKind::MoveFrom),
+            event(&renamed, EventKind::MoveTo),
+            event(&renamed, EventKind::Remove),
+        ];
+        let events = executor::block_on(async {
+            let mut events = vec![];
+            while events.len() < expected.len() {
+                events.push(watcher.next().await.unwrap()?);
+            }
+            Ok::<_, io::Error>(events)
+        })?;
+        assert_eq!(events, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn unwatched_paths_are_not_reported() -> io::Result<()> {
+        drop(env_logger::try_init());
+        let tmp_dir = TempDir::new("unwatched_paths_are_not_reported")?;
+        let watched = tmp_dir.path().join("watched");
+        let unwatched = tmp_dir.path().join("unwatched");
+        fs::create_dir(&watched)?;
+        fs::create_dir(&unwatched)?;
+
+        let mut watcher = Watcher::new()?;
+        watcher.watch(&watched)?;
+        watcher.watch(&unwatched)?;
+        watcher.unwatch(&unwatched)?;
+        let err = watcher.unwatch(&unwatched).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        fs::write(unwatched.join("file"), b"")?;
+        fs::write(watched.join("file"), b"")?;
+
+        let event = executor::block_on(watcher.next()).unwrap()?;
+        assert_eq!(event, self::event(&watched.join("file"), EventKind::Create));
+        Ok(())
+    }
+    #[test]
+    fn aliases_share_a_watch() -> io::Result<()> {
+        drop(env_logger::try_init());
+        let tmp_dir = TempDir::new("aliases_share_a_watch")?;
+        let dir = tmp_dir.path().join("dir");
+        let alias = tmp_dir.path().join("alias");
+        fs::create_dir(&dir)?;
+        std::os::unix::fs::symlink(&dir, &alias)?;
+
+        let mut watcher = Watcher::new()?;
+        watcher.watch(&dir)?;
+        watcher.watch(&alias)?;
+
+        // Events are reported under the first path, and then under the
+        // alias once that is the only one left.
+        fs::create_dir(dir.join("sub"))?;
+        let event = executor::block_on(watcher.next()).unwrap()?;
+        assert_eq!(event, self::event(&dir.join("sub"), EventKind::Create));
+        watcher.unwatch(&dir)?;
+        fs::remove_dir(dir.join("sub"))?;
+        let event = executor::block_on(watcher.next()).unwrap()?;
+        assert_eq!(event, self::event(&alias.join("sub"), EventKind::Remove));
+
+        watcher.unwatch(&alias)?;
+        let err = watcher.unwatch(&dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        Ok(())
+    }
+}
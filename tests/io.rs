@@ -0,0 +1,225 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::io;
+
+use std::future::Future;
+use std::net::Shutdown;
+use std::pin::Pin;
+use std::task::Context;
+use std::thread;
+use std::time::Duration;
+
+use futures::executor;
+use futures::future;
+use futures::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use futures::task::noop_waker;
+
+use romio::io::{copy_bidirectional, stdout, write_all_from, BufStream, SharedStream};
+use romio::uds::UnixStream;
+
+#[test]
+fn buf_stream_returns_partial_line_then_eof() -> io::Result<()> {
+    let (mut peer, stream) = UnixStream::pair()?;
+    let mut stream = BufStream::new(stream);
+
+    executor::block_on(async {
+        peer.write_all(b"complete\nparti").await?;
+        peer.write_all(b"al").await?;
+        drop(peer);
+
+        let mut line = String::new();
+        assert_eq!(stream.read_line(&mut line).await?, 9);
+        assert_eq!(line, "complete\n");
+
+        line.clear();
+        assert_eq!(stream.read_line(&mut line).await?, 7);
+        assert_eq!(line, "partial");
+
+        line.clear();
+        assert_eq!(stream.read_line(&mut line).await?, 0);
+        let mut rest = Vec::new();
+        assert_eq!(stream.read_until(b'\n', &mut rest).await?, 0);
+        Ok(())
+    })
+}
+
+#[test]
+fn buf_stream_writes_on_flush() -> io::Result<()> {
+    let mut stream = BufStream::with_capacity(16, 16, Vec::new());
+
+    executor::block_on(async {
+        stream.write_all(b"buffered").await?;
+        assert!(stream.get_ref().is_empty());
+        stream.flush().await?;
+        assert_eq!(stream.get_ref(), b"buffered");
+
+        // Writes larger than the buffer skip it.
+        stream.write_all(&[0; 32]).await?;
+        assert_eq!(stream.get_ref().len(), 40);
+        Ok(())
+    })
+}
+
+#[test]
+fn write_all_from_tracks_progress_across_cancellation() -> io::Result<()> {
+    let (mut tx, mut rx) = UnixStream::pair()?;
+    let data: Vec<u8> = (0..4 << 20).map(|i| (i % 251) as u8).collect();
+    let mut cursor = 0;
+
+    // The socket buffer fills up long before the data is through, and each
+    // write is cancelled once it stalls.
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..100 {
+        let mut write = write_all_from(&mut tx, &data, &mut cursor);
+        assert!(Pin::new(&mut write).poll(&mut cx).is_pending());
+        drop(write);
+        if cursor > 0 {
+            break;
+        }
+        // The reactor hasn't reported the socket writable yet.
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(cursor > 0 && cursor < data.len());
+
+    // Resuming from the cursor delivers every byte exactly once.
+    let mut received = Vec::new();
+    executor::block_on(async {
+        let write = async {
+            write_all_from(&mut tx, &data, &mut cursor).await?;
+            drop(tx);
+            Ok::<_, io::Error>(())
+        };
+        let (written, read) = future::join(write, rx.read_to_end(&mut received)).await;
+        written?;
+        read
+    })?;
+    assert_eq!(cursor, data.len());
+    assert!(received == data);
+
+    Ok(())
+}
+
+#[test]
+fn shared_stream_clones_read_and_write_concurrently() -> io::Result<()> {
+    let (a, b) = UnixStream::pair()?;
+    let (mut a_read, mut a_write) = {
+        let a = SharedStream::new(a);
+        (a.clone(), a)
+    };
+    let (mut b_read, mut b_write) = {
+        let b = SharedStream::new(b);
+        (b.clone(), b)
+    };
+
+    // Far more than the socket buffers hold, so each write only completes
+    // while the other end's reading clone makes progress at the same time.
+    let a_to_b: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+    let b_to_a: Vec<u8> = (0..1 << 20).map(|i| (i % 241) as u8).collect();
+    let mut at_a = vec![0; b_to_a.len()];
+    let mut at_b = vec![0; a_to_b.len()];
+
+    executor::block_on(async {
+        let (r1, r2, r3, r4) = future::join4(
+            a_write.write_all(&a_to_b),
+            b_write.write_all(&b_to_a),
+            a_read.read_exact(&mut at_a),
+            b_read.read_exact(&mut at_b),
+        )
+        .await;
+        r1.and(r2).and(r3).and(r4)
+    })?;
+    assert!(at_a == b_to_a);
+    assert!(at_b == a_to_b);
+
+    drop(a_write);
+    assert!(a_read.try_unwrap().is_ok());
+    Ok(())
+}
+
+#[test]
+fn copy_bidirectional_relays_until_both_ends_close() -> io::Result<()> {
+    let (mut peer_a, mut a) = UnixStream::pair()?;
+    let (mut peer_b, mut b) = UnixStream::pair()?;
+
+    executor::block_on(async {
+        let relay = copy_bidirectional(&mut a, &mut b);
+        let at_a = async {
+            peer_a.write_all(b"ping").await?;
+            peer_a.shutdown(Shutdown::Write)?;
+            let mut buf = Vec::new();
+            peer_a.read_to_end(&mut buf).await?;
+            Ok::<_, io::Error>(buf)
+        };
+        let at_b = async {
+            let mut buf = Vec::new();
+            peer_b.read_to_end(&mut buf).await?;
+            peer_b.write_all(b"pong!").await?;
+            peer_b.shutdown(Shutdown::Write)?;
+            Ok::<_, io::Error>(buf)
+        };
+        let (relayed, at_a, at_b) = future::join3(relay, at_a, at_b).await;
+        assert_eq!(relayed?, (4, 5));
+        assert_eq!(at_a?, b"pong!");
+        assert_eq!(at_b?, b"ping");
+        Ok(())
+    })
+}
+
+#[test]
+fn copy_bidirectional_relays_between_tcp_streams() -> io::Result<()> {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Accepts one connection, sends `msg` on it while reading everything
+    /// that comes back.
+    fn peer(msg: Vec<u8>) -> io::Result<(std::net::SocketAddr, thread::JoinHandle<Vec<u8>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let writer = thread::spawn(move || {
+                writer.write_all(&msg).unwrap();
+                writer.shutdown(Shutdown::Write).unwrap();
+            });
+            let mut buf = Vec::new();
+            stream.read_to_end(&mut buf).unwrap();
+            writer.join().unwrap();
+            buf
+        });
+        Ok((addr, handle))
+    }
+
+    // Far more than the socket buffers and pipes hold.
+    let a_to_b: Vec<u8> = (0..4 << 20).map(|i| (i % 251) as u8).collect();
+    let b_to_a: Vec<u8> = (0..1 << 20).map(|i| (i % 241) as u8).collect();
+    let (addr_a, peer_a) = peer(a_to_b.clone())?;
+    let (addr_b, peer_b) = peer(b_to_a.clone())?;
+
+    let relayed = executor::block_on(async {
+        let mut a = romio::TcpStream::connect(&addr_a).await?;
+        let mut b = romio::TcpStream::connect(&addr_b).await?;
+        copy_bidirectional(&mut a, &mut b).await
+    })?;
+    assert_eq!(relayed, (a_to_b.len() as u64, b_to_a.len() as u64));
+    assert!(peer_a.join().unwrap() == b_to_a);
+    assert!(peer_b.join().unwrap() == a_to_b);
+    Ok(())
+}
+
+#[test]
+fn stdout_leaves_the_descriptor_mode_alone() -> io::Result<()> {
+    let flags = || unsafe { libc::fcntl(libc::STDOUT_FILENO, libc::F_GETFL) };
+    let before = flags();
+
+    let mut out = stdout();
+    executor::block_on(async {
+        out.write_all(b"").await?;
+        out.flush().await
+    })?;
+    assert_eq!(flags(), before);
+    drop(out);
+    assert_eq!(flags(), before);
+    Ok(())
+}
@@ -0,0 +1,72 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::env;
+use std::io::Write;
+use std::net;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::{UnixListener as StdUnixListener, UnixStream as StdUnixStream};
+
+use futures::executor;
+use tempdir::TempDir;
+
+use romio::tcp::TcpListener;
+use romio::uds::UnixListener;
+
+/// Moves `fd` to `target`, which must not be open.
+fn move_fd(fd: RawFd, target: RawFd) {
+    if fd == target {
+        return;
+    }
+    assert_eq!(unsafe { libc::fcntl(target, libc::F_GETFD) }, -1);
+    assert_eq!(unsafe { libc::dup2(fd, target) }, target);
+    unsafe { libc::close(fd) };
+}
+
+// Socket activation state is global to the process, so this is the only
+// test in its binary.
+#[test]
+fn listeners_are_taken_from_socket_activation() {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("listen_fds").unwrap();
+    let path = tmp_dir.path().join("sock");
+
+    // Lay the sockets out as systemd would: a Unix listener, a TCP listener
+    // and something else, from descriptor 3 on.
+    let unix = StdUnixListener::bind(&path).unwrap().into_raw_fd();
+    let tcp = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let tcp_addr = tcp.local_addr().unwrap();
+    let tcp = tcp.into_raw_fd();
+    let (other, _peer) = StdUnixStream::pair().unwrap();
+    let other = other.into_raw_fd();
+    move_fd(unix, 3);
+    move_fd(tcp, 4);
+    move_fd(other, 5);
+
+    env::set_var("LISTEN_PID", std::process::id().to_string());
+    env::set_var("LISTEN_FDS", "3");
+    env::set_var("LISTEN_FDNAMES", "unix:tcp:other");
+
+    let tcp_listeners = TcpListener::from_env().unwrap();
+    assert_eq!(tcp_listeners.len(), 1);
+    assert_eq!(tcp_listeners[0].as_raw_fd(), 4);
+    assert_eq!(tcp_listeners[0].local_addr().unwrap(), tcp_addr);
+    assert!(env::var_os("LISTEN_FDS").is_none());
+    assert!(env::var_os("LISTEN_FDNAMES").is_none());
+
+    let mut unix_listeners = UnixListener::from_listen_fds().unwrap();
+    assert_eq!(unix_listeners.len(), 1);
+    assert!(UnixListener::from_listen_fds().unwrap().is_empty());
+    assert!(TcpListener::from_env().unwrap().is_empty());
+
+    let flags = unsafe { libc::fcntl(3, libc::F_GETFD) };
+    assert_ne!(flags & libc::FD_CLOEXEC, 0);
+
+    let mut client = StdUnixStream::connect(&path).unwrap();
+    client.write_all(b"hello").unwrap();
+    executor::block_on(async {
+        let (_stream, _) = unix_listeners[0].accept().await.unwrap();
+    });
+
+    // The leftover descriptor was never taken.
+    drop(unsafe { StdUnixStream::from_raw_fd(5) });
+}
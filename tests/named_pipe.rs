@@ -0,0 +1,83 @@
+#![cfg(windows)]
+#![feature(async_await)]
+use std::io;
+use std::thread;
+
+use futures::executor;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+use romio::windows::{NamedPipeClient, NamedPipeServer};
+
+type Error = Box<dyn std::error::Error + 'static>;
+
+const THE_WINTERS_TALE: &[u8] = b"
+                    Each your doing,
+    So singular in each particular,
+    Crowns what you are doing in the present deed,
+    That all your acts are queens.
+";
+
+fn pipe_name(test: &str) -> String {
+    format!(r"\\.\pipe\romio-{}-{}", test, std::process::id())
+}
+
+#[test]
+fn server_reads_client() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let name = pipe_name("server_reads_client");
+    let server = NamedPipeServer::create(&name)?;
+
+    let client = thread::spawn(move || {
+        executor::block_on(async {
+            let mut client = NamedPipeClient::connect(&name).await?;
+            client.write_all(THE_WINTERS_TALE).await
+        })
+    });
+
+    executor::block_on(async {
+        let mut server = server;
+        server.accept().await?;
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(buf, THE_WINTERS_TALE);
+        Ok::<_, io::Error>(())
+    })?;
+
+    client.join().unwrap()?;
+    Ok(())
+}
+
+#[test]
+fn next_instance_serves_second_client() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let name = pipe_name("next_instance_serves_second_client");
+    let first = NamedPipeServer::create(&name)?;
+    let second = first.next_instance()?;
+
+    executor::block_on(async {
+        let mut clients = vec![];
+        for server in &[first, second] {
+            let mut client = NamedPipeClient::connect(&name).await?;
+            server.accept().await?;
+            client.write_all(b"hello").await?;
+            clients.push(client);
+        }
+        Ok::<_, io::Error>(())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn create_fails_for_existing_pipe() -> Result<(), Error> {
+    let name = pipe_name("create_fails_for_existing_pipe");
+    let _server = NamedPipeServer::create(&name)?;
+    assert!(NamedPipeServer::create(&name).is_err());
+    Ok(())
+}
+
+#[test]
+fn connect_fails_without_server() {
+    let name = pipe_name("connect_fails_without_server");
+    let err = executor::block_on(NamedPipeClient::connect(&name)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,60 @@
+#![cfg(all(feature = "netlink", target_os = "linux"))]
+#![feature(async_await)]
+use std::convert::TryInto;
+
+use futures::executor;
+
+use romio::netlink::NetlinkSocket;
+
+/// Builds an `RTM_GETLINK` dump request: a netlink header followed by an
+/// empty `ifinfomsg`.
+fn get_links(seq: u32) -> Vec<u8> {
+    let flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+    let mut msg = Vec::new();
+    msg.extend_from_slice(&32u32.to_ne_bytes());
+    msg.extend_from_slice(&libc::RTM_GETLINK.to_ne_bytes());
+    msg.extend_from_slice(&flags.to_ne_bytes());
+    msg.extend_from_slice(&seq.to_ne_bytes());
+    msg.extend_from_slice(&0u32.to_ne_bytes());
+    msg.extend_from_slice(&[0; 16]);
+    msg
+}
+
+#[test]
+fn route_dump_lists_links() {
+    drop(env_logger::try_init());
+    let mut socket = NetlinkSocket::new(libc::NETLINK_ROUTE).unwrap();
+    socket.join_group(libc::RTNLGRP_LINK).unwrap();
+    socket.set_recv_buffer_size(1 << 16).unwrap();
+    assert_ne!(socket.local_pid(), 0);
+
+    executor::block_on(async {
+        socket.send(&get_links(7)).await.unwrap();
+
+        let mut links = 0;
+        let mut buf = vec![0; 8192];
+        'dump: loop {
+            let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(from, 0);
+
+            let mut msgs = &buf[..n];
+            while msgs.len() >= 16 {
+                let len = u32::from_ne_bytes(msgs[0..4].try_into().unwrap()) as usize;
+                let kind = u16::from_ne_bytes(msgs[4..6].try_into().unwrap());
+                let seq = u32::from_ne_bytes(msgs[8..12].try_into().unwrap());
+                let pid = u32::from_ne_bytes(msgs[12..16].try_into().unwrap());
+                assert_eq!((seq, pid), (7, socket.local_pid()));
+                match kind as libc::c_int {
+                    libc::NLMSG_DONE => break 'dump,
+                    libc::NLMSG_ERROR => panic!("dump failed"),
+                    _ => assert_eq!(kind, libc::RTM_NEWLINK),
+                }
+                links += 1;
+                // Messages are aligned to 4 bytes.
+                msgs = &msgs[std::cmp::min((len + 3) & !3, msgs.len())..];
+            }
+        }
+        // There is at least the loopback interface.
+        assert!(links >= 1);
+    });
+}
@@ -0,0 +1,92 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::io;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use futures::executor;
+use futures::future;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+use romio::process::CommandExt;
+
+#[test]
+fn wait_with_output_collects_both_streams() -> io::Result<()> {
+    let child = Command::new("sh")
+        .args(&["-c", "echo out; echo err >&2; exit 3"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn_async()?;
+
+    let output = executor::block_on(child.wait_with_output())?;
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"out\n");
+    assert_eq!(output.stderr, b"err\n");
+    Ok(())
+}
+
+#[test]
+fn stdin_and_stdout_stream_through_the_child() -> io::Result<()> {
+    let mut child = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn_async()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+
+    executor::block_on(async {
+        // More than a pipe holds, so both directions have to make progress
+        // at the same time.
+        let data = vec![b'x'; 1 << 20];
+        let write = async {
+            stdin.write_all(&data).await?;
+            drop(stdin);
+            Ok::<_, io::Error>(())
+        };
+        let read = async {
+            let mut echoed = Vec::new();
+            stdout.read_to_end(&mut echoed).await?;
+            Ok::<_, io::Error>(echoed)
+        };
+        let (written, echoed) = future::join(write, read).await;
+        written?;
+        assert_eq!(echoed?, data);
+
+        assert!(child.wait().await?.success());
+        Ok(())
+    })
+}
+
+#[test]
+fn children_are_waited_for_concurrently() -> io::Result<()> {
+    let mut children = (0..4)
+        .map(|_| Command::new("sleep").arg("0.2").spawn_async())
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let start = Instant::now();
+    executor::block_on(async {
+        let statuses = future::join_all(children.iter_mut().map(|child| child.wait())).await;
+        for status in statuses {
+            assert!(status?.success());
+        }
+        Ok::<_, io::Error>(())
+    })?;
+    assert!(start.elapsed() < Duration::from_millis(700));
+    Ok(())
+}
+
+#[test]
+fn killed_children_report_it() -> io::Result<()> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut child = Command::new("sleep").arg("10").spawn_async()?;
+    assert!(child.try_wait()?.is_none());
+    child.kill()?;
+
+    let status = executor::block_on(child.wait())?;
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+    // waiting again returns the same status
+    assert_eq!(executor::block_on(child.wait())?, status);
+    child.kill()?;
+    Ok(())
+}
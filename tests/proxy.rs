@@ -0,0 +1,238 @@
+#![feature(async_await)]
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+use futures::executor;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+use romio::proxy::{HttpTunnelStream, Socks5Stream, TargetAddr};
+
+const MACBETH: &[u8] = b"
+    Tomorrow, and tomorrow, and tomorrow,
+    Creeps in this petty pace from day to day,
+    To the last syllable of recorded time;
+";
+
+/// Runs a proxy that handles one connection with `f`, returning its
+/// address.
+fn proxy<F>(f: F) -> SocketAddr
+where
+    F: FnOnce(TcpStream) -> io::Result<()> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        f(stream).unwrap();
+    });
+    addr
+}
+
+fn read_vec(stream: &mut TcpStream, n: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; n];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Plays the target once the tunnel is up: echoes everything back.
+fn echo(mut stream: TcpStream) -> io::Result<()> {
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf)?;
+    stream.write_all(&buf)
+}
+
+/// Handles the SOCKS5 handshake, checking the greeting, the credentials and
+/// the request, then replies with `rep`.
+fn socks5(
+    mut stream: TcpStream,
+    credentials: Option<&[u8]>,
+    request: &[u8],
+    rep: u8,
+) -> io::Result<()> {
+    let greeting = read_vec(&mut stream, 2)?;
+    assert_eq!(greeting[0], 5);
+    let methods = read_vec(&mut stream, greeting[1] as usize)?;
+    match credentials {
+        Some(credentials) => {
+            assert!(methods.contains(&2));
+            stream.write_all(&[5, 2])?;
+            assert_eq!(read_vec(&mut stream, credentials.len())?, credentials);
+            stream.write_all(&[1, 0])?;
+        }
+        None => stream.write_all(&[5, 0])?,
+    }
+    assert_eq!(read_vec(&mut stream, request.len())?, request);
+    stream.write_all(&[5, rep, 0, 1, 127, 0, 0, 1, 0x1f, 0x90])?;
+    if rep == 0 {
+        echo(stream)?;
+    }
+    Ok(())
+}
+
+/// Reads an HTTP request header.
+fn read_header(stream: &mut TcpStream) -> io::Result<String> {
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n\r\n") {
+        header.extend(read_vec(stream, 1)?);
+    }
+    Ok(String::from_utf8(header).unwrap())
+}
+
+/// Sends `MACBETH` to the echoing target, returning what comes back.
+async fn round_trip<S, F>(stream: &mut S, tcp: F) -> Vec<u8>
+where
+    S: AsyncReadExt + AsyncWriteExt + Unpin,
+    F: Fn(&S) -> &romio::TcpStream,
+{
+    stream.write_all(MACBETH).await.unwrap();
+    tcp(stream).shutdown(Shutdown::Write).unwrap();
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await.unwrap();
+    buf
+}
+
+#[test]
+fn target_addr_parses_ips() {
+    assert_eq!(
+        TargetAddr::from(("127.0.0.1", 80)),
+        TargetAddr::Ip("127.0.0.1:80".parse().unwrap())
+    );
+    assert_eq!(
+        TargetAddr::from(("example.com", 80)),
+        TargetAddr::Domain("example.com".to_string(), 80)
+    );
+    assert_eq!(
+        TargetAddr::from(("::1", 443)).to_string(),
+        "[::1]:443".to_string()
+    );
+}
+
+#[test]
+fn socks5_connects_to_domain() {
+    drop(env_logger::try_init());
+    let request = b"\x05\x01\x00\x03\x0bexample.com\x00\x50";
+    let addr = proxy(move |stream| socks5(stream, None, request, 0));
+
+    executor::block_on(async {
+        let mut stream = Socks5Stream::connect(&addr, ("example.com", 80))
+            .await
+            .unwrap();
+        assert_eq!(
+            round_trip(&mut stream, Socks5Stream::get_ref).await,
+            MACBETH
+        );
+    });
+}
+
+#[test]
+fn socks5_logs_in() {
+    drop(env_logger::try_init());
+    let request = b"\x05\x01\x00\x01\x0a\x00\x00\x01\x01\xbb";
+    let addr = proxy(move |stream| socks5(stream, Some(b"\x01\x04romi\x02o!"), request, 0));
+
+    executor::block_on(async {
+        let target: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        let mut stream = Socks5Stream::connect_with_password(&addr, target, "romi", "o!")
+            .await
+            .unwrap();
+        assert_eq!(
+            round_trip(&mut stream, Socks5Stream::get_ref).await,
+            MACBETH
+        );
+    });
+}
+
+#[test]
+fn socks5_reports_refused_connection() {
+    drop(env_logger::try_init());
+    let request = b"\x05\x01\x00\x01\x0a\x00\x00\x01\x00\x50";
+    let addr = proxy(move |stream| socks5(stream, None, request, 5));
+
+    let target: SocketAddr = "10.0.0.1:80".parse().unwrap();
+    let err = executor::block_on(Socks5Stream::connect(&addr, target)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+}
+
+#[test]
+fn http_tunnel_connects() {
+    drop(env_logger::try_init());
+    let addr = proxy(|mut stream| {
+        let header = read_header(&mut stream)?;
+        assert_eq!(
+            header,
+            "CONNECT example.com:443 HTTP/1.1\r\n\
+             Host: example.com:443\r\n\
+             Proxy-Authorization: Basic cm9taTpvIQ==\r\n\r\n"
+        );
+        // The first bytes of the target may arrive along with the response.
+        stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello ")?;
+        echo(stream)
+    });
+
+    executor::block_on(async {
+        let mut stream =
+            HttpTunnelStream::connect_with_password(&addr, ("example.com", 443), "romi", "o!")
+                .await
+                .unwrap();
+        let mut expected = b"hello ".to_vec();
+        expected.extend_from_slice(MACBETH);
+        assert_eq!(
+            round_trip(&mut stream, HttpTunnelStream::get_ref).await,
+            expected
+        );
+    });
+}
+
+#[test]
+fn http_tunnel_reports_status() {
+    drop(env_logger::try_init());
+    let addr = proxy(|mut stream| {
+        read_header(&mut stream)?;
+        stream.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+    });
+
+    let err =
+        executor::block_on(HttpTunnelStream::connect(&addr, ("example.com", 443))).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn http_tunnel_rejects_invalid_input() {
+    drop(env_logger::try_init());
+    // Nothing listens here, so connecting would fail with another error.
+    let addr: SocketAddr = "127.0.0.1:1".parse().unwrap();
+
+    let connect = |host: &str, auth: Option<(&str, &str)>| {
+        let target = (host, 443);
+        let err = executor::block_on(match auth {
+            Some((username, password)) => {
+                HttpTunnelStream::connect_with_password(&addr, target, username, password)
+            }
+            None => HttpTunnelStream::connect(&addr, target),
+        })
+        .unwrap_err();
+        err.kind()
+    };
+    for host in &["", "example.com\r\nX-Evil: 1", "a b", "user@example.com"] {
+        assert_eq!(
+            connect(host, None),
+            io::ErrorKind::InvalidInput,
+            "{:?}",
+            host
+        );
+    }
+    for auth in &[
+        ("ro\r\nmi", "o!"),
+        ("romi", "o !"),
+        ("ro:mi", "o!"),
+        ("romi", "o@"),
+    ] {
+        assert_eq!(
+            connect("example.com", Some(*auth)),
+            io::ErrorKind::InvalidInput,
+            "{:?}",
+            auth
+        );
+    }
+}
@@ -0,0 +1,252 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
+
+use futures::executor;
+use futures::future::poll_fn;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+use mio::unix::EventedFd;
+use mio::{Evented, PollOpt, Ready, Token};
+
+use romio::raw::{socket_info, AsyncFd, PipeReader, PipeWriter, PollEvented, SocketInfo};
+
+fn info(domain: libc::c_int, sock_type: libc::c_int, protocol: libc::c_int) -> SocketInfo {
+    SocketInfo {
+        domain,
+        sock_type,
+        protocol,
+    }
+}
+
+#[test]
+fn classifies_inet_sockets() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let udp = std::net::UdpSocket::bind("[::1]:0").unwrap();
+
+    // The protocol is only known where the kernel reports it.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let (tcp_proto, udp_proto) = (libc::IPPROTO_TCP, libc::IPPROTO_UDP);
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let (tcp_proto, udp_proto) = (0, 0);
+
+    assert_eq!(
+        socket_info(listener.as_raw_fd()).unwrap(),
+        info(libc::AF_INET, libc::SOCK_STREAM, tcp_proto)
+    );
+    assert_eq!(
+        socket_info(udp.as_raw_fd()).unwrap(),
+        info(libc::AF_INET6, libc::SOCK_DGRAM, udp_proto)
+    );
+}
+
+#[test]
+fn classifies_unix_sockets() {
+    let (stream, _) = UnixStream::pair().unwrap();
+    let (datagram, _) = UnixDatagram::pair().unwrap();
+
+    assert_eq!(
+        socket_info(stream.as_raw_fd()).unwrap(),
+        info(libc::AF_UNIX, libc::SOCK_STREAM, 0)
+    );
+    assert_eq!(
+        socket_info(datagram.as_raw_fd()).unwrap(),
+        info(libc::AF_UNIX, libc::SOCK_DGRAM, 0)
+    );
+
+    let mut fds = [0; 2];
+    let ret = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_SEQPACKET, 0, fds.as_mut_ptr()) };
+    assert_eq!(ret, 0);
+    let seqpacket = socket_info(fds[0]);
+    unsafe {
+        libc::close(fds[0]);
+        libc::close(fds[1]);
+    }
+    assert_eq!(seqpacket.unwrap().sock_type, libc::SOCK_SEQPACKET);
+}
+
+#[test]
+fn rejects_non_sockets() {
+    let file = std::fs::File::open("Cargo.toml").unwrap();
+    assert!(socket_info(file.as_raw_fd()).is_err());
+}
+
+/// The read end of a non-blocking pipe, wrapped by hand.
+struct RawPipe(File);
+
+impl Read for RawPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Evented for RawPipe {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).deregister(poll)
+    }
+}
+
+fn pipe() -> (RawPipe, File) {
+    let mut fds = [0; 2];
+    unsafe {
+        assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+        assert_eq!(libc::fcntl(fds[0], libc::F_SETFL, libc::O_NONBLOCK), 0);
+        (
+            RawPipe(File::from_raw_fd(fds[0])),
+            File::from_raw_fd(fds[1]),
+        )
+    }
+}
+
+#[test]
+fn poll_evented_drives_foreign_resources() {
+    let (reader, mut writer) = pipe();
+    let mut reader = PollEvented::new(reader);
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        writer.write_all(b"ping").unwrap();
+        writer
+    });
+
+    let mut buf = [0; 4];
+    executor::block_on(reader.read_exact(&mut buf)).unwrap();
+    assert_eq!(&buf, b"ping");
+    drop(handle.join().unwrap());
+
+    let mut rest = Vec::new();
+    executor::block_on(reader.read_to_end(&mut rest)).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn poll_evented_readiness_can_be_cleared_and_unwrapped() {
+    let (reader, mut writer) = pipe();
+    let reader = PollEvented::new(reader);
+
+    writer.write_all(b"x").unwrap();
+    let n = executor::block_on(poll_fn(|cx| -> Poll<io::Result<usize>> {
+        futures::ready!(reader.poll_read_ready(cx))?;
+        let mut buf = [0; 8];
+        match (&reader.get_ref().0).read(&mut buf) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                reader.clear_read_ready(cx)?;
+                Poll::Pending
+            }
+            res => Poll::Ready(res),
+        }
+    }))
+    .unwrap();
+    assert_eq!(n, 1);
+
+    let mut file = reader.into_inner().unwrap().0;
+    writer.write_all(b"y").unwrap();
+    let mut buf = [0; 1];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"y");
+}
+
+#[test]
+fn async_fd_reads_and_writes_a_pipe() {
+    let (reader, writer) = pipe();
+    let mut reader = AsyncFd::new(reader.0).unwrap();
+    let mut writer = AsyncFd::new(writer).unwrap();
+
+    executor::block_on(async {
+        // Fill the pipe so the writer has to wait for the reader.
+        let data = vec![7; 1 << 20];
+        let write = async {
+            writer.write_all(&data).await.unwrap();
+            drop(writer.into_inner().unwrap());
+        };
+        let read = async {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).await.unwrap();
+            received
+        };
+        let ((), received) = futures::future::join(write, read).await;
+        assert_eq!(received, data);
+    });
+}
+
+#[test]
+fn async_fd_rejects_regular_files() {
+    let file = File::open("Cargo.toml").unwrap();
+    assert!(AsyncFd::new(file).is_err());
+}
+
+#[test]
+fn pipe_carries_data_past_its_capacity() {
+    let (mut reader, mut writer) = romio::raw::pipe().unwrap();
+    let data = vec![3; 1 << 20];
+
+    executor::block_on(async {
+        let write = async {
+            writer.write_all(&data).await.unwrap();
+            drop(writer);
+        };
+        let read = async {
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).await.unwrap();
+            received
+        };
+        let ((), received) = futures::future::join(write, read).await;
+        assert_eq!(received, data);
+    });
+}
+
+#[test]
+fn pipes_adopt_child_stdio() {
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = PipeWriter::try_from(child.stdin.take().unwrap()).unwrap();
+    let mut stdout = PipeReader::try_from(child.stdout.take().unwrap()).unwrap();
+
+    executor::block_on(async {
+        stdin.write_all(b"echo").await.unwrap();
+        drop(stdin);
+
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).await.unwrap();
+        assert_eq!(output, b"echo");
+    });
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn pipes_reject_other_descriptors() {
+    let file = File::open("Cargo.toml").unwrap();
+    let err = PipeReader::try_from(file).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
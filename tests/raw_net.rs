@@ -0,0 +1,83 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::io;
+use std::net::IpAddr;
+
+use futures::executor;
+
+use romio::raw_net::{IcmpSocket, RawSocket};
+
+/// Raw sockets need privileges, which tests don't always have.
+fn privileged<T>(res: io::Result<T>) -> Option<T> {
+    match res {
+        Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            eprintln!("skipping: raw sockets are not permitted");
+            None
+        }
+        res => Some(res.unwrap()),
+    }
+}
+
+#[test]
+fn raw_socket_carries_ip_payloads() {
+    drop(env_logger::try_init());
+    // A protocol number set aside for experiments, which nothing else uses.
+    let protocol = 253;
+    let mut receiver = match privileged(RawSocket::new_v4(protocol)) {
+        Some(socket) => socket,
+        None => return,
+    };
+    let mut sender = RawSocket::new_v4(protocol).unwrap();
+    let localhost: IpAddr = "127.0.0.1".parse().unwrap();
+    receiver.bind(&localhost).unwrap();
+    sender.set_ttl(7).unwrap();
+    assert_eq!(sender.ttl().unwrap(), 7);
+
+    executor::block_on(async {
+        assert_eq!(
+            sender
+                .send_to(b"in the present deed", &localhost)
+                .await
+                .unwrap(),
+            19
+        );
+
+        let mut buf = [0; 64];
+        let (n, from) = receiver.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from, localhost);
+        // An IPv4 raw socket receives the IP header, here without options.
+        assert_eq!(n, 20 + 19);
+        assert_eq!(buf[8], 7);
+        assert_eq!(buf[9], protocol as u8);
+        assert_eq!(&buf[20..n], b"in the present deed");
+    });
+}
+
+#[test]
+fn icmp_socket_pings_localhost() {
+    drop(env_logger::try_init());
+    let mut socket = match privileged(IcmpSocket::new_v4()) {
+        Some(socket) => socket,
+        None => return,
+    };
+    let localhost: IpAddr = "127.0.0.1".parse().unwrap();
+
+    executor::block_on(async {
+        socket
+            .send_echo(&localhost, 0x6d6f, 3, b"queens")
+            .await
+            .unwrap();
+
+        let mut buf = [0; 1500];
+        loop {
+            let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+            // The request itself is received too; wait for the reply.
+            if buf[0] != 0 || buf[4..8] != [0x6d, 0x6f, 0, 3] {
+                continue;
+            }
+            assert_eq!(from, localhost);
+            assert_eq!(&buf[8..n], b"queens");
+            break;
+        }
+    });
+}
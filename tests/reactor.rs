@@ -0,0 +1,294 @@
+#![feature(async_await)]
+use std::convert::TryFrom;
+use std::future::Future;
+use std::net::TcpListener;
+use std::pin::Pin;
+use std::task::Context;
+use std::time::{Duration, Instant};
+
+use futures::task::noop_waker;
+
+use romio::raw::Reactor;
+use romio::{TcpStream, UdpSocket};
+
+#[test]
+fn turn_returns_after_max_wait() {
+    drop(env_logger::try_init());
+    let mut reactor = Reactor::new().unwrap();
+
+    let start = Instant::now();
+    reactor.turn(Some(Duration::from_millis(20))).unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(15), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+}
+
+#[test]
+fn entered_reactor_drives_io() {
+    drop(env_logger::try_init());
+    let mut reactor = Reactor::new().unwrap();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let mut buf = [0; 16];
+
+    reactor.enter(|| {
+        let mut recv = socket.recv_from(&mut buf);
+        assert!(Pin::new(&mut recv).poll(&mut cx).is_pending());
+    });
+
+    std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .send_to(b"hi", &addr)
+        .unwrap();
+    reactor.turn(Some(Duration::from_secs(5))).unwrap();
+
+    let mut recv = socket.recv_from(&mut buf);
+    match Pin::new(&mut recv).poll(&mut cx) {
+        std::task::Poll::Ready(res) => assert_eq!(res.unwrap().0, 2),
+        std::task::Poll::Pending => panic!("datagram not dispatched"),
+    }
+}
+
+#[test]
+fn turn_wakes_for_timer_deadline() {
+    drop(env_logger::try_init());
+    let mut reactor = Reactor::new().unwrap();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let mut stream = TcpStream::try_from(client).unwrap();
+    let mut buf = [0; 16];
+    let mut read = stream.read_with_timeout(&mut buf, Duration::from_millis(50));
+
+    let start = Instant::now();
+    reactor.enter(|| assert!(Pin::new(&mut read).poll(&mut cx).is_pending()));
+
+    // Tickless: no I/O will happen, so only the deadline ends the waits.
+    let res = loop {
+        reactor.turn(None).unwrap();
+        if let std::task::Poll::Ready(res) = Pin::new(&mut read).poll(&mut cx) {
+            break res;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "deadline not fired"
+        );
+    };
+    assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn socket_bound_to_handle_is_driven_by_its_reactor() {
+    drop(env_logger::try_init());
+    let mut reactor = Reactor::new().unwrap();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    // No `enter`: the handle alone decides where the socket is registered.
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let mut socket = UdpSocket::bind_on(&reactor.handle(), &addr).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let mut buf = [0; 16];
+
+    let mut recv = socket.recv_from(&mut buf);
+    assert!(Pin::new(&mut recv).poll(&mut cx).is_pending());
+    drop(recv);
+
+    std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .send_to(b"hi", &addr)
+        .unwrap();
+    reactor.turn(Some(Duration::from_secs(5))).unwrap();
+
+    let mut recv = socket.recv_from(&mut buf);
+    match Pin::new(&mut recv).poll(&mut cx) {
+        std::task::Poll::Ready(res) => assert_eq!(res.unwrap().0, 2),
+        std::task::Poll::Pending => panic!("datagram not dispatched"),
+    }
+}
+
+#[test]
+fn io_fails_once_its_reactor_is_gone() {
+    drop(env_logger::try_init());
+    let reactor = Reactor::new().unwrap();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let mut socket = UdpSocket::bind_on(&reactor.handle(), &addr).unwrap();
+    drop(reactor);
+
+    let mut buf = [0; 16];
+    let mut recv = socket.recv_from(&mut buf);
+    match Pin::new(&mut recv).poll(&mut cx) {
+        std::task::Poll::Ready(res) => assert!(res.is_err()),
+        std::task::Poll::Pending => panic!("recv waits for a dropped reactor"),
+    }
+
+    let handle = Reactor::new().unwrap().handle();
+    assert!(UdpSocket::bind_on(&handle, &addr).is_err());
+}
+
+#[test]
+fn background_reactor_accepts_and_shuts_down() {
+    use futures::executor;
+    use romio::TcpListener;
+
+    drop(env_logger::try_init());
+    let reactor = Reactor::new().unwrap().background().unwrap();
+    let handle = reactor.handle();
+
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let mut listener = TcpListener::bind_on(&handle, &addr).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    executor::block_on(async {
+        let connect = TcpStream::connect_on(&handle, &addr);
+        let accept = listener.accept();
+        let (stream, accepted) = futures::future::join(connect, accept).await;
+        let (accepted, peer) = accepted.unwrap();
+        assert_eq!(stream.unwrap().local_addr().unwrap(), peer);
+        assert_eq!(accepted.local_addr().unwrap(), addr);
+
+        reactor.shutdown_now().await.unwrap();
+    });
+}
+
+#[test]
+fn shutdown_wakes_and_fails_bound_io() {
+    use futures::task::{waker, ArcWake};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct Flag(AtomicBool);
+
+    impl ArcWake for Flag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    drop(env_logger::try_init());
+    let reactor = Reactor::new().unwrap();
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = waker(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let mut buf = [0; 16];
+    reactor.enter(|| {
+        let mut recv = socket.recv_from(&mut buf);
+        assert!(Pin::new(&mut recv).poll(&mut cx).is_pending());
+    });
+    assert!(!flag.0.load(Ordering::SeqCst));
+
+    reactor.shutdown();
+    assert!(flag.0.load(Ordering::SeqCst));
+
+    let mut recv = socket.recv_from(&mut buf);
+    match Pin::new(&mut recv).poll(&mut cx) {
+        std::task::Poll::Ready(res) => assert!(res.is_err()),
+        std::task::Poll::Pending => panic!("recv waits for a shut down reactor"),
+    }
+}
+
+#[test]
+fn local_reactor_drives_spawned_tasks() {
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use futures::task::LocalSpawnExt;
+    use romio::raw::LocalReactor;
+    use romio::TcpListener;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    drop(env_logger::try_init());
+    let mut reactor = LocalReactor::new().unwrap();
+    let mut spawner = reactor.spawner();
+
+    let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let served = Rc::new(Cell::new(false));
+
+    let flag = served.clone();
+    spawner
+        .spawn_local(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+            flag.set(true);
+        })
+        .unwrap();
+
+    let echoed = reactor
+        .run_until(async move {
+            let mut stream = TcpStream::connect(&addr).await.unwrap();
+            stream.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            buf
+        })
+        .unwrap();
+    assert_eq!(&echoed, b"ping");
+
+    reactor.run().unwrap();
+    assert!(served.get());
+}
+
+#[test]
+fn local_reactor_wakes_for_other_threads() {
+    use futures::channel::oneshot;
+    use romio::raw::LocalReactor;
+    use std::thread;
+
+    drop(env_logger::try_init());
+    let mut reactor = LocalReactor::new().unwrap();
+    let (tx, rx) = oneshot::channel();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        tx.send(7).unwrap();
+    });
+
+    let start = Instant::now();
+    assert_eq!(reactor.run_until(rx).unwrap(), Ok(7));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    sender.join().unwrap();
+}
+
+#[test]
+fn reactor_pool_drives_entered_io() {
+    use futures::executor;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use romio::raw::{Balance, ReactorPool};
+    use romio::TcpListener;
+
+    drop(env_logger::try_init());
+    let pool = ReactorPool::builder()
+        .reactors(3)
+        .balance(Balance::RoundRobin)
+        .build()
+        .unwrap();
+    assert_eq!(pool.reactors(), 3);
+
+    let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    pool.enter(|| {
+        executor::block_on(async {
+            let connect = TcpStream::connect(&addr);
+            let (client, accepted) = futures::future::join(connect, listener.accept()).await;
+            let (mut client, mut server) = (client.unwrap(), accepted.unwrap().0);
+
+            client.write_all(b"hello").await.unwrap();
+            let mut buf = [0; 5];
+            server.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"hello");
+        })
+    });
+}
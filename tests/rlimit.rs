@@ -0,0 +1,90 @@
+#![cfg(target_os = "linux")]
+#![feature(async_await)]
+//! Runs in its own process, since it lowers the file descriptor limit and
+//! relies on the default reactor not having been created yet.
+use std::io;
+use std::net::TcpStream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::executor;
+use futures::task::noop_waker;
+use futures::{Stream, StreamExt};
+
+use romio::tcp::IncomingExt;
+use romio::TcpListener;
+
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd").unwrap().count()
+}
+
+fn set_fd_limit(limit: libc::rlim_t) -> libc::rlim_t {
+    unsafe {
+        let mut rlim: libc::rlimit = std::mem::zeroed();
+        assert_eq!(libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlim), 0);
+        let old = rlim.rlim_cur;
+        rlim.rlim_cur = limit;
+        assert_eq!(libc::setrlimit(libc::RLIMIT_NOFILE, &rlim), 0);
+        old
+    }
+}
+
+/// Returns the descriptor the next open would get.
+fn lowest_free_fd() -> libc::rlim_t {
+    let fd = unsafe { libc::dup(0) };
+    assert!(fd >= 0);
+    unsafe { libc::close(fd) };
+    fd as libc::rlim_t
+}
+
+#[test]
+fn fd_exhaustion_is_reported_without_leaking() {
+    let addr = "127.0.0.1:0".parse().unwrap();
+    let before = open_fds();
+    let free = lowest_free_fd();
+
+    // No descriptor left for the socket itself.
+    let old_limit = set_fd_limit(free);
+    let err = TcpListener::bind(&addr).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EMFILE));
+
+    // Room for the socket, but not for the reactor it registers with.
+    set_fd_limit(free + 1);
+    let mut listener = TcpListener::bind(&addr).unwrap();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..2 {
+        let mut incoming = listener.incoming();
+        match Pin::new(&mut incoming).poll_next(&mut cx) {
+            Poll::Ready(Some(Err(e))) => assert_eq!(e.raw_os_error(), Some(libc::EMFILE)),
+            res => panic!("expected EMFILE, got {:?}", res),
+        }
+    }
+    drop(listener);
+    assert_eq!(open_fds(), before);
+
+    // Once descriptors are available again, everything works.
+    set_fd_limit(old_limit);
+    let mut listener = TcpListener::bind(&addr).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = TcpStream::connect(&addr).unwrap();
+    let accepted: io::Result<_> = executor::block_on(listener.incoming().next()).unwrap();
+    assert_eq!(
+        accepted.unwrap().peer_addr().unwrap(),
+        client.local_addr().unwrap()
+    );
+
+    // With the reactor up, running out of descriptors only fails accepting,
+    // which `retry_transient` waits out.
+    let client = TcpStream::connect(&addr).unwrap();
+    set_fd_limit(lowest_free_fd());
+    let mut incoming = listener.incoming().retry_transient();
+    let res = Pin::new(&mut incoming).poll_next(&mut cx);
+    assert!(res.is_pending(), "{:?}", res);
+    set_fd_limit(old_limit);
+    let accepted = executor::block_on(incoming.next()).unwrap();
+    assert_eq!(
+        accepted.unwrap().peer_addr().unwrap(),
+        client.local_addr().unwrap()
+    );
+}
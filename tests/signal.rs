@@ -0,0 +1,55 @@
+#![cfg(unix)]
+#![feature(async_await)]
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+use futures::executor;
+use futures::task::noop_waker_ref;
+use futures::{Stream, StreamExt};
+
+use romio::signal::unix::Signal;
+
+#[test]
+fn signal_is_delivered_to_every_stream() -> io::Result<()> {
+    let mut first = Signal::new(libc::SIGUSR1)?;
+    let mut second = Signal::new(libc::SIGUSR1)?;
+
+    let sender = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(unsafe { libc::kill(libc::getpid(), libc::SIGUSR1) }, 0);
+    });
+
+    executor::block_on(async {
+        first.next().await.unwrap()?;
+        second.next().await.unwrap()
+    })?;
+    sender.join().unwrap();
+    Ok(())
+}
+
+#[test]
+fn deliveries_are_coalesced() -> io::Result<()> {
+    let mut signal = Signal::new(libc::SIGUSR2)?;
+    for _ in 0..3 {
+        assert_eq!(unsafe { libc::raise(libc::SIGUSR2) }, 0);
+    }
+
+    let mut cx = Context::from_waker(noop_waker_ref());
+    match Pin::new(&mut signal).poll_next(&mut cx) {
+        Poll::Ready(Some(res)) => res?,
+        other => panic!("expected a delivery, got {:?}", other),
+    }
+    assert!(Pin::new(&mut signal).poll_next(&mut cx).is_pending());
+    Ok(())
+}
+
+#[test]
+fn uncatchable_signals_are_rejected() {
+    for &signal in &[libc::SIGKILL, libc::SIGSTOP, libc::SIGSEGV, 0, 1000] {
+        let err = Signal::new(signal).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -8,6 +8,7 @@ use futures::executor;
 use futures::io::{AsyncReadExt, AsyncWriteExt};
 use futures::StreamExt;
 
+use romio::tcp::IncomingExt;
 use romio::TcpListener;
 
 const THE_WINTERS_TALE: &[u8] = b"
@@ -88,3 +89,1237 @@ fn listener_from_std() {
     let std_socket = std::net::TcpListener::bind(&addr).unwrap();
     let _ = TcpListener::try_from(std_socket).unwrap();
 }
+
+#[test]
+fn write_stall_is_tracked() {
+    use futures::io::AsyncWrite;
+    use futures::task::noop_waker;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let mut stream = executor::block_on(romio::TcpStream::connect(&addr)).unwrap();
+    let (mut peer, _) = server.accept().unwrap();
+
+    let seen = Arc::new(Mutex::new(None));
+    let seen2 = seen.clone();
+    stream.on_write_stall(Duration::from_millis(10), move |stall| {
+        *seen2.lock().unwrap() = Some(stall);
+    });
+
+    // Fill the send buffer while the peer is not reading.
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let chunk = vec![0; 64 * 1024];
+    let mut written = 0;
+    loop {
+        match Pin::new(&mut stream).poll_write(&mut cx, &chunk) {
+            Poll::Ready(Ok(n)) => written += n,
+            Poll::Ready(Err(e)) => panic!("write failed: {}", e),
+            Poll::Pending => break,
+        }
+    }
+
+    thread::sleep(Duration::from_millis(20));
+    let first = stream.write_stall().unwrap();
+    assert!(first >= Duration::from_millis(20));
+    thread::sleep(Duration::from_millis(20));
+    assert!(stream.write_stall().unwrap() > first);
+    assert!(seen.lock().unwrap().is_none());
+
+    // Drain everything on the peer so the blocked write can make progress.
+    let reader = thread::spawn(move || {
+        let mut buf = vec![0; written + chunk.len()];
+        peer.read_exact(&mut buf).unwrap();
+    });
+    executor::block_on(stream.write_all(&vec![0; 64 * 1024])).unwrap();
+    reader.join().unwrap();
+
+    assert!(stream.write_stall().is_none());
+    assert!(seen.lock().unwrap().unwrap() >= Duration::from_millis(40));
+}
+
+#[test]
+fn write_vectored_caps_at_iov_max() {
+    use std::io::IoSlice;
+
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+    let expected = data.clone();
+
+    let reader = thread::spawn(move || {
+        let (mut peer, _) = server.accept().unwrap();
+        let mut buf = vec![0; expected.len()];
+        peer.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+    });
+
+    executor::block_on(async {
+        let mut stream = romio::TcpStream::connect(&addr).await.unwrap();
+        let mut sent = 0;
+        let mut calls = 0;
+        while sent < data.len() {
+            let slices: Vec<_> = data[sent..].chunks(1).map(IoSlice::new).collect();
+            let n = stream.write_vectored(&slices).await.unwrap();
+            assert!(n > 0 && n <= 1024);
+            sent += n;
+            calls += 1;
+        }
+        assert_eq!(sent, data.len());
+        assert!(calls >= 2);
+    });
+
+    reader.join().unwrap();
+}
+
+#[test]
+fn read_vectored_scatters_into_each_buffer() {
+    use std::io::IoSliceMut;
+
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let writer = thread::spawn(move || {
+        let (mut peer, _) = server.accept().unwrap();
+        peer.write_all(b"HEADbody-of-frame").unwrap();
+    });
+
+    executor::block_on(async {
+        let mut stream = romio::TcpStream::connect(&addr).await.unwrap();
+        writer.join().unwrap();
+
+        let mut header = [0; 4];
+        let mut body = [0; 32];
+        let n = {
+            let mut bufs = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)];
+            stream.read_vectored(&mut bufs).await.unwrap()
+        };
+        assert_eq!(n, 17);
+        assert_eq!(&header, b"HEAD");
+        assert_eq!(&body[..13], b"body-of-frame");
+    });
+}
+
+#[test]
+fn read_with_timeout_times_out() {
+    use std::io;
+    use std::time::{Duration, Instant};
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+
+    // A peer that stays silent until told to speak.
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(&addr).unwrap();
+        rx.recv().unwrap();
+        client.write_all(THE_WINTERS_TALE).unwrap();
+        rx.recv().ok();
+    });
+
+    executor::block_on(async {
+        let mut incoming = server.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+
+        let start = Instant::now();
+        let err = stream
+            .read_with_timeout(&mut buf, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // Timing out must not lose anything sent afterwards.
+        tx.send(()).unwrap();
+        let mut read = 0;
+        while read < buf.len() {
+            read += stream
+                .read_with_timeout(&mut buf[read..], Duration::from_secs(5))
+                .await
+                .unwrap();
+        }
+        assert_eq!(buf, THE_WINTERS_TALE);
+        drop(tx);
+    });
+}
+
+#[test]
+fn throttled_incoming_limits_rate() {
+    use std::time::{Duration, Instant};
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    // Everything is already waiting in the backlog when accepting starts.
+    let clients: Vec<_> = (0..11)
+        .map(|_| TcpStream::connect(&addr).unwrap())
+        .collect();
+
+    executor::block_on(async {
+        let mut incoming = server.incoming().throttle(10);
+        incoming.next().await.unwrap().unwrap();
+        let start = Instant::now();
+        for _ in 0..10 {
+            incoming.next().await.unwrap().unwrap();
+        }
+        // No more than 10 connections were yielded within any one second.
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    });
+    drop(clients);
+}
+
+#[test]
+fn read_reports_eof_repeatedly() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client.write_all(b"x").unwrap();
+    });
+
+    executor::block_on(async {
+        let mut incoming = server.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+        let mut buf = [0; 16];
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 1);
+        // Once the peer is gone the stream stays readable at EOF under both
+        // epoll and kqueue, and every read reports it.
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn paused_incoming_leaves_connections_queued() {
+    use futures::task::noop_waker;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::task::Context;
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut incoming = server.incoming().pausable();
+    let handle = incoming.handle();
+    handle.pause();
+
+    // The handshake completes against the backlog even though nothing
+    // accepts.
+    let client = TcpStream::connect(&addr).unwrap();
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    thread::sleep(Duration::from_millis(50));
+    assert!(Pin::new(&mut incoming).poll_next(&mut cx).is_pending());
+
+    let resumer = handle.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        resumer.resume();
+    });
+
+    executor::block_on(async {
+        let stream = incoming.next().await.unwrap().unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), client.local_addr().unwrap());
+    });
+    assert!(!handle.is_paused());
+}
+
+#[test]
+fn limited_incoming_waits_for_a_permit() {
+    use futures::task::noop_waker;
+    use futures::Stream;
+    use std::pin::Pin;
+    use std::task::Context;
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut incoming = server.incoming().limit_connections(2);
+
+    let clients: Vec<_> = (0..3).map(|_| TcpStream::connect(&addr).unwrap()).collect();
+    let (first, second) = executor::block_on(async {
+        let first = incoming.next().await.unwrap().unwrap();
+        let second = incoming.next().await.unwrap().unwrap();
+        (first, second)
+    });
+    assert_eq!(incoming.open_connections(), 2);
+
+    // The third client stays in the backlog while both permits are held.
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    thread::sleep(Duration::from_millis(50));
+    assert!(Pin::new(&mut incoming).poll_next(&mut cx).is_pending());
+
+    let (_stream, permit) = first;
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        drop(permit);
+    });
+    executor::block_on(async {
+        let (stream, _permit) = incoming.next().await.unwrap().unwrap();
+        assert_eq!(
+            stream.peer_addr().unwrap(),
+            clients[2].local_addr().unwrap()
+        );
+    });
+    drop(second);
+    assert_eq!(incoming.open_connections(), 0);
+}
+
+#[test]
+fn incoming_adapters_chain() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut incoming = server
+        .incoming()
+        .retry_transient()
+        .throttle(1000)
+        .limit_connections(1);
+
+    let client = TcpStream::connect(&addr).unwrap();
+    let (stream, _permit) = executor::block_on(incoming.next()).unwrap().unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), client.local_addr().unwrap());
+    assert_eq!(incoming.open_connections(), 1);
+}
+
+#[test]
+fn incoming_until_ends_on_shutdown() {
+    use futures::channel::oneshot;
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+    let mut incoming = server.incoming().until(stopped);
+
+    let client = thread::spawn(move || {
+        let stream = TcpStream::connect(&addr).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        stop.send(()).unwrap();
+        stream
+    });
+
+    executor::block_on(async {
+        assert!(incoming.next().await.unwrap().is_ok());
+        assert!(incoming.next().await.is_none());
+        assert!(incoming.next().await.is_none());
+    });
+    assert!(incoming.is_shut_down());
+    drop(client.join().unwrap());
+
+    // the listener is usable again once the stream is gone
+    drop(incoming);
+    assert_eq!(server.local_addr().unwrap(), addr);
+}
+
+#[test]
+fn read_header_within_drops_dribbling_client() {
+    use std::io;
+    use std::time::{Duration, Instant};
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(&addr).unwrap();
+        for byte in b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n" {
+            if client.write_all(&[*byte]).is_err() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    });
+
+    executor::block_on(async {
+        let mut incoming = server.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let mut buf = [0; 1024];
+
+        let err = stream
+            .read_header_within(&mut buf, b"\r\n\r\n", deadline)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(Instant::now() >= deadline);
+    });
+}
+
+#[test]
+fn read_header_within_returns_header_and_excess() {
+    use std::time::{Duration, Instant};
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+        thread::sleep(Duration::from_millis(20));
+        client.write_all(b"\r\nbody").unwrap();
+    });
+
+    executor::block_on(async {
+        let mut incoming = server.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut buf = [0; 1024];
+
+        let (header_len, mut filled) = stream
+            .read_header_within(&mut buf, b"\r\n\r\n", deadline)
+            .await
+            .unwrap();
+        assert_eq!(&buf[..header_len], b"GET / HTTP/1.1\r\n\r\n");
+        while filled < header_len + 4 {
+            filled += stream.read(&mut buf[filled..]).await.unwrap();
+        }
+        assert_eq!(&buf[header_len..filled], b"body");
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn busy_poll_is_settable() {
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    let stream = romio::TcpStream::try_from(client).unwrap();
+
+    stream.set_busy_poll(0).unwrap();
+    assert_eq!(stream.busy_poll().unwrap(), 0);
+
+    match stream.set_busy_poll(50) {
+        Ok(()) => assert_eq!(stream.busy_poll().unwrap(), 50),
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied),
+    }
+}
+
+#[test]
+fn drain_backlog_hands_over_queued_connections() {
+    use std::net::Shutdown;
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let mut clients: Vec<_> = (0..2).map(|_| TcpStream::connect(&addr).unwrap()).collect();
+    thread::sleep(Duration::from_millis(50));
+
+    let mut peers = vec![];
+    let drained = server
+        .drain_backlog(|stream| {
+            peers.push(stream.peer_addr().unwrap());
+            stream.shutdown(Shutdown::Both).unwrap();
+        })
+        .unwrap();
+    assert_eq!(drained, 2);
+    assert_eq!(
+        server
+            .drain_backlog(|_| panic!("backlog is empty"))
+            .unwrap(),
+        0
+    );
+    drop(server);
+
+    for client in &mut clients {
+        assert!(peers.contains(&client.local_addr().unwrap()));
+        // A clean close rather than a reset.
+        let mut buf = [0; 1];
+        assert_eq!(client.read(&mut buf).unwrap(), 0);
+    }
+}
+
+#[test]
+fn read_pooled_fills_and_signals_eof() {
+    use romio::pool::BufferPool;
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client.write_all(THE_WINTERS_TALE).unwrap();
+    });
+
+    let pool = BufferPool::new(64, 4);
+    executor::block_on(async {
+        let mut incoming = server.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+
+        let mut received = vec![];
+        loop {
+            let buf = stream.read_pooled(&pool).await.unwrap();
+            if buf.is_empty() {
+                break;
+            }
+            assert!(buf.len() <= pool.buf_size());
+            received.extend_from_slice(&buf);
+        }
+        assert_eq!(received, THE_WINTERS_TALE);
+    });
+}
+
+#[test]
+fn empty_write_completes_while_blocked() {
+    use futures::io::AsyncWrite;
+    use futures::task::noop_waker;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut stream = executor::block_on(romio::TcpStream::connect(&addr)).unwrap();
+    let (_peer, _) = server.accept().unwrap();
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let chunk = vec![0; 64 * 1024];
+    while let Poll::Ready(res) = Pin::new(&mut stream).poll_write(&mut cx, &chunk) {
+        res.unwrap();
+    }
+
+    // The stream isn't writable, but writing nothing doesn't need it to be.
+    match Pin::new(&mut stream).poll_write(&mut cx, b"") {
+        Poll::Ready(res) => assert_eq!(res.unwrap(), 0),
+        Poll::Pending => panic!("empty write waited for readiness"),
+    }
+    assert!(stream.write_stall().is_some());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn incoming_cpu_on_accepted_stream() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client.write_all(THE_WINTERS_TALE).unwrap();
+    });
+
+    executor::block_on(async {
+        let mut incoming = server.incoming();
+        let mut stream = incoming.next().await.unwrap().unwrap();
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        // Packets have been received, so the kernel knows where.
+        let cpu = stream.incoming_cpu().unwrap().expect("no incoming cpu");
+        let cpus = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_CONF) };
+        assert!((cpu as libc::c_long) < cpus);
+    });
+}
+
+#[test]
+fn connect_nowait_is_usable_before_connected() {
+    use futures::future;
+    use std::collections::HashMap;
+
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let mut table = HashMap::new();
+    table.insert(1, romio::TcpStream::connect_nowait(&addr).unwrap());
+    table.insert(2, romio::TcpStream::connect_nowait(&addr).unwrap());
+
+    executor::block_on(async {
+        let first = table.get_mut(&1).unwrap();
+        future::poll_fn(|cx| first.poll_connected(cx))
+            .await
+            .unwrap();
+        first.write_all(b"one").await.unwrap();
+
+        // Writing waits for the connection on its own.
+        table.get_mut(&2).unwrap().write_all(b"two").await.unwrap();
+    });
+
+    let mut got = Vec::new();
+    for _ in 0..2 {
+        let (mut stream, _) = server.accept().unwrap();
+        let mut buf = [0; 3];
+        stream.read_exact(&mut buf).unwrap();
+        got.push(buf);
+    }
+    got.sort();
+    assert_eq!(got, [*b"one", *b"two"]);
+}
+
+#[test]
+fn read_readiness_waits_for_the_connect_outcome() {
+    use futures::future;
+
+    drop(env_logger::try_init());
+    // Nothing listens on a port that was just freed.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut stream = romio::TcpStream::connect_nowait(&addr).unwrap();
+    let err = executor::block_on(future::poll_fn(|cx| stream.poll_read_ready(cx))).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+}
+
+#[cfg(unix)]
+#[test]
+fn tune_low_latency_sets_nodelay() {
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let stream = executor::block_on(romio::TcpStream::connect(&addr)).unwrap();
+    stream.set_nodelay(false).unwrap();
+    stream.tune_low_latency().unwrap();
+    assert!(stream.nodelay().unwrap());
+}
+
+#[test]
+fn accept_many_drains_a_burst() {
+    use futures::future;
+
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let clients: Vec<_> = (0..5).map(|_| TcpStream::connect(&addr).unwrap()).collect();
+
+    // The handshakes have completed, so the whole burst is queued and one
+    // readiness notification yields all of it.
+    let mut batch = Vec::new();
+    executor::block_on(async {
+        let n = future::poll_fn(|cx| server.poll_accept_multi(cx, &mut batch, 16))
+            .await
+            .unwrap();
+        assert_eq!(n, clients.len());
+        assert_eq!(server.accept_many(&mut batch, 0).await.unwrap(), 0);
+    });
+
+    let mut peers: Vec<_> = batch.iter().map(|(_, addr)| *addr).collect();
+    let mut locals: Vec<_> = clients.iter().map(|c| c.local_addr().unwrap()).collect();
+    peers.sort();
+    locals.sort();
+    assert_eq!(peers, locals);
+}
+
+#[test]
+fn dual_protocol_bind_shares_the_port() {
+    drop(env_logger::try_init());
+
+    let exchange = |addr: &SocketAddr| {
+        let (mut listener, mut socket) = romio::bind_dual_protocol(addr).unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(socket.local_addr().unwrap(), addr);
+
+        let mut client = TcpStream::connect(&addr).unwrap();
+        let peer = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        peer.send_to(b"ping", &addr).unwrap();
+
+        executor::block_on(async {
+            let mut buf = [0; 4];
+            let (n, from) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(
+                (&buf[..n], from),
+                (&b"ping"[..], peer.local_addr().unwrap())
+            );
+
+            // Closing on the server side leaves the connection in TIME_WAIT.
+            let stream = listener.incoming().next().await.unwrap().unwrap();
+            drop(stream);
+        });
+        assert_eq!(client.read(&mut [0; 1]).unwrap(), 0);
+        addr
+    };
+
+    let addr = exchange(&"127.0.0.1:0".parse().unwrap());
+    assert_eq!(exchange(&addr), addr);
+}
+
+#[cfg(unix)]
+#[test]
+fn into_std_round_trips_through_blocking_mode() {
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let writer = thread::spawn(move || {
+        let (mut stream, _) = server.accept().unwrap();
+        stream.write_all(b"first").unwrap();
+        thread::sleep(std::time::Duration::from_millis(50));
+        stream.write_all(b"second").unwrap();
+    });
+
+    let stream = executor::block_on(romio::TcpStream::connect(&addr)).unwrap();
+    let mut stream = stream.into_std().unwrap();
+
+    // A non-blocking socket would fail with `WouldBlock` while the peer
+    // waits between its writes.
+    let mut buf = [0; 11];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"firstsecond");
+    writer.join().unwrap();
+
+    let mut stream = romio::TcpStream::from_std_nonblocking(stream).unwrap();
+    executor::block_on(async {
+        assert_eq!(stream.read(&mut [0; 1]).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn accept_keeps_the_listener_usable() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    executor::block_on(async {
+        for _ in 0..2 {
+            let client = TcpStream::connect(&addr).unwrap();
+            let (stream, peer) = server.accept().await.unwrap();
+            assert_eq!(peer, client.local_addr().unwrap());
+            assert_eq!(stream.peer_addr().unwrap(), peer);
+        }
+    });
+    assert_eq!(server.local_addr().unwrap(), addr);
+}
+
+#[cfg(unix)]
+#[test]
+fn builder_reuse_port_lets_listeners_share_a_port() {
+    drop(env_logger::try_init());
+    let builder = TcpListener::builder().reuse_port(true).backlog(16);
+    let mut first = builder.bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = first.local_addr().unwrap();
+    let mut second = builder.bind(&addr).unwrap();
+    assert_eq!(second.local_addr().unwrap(), addr);
+
+    let err = TcpListener::bind(&addr).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+    // Whichever listener the kernel picks, the connection is accepted.
+    let client = TcpStream::connect(&addr).unwrap();
+    let mut cx = std::task::Context::from_waker(futures::task::noop_waker_ref());
+    let peer = executor::block_on(futures::future::poll_fn(|_| loop {
+        for listener in [&mut first, &mut second].iter_mut() {
+            if let std::task::Poll::Ready(res) = listener.poll_accept(&mut cx) {
+                return std::task::Poll::Ready(res.unwrap().1);
+            }
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+    }));
+    assert_eq!(peer, client.local_addr().unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn builder_rejects_v6_only_for_ipv4() {
+    let err = TcpListener::builder()
+        .only_v6(true)
+        .bind(&"127.0.0.1:0".parse().unwrap())
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn bind_reuseport_spreads_connections() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let listeners = TcpListener::bind_reuseport(&"127.0.0.1:0".parse().unwrap(), 4).unwrap();
+    let addr = listeners[0].local_addr().unwrap();
+    assert!(listeners.iter().all(|l| l.local_addr().unwrap() == addr));
+
+    let (tx, rx) = mpsc::channel();
+    for (i, mut listener) in listeners.into_iter().enumerate() {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            executor::block_on(async {
+                while let Ok(_) = listener.accept().await {
+                    if tx.send(i).is_err() {
+                        break;
+                    }
+                }
+            })
+        });
+    }
+
+    // The kernel hashes on the client's port, so a few dozen connections
+    // reach more than one listener.
+    let clients: Vec<_> = (0..32)
+        .map(|_| TcpStream::connect(&addr).unwrap())
+        .collect();
+    let mut seen = [false; 4];
+    for _ in &clients {
+        seen[rx.recv_timeout(Duration::from_secs(5)).unwrap()] = true;
+    }
+    assert!(seen.iter().filter(|&&s| s).count() > 1);
+
+    let err = TcpListener::bind_reuseport(&addr, 0).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn connect_timeout_fires_on_full_backlog() {
+    use romio::tcp::TcpStream as AsyncStream;
+    use std::time::{Duration, Instant};
+
+    drop(env_logger::try_init());
+    // Nothing accepts, so once the queue is full the kernel drops further
+    // SYNs and connects hang.
+    let server = TcpListener::builder()
+        .backlog(0)
+        .bind(&"127.0.0.1:0".parse().unwrap())
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    let _queued: Vec<_> = (0..4)
+        .map(|_| AsyncStream::connect_nowait(&addr).unwrap())
+        .collect();
+    thread::sleep(Duration::from_millis(50));
+
+    let start = Instant::now();
+    let timeout = Duration::from_millis(200);
+    let err = executor::block_on(AsyncStream::connect_timeout(&addr, timeout)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= timeout);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn socket_options_round_trip() {
+    use std::time::Duration;
+
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let stream = executor::block_on(romio::TcpStream::connect(&addr)).unwrap();
+
+    stream.set_nodelay(true).unwrap();
+    assert!(stream.nodelay().unwrap());
+
+    stream.set_keepalive(Some(Duration::from_secs(30))).unwrap();
+    assert_eq!(stream.keepalive().unwrap(), Some(Duration::from_secs(30)));
+    stream.set_keepalive(None).unwrap();
+    assert_eq!(stream.keepalive().unwrap(), None);
+
+    stream.set_linger(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(stream.linger().unwrap(), Some(Duration::from_secs(5)));
+
+    stream.set_ttl(42).unwrap();
+    assert_eq!(stream.ttl().unwrap(), 42);
+}
+
+#[test]
+fn peek_leaves_data_for_the_next_read() {
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let mut stream = executor::block_on(romio::TcpStream::connect(&addr)).unwrap();
+    let (mut peer, _) = server.accept().unwrap();
+    peer.write_all(b"\x16hello").unwrap();
+
+    executor::block_on(async {
+        let mut first = [0; 1];
+        assert_eq!(stream.peek(&mut first).await.unwrap(), 1);
+        assert_eq!(first, [0x16]);
+        assert_eq!(stream.peek(&mut first).await.unwrap(), 1);
+
+        let mut buf = [0; 6];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"\x16hello");
+
+        drop(peer);
+        assert_eq!(stream.peek(&mut first).await.unwrap(), 0);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn accepted_stream_hands_off_to_blocking_thread() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let mut client = TcpStream::connect(&addr).unwrap();
+    let (stream, _) = executor::block_on(server.accept()).unwrap();
+    let stream = TcpStream::try_from(stream).unwrap();
+
+    let worker = thread::spawn(move || {
+        let mut stream = stream;
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).unwrap();
+        stream.write_all(&buf).unwrap();
+    });
+    thread::sleep(std::time::Duration::from_millis(50));
+    client.write_all(b"hello").unwrap();
+    let mut buf = [0; 5];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+    worker.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn raw_fds_round_trip() {
+    use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+
+    drop(env_logger::try_init());
+    let server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let fd = server.as_raw_fd();
+    assert_eq!(server.into_raw_fd(), fd);
+    let mut server = unsafe { TcpListener::from_raw_fd(fd) };
+
+    let mut client = TcpStream::connect(&addr).unwrap();
+    executor::block_on(async {
+        let (stream, _) = server.accept().await.unwrap();
+        let fd = stream.into_raw_fd();
+        let mut stream = unsafe { romio::TcpStream::from_raw_fd(fd) };
+
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn listener_into_std_accepts_backlog() {
+    drop(env_logger::try_init());
+    let server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+    let client = TcpStream::connect(&addr).unwrap();
+
+    let server = std::net::TcpListener::try_from(server).unwrap();
+    let (stream, peer) = server.accept().unwrap();
+    assert_eq!(peer, client.local_addr().unwrap());
+    drop(stream);
+}
+
+#[test]
+fn connect_host_resolves_names() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    executor::block_on(async {
+        // `localhost` may resolve to `::1` first, where nothing listens, so
+        // this also covers moving on to the next address.
+        let stream = romio::TcpStream::connect_host(format!("localhost:{}", port))
+            .await
+            .unwrap();
+        let (accepted, peer) = server.accept().await.unwrap();
+        assert_eq!(peer, stream.local_addr().unwrap());
+        drop(accepted);
+    });
+}
+
+#[test]
+fn connect_host_reports_last_error() {
+    drop(env_logger::try_init());
+    // Nothing listens on a freshly closed port.
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let res = executor::block_on(romio::TcpStream::connect_host(addr));
+    assert_eq!(
+        res.unwrap_err().kind(),
+        std::io::ErrorKind::ConnectionRefused
+    );
+}
+
+#[test]
+fn connect_happy_reaches_loopback() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    executor::block_on(async {
+        let stream = romio::TcpStream::connect_happy("localhost", port)
+            .await
+            .unwrap();
+        let (_accepted, peer) = server.accept().await.unwrap();
+        assert_eq!(peer, stream.local_addr().unwrap());
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn send_file_streams_a_file_range() {
+    use tempdir::TempDir;
+
+    drop(env_logger::try_init());
+    let dir = TempDir::new("romio").unwrap();
+    let path = dir.path().join("tale");
+    // Large enough to fill the socket buffer along the way.
+    let data: Vec<u8> = (0..4 * 1024 * 1024).map(|i| i as u8).collect();
+    std::fs::write(&path, &data).unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let reader = thread::spawn(move || {
+        let (mut peer, _) = server.accept().unwrap();
+        let mut buf = Vec::new();
+        peer.read_to_end(&mut buf).unwrap();
+        buf
+    });
+
+    executor::block_on(async {
+        let mut stream = romio::TcpStream::connect(&addr).await.unwrap();
+        let len = data.len() - 10;
+        assert_eq!(stream.send_file(&file, 10, len).await.unwrap(), len);
+        // Asking for more than is left stops at the end of the file.
+        assert_eq!(stream.send_file(&file, 0, 100).await.unwrap(), 100);
+        assert_eq!(
+            stream
+                .send_file(&file, data.len() as u64 - 5, 100)
+                .await
+                .unwrap(),
+            5
+        );
+    });
+
+    let received = reader.join().unwrap();
+    let mut expected = data[10..].to_vec();
+    expected.extend_from_slice(&data[..100]);
+    expected.extend_from_slice(&data[data.len() - 5..]);
+    assert!(received == expected);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn splice_relays_through_a_pipe() {
+    drop(env_logger::try_init());
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    let writer = thread::spawn(move || {
+        let (mut peer, _) = server.accept().unwrap();
+        peer.write_all(THE_WINTERS_TALE).unwrap();
+    });
+
+    let sink = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let sink_addr = sink.local_addr().unwrap();
+    let reader = thread::spawn(move || {
+        let (mut peer, _) = sink.accept().unwrap();
+        let mut buf = Vec::new();
+        peer.read_to_end(&mut buf).unwrap();
+        buf
+    });
+
+    executor::block_on(async {
+        let mut from = romio::TcpStream::connect(&addr).await.unwrap();
+        let mut to = romio::TcpStream::connect(&sink_addr).await.unwrap();
+        let (mut rx, mut tx) = romio::raw::pipe().unwrap();
+        loop {
+            let n = from.splice_to(&mut tx, 4096).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            let mut left = n;
+            while left > 0 {
+                left -= to.splice_from(&mut rx, left).await.unwrap();
+            }
+        }
+    });
+
+    writer.join().unwrap();
+    assert_eq!(reader.join().unwrap(), THE_WINTERS_TALE);
+}
+
+#[cfg(unix)]
+#[test]
+fn stream_builder_connects_from_local_addr() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    executor::block_on(async {
+        let local = "127.0.0.2:0".parse().unwrap();
+        let stream = romio::TcpStream::builder()
+            .local_addr(local)
+            .connect(&addr)
+            .await
+            .unwrap();
+        let (_accepted, peer) = server.accept().await.unwrap();
+        assert_eq!(peer, stream.local_addr().unwrap());
+        assert_eq!(peer.ip(), local.ip());
+
+        let local = "[::1]:0".parse().unwrap();
+        let err = romio::TcpStream::builder()
+            .local_addr(local)
+            .connect(&addr)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn stream_builder_binds_to_device() {
+    drop(env_logger::try_init());
+    let mut server = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = server.local_addr().unwrap();
+
+    executor::block_on(async {
+        let builder = romio::TcpStream::builder().bind_device("no-such-if0");
+        assert!(builder.connect(&addr).await.is_err());
+
+        // Binding to a device may need privileges this test doesn't have.
+        match romio::TcpStream::builder()
+            .bind_device("lo")
+            .connect(&addr)
+            .await
+        {
+            Ok(stream) => {
+                let (_accepted, peer) = server.accept().await.unwrap();
+                assert_eq!(peer, stream.local_addr().unwrap());
+            }
+            Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied),
+        }
+    });
+}
+
+#[test]
+fn write_shutdown_half_closes() {
+    drop(env_logger::try_init());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // The peer only answers once it has read to the end.
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        stream.write_all(&buf).unwrap();
+    });
+
+    executor::block_on(async {
+        let mut stream = romio::TcpStream::connect(&addr).await.unwrap();
+        stream.write_all(THE_WINTERS_TALE).await.unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, THE_WINTERS_TALE);
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn urgent_data_is_received_out_of_band() {
+    drop(env_logger::try_init());
+    let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    executor::block_on(async {
+        let mut client = romio::TcpStream::connect(&addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.write_all(b"Each your doing").await.unwrap();
+        client.send_oob(b'!').await.unwrap();
+        assert_eq!(server.recv_oob().await.unwrap(), b'!');
+
+        // The urgent byte is left out of the stream.
+        client.write_all(b", so singular").await.unwrap();
+        let mut buf = [0; 28];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"Each your doing, so singular");
+
+        server.set_oob_inline(true).unwrap();
+        assert!(server.oob_inline().unwrap());
+        client.send_oob(b'?').await.unwrap();
+        let mut byte = [0; 1];
+        server.read_exact(&mut byte).await.unwrap();
+        assert_eq!(&byte, b"?");
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn connect_with_data_delivers_the_data() {
+    drop(env_logger::try_init());
+    let mut listener = TcpListener::builder()
+        .fastopen(16)
+        .bind(&"127.0.0.1:0".parse().unwrap())
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    executor::block_on(async {
+        // The first connection fetches a cookie, when Fast Open is on.
+        for _ in 0..2 {
+            let mut client = romio::TcpStream::connect_with_data(&addr, THE_WINTERS_TALE)
+                .await
+                .unwrap();
+            let (mut server, _) = listener.accept().await.unwrap();
+
+            let mut buf = vec![0; THE_WINTERS_TALE.len()];
+            server.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, THE_WINTERS_TALE);
+            server.write_all(b"queens").await.unwrap();
+            drop(server);
+
+            let mut reply = Vec::new();
+            client.read_to_end(&mut reply).await.unwrap();
+            assert_eq!(reply, b"queens");
+        }
+    });
+}
+
+#[cfg(all(feature = "mptcp", target_os = "linux"))]
+#[test]
+fn multipath_sockets_connect() {
+    drop(env_logger::try_init());
+    let mut listener = TcpListener::builder()
+        .multipath(true)
+        .bind(&"127.0.0.1:0".parse().unwrap())
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    // Whether MPTCP is available depends on the kernel; plain TCP is used
+    // otherwise, and must work the same.
+    let multipath = listener.is_multipath().unwrap();
+
+    executor::block_on(async {
+        let mut client = romio::TcpStream::builder()
+            .multipath(true)
+            .connect(&addr)
+            .await
+            .unwrap();
+        assert_eq!(client.is_multipath().unwrap(), multipath);
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        client.write_all(THE_WINTERS_TALE).await.unwrap();
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, THE_WINTERS_TALE);
+    });
+}
@@ -0,0 +1,168 @@
+#![feature(async_await)]
+use std::io;
+use std::time::{Duration, Instant};
+
+use futures::{executor, future, StreamExt};
+
+use romio::timer::{Delay, Interval, Timeout, TimeoutIo};
+
+#[test]
+fn delay_completes_at_its_deadline() -> io::Result<()> {
+    let start = Instant::now();
+    executor::block_on(Delay::new(start + Duration::from_millis(50)))?;
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    Ok(())
+}
+
+#[test]
+fn reset_delay_can_be_awaited_again() -> io::Result<()> {
+    let start = Instant::now();
+    let mut delay = Delay::new(start + Duration::from_secs(60));
+    delay.reset(start + Duration::from_millis(20));
+    executor::block_on(&mut delay)?;
+
+    delay.reset(Instant::now() + Duration::from_millis(20));
+    executor::block_on(&mut delay)?;
+    assert!(start.elapsed() >= Duration::from_millis(40));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    Ok(())
+}
+
+#[test]
+fn interval_ticks_every_period() -> io::Result<()> {
+    let start = Instant::now();
+    let period = Duration::from_millis(20);
+    let mut ticks = Interval::new(start, period);
+
+    executor::block_on(async {
+        let mut last = None;
+        for _ in 0..3 {
+            let tick = ticks.next().await.unwrap()?;
+            if let Some(last) = last {
+                assert!(tick >= last + period);
+            }
+            last = Some(tick);
+        }
+        Ok::<_, io::Error>(())
+    })?;
+    assert!(start.elapsed() >= period * 2);
+    Ok(())
+}
+
+#[test]
+fn timeout_fails_a_stalled_future() {
+    let start = Instant::now();
+    let stalled = future::pending::<io::Result<()>>();
+    let err = executor::block_on(Timeout::new(stalled, Duration::from_millis(50))).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn timeout_passes_through_a_timely_result() -> io::Result<()> {
+    let res = executor::block_on(Timeout::new(
+        async { Ok::<_, io::Error>(7) },
+        Duration::from_secs(5),
+    ))?;
+    assert_eq!(res, 7);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn timeout_io_fails_stalled_reads_but_not_slow_ones() -> io::Result<()> {
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use romio::uds::UnixStream;
+
+    let (a, mut b) = UnixStream::pair()?;
+    let mut a = TimeoutIo::new(a);
+    a.set_read_timeout(Some(Duration::from_millis(100)));
+    assert_eq!(a.read_timeout(), Some(Duration::from_millis(100)));
+
+    // Each byte arrives well within the timeout, even though the whole
+    // message takes longer than it.
+    let writer = std::thread::spawn(move || {
+        executor::block_on(async {
+            for &byte in b"slow" {
+                std::thread::sleep(Duration::from_millis(40));
+                b.write_all(&[byte]).await?;
+            }
+            Ok::<_, io::Error>(b)
+        })
+    });
+
+    executor::block_on(async {
+        let mut buf = [0; 4];
+        a.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"slow");
+
+        let err = a.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        Ok::<_, io::Error>(())
+    })?;
+    drop(writer.join().unwrap()?);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn timeout_io_restarts_for_a_read_that_replaces_a_dropped_one() -> io::Result<()> {
+    use futures::io::AsyncReadExt;
+    use futures::task::noop_waker;
+    use romio::uds::UnixStream;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::thread;
+
+    let (a, _b) = UnixStream::pair()?;
+    let mut a = TimeoutIo::new(a);
+    a.set_read_timeout(Some(Duration::from_millis(50)));
+
+    // A read from this thread starts waiting, and is abandoned.
+    let mut buf = [0; 4];
+    executor::block_on(future::poll_fn(|cx| {
+        assert!(Pin::new(&mut a.read(&mut buf)).poll(cx).is_pending());
+        Poll::Ready(())
+    }));
+    thread::sleep(Duration::from_millis(100));
+
+    // Another task gets a timeout of its own.
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(Pin::new(&mut a.read(&mut buf)).poll(&mut cx).is_pending());
+    thread::sleep(Duration::from_millis(100));
+
+    // The same task carries on with the clock, unless it is reset.
+    let mut cx = Context::from_waker(&waker);
+    let res = Pin::new(&mut a.read(&mut buf)).poll(&mut cx);
+    match res {
+        Poll::Ready(Err(ref e)) if e.kind() == io::ErrorKind::TimedOut => {}
+        res => panic!("read didn't time out: {:?}", res),
+    }
+    assert!(Pin::new(&mut a.read(&mut buf)).poll(&mut cx).is_pending());
+    thread::sleep(Duration::from_millis(100));
+    a.reset_read_timeout();
+    assert!(Pin::new(&mut a.read(&mut buf)).poll(&mut cx).is_pending());
+    Ok(())
+}
+
+#[test]
+fn timeout_io_fails_stalled_datagram_receives() -> io::Result<()> {
+    use romio::UdpSocket;
+    use std::pin::Pin;
+
+    use async_datagram::AsyncDatagram;
+
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap())?;
+    let mut socket = TimeoutIo::new(socket);
+    socket.set_read_timeout(Some(Duration::from_millis(50)));
+
+    let mut buf = [0; 16];
+    let err = executor::block_on(future::poll_fn(|cx| {
+        Pin::new(&mut socket).poll_recv_from(cx, &mut buf)
+    }))
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    Ok(())
+}
@@ -0,0 +1,158 @@
+#![feature(async_await)]
+#![cfg(feature = "rustls")]
+use std::io;
+use std::sync::Arc;
+
+use futures::executor;
+use futures::future;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+use romio::tls::{TlsAcceptor, TlsConnector};
+use romio::{TcpListener, TcpStream};
+
+const CA: &[u8] = include_bytes!("tls/ca.pem");
+const CERT: &[u8] = include_bytes!("tls/end.pem");
+const KEY: &[u8] = include_bytes!("tls/end.key");
+
+const THE_TEMPEST: &[u8] = b"
+    We are such stuff
+    As dreams are made on, and our little life
+    Is rounded with a sleep.
+";
+
+fn acceptor() -> TlsAcceptor {
+    let certs = vec![CertificateDer::from_pem_slice(CERT).unwrap()];
+    let key = PrivateKeyDer::from_pem_slice(KEY).unwrap();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    TlsAcceptor::from(Arc::new(config))
+}
+
+fn connector(trusted: bool) -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    if trusted {
+        roots
+            .add(CertificateDer::from_pem_slice(CA).unwrap())
+            .unwrap();
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+/// Returns both ends of a TCP connection.
+async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let mut listener = TcpListener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, accepted) = future::join(TcpStream::connect(&addr), listener.accept()).await;
+    (client.unwrap(), accepted.unwrap().0)
+}
+
+#[test]
+fn echoes_over_tls() {
+    drop(env_logger::try_init());
+    executor::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let connector = connector(true);
+        let acceptor = acceptor();
+        let (client, server) = future::join(
+            connector.connect("localhost", client),
+            acceptor.accept(server),
+        )
+        .await;
+        let (mut client, mut server) = (client.unwrap(), server.unwrap());
+
+        let echo = async {
+            let mut buf = Vec::new();
+            server.read_to_end(&mut buf).await.unwrap();
+            server.write_all(&buf).await.unwrap();
+            server.close().await.unwrap();
+        };
+        let request = async {
+            client.write_all(THE_TEMPEST).await.unwrap();
+            // Sends `close_notify`, which ends the read on the other side.
+            client.close().await.unwrap();
+        };
+        future::join(echo, request).await;
+
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, THE_TEMPEST);
+    });
+}
+
+#[test]
+fn untrusted_certificate_fails_handshake() {
+    drop(env_logger::try_init());
+    executor::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let connector = connector(false);
+        let acceptor = acceptor();
+        let (client, server) = future::join(
+            connector.connect("localhost", client),
+            acceptor.accept(server),
+        )
+        .await;
+        assert_eq!(client.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(server.is_err());
+    });
+}
+
+#[test]
+fn wrong_name_fails_handshake() {
+    drop(env_logger::try_init());
+    executor::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let connector = connector(true);
+        let acceptor = acceptor();
+        let (client, _) = future::join(
+            connector.connect("example.com", client),
+            acceptor.accept(server),
+        )
+        .await;
+        assert_eq!(client.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    });
+}
+
+#[test]
+fn truncation_is_reported() {
+    drop(env_logger::try_init());
+    executor::block_on(async {
+        let (client, server) = tcp_pair().await;
+        let connector = connector(true);
+        let acceptor = acceptor();
+        let (client, server) = future::join(
+            connector.connect("localhost", client),
+            acceptor.accept(server),
+        )
+        .await;
+        let mut client = client.unwrap();
+
+        // Closes the TCP connection without `close_notify`.
+        let (tcp, _) = server.unwrap().into_inner();
+        drop(tcp);
+
+        let mut buf = Vec::new();
+        let err = client.read_to_end(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    });
+}
+
+#[test]
+fn invalid_domain_is_rejected() {
+    drop(env_logger::try_init());
+    executor::block_on(async {
+        let (client, _server) = tcp_pair().await;
+        let err = connector(true)
+            .connect("not a domain", client)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    });
+}
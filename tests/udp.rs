@@ -1,5 +1,7 @@
 #![feature(async_await)]
 use std::convert::TryFrom;
+#[cfg(unix)]
+use std::net::{Ipv4Addr, SocketAddrV4};
 use std::net::SocketAddr;
 use futures::executor;
 use romio::UdpSocket;
@@ -35,4 +37,554 @@ fn socket_from_std() {
     let std_socket = std::net::UdpSocket::bind(&addr).unwrap();
     let socket = UdpSocket::try_from(std_socket).unwrap();
     executor::block_on(exchange(socket));
-}
\ No newline at end of file
+}
+
+#[cfg(unix)]
+#[test]
+fn bind_multicast_receivers_share_group() {
+    drop(env_logger::try_init());
+    let mut group: SocketAddrV4 = "239.255.42.99:0".parse().unwrap();
+    let any = Ipv4Addr::UNSPECIFIED;
+    let mut a = UdpSocket::bind_multicast_v4(&group, &any).unwrap();
+    // The second receiver shares the port the first one was given.
+    group.set_port(a.local_addr().unwrap().port());
+    let mut b = UdpSocket::bind_multicast_v4(&group, &any).unwrap();
+
+    let sender = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    sender.set_multicast_loop_v4(true).unwrap();
+    sender.send_to(THE_WINTERS_TALE, &group).unwrap();
+
+    executor::block_on(async {
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        let (n, _) = a.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+        let (n, _) = b.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn multicast_interface_round_trips() {
+    drop(env_logger::try_init());
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    assert_eq!(socket.multicast_if_v4().unwrap(), Ipv4Addr::UNSPECIFIED);
+    socket.set_multicast_if_v4(&Ipv4Addr::LOCALHOST).unwrap();
+    assert_eq!(socket.multicast_if_v4().unwrap(), Ipv4Addr::LOCALHOST);
+
+    // Sent from the loopback interface, the group's datagrams loop back to
+    // a member that joined there.
+    let mut group: SocketAddrV4 = "239.255.42.98:0".parse().unwrap();
+    let mut member = UdpSocket::bind_multicast_v4(&group, &Ipv4Addr::LOCALHOST).unwrap();
+    group.set_port(member.local_addr().unwrap().port());
+    socket.set_multicast_loop_v4(true).unwrap();
+    executor::block_on(async {
+        let mut socket = socket;
+        socket
+            .send_to(THE_WINTERS_TALE, &group.into())
+            .await
+            .unwrap();
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        let (n, _) = member.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn broadcast_ttl_and_buffer_options_round_trip() {
+    drop(env_logger::try_init());
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+
+    socket.set_broadcast(true).unwrap();
+    assert!(socket.broadcast().unwrap());
+    socket.set_ttl(42).unwrap();
+    assert_eq!(socket.ttl().unwrap(), 42);
+
+    // The kernel may round the sizes up, but not below what was asked for.
+    socket.set_recv_buffer_size(32 * 1024).unwrap();
+    assert!(socket.recv_buffer_size().unwrap() >= 32 * 1024);
+    socket.set_send_buffer_size(32 * 1024).unwrap();
+    assert!(socket.send_buffer_size().unwrap() >= 32 * 1024);
+}
+
+#[cfg(unix)]
+#[test]
+fn peek_from_leaves_the_datagram_queued() {
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(THE_WINTERS_TALE, &addr).unwrap();
+
+    executor::block_on(async {
+        let mut header = [0; 4];
+        let (n, peer) = socket.peek_from(&mut header).await.unwrap();
+        assert_eq!((n, peer), (4, sender.local_addr().unwrap()));
+        assert_eq!(&header, &THE_WINTERS_TALE[..4]);
+
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        let (n, peer) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(peer, sender.local_addr().unwrap());
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+    });
+}
+
+#[test]
+fn split_halves_run_on_separate_threads() {
+    drop(env_logger::try_init());
+    let a = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let mut b = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b_addr = b.local_addr().unwrap();
+    let (mut recv, mut send) = a.split();
+
+    // Echo every datagram back from the other socket.
+    let echo = std::thread::spawn(move || {
+        executor::block_on(async {
+            let mut buf = vec![0; 64];
+            for _ in 0..2 {
+                let (n, from) = b.recv_from(&mut buf).await.unwrap();
+                b.send_to(&buf[..n], &from).await.unwrap();
+            }
+        })
+    });
+    let receiver = std::thread::spawn(move || {
+        executor::block_on(async {
+            let mut buf = vec![0; 64];
+            let mut got = Vec::new();
+            for _ in 0..2 {
+                let (n, from) = recv.recv_from(&mut buf).await.unwrap();
+                assert_eq!(from, b_addr);
+                got.push(buf[..n].to_vec());
+            }
+            (recv, got)
+        })
+    });
+
+    executor::block_on(async {
+        send.send_to(b"one", &b_addr).await.unwrap();
+        send.send_to(b"two", &b_addr).await.unwrap();
+    });
+    echo.join().unwrap();
+    let (recv, got) = receiver.join().unwrap();
+    assert_eq!(got, [b"one".to_vec(), b"two".to_vec()]);
+
+    let other = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let (other_recv, other_send) = other.split();
+    let romio::udp::ReuniteError(recv, _) = recv.reunite(other_send).unwrap_err();
+    drop(other_recv);
+    let socket = recv.reunite(send).unwrap();
+    assert_eq!(socket.local_addr().unwrap(), a_addr);
+}
+
+#[test]
+fn oversized_datagram_is_invalid_input() {
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let buf = vec![0; 70_000];
+
+    let err = executor::block_on(socket.send_to(&buf, &addr)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let os = err
+        .get_ref()
+        .unwrap()
+        .downcast_ref::<std::io::Error>()
+        .unwrap();
+    assert_eq!(os.raw_os_error(), Some(libc::EMSGSIZE));
+}
+
+#[test]
+fn zero_length_datagram_is_not_eof() {
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    executor::block_on(async {
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        socket.send_to(&[], &addr).await.unwrap();
+        socket.send_to(THE_WINTERS_TALE, &addr).await.unwrap();
+
+        // kqueue reports the empty datagram as 0 bytes readable; it must be
+        // delivered as a datagram of its own, followed by the next one.
+        let (n, sender) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!((n, sender), (0, addr));
+        let (n, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn busy_poll_is_settable() {
+    drop(env_logger::try_init());
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+
+    // Lowering the value never needs privileges.
+    socket.set_busy_poll(0).unwrap();
+    assert_eq!(socket.busy_poll().unwrap(), 0);
+
+    match socket.set_busy_poll(50) {
+        Ok(()) => assert_eq!(socket.busy_poll().unwrap(), 50),
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied),
+    }
+}
+
+#[test]
+fn connected_socket_sends_and_receives() {
+    drop(env_logger::try_init());
+    let mut a = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let mut b = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    a.connect(&b.local_addr().unwrap()).unwrap();
+    b.connect(&a.local_addr().unwrap()).unwrap();
+
+    executor::block_on(async {
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        a.send(THE_WINTERS_TALE).await.unwrap();
+        let n = b.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn roaming_socket_reports_migrated_peer() {
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let old_path = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let new_path = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    // Start out kernel-connected, then switch to following the peer.
+    socket.connect(&old_path.local_addr().unwrap()).unwrap();
+    socket
+        .connect_roaming(&old_path.local_addr().unwrap())
+        .unwrap();
+
+    executor::block_on(async {
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        old_path.send_to(b"hello", &addr).unwrap();
+        let (_, from) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from, old_path.local_addr().unwrap());
+
+        // The peer rebinds to a new address, which must be visible.
+        new_path.send_to(b"moved", &addr).unwrap();
+        let (_, from) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from, new_path.local_addr().unwrap());
+
+        socket.connect_roaming(&from).unwrap();
+        socket.send(THE_WINTERS_TALE).await.unwrap();
+    });
+
+    let mut buf = vec![0; THE_WINTERS_TALE.len()];
+    let (n, from) = new_path.recv_from(&mut buf).unwrap();
+    assert_eq!((&buf[..n], from), (THE_WINTERS_TALE, addr));
+}
+
+#[cfg(unix)]
+#[test]
+fn send_to_many_delivers_each_datagram_once() {
+    use std::os::unix::io::AsRawFd;
+
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = receiver.local_addr().unwrap();
+
+    // A tiny send buffer makes the kernel take the batch in pieces.
+    let size: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_SNDBUF,
+            &size as *const _ as *const libc::c_void,
+            std::mem::size_of_val(&size) as libc::socklen_t,
+        )
+    };
+    assert_eq!(ret, 0);
+
+    let payloads: Vec<[u8; 2]> = (0..256u16).map(|i| i.to_be_bytes()).collect();
+    let msgs: Vec<(&[u8], SocketAddr)> = payloads.iter().map(|p| (&p[..], target)).collect();
+    let sent = executor::block_on(socket.send_to_many(&msgs)).unwrap();
+    assert_eq!(sent, msgs.len());
+
+    receiver.set_nonblocking(true).unwrap();
+    let mut buf = [0; 2];
+    let mut seen = Vec::new();
+    while let Ok(n) = receiver.recv(&mut buf) {
+        assert_eq!(n, 2);
+        seen.push(u16::from_be_bytes(buf));
+    }
+    assert_eq!(seen, (0..256).collect::<Vec<_>>());
+}
+
+#[cfg(unix)]
+#[test]
+fn send_to_many_tells_how_many_were_sent_before_an_error() {
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = receiver.local_addr().unwrap();
+
+    // The third datagram is too long for UDP.
+    let huge = vec![0; 70_000];
+    let msgs = [
+        (&b"a"[..], target),
+        (&b"b"[..], target),
+        (&huge[..], target),
+        (&b"c"[..], target),
+    ];
+    let mut send = socket.send_to_many(&msgs);
+    assert!(executor::block_on(&mut send).is_err());
+    assert_eq!(send.sent(), 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn recv_from_many_takes_what_is_waiting() {
+    drop(env_logger::try_init());
+    let mut socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let from = sender.local_addr().unwrap();
+
+    for i in 0..3u8 {
+        sender.send_to(&[i; 3], &addr).unwrap();
+    }
+    sender.send_to(b"too long", &addr).unwrap();
+
+    executor::block_on(async {
+        // More buffers than datagrams: only the waiting ones are filled.
+        let mut bufs = vec![[0; 4]; 8];
+        let mut received = Vec::new();
+        while received.len() < 4 {
+            let bufs = &mut bufs[received.len()..];
+            received.extend(socket.recv_from_many(bufs).await.unwrap());
+        }
+        assert_eq!(received, vec![(3, from), (3, from), (3, from), (4, from)]);
+        for i in 0..3u8 {
+            assert_eq!(bufs[i as usize][..3], [i; 3]);
+        }
+        assert_eq!(&bufs[3], b"too ");
+
+        let none: &mut [Vec<u8>] = &mut [];
+        assert!(socket.recv_from_many(none).await.unwrap().is_empty());
+    });
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn recv_from_with_orig_dst_reports_the_destination() {
+    drop(env_logger::try_init());
+    // Bound to the unspecified address, the socket only learns which of its
+    // addresses a datagram was sent to from the control message.
+    let mut socket = UdpSocket::bind(&"0.0.0.0:0".parse().unwrap()).unwrap();
+    let port = socket.local_addr().unwrap().port();
+    let target = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, port));
+    let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    socket.set_recv_orig_dst(true).unwrap();
+    sender.send_to(b"query", &target).unwrap();
+    let mut buf = [0; 16];
+    let (n, from, orig_dst) = executor::block_on(socket.recv_from_with_orig_dst(&mut buf)).unwrap();
+    assert_eq!(&buf[..n], b"query");
+    assert_eq!(from, sender.local_addr().unwrap());
+    assert_eq!(orig_dst, target);
+
+    socket.set_recv_orig_dst(false).unwrap();
+    sender.send_to(b"query", &target).unwrap();
+    let err = executor::block_on(socket.recv_from_with_orig_dst(&mut buf)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn no_check_datagrams_still_round_trip() {
+    drop(env_logger::try_init());
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    assert!(!socket.no_check().unwrap());
+    socket.set_no_check(true).unwrap();
+    assert!(socket.no_check().unwrap());
+    executor::block_on(exchange(socket));
+
+    let socket = UdpSocket::bind(&"[::1]:0".parse().unwrap()).unwrap();
+    let err = socket.set_no_check(true).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn ecn_codepoints_round_trip() {
+    drop(env_logger::try_init());
+    const ECT_0: u8 = 0b10;
+    const CE: u8 = 0b11;
+
+    for addr in &["127.0.0.1:0", "[::1]:0"] {
+        let mut socket = match UdpSocket::bind(&addr.parse().unwrap()) {
+            Ok(socket) => socket,
+            // No IPv6 loopback here.
+            Err(_) => continue,
+        };
+        let target = socket.local_addr().unwrap();
+        socket.set_recv_ecn(true).unwrap();
+
+        executor::block_on(async {
+            let mut buf = [0; 16];
+            for &ecn in &[0, ECT_0, CE] {
+                socket.send_to_ecn(b"data", &target, ecn).await.unwrap();
+                let (n, from, got) = socket.recv_from_with_ecn(&mut buf).await.unwrap();
+                assert_eq!((&buf[..n], from, got), (&b"data"[..], target, ecn));
+            }
+
+            let err = socket.send_to_ecn(b"data", &target, 4).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        });
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn meta_reports_destination_for_replies() {
+    drop(env_logger::try_init());
+    // Bound to the unspecified address, the socket receives for every
+    // loopback address, and only learns which one from the control message.
+    let mut socket = UdpSocket::bind(&"0.0.0.0:0".parse().unwrap()).unwrap();
+    let port = socket.local_addr().unwrap().port();
+    socket.set_recv_pktinfo(true).unwrap();
+    socket.set_recv_ttl(true).unwrap();
+    socket.set_recv_ecn(true).unwrap();
+
+    let client = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    client.set_ttl(42).unwrap();
+    let dst: SocketAddr = ([127, 0, 0, 2], port).into();
+    client.send_to(b"query", &dst).unwrap();
+
+    executor::block_on(async {
+        let mut buf = [0; 16];
+        let (n, from, meta) = socket.recv_from_with_meta(&mut buf).await.unwrap();
+        assert_eq!(
+            (&buf[..n], from),
+            (&b"query"[..], client.local_addr().unwrap())
+        );
+        assert_eq!(meta.dst, Some(dst.ip()));
+        assert!(meta.interface.is_some());
+        assert_eq!(meta.ttl, Some(42));
+        assert_eq!(meta.ecn, Some(0));
+
+        socket
+            .send_to_from(b"answer", &from, &dst.ip())
+            .await
+            .unwrap();
+
+        let source = "::1".parse().unwrap();
+        let err = socket
+            .send_to_from(b"answer", &from, &source)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    });
+
+    let mut buf = [0; 16];
+    let (n, from) = client.recv_from(&mut buf).unwrap();
+    assert_eq!((&buf[..n], from), (&b"answer"[..], dst));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn gso_sends_segments_that_gro_receives_coalesced() {
+    drop(env_logger::try_init());
+    let mut sender = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let mut receiver = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let from = sender.local_addr().unwrap();
+    let target = receiver.local_addr().unwrap();
+
+    sender.set_gso_segment_size(100).unwrap();
+    assert_eq!(sender.gso_segment_size().unwrap(), 100);
+    let data: Vec<u8> = (0..250).map(|i| i as u8).collect();
+
+    executor::block_on(async {
+        // Without GRO, each segment arrives as a datagram of its own.
+        assert!(!receiver.gro().unwrap());
+        sender.send_to(&data, &target).await.unwrap();
+        let mut buf = [0; 1024];
+        for chunk in data.chunks(100) {
+            let (n, addr) = receiver.recv_from(&mut buf).await.unwrap();
+            assert_eq!((&buf[..n], addr), (chunk, from));
+        }
+
+        // With GRO, the segments may be handed over together.
+        receiver.set_gro(true).unwrap();
+        assert!(receiver.gro().unwrap());
+        sender.send_to(&data, &target).await.unwrap();
+        let mut received = Vec::new();
+        while received.len() < data.len() {
+            let (n, addr, segment_size) = receiver.recv_from_gro(&mut buf).await.unwrap();
+            assert_eq!(addr, from);
+            assert_eq!(segment_size, n.min(100));
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(received, data);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn readiness_drives_raw_syscalls() {
+    use futures::future::poll_fn;
+    use futures::ready;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::task::Poll;
+
+    drop(env_logger::try_init());
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let std_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        std_socket.send_to(b"raw", &addr).unwrap();
+    });
+
+    let mut buf = [0; 16];
+    let mut would_block = 0;
+    let n = executor::block_on(poll_fn(|cx| -> Poll<io::Result<usize>> {
+        ready!(socket.poll_read_ready(cx))?;
+        let ptr = buf.as_mut_ptr() as *mut libc::c_void;
+        let n = unsafe { libc::recv(socket.as_raw_fd(), ptr, buf.len(), 0) };
+        if n >= 0 {
+            return Poll::Ready(Ok(n as usize));
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() == io::ErrorKind::WouldBlock {
+            would_block += 1;
+            socket.clear_read_ready(cx)?;
+            return Poll::Pending;
+        }
+        Poll::Ready(Err(err))
+    }))
+    .unwrap();
+
+    sender.join().unwrap();
+    assert_eq!(&buf[..n], b"raw");
+    // Each clear parks the task until the datagram arrives, rather than
+    // leaving the socket read-ready to be spun on.
+    assert!(would_block <= 1, "spun {} times", would_block);
+}
+
+#[cfg(unix)]
+#[test]
+fn into_std_keeps_queued_datagrams() {
+    let socket = UdpSocket::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let addr = socket.local_addr().unwrap();
+    let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.send_to(b"queued", &addr).unwrap();
+
+    let socket = std::net::UdpSocket::try_from(socket).unwrap();
+    let mut buf = [0; 16];
+    let (n, peer) = socket.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"queued");
+    assert_eq!(peer, sender.local_addr().unwrap());
+}
@@ -29,7 +29,7 @@ fn listener_reads() -> Result<(), Error> {
     let tmp_dir = TempDir::new("listener_reads")?;
     let file_path = tmp_dir.path().join("sock");
 
-    let listener = UnixListener::bind(&file_path)?;
+    let mut listener = UnixListener::bind(&file_path)?;
     let file_path = listener.local_addr()?;
 
     // client thread
@@ -56,7 +56,7 @@ fn listener_writes() -> Result<(), Error> {
     let tmp_dir = TempDir::new("listener_writes")?;
     let file_path = tmp_dir.path().join("sock");
 
-    let listener = UnixListener::bind(&file_path)?;
+    let mut listener = UnixListener::bind(&file_path)?;
     let file_path = listener.local_addr()?;
 
     // client thread
@@ -83,7 +83,7 @@ fn both_sides_async_using_threadpool() -> Result<(), Error> {
     let tmp_dir = TempDir::new("both_sides_async")?;
     let file_path = tmp_dir.path().join("sock");
 
-    let listener = UnixListener::bind(&file_path)?;
+    let mut listener = UnixListener::bind(&file_path)?;
     let file_path = listener.local_addr()?;
 
     let mut pool = executor::ThreadPool::new().unwrap();
@@ -199,3 +199,710 @@ fn reads_bytes() {
     let expected = "The thrust of a sword will end this surrender";
     assert_eq!(buf, expected.as_bytes());
 }
+
+#[test]
+fn write_vectored_caps_at_iov_max() {
+    use std::io::IoSlice;
+
+    drop(env_logger::try_init());
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let data: Vec<u8> = (0..2000).map(|i| i as u8).collect();
+
+    executor::block_on(async {
+        let mut sent = 0;
+        while sent < data.len() {
+            let slices: Vec<_> = data[sent..].chunks(1).map(IoSlice::new).collect();
+            let n = client.write_vectored(&slices).await.unwrap();
+            assert!(n > 0 && n <= 1024);
+            sent += n;
+        }
+
+        let mut buf = vec![0; data.len()];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, data);
+    });
+}
+
+#[test]
+fn read_vectored_scatters_into_each_buffer() {
+    use std::io::IoSliceMut;
+
+    drop(env_logger::try_init());
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+
+    executor::block_on(async {
+        client.write_all(b"HEADbody-of-frame").await.unwrap();
+
+        let mut header = [0; 4];
+        let mut body = [0; 32];
+        let n = {
+            let mut bufs = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut body)];
+            server.read_vectored(&mut bufs).await.unwrap()
+        };
+        assert_eq!(n, 17);
+        assert_eq!(&header, b"HEAD");
+        assert_eq!(&body[..13], b"body-of-frame");
+    });
+}
+
+#[test]
+fn split_halves_run_on_separate_threads() {
+    drop(env_logger::try_init());
+    let (a, b) = UnixStream::pair().unwrap();
+    let (mut a_read, mut a_write) = a.split();
+    let data: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+
+    // `b` echoes everything back, so `a` has to read while it writes.
+    let echo = thread::spawn(move || {
+        executor::block_on(async {
+            let (mut b_read, mut b_write) = b.split();
+            b_read.copy_into(&mut b_write).await.unwrap();
+            b_write.close().await.unwrap();
+        })
+    });
+    let expected = data.clone();
+    let writer = thread::spawn(move || {
+        executor::block_on(async {
+            a_write.write_all(&data).await.unwrap();
+            // Lets the echo see EOF, while `a_read` keeps reading.
+            a_write.close().await.unwrap();
+            a_write
+        })
+    });
+
+    let mut echoed = Vec::new();
+    executor::block_on(a_read.read_to_end(&mut echoed)).unwrap();
+    assert!(echoed == expected);
+    echo.join().unwrap();
+
+    let a_write = writer.join().unwrap();
+    let (other_read, other_write) = UnixStream::pair().unwrap().0.split();
+    let err = a_read.reunite(other_write).unwrap_err();
+    drop(other_read);
+    assert!(err.0.reunite(a_write).is_ok());
+}
+
+#[test]
+fn zero_length_unix_datagram_is_not_eof() -> Result<(), Error> {
+    use romio::uds::UnixDatagram;
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("zero_length_unix_datagram")?;
+    let path = tmp_dir.path().join("sock");
+    let mut receiver = UnixDatagram::bind(&path)?;
+    let mut sender = UnixDatagram::unbound()?;
+
+    executor::block_on(async {
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        sender.send_to(&[], &path).await?;
+        sender.send_to(THE_WINTERS_TALE, &path).await?;
+
+        // kqueue reports the empty datagram as 0 bytes readable; it must be
+        // delivered as a datagram of its own, followed by the next one.
+        let (n, _) = receiver.recv_from(&mut buf).await?;
+        assert_eq!(n, 0);
+        let (n, _) = receiver.recv_from(&mut buf).await?;
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+        Ok(())
+    })
+}
+
+#[test]
+fn connected_unix_datagrams_send_and_recv() -> Result<(), Error> {
+    use romio::uds::UnixDatagram;
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("connected_unix_datagram")?;
+    let server_path = tmp_dir.path().join("server");
+    let client_path = tmp_dir.path().join("client");
+    let mut server = UnixDatagram::bind(&server_path)?;
+    let mut client = UnixDatagram::bind(&client_path)?;
+    client.connect(&server_path)?;
+    assert_eq!(client.peer_addr()?.as_pathname(), Some(&*server_path));
+
+    let (mut a, mut b) = UnixDatagram::pair()?;
+
+    executor::block_on(async {
+        let mut buf = vec![0; THE_WINTERS_TALE.len()];
+        client.send(THE_WINTERS_TALE).await?;
+        let (n, from) = server.recv_from(&mut buf).await?;
+        assert_eq!(&buf[..n], THE_WINTERS_TALE);
+        assert_eq!(from.as_pathname(), Some(&*client_path));
+
+        server.send_to(b"reply", &client_path).await?;
+        let n = client.recv(&mut buf).await?;
+        assert_eq!(&buf[..n], b"reply");
+
+        a.send(b"paired").await?;
+        let n = b.recv(&mut buf).await?;
+        assert_eq!(&buf[..n], b"paired");
+        Ok(())
+    })
+}
+
+#[test]
+fn file_descriptors_pass_over_streams_and_datagrams() -> Result<(), Error> {
+    use romio::uds::UnixDatagram;
+    use std::fs::File;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    drop(env_logger::try_init());
+    let pipe = || {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    };
+    let (mut a, mut b) = UnixStream::pair()?;
+    let (mut c, mut d) = UnixDatagram::pair()?;
+
+    executor::block_on(async {
+        let (mut read_end, write_end) = pipe();
+        assert_eq!(a.send_with_fds(b"pipe", &[write_end.as_raw_fd()]).await?, 4);
+        drop(write_end);
+
+        let mut buf = [0; 16];
+        let mut fds = [-1; 2];
+        let (n, nfds) = b.recv_with_fds(&mut buf, &mut fds).await?;
+        assert_eq!((&buf[..n], nfds), (&b"pipe"[..], 1));
+        let flags = unsafe { libc::fcntl(fds[0], libc::F_GETFD) };
+        assert!(flags & libc::FD_CLOEXEC != 0);
+
+        // The received descriptor is the write end of the same pipe.
+        let mut passed = unsafe { File::from_raw_fd(fds[0]) };
+        passed.write_all(b"through the pipe")?;
+        drop(passed);
+        let mut got = String::new();
+        read_end.read_to_string(&mut got)?;
+        assert_eq!(got, "through the pipe");
+
+        // Plain data carries no descriptors.
+        a.write_all(b"data").await?;
+        assert_eq!(b.recv_with_fds(&mut buf, &mut fds).await?, (4, 0));
+
+        let (read_end, write_end) = pipe();
+        let sent = [read_end.as_raw_fd(), write_end.as_raw_fd()];
+        c.send_with_fds(b"two", &sent).await?;
+        let err = d.recv_with_fds(&mut buf, &mut fds[..1]).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        // Both descriptors were closed, so the pipe has no reader left.
+        drop(read_end);
+        let mut write_end = write_end;
+        assert_eq!(
+            write_end.write(b"x").unwrap_err().kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn drain_backlog_hands_over_queued_connections() -> Result<(), Error> {
+    use std::net::Shutdown;
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("drain_backlog")?;
+    let file_path = tmp_dir.path().join("sock");
+    let mut server = UnixListener::bind(&file_path)?;
+
+    let mut client = StdStream::connect(&file_path)?;
+    let mut seen = 0;
+    let drained = server.drain_backlog(|stream| {
+        seen += 1;
+        stream.shutdown(Shutdown::Both).unwrap();
+    })?;
+    assert_eq!((drained, seen), (1, 1));
+    drop(server);
+
+    let mut buf = [0; 1];
+    assert_eq!(client.read(&mut buf)?, 0);
+    Ok(())
+}
+
+#[test]
+fn accept_keeps_the_listener_usable() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("accept")?;
+    let file_path = tmp_dir.path().join("sock");
+    let mut server = UnixListener::bind(&file_path)?;
+
+    executor::block_on(async {
+        for _ in 0..2 {
+            let mut client = StdStream::connect(&file_path)?;
+            let (mut stream, _) = server.accept().await?;
+            client.write_all(b"ping")?;
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+        }
+        Ok::<_, Error>(())
+    })?;
+    assert_eq!(
+        server.local_addr()?.as_pathname(),
+        Some(file_path.as_path())
+    );
+    Ok(())
+}
+
+#[test]
+fn builder_binds_a_listening_socket() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("builder")?;
+    let file_path = tmp_dir.path().join("sock");
+    let mut server = UnixListener::builder()
+        .backlog(4)
+        .recv_buffer_size(64 * 1024)
+        .bind(&file_path)?;
+
+    let client = StdStream::connect(&file_path)?;
+    let (stream, _) = executor::block_on(server.accept())?;
+    drop(client);
+    drop(stream);
+
+    let err = UnixListener::builder().bind(&file_path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    Ok(())
+}
+
+#[test]
+fn pairs_are_close_on_exec() -> Result<(), Error> {
+    use romio::uds::UnixDatagram;
+    use std::os::unix::io::AsRawFd;
+
+    fn cloexec(fd: i32) -> bool {
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        assert!(flags != -1);
+        flags & libc::FD_CLOEXEC != 0
+    }
+
+    let (a, b) = UnixStream::pair()?;
+    assert!(cloexec(a.as_raw_fd()) && cloexec(b.as_raw_fd()));
+    let (a, b) = UnixDatagram::pair()?;
+    assert!(cloexec(a.as_raw_fd()) && cloexec(b.as_raw_fd()));
+    Ok(())
+}
+
+#[test]
+fn last_reader_to_poll_is_woken() -> Result<(), Error> {
+    use futures::task::{waker, ArcWake};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    struct Flag(AtomicBool);
+
+    impl ArcWake for Flag {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let (mut a, mut b) = UnixStream::pair()?;
+    let first = Arc::new(Flag(AtomicBool::new(false)));
+    let second = Arc::new(Flag(AtomicBool::new(false)));
+    let mut buf = [0; 1];
+
+    // Each direction keeps a single waker, so the second poll replaces the
+    // first one's.
+    for flag in &[&first, &second] {
+        let waker = waker((*flag).clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut a).poll_read(&mut cx, &mut buf).is_pending());
+    }
+
+    executor::block_on(b.write_all(b"x"))?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !second.0.load(Ordering::SeqCst) {
+        assert!(Instant::now() < deadline, "second reader never woken");
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(!first.0.load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "from two tasks at once")]
+fn reader_polling_after_takeover_panics() {
+    use futures::task::{waker, ArcWake};
+    use std::sync::Arc;
+
+    struct Nop;
+
+    impl ArcWake for Nop {
+        fn wake_by_ref(_arc_self: &Arc<Self>) {}
+    }
+
+    let (mut a, _b) = UnixStream::pair().unwrap();
+    let first = waker(Arc::new(Nop));
+    let second = waker(Arc::new(Nop));
+    let mut buf = [0; 1];
+
+    // The first reader is still waiting when it polls again, after the
+    // second one took over.
+    for waker in &[&first, &second, &first] {
+        let mut cx = Context::from_waker(waker);
+        assert!(Pin::new(&mut a).poll_read(&mut cx, &mut buf).is_pending());
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn accept_reports_autobound_abstract_peer() -> Result<(), Error> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::io::FromRawFd;
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("autobind")?;
+    let file_path = tmp_dir.path().join("sock");
+    let mut listener = UnixListener::bind(&file_path)?;
+
+    // Binding with only the address family asks the kernel to pick a unique
+    // abstract name.
+    let client = unsafe {
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0);
+        assert!(fd >= 0);
+        let client = StdStream::from_raw_fd(fd);
+        let mut addr: libc::sockaddr_un = std::mem::zeroed();
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        let len = std::mem::size_of::<libc::sa_family_t>() as libc::socklen_t;
+        let ret = libc::bind(fd, &addr as *const _ as *const libc::sockaddr, len);
+        assert_eq!(ret, 0, "{}", std::io::Error::last_os_error());
+        client
+    };
+    let client_addr = client.local_addr()?;
+    let name = client_addr.as_abstract_name().unwrap().to_vec();
+    assert!(!name.is_empty());
+
+    // Connecting to a Unix listener completes as soon as it is queued.
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    let path = file_path.to_str().unwrap().as_bytes();
+    for (dst, src) in addr.sun_path.iter_mut().zip(path) {
+        *dst = *src as libc::c_char;
+    }
+    let ret = unsafe {
+        libc::connect(
+            std::os::unix::io::AsRawFd::as_raw_fd(&client),
+            &addr as *const _ as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t,
+        )
+    };
+    assert_eq!(ret, 0, "{}", std::io::Error::last_os_error());
+
+    executor::block_on(async {
+        let mut incoming = listener.incoming_with_addr();
+        let (_stream, addr) = incoming.next().await.unwrap()?;
+        assert_eq!(addr.as_abstract_name(), Some(&name[..]));
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_timeout_fires_on_full_backlog() -> Result<(), Error> {
+    use std::os::unix::io::AsRawFd;
+    use std::time::{Duration, Instant};
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("connect_timeout")?;
+    let file_path = tmp_dir.path().join("sock");
+    let listener = std::os::unix::net::UnixListener::bind(&file_path)?;
+    assert_eq!(unsafe { libc::listen(listener.as_raw_fd(), 0) }, 0);
+
+    executor::block_on(async {
+        // Nobody accepts, so connects succeed until the backlog is full.
+        let timeout = Duration::from_millis(100);
+        let mut queued = Vec::new();
+        let (err, elapsed) = loop {
+            let start = Instant::now();
+            match UnixStream::connect_timeout(&file_path, timeout).await {
+                Ok(stream) => queued.push(stream),
+                Err(e) => break (e, start.elapsed()),
+            }
+            assert!(queued.len() < 1024, "backlog never filled up");
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(elapsed >= timeout);
+
+        // Making room lets the next attempt through.
+        drop(listener.accept()?);
+        UnixStream::connect_timeout(&file_path, Duration::from_secs(5)).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn raw_fds_and_std_round_trip() -> Result<(), Error> {
+    use std::convert::TryFrom;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    drop(env_logger::try_init());
+    let (a, b) = UnixStream::pair()?;
+    let a = unsafe { UnixStream::from_raw_fd(a.into_raw_fd()) };
+    let mut b = StdStream::try_from(b)?;
+
+    executor::block_on(async {
+        let mut a = a;
+        a.write_all(b"hello").await?;
+        let mut buf = [0; 5];
+        b.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"hello");
+
+        let mut b = UnixStream::try_from(b)?;
+        b.write_all(b"world").await?;
+        a.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"world");
+        Ok(())
+    })
+}
+
+#[test]
+fn readable_waits_for_data_without_reading() -> Result<(), Error> {
+    use futures::task::noop_waker;
+    use std::future::Future;
+
+    let (mut a, mut b) = UnixStream::pair()?;
+    executor::block_on(a.writable())?;
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(Pin::new(&mut a.readable()).poll(&mut cx).is_pending());
+
+    executor::block_on(async {
+        b.write_all(b"hello").await?;
+        assert!(a.readable().await?.is_readable());
+        // Nothing was consumed, so the stream stays readable.
+        assert!(a.readable().await?.is_readable());
+
+        let mut buf = [0; 5];
+        a.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"hello");
+        Ok::<_, Error>(())
+    })?;
+
+    // Draining the stream down to `WouldBlock` clears the readiness.
+    let mut buf = [0; 1];
+    assert!(Pin::new(&mut a).poll_read(&mut cx, &mut buf).is_pending());
+    assert!(Pin::new(&mut a.readable()).poll(&mut cx).is_pending());
+    Ok(())
+}
+
+#[test]
+fn accept_with_cred_reports_the_peer() -> Result<(), Error> {
+    let dir = TempDir::new("romio")?;
+    let path = dir.path().join("sock");
+    let mut listener = UnixListener::bind(&path)?;
+
+    executor::block_on(async {
+        let client = UnixStream::connect(&path).await?;
+        let (stream, _, cred) = listener.accept_with_cred().await?;
+        assert_eq!(cred.uid, unsafe { libc::getuid() });
+        assert_eq!(cred.gid, unsafe { libc::getgid() });
+        assert_eq!(cred, stream.peer_cred()?);
+        drop(client);
+        Ok(())
+    })
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn abstract_names_bind_and_connect() -> Result<(), Error> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    drop(env_logger::try_init());
+    let name = format!("romio-abstract-{}", std::process::id());
+    let mut path = vec![0];
+    path.extend_from_slice(name.as_bytes());
+    let path = std::str::from_utf8(&path)?;
+    let addr = SocketAddr::from_abstract_name(name.as_bytes())?;
+
+    executor::block_on(async {
+        let mut listener = UnixListener::bind(path)?;
+        let local = listener.local_addr()?;
+        assert_eq!(local.as_abstract_name(), Some(name.as_bytes()));
+
+        let mut client = UnixStream::connect_addr(&addr).await?;
+        let (mut server, _) = listener.accept().await?;
+        client.write_all(THE_WINTERS_TALE).await?;
+        drop(client);
+        let mut buf = Vec::new();
+        server.read_to_end(&mut buf).await?;
+        assert_eq!(buf, THE_WINTERS_TALE);
+        drop(listener);
+
+        // The name is free again once the listener is closed.
+        let mut listener = UnixListener::bind_addr(&addr)?;
+        let _client = UnixStream::connect(path).await?;
+        listener.accept().await?;
+
+        let unnamed = StdStream::pair()?.0.local_addr()?;
+        let err = UnixStream::connect_addr(&unnamed).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        Ok(())
+    })
+}
+
+#[test]
+fn seqpacket_keeps_message_boundaries() -> Result<(), Error> {
+    use romio::uds::{SeqPacketListener, SeqPacketStream};
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("seqpacket")?;
+    let path = tmp_dir.path().join("sock");
+    let mut listener = SeqPacketListener::bind(&path)?;
+
+    executor::block_on(async {
+        let mut client = SeqPacketStream::connect(&path).await?;
+        let (mut server, _) = listener.accept().await?;
+        assert_eq!(server.peer_cred()?, client.peer_cred()?);
+
+        client.send(b"Each your doing,").await?;
+        client.send(b"So singular").await?;
+        let mut buf = [0; 64];
+        assert_eq!(server.recv(&mut buf).await?, 16);
+        assert_eq!(&buf[..16], b"Each your doing,");
+
+        // A message longer than the buffer is cut short, not split.
+        let mut short = [0; 2];
+        assert_eq!(server.recv(&mut short).await?, 2);
+        assert_eq!(&short, b"So");
+
+        client.shutdown(std::net::Shutdown::Write)?;
+        assert_eq!(server.recv(&mut buf).await?, 0);
+
+        let (mut a, mut b) = SeqPacketStream::pair()?;
+        a.send(b"in each particular").await?;
+        assert_eq!(b.recv(&mut buf).await?, 18);
+        Ok(())
+    })
+}
+
+#[test]
+fn builder_sets_file_mode_and_replaces_stale_sockets() -> Result<(), Error> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("builder_mode")?;
+    let file_path = tmp_dir.path().join("sock");
+    // A socket file left behind by a listener that is gone.
+    drop(std::os::unix::net::UnixListener::bind(&file_path)?);
+
+    let err = UnixListener::builder().bind(&file_path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let mut server = UnixListener::builder()
+        .mode(0o606)
+        .chown(uid, gid)
+        .unlink_existing(true)
+        .bind(&file_path)?;
+    let meta = std::fs::metadata(&file_path)?;
+    assert_eq!(meta.permissions().mode() & 0o777, 0o606);
+    assert_eq!((meta.uid(), meta.gid()), (uid, gid));
+
+    let _client = StdStream::connect(&file_path)?;
+    executor::block_on(server.accept())?;
+
+    // Other kinds of files are left alone.
+    let file = tmp_dir.path().join("file");
+    std::fs::write(&file, b"")?;
+    assert!(UnixListener::builder()
+        .unlink_existing(true)
+        .bind(&file)
+        .is_err());
+    assert!(file.exists());
+    Ok(())
+}
+
+#[test]
+fn builder_chown_keeps_the_default_mode() -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("builder_chown")?;
+    let file_path = tmp_dir.path().join("sock");
+    let expected = std::os::unix::net::UnixListener::bind(&file_path)
+        .and_then(|_| std::fs::metadata(&file_path))?
+        .permissions()
+        .mode();
+    std::fs::remove_file(&file_path)?;
+
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let _server = UnixListener::builder().chown(uid, gid).bind(&file_path)?;
+    let meta = std::fs::metadata(&file_path)?;
+    assert_eq!(meta.permissions().mode(), expected);
+    Ok(())
+}
+
+#[test]
+fn bind_with_cleanup_removes_the_socket_file() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("cleanup")?;
+    let file_path = tmp_dir.path().join("sock");
+
+    let mut listener = UnixListener::bind_with_cleanup(&file_path)?;
+    assert!(file_path.exists());
+    drop(listener.incoming());
+    assert!(file_path.exists());
+    drop(listener);
+    assert!(!file_path.exists());
+
+    // A file that replaced the listener's own is left alone.
+    let listener = UnixListener::bind_with_cleanup(&file_path)?;
+    std::fs::remove_file(&file_path)?;
+    let other = UnixListener::bind(&file_path)?;
+    drop(listener);
+    assert!(file_path.exists());
+    drop(other);
+    std::fs::remove_file(&file_path)?;
+
+    // So is one handed over with the socket.
+    let listener = UnixListener::builder()
+        .remove_on_drop(true)
+        .bind(&file_path)?;
+    let std_listener = listener.into_std()?;
+    assert!(file_path.exists());
+    drop(std_listener);
+    Ok(())
+}
+
+#[test]
+fn incoming_borrows_the_listener() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let tmp_dir = TempDir::new("incoming_borrows")?;
+    let file_path = tmp_dir.path().join("sock");
+    let mut listener = UnixListener::bind(&file_path)?;
+
+    executor::block_on(async {
+        for _ in 0..2 {
+            let _client = StdStream::connect(&file_path)?;
+            let mut incoming = listener.incoming_with_addr();
+            incoming.next().await.unwrap()?;
+            // The listener is back in hand between connections.
+            assert_eq!(listener.local_addr()?.as_pathname(), Some(&*file_path));
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn write_shutdown_half_closes() -> Result<(), Error> {
+    drop(env_logger::try_init());
+    let (mut stream, mut peer) = UnixStream::pair()?;
+
+    executor::block_on(async {
+        stream.write_all(THE_WINTERS_TALE).await?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+
+        let mut buf = Vec::new();
+        peer.read_to_end(&mut buf).await?;
+        assert_eq!(buf, THE_WINTERS_TALE);
+        peer.write_all(b"queens").await?;
+        drop(peer);
+
+        buf.clear();
+        stream.read_to_end(&mut buf).await?;
+        assert_eq!(buf, b"queens");
+        Ok(())
+    })
+}